@@ -1,3 +1,4 @@
+use std::cell::RefCell;
 use std::env;
 use std::fs::File;
 
@@ -8,8 +9,8 @@ fn main() {
     if args.len() < 2 {
         panic!("no zip file specified")
     }
-    let mut file = File::open(args[1].as_str()).unwrap();
-    for (i, mut file) in SeekingParser::<File, 128>::new(&mut file).enumerate() {
+    let file = RefCell::new(File::open(args[1].as_str()).unwrap());
+    for (i, mut file) in SeekingParser::<File, 128>::new(&file).enumerate() {
         println!("{}: {}({} Bytes)", i, file.file_name().unwrap_or("NoFileName"), file.file_size());
         let mut buf = [0u8; 16];
         if let Ok(n) = file.read(&mut buf) {
@@ -6,21 +6,26 @@ use std::cmp;
 use zip_parser as zip;
 use zip::prelude::*;
 
-fn parse<'a, P>(parser: P)
-where
-    P: Iterator,
-    P::Item: zip_parser::LocalFileOps
-{
-    for (i, mut file) in parser.enumerate() {
+fn parse<S: zip::Read, P: Parser<S>>(mut parser: P) {
+    let mut i = 0;
+    loop {
+        let mut file = match parser.next_entry() {
+            Ok(Some(file)) => file,
+            Ok(None) => break,
+            Err(e) => {
+                println!("listing failed: {}", e);
+                break;
+            }
+        };
         println!("#{}: {}({} Bytes)", i, file.file_name().unwrap_or("NoFileName"), file.file_size());
-        let mut buf = Vec::new();
-        buf.resize(file.file_size() as usize, 0);
+        let mut buf = vec![0; file.file_size() as usize];
         if let Err(e) = file.read_exact(&mut buf) {
             println!("read failed: {}", e);
         } else {
-            println!("Data: {:02X?}", &buf[..16]);
+            println!("Data: {:02X?}", &buf[..cmp::min(buf.len(), 16)]);
         }
         println!();
+        i += 1;
     }
 }
 
@@ -52,10 +57,8 @@ impl zip_parser::Read for DataBuffer {
             buf[..len].copy_from_slice(&self.buffer[self.index..self.index + len]);
             self.index += len;
             // println!("return {}", len);
-            Ok(len)
-        } else {
-            Err(ParsingError::StreamEnding)
         }
+        Ok(len)
     }
 }
 
@@ -0,0 +1,115 @@
+//! Picking the central directory or the local file headers from what the stream supports
+
+use core::cell::RefCell;
+use core::str::Utf8Error;
+
+use crate::{
+    CentralDirEnd, LocalFile, LocalFileOps, Parser, ParsingError, PullEntry, PullParser, Read, Seek,
+    SeekFrom, SeekingParser,
+};
+
+/// Parser going through the central directory when the stream reports its length and the end
+/// of central directory record is found, and through the local file headers otherwise.
+///
+/// The entries read as those of the selected parser: [`SeekingParser`] ones are decompressed
+/// when the method is supported, [`PullParser`] ones hold the raw data of the archive.
+// no allocator to box the parsers, only one of them is ever built anyway
+#[allow(clippy::large_enum_variant)]
+pub enum AutoParser<'a, S: Read + Seek, const N: usize = 128> {
+    Seeking(SeekingParser<'a, S, N>),
+    Sequential(PullParser<'a, S, N>),
+}
+
+/// Entry of an [`AutoParser`]
+#[allow(clippy::large_enum_variant)]
+pub enum AutoEntry<'e, 'a, S: Read + Seek, const N: usize> {
+    Seeking(LocalFile<'a, S, N>),
+    Sequential(PullEntry<'e, 'a, S, N, 512>),
+}
+
+impl<'a, S: Read + Seek, const N: usize> AutoParser<'a, S, N> {
+    /// Probe `stream`, which must be at the start of the archive.
+    ///
+    /// A stream which can't tell its length, e.g. standard input, is never seeked.
+    pub fn new(stream: &'a mut RefCell<S>) -> Self {
+        let handle = stream.get_mut();
+        if let Some(len) = handle.stream_len() {
+            let origin = handle.seek(SeekFrom::Current(0));
+            if CentralDirEnd::find(handle, len).is_some() {
+                return Self::Seeking(SeekingParser::new(stream));
+            }
+            diag!(info, "no central directory, going through the local file headers");
+            if let Ok(origin) = origin {
+                let _ = handle.seek(SeekFrom::Start(origin));
+            }
+        }
+        Self::Sequential(PullParser::new(stream.get_mut()))
+    }
+
+    /// Whether the entries are listed from the central directory
+    pub fn is_seeking(&self) -> bool {
+        matches!(self, Self::Seeking(_))
+    }
+}
+
+impl<'a, S: Read + Seek, const N: usize> Parser<S> for AutoParser<'a, S, N> {
+    type Entry<'e> = AutoEntry<'e, 'a, S, N> where Self: 'e;
+
+    fn next_entry(&mut self) -> Result<Option<Self::Entry<'_>>, ParsingError> {
+        match self {
+            Self::Seeking(parser) => Ok(parser.next_entry()?.map(AutoEntry::Seeking)),
+            Self::Sequential(parser) => Ok(parser.next_entry()?.map(AutoEntry::Sequential)),
+        }
+    }
+
+    fn entry_count(&self) -> Option<u64> {
+        match self {
+            Self::Seeking(parser) => parser.entry_count(),
+            Self::Sequential(parser) => parser.entry_count(),
+        }
+    }
+
+    fn find_entry(&mut self, name: &str) -> Result<Option<Self::Entry<'_>>, ParsingError> {
+        match self {
+            Self::Seeking(parser) => Ok(parser.find_entry(name)?.map(AutoEntry::Seeking)),
+            Self::Sequential(parser) => Ok(parser.find_entry(name)?.map(AutoEntry::Sequential)),
+        }
+    }
+}
+
+impl<S: Read + Seek, const N: usize> LocalFileOps for AutoEntry<'_, '_, S, N> {
+    fn file_name(&self) -> Result<&str, Utf8Error> {
+        match self {
+            Self::Seeking(file) => file.file_name(),
+            Self::Sequential(entry) => entry.file_name(),
+        }
+    }
+
+    fn file_size(&self) -> u64 {
+        match self {
+            Self::Seeking(file) => file.file_size(),
+            Self::Sequential(entry) => entry.file_size(),
+        }
+    }
+
+    fn has_directory_attribute(&self) -> bool {
+        match self {
+            Self::Seeking(file) => file.has_directory_attribute(),
+            Self::Sequential(entry) => entry.has_directory_attribute(),
+        }
+    }
+
+    fn is_dir(&self) -> bool {
+        match self {
+            Self::Seeking(file) => file.is_dir(),
+            Self::Sequential(entry) => entry.is_dir(),
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        match self {
+            Self::Seeking(file) => LocalFileOps::read(file, buf),
+            Self::Sequential(entry) => entry.read(buf),
+        }
+    }
+}
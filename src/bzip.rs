@@ -0,0 +1,91 @@
+//! BZIP2 (method 12) decompression backed by the `bzip2` crate
+
+use core::fmt;
+
+use bzip2::{Decompress, Status};
+
+use crate::{CompressMethod, Decompressor, ParsingError};
+
+/// Streaming BZIP2 decompressor.
+///
+/// The decompression state is allocated on the first entry, it takes up to 3.5 MiB
+/// for archives compressed with 900 KiB blocks (`zip -Z bzip2` does so).
+pub struct Bzip2Decoder {
+    stream: Option<Decompress>,
+    done: bool,
+}
+
+impl Bzip2Decoder {
+    pub fn new() -> Self {
+        Self {
+            stream: None,
+            done: false,
+        }
+    }
+
+    /// Get ready for another BZIP2 stream
+    pub fn reset(&mut self) {
+        self.stream = Some(Decompress::new(false));
+        self.done = false;
+    }
+
+    /// The end of the BZIP2 stream was reached
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Decompress `input` into `output`, returns `(consumed, produced)`.
+    ///
+    /// Decompressed data not fitting in `output` is kept for the next call, which may pass an
+    /// empty `input`. `last` tells that `input` ends the compressed data.
+    pub fn decompress(&mut self, input: &[u8], output: &mut [u8], last: bool) -> Result<(usize, usize), ParsingError> {
+        if self.done {
+            return Ok((0, 0));
+        }
+        let stream = self.stream.get_or_insert_with(|| Decompress::new(false));
+
+        let (total_in, total_out) = (stream.total_in(), stream.total_out());
+        let status = stream.decompress(input, output).map_err(|_| ParsingError::DecompressionFailed)?;
+        let consumed = (stream.total_in() - total_in) as usize;
+        let produced = (stream.total_out() - total_out) as usize;
+        self.done = status == Status::StreamEnd;
+
+        if last && !self.done && consumed == 0 && produced == 0 && !output.is_empty() {
+            // the stream is truncated
+            return Err(ParsingError::DecompressionFailed);
+        }
+        Ok((consumed, produced))
+    }
+}
+
+impl Default for Bzip2Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Bzip2Decoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bzip2Decoder")
+            .field("done", &self.done)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Decompressor for Bzip2Decoder {
+    fn init(&mut self, method: CompressMethod) -> Result<(), ParsingError> {
+        if method != CompressMethod::BZIP2 {
+            return Err(ParsingError::UnsupportedCompressMethod(method));
+        }
+        self.reset();
+        Ok(())
+    }
+
+    fn feed(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), ParsingError> {
+        self.decompress(input, output, false)
+    }
+
+    fn finish(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), ParsingError> {
+        self.decompress(input, output, true)
+    }
+}
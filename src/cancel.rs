@@ -0,0 +1,100 @@
+//! Aborting long reads from another thread or a UI callback
+
+use crate::{LocalFileOps, ParsingError, Read, Seek, SeekFrom};
+
+use core::str::Utf8Error;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Tells whether the ongoing work should be given up
+pub trait CancelToken {
+    fn is_cancelled(&self) -> bool;
+}
+
+impl CancelToken for AtomicBool {
+    fn is_cancelled(&self) -> bool {
+        self.load(Ordering::Relaxed)
+    }
+}
+
+impl<T: CancelToken + ?Sized> CancelToken for &T {
+    fn is_cancelled(&self) -> bool {
+        (**self).is_cancelled()
+    }
+}
+
+/// Cancellation asked by a callback, e.g. `CancelFn(|| deadline_passed())`
+pub struct CancelFn<F>(pub F);
+
+impl<F: Fn() -> bool> CancelToken for CancelFn<F> {
+    fn is_cancelled(&self) -> bool {
+        (self.0)()
+    }
+}
+
+/// Reader checking `token` before every read, failing with [`ParsingError::Cancelled`] once it is set.
+///
+/// Wraps an entry ([`LocalFileOps`]), so [`extract_with_buffer`](crate::extract_with_buffer) and
+/// the other entry helpers stop after the current chunk, or a stream ([`Read`]), e.g. the one
+/// of a [`HybridParser`](crate::HybridParser).
+pub struct Cancellable<R, C> {
+    inner: R,
+    token: C,
+}
+
+impl<R, C: CancelToken> Cancellable<R, C> {
+    pub fn new(inner: R, token: C) -> Self {
+        Self { inner, token }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn check(&self) -> Result<(), ParsingError> {
+        if self.token.is_cancelled() {
+            Err(ParsingError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<R: LocalFileOps, C: CancelToken> LocalFileOps for Cancellable<R, C> {
+    fn file_name(&self) -> Result<&str, Utf8Error> {
+        self.inner.file_name()
+    }
+
+    fn file_size(&self) -> u64 {
+        self.inner.file_size()
+    }
+
+    fn has_directory_attribute(&self) -> bool {
+        self.inner.has_directory_attribute()
+    }
+
+    fn is_dir(&self) -> bool {
+        self.inner.is_dir()
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        self.check()?;
+        self.inner.read(buf)
+    }
+}
+
+impl<R: Read, C: CancelToken> Read for Cancellable<R, C> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        self.check()?;
+        self.inner.read(buf)
+    }
+}
+
+impl<R: Seek, C: CancelToken> Seek for Cancellable<R, C> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, ParsingError> {
+        self.inner.seek(pos)
+    }
+
+    fn stream_len(&mut self) -> Option<u64> {
+        self.inner.stream_len()
+    }
+}
@@ -0,0 +1,91 @@
+//! Central directory records read once, the entries being opened from them as often as needed
+
+#[cfg(feature = "std")]
+use std::{collections::HashMap, string::String, vec::Vec};
+
+use crate::{EntryMetadata, LocalFileInfo, ParsingError, Read, Seek, SeekingParser};
+
+/// Central directory record of an entry, enough to open it with [`SeekingParser::open`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CentralEntry<const N: usize = 128> {
+    /// Name, compression method and sizes, the data offset being known once the entry is opened
+    pub info: LocalFileInfo<N>,
+    pub metadata: EntryMetadata,
+    /// Index of the entry in the central directory
+    pub file_index: i32,
+}
+
+/// All the records of the central directory, read up front so that the entries can then be
+/// opened in any order and any number of times with [`SeekingParser::open`].
+///
+/// Without `std`, at most `M` records are kept inline, mind the stack: each one holds a name of
+/// `N` bytes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CentralDirectory<const N: usize = 128, const M: usize = 64> {
+    #[cfg(feature = "std")]
+    entries: Vec<CentralEntry<N>>,
+    #[cfg(not(feature = "std"))]
+    entries: heapless::Vec<CentralEntry<N>, M>,
+    /// index of the first entry of each name, see [`CentralDirectory::with_name_index`]
+    #[cfg(feature = "std")]
+    names: Option<HashMap<String, usize>>,
+}
+
+impl<const N: usize, const M: usize> CentralDirectory<N, M> {
+    /// Read the records of the central directory found by `parser`, from the first one whatever
+    /// the entries it listed.
+    ///
+    /// Fails with the error stopping the listing, or with [`ParsingError::BufferTooSmall`] if
+    /// the archive has more than `M` entries without `std`.
+    pub fn read<S: Read + Seek>(parser: &SeekingParser<'_, S, N>) -> Result<Self, ParsingError> {
+        let mut records = parser.entries();
+        let mut directory = Self::default();
+        while let Some(entry) = records.next_record() {
+            #[cfg(feature = "std")]
+            directory.entries.push(entry);
+            #[cfg(not(feature = "std"))]
+            directory.entries.push(entry).map_err(|_| ParsingError::BufferTooSmall)?;
+        }
+        match records.last_error {
+            Some(context) => Err(context.error),
+            None => Ok(directory),
+        }
+    }
+
+    /// The records, in central directory order
+    pub fn entries(&self) -> &[CentralEntry<N>] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Index the records by name, so that [`CentralDirectory::get`] doesn't go through all of
+    /// them, for archives with many entries
+    #[cfg(feature = "std")]
+    pub fn with_name_index(mut self) -> Self {
+        let mut names = HashMap::with_capacity(self.entries.len());
+        for (i, entry) in self.entries.iter().enumerate() {
+            // the names which aren't UTF-8 can't be looked up anyway
+            if let Ok(name) = entry.info.file_name() {
+                names.entry(String::from(name)).or_insert(i);
+            }
+        }
+        self.names = Some(names);
+        self
+    }
+
+    /// The first record named `name`
+    pub fn get(&self, name: &str) -> Option<&CentralEntry<N>> {
+        #[cfg(feature = "std")]
+        if let Some(names) = &self.names {
+            return names.get(name).and_then(|&i| self.entries.get(i));
+        }
+        self.entries.iter().find(|entry| entry.info.file_name() == Ok(name))
+    }
+}
@@ -0,0 +1,79 @@
+//! Locating parsing errors in the stream, "InvalidSignature" alone doesn't say much about a large archive
+
+use core::cmp;
+use core::fmt::Display;
+use core::str::{self, Utf8Error};
+
+use crate::ParsingError;
+
+/// A [`ParsingError`] along with where it happened: the absolute stream offset and, when
+/// parsing an entry, its index and name truncated to `N` bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ErrorContext<const N: usize = 128> {
+    pub error: ParsingError,
+    /// Stream offset of the record or the data being parsed
+    pub offset: u64,
+    /// Index of the entry, `None` outside of the entries
+    pub file_index: Option<i32>,
+    file_name_buffer: [u8; N],
+    file_name_length: usize,
+}
+
+impl<const N: usize> ErrorContext<N> {
+    pub fn new(error: ParsingError, offset: u64) -> Self {
+        Self {
+            error,
+            offset,
+            file_index: None,
+            file_name_buffer: [0; N],
+            file_name_length: 0,
+        }
+    }
+
+    /// The error happened in the entry `file_index`, named `file_name`
+    pub fn with_entry(mut self, file_index: i32, file_name: &[u8]) -> Self {
+        let len = cmp::min(file_name.len(), N);
+        self.file_index = Some(file_index);
+        self.file_name_buffer[..len].copy_from_slice(&file_name[..len]);
+        self.file_name_length = len;
+        self
+    }
+
+    pub fn file_name(&self) -> Result<&str, Utf8Error> {
+        str::from_utf8(self.file_name_bytes())
+    }
+
+    /// Empty outside of the entries
+    pub fn file_name_bytes(&self) -> &[u8] {
+        &self.file_name_buffer[..self.file_name_length]
+    }
+}
+
+impl<const N: usize> Display for ErrorContext<N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} at offset {:#x}", self.error, self.offset)?;
+        if let Some(i) = self.file_index {
+            write!(f, ", entry #{}", i)?;
+            match self.file_name() {
+                Ok("") => {}
+                Ok(name) => write!(f, " ({})", name)?,
+                Err(_) => write!(f, " ({})", self.file_name_bytes().escape_ascii())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<const N: usize> std::error::Error for ErrorContext<N> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<const N: usize> defmt::Format for ErrorContext<N> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{}", defmt::Display2Format(self))
+    }
+}
@@ -0,0 +1,43 @@
+//! Code page 437, the encoding of the names and comments not flagged as UTF-8
+
+use crate::ParsingError;
+
+/// Characters of the bytes from 0x80, the lower ones being ASCII
+const CP437_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+/// Character of `byte` in code page 437
+pub(crate) fn cp437_char(byte: u8) -> char {
+    if byte < 0x80 {
+        byte as char
+    } else {
+        CP437_HIGH[byte as usize - 0x80]
+    }
+}
+
+/// Decode `bytes` from code page 437 into UTF-8 in `buf`.
+///
+/// Fails with [`ParsingError::BufferTooSmall`] if the text doesn't fit, every byte from 0x80
+/// taking 2 or 3 bytes once decoded.
+pub fn decode_cp437<'b>(bytes: &[u8], buf: &'b mut [u8]) -> Result<&'b str, ParsingError> {
+    let mut len = 0;
+    for &byte in bytes {
+        let c = cp437_char(byte);
+        let end = len + c.len_utf8();
+        if end > buf.len() {
+            return Err(ParsingError::BufferTooSmall);
+        }
+        c.encode_utf8(&mut buf[len..end]);
+        len = end;
+    }
+    // only whole characters were written
+    core::str::from_utf8(&buf[..len]).map_err(|_| ParsingError::Generic)
+}
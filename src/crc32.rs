@@ -0,0 +1,58 @@
+//! CRC-32 (IEEE 802.3) as used by the zip format
+
+const TABLE: [u32; 256] = make_table();
+
+const fn make_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { 0xEDB8_8320 ^ (crc >> 1) } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Continue the CRC `crc` (0 for no data yet) over `data`
+pub(crate) fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in data {
+        crc = crc32_step(crc, byte);
+    }
+    !crc
+}
+
+/// Shift `byte` into the CRC-32 register, without the inversions of [`crc32_update`]
+pub(crate) fn crc32_step(crc: u32, byte: u8) -> u32 {
+    TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+}
+
+/// CRC-32 computation of the entry data, e.g. by the CRC peripheral of a microcontroller.
+///
+/// It must compute the zip CRC-32: polynomial `0x04C11DB7` reflected, initial value and final
+/// XOR `0xFFFFFFFF`.
+pub trait Crc32: Sync {
+    /// Continue the CRC `crc` (0 for no data yet) over `data`
+    fn update(&self, crc: u32, data: &[u8]) -> u32;
+}
+
+impl core::fmt::Debug for dyn Crc32 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("Crc32")
+    }
+}
+
+/// Table driven CRC-32, used unless another [`Crc32`] is given
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SoftwareCrc32;
+
+impl Crc32 for SoftwareCrc32 {
+    fn update(&self, crc: u32, data: &[u8]) -> u32 {
+        crc32_update(crc, data)
+    }
+}
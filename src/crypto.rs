@@ -0,0 +1,84 @@
+//! Traditional PKWARE encryption (ZipCrypto), and the passwords to decrypt it
+
+use crate::crc32::crc32_step;
+
+/// Random header before the encrypted data, its last byte checks the password
+pub(crate) const ENCRYPTION_HEADER_LEN: usize = 12;
+/// Longest password a [`PasswordProvider`] can hand over
+pub const MAX_PASSWORD_LEN: usize = 128;
+
+/// Supplies the passwords of encrypted entries, asked again as long as the password is wrong
+pub trait PasswordProvider {
+    /// Copy the password to try on the entry named `name` into `password` and return its length,
+    /// or `None` to give up. `attempt` counts the wrong passwords already tried on the entry.
+    fn password(&mut self, name: &[u8], attempt: u32, password: &mut [u8]) -> Option<usize>;
+}
+
+impl<T: PasswordProvider + ?Sized> PasswordProvider for &mut T {
+    fn password(&mut self, name: &[u8], attempt: u32, password: &mut [u8]) -> Option<usize> {
+        (**self).password(name, attempt, password)
+    }
+}
+
+/// Passwords given by a callback, e.g. `PasswordFn(|_, attempt| passwords.get(attempt as usize))`
+pub struct PasswordFn<F>(pub F);
+
+impl<F, P> PasswordProvider for PasswordFn<F>
+where
+    F: FnMut(&[u8], u32) -> Option<P>,
+    P: AsRef<[u8]>,
+{
+    fn password(&mut self, name: &[u8], attempt: u32, password: &mut [u8]) -> Option<usize> {
+        let given = (self.0)(name, attempt)?;
+        let given = given.as_ref();
+        // a longer password is reported through its length
+        let len = given.len().min(password.len());
+        password[..len].copy_from_slice(&given[..len]);
+        Some(given.len())
+    }
+}
+
+/// ZipCrypto keys, updated with every decrypted byte
+#[derive(Debug, Clone)]
+pub(crate) struct ZipCrypto {
+    keys: [u32; 3],
+}
+
+impl ZipCrypto {
+    pub fn new(password: &[u8]) -> Self {
+        let mut cipher = Self {
+            keys: [0x1234_5678, 0x2345_6789, 0x3456_7890],
+        };
+        cipher.advance(password);
+        cipher
+    }
+
+    fn update_keys(&mut self, plain: u8) {
+        self.keys[0] = crc32_step(self.keys[0], plain);
+        self.keys[1] = self.keys[1]
+            .wrapping_add(self.keys[0] & 0xFF)
+            .wrapping_mul(134_775_813)
+            .wrapping_add(1);
+        self.keys[2] = crc32_step(self.keys[2], (self.keys[1] >> 24) as u8);
+    }
+
+    fn key_byte(&self) -> u8 {
+        let temp = (self.keys[2] | 2) & 0xFFFF;
+        ((temp * (temp ^ 1)) >> 8) as u8
+    }
+
+    /// Decrypt `data` in place
+    pub fn decrypt(&mut self, data: &mut [u8]) {
+        for byte in data {
+            *byte ^= self.key_byte();
+            self.update_keys(*byte);
+        }
+    }
+
+    /// Update the keys as if the decrypted bytes `plain` went through [`ZipCrypto::decrypt`]
+    pub fn advance(&mut self, plain: &[u8]) {
+        for &byte in plain {
+            self.update_keys(byte);
+        }
+    }
+}
@@ -0,0 +1,44 @@
+//! Conversions of entry times to the dates of the `time` and `chrono` crates
+
+use crate::{DosDateTime, UnixTime};
+
+#[cfg(feature = "time")]
+impl DosDateTime {
+    /// Date and time in the time zone of the archiver, which DOS times don't record.
+    /// `None` if the value is not a valid date, e.g. a month 0.
+    pub fn to_primitive_date_time(&self) -> Option<time::PrimitiveDateTime> {
+        let month = time::Month::try_from(self.month()).ok()?;
+        let date = time::Date::from_calendar_date(self.year() as i32, month, self.day()).ok()?;
+        let time = time::Time::from_hms(self.hour(), self.minute(), self.second()).ok()?;
+        Some(time::PrimitiveDateTime::new(date, time))
+    }
+}
+
+#[cfg(feature = "time")]
+impl UnixTime {
+    /// `None` if out of the range of `time`
+    pub fn to_offset_date_time(&self) -> Option<time::OffsetDateTime> {
+        time::OffsetDateTime::from_unix_timestamp(self.seconds)
+            .ok()?
+            .replace_nanosecond(self.nanoseconds)
+            .ok()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl DosDateTime {
+    /// Date and time in the time zone of the archiver, which DOS times don't record.
+    /// `None` if the value is not a valid date, e.g. a month 0.
+    pub fn to_naive_date_time(&self) -> Option<chrono::NaiveDateTime> {
+        chrono::NaiveDate::from_ymd_opt(self.year() as i32, self.month() as u32, self.day() as u32)?
+            .and_hms_opt(self.hour() as u32, self.minute() as u32, self.second() as u32)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl UnixTime {
+    /// Date and time in UTC, `None` if out of the range of `chrono`
+    pub fn to_naive_date_time(&self) -> Option<chrono::NaiveDateTime> {
+        chrono::DateTime::from_timestamp(self.seconds, self.nanoseconds).map(|time| time.naive_utc())
+    }
+}
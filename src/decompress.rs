@@ -0,0 +1,170 @@
+//! Pluggable decompression of entry data
+
+use crate::{CompressMethod, ParserEvent, ParsingError};
+
+/// Decompressor of a compression method, e.g. a hardware block or a firmware routine.
+///
+/// Built-in ones are behind features (`Inflater` with `inflate`), others are plugged in with
+/// [`Decompressors::register`].
+pub trait Decompressor {
+    /// Get ready for a new entry compressed with `method`
+    fn init(&mut self, method: CompressMethod) -> Result<(), ParsingError>;
+
+    /// Decompress `input` into `output`, more input follows. Returns `(consumed, produced)`.
+    ///
+    /// Output not fitting in `output` is kept for the next call, which may pass an empty `input`.
+    fn feed(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), ParsingError>;
+
+    /// Same as [`Decompressor::feed`], but `input` ends the compressed data.
+    ///
+    /// Call it again until nothing is produced to get all the output.
+    fn finish(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), ParsingError>;
+}
+
+/// Decompressors to use for each compression method, holding up to `M` of them
+pub struct Decompressors<'d, const M: usize = 4> {
+    entries: heapless::Vec<(CompressMethod, &'d mut dyn Decompressor), M>,
+}
+
+impl<'d, const M: usize> Decompressors<'d, M> {
+    pub fn new() -> Self {
+        Self { entries: heapless::Vec::new() }
+    }
+
+    /// Use `decompressor` for `method`, replacing the previous one if any.
+    ///
+    /// Fails with [`ParsingError::BufferTooSmall`] when `M` decompressors are already registered.
+    pub fn register(&mut self, method: CompressMethod, decompressor: &'d mut dyn Decompressor) -> Result<(), ParsingError> {
+        if let Some(entry) = self.entries.iter_mut().find(|(m, _)| *m == method) {
+            entry.1 = decompressor;
+            return Ok(());
+        }
+        self.entries
+            .push((method, decompressor))
+            .map_err(|_| ParsingError::BufferTooSmall)
+    }
+
+    pub fn get(&mut self, method: CompressMethod) -> Option<&mut (dyn Decompressor + 'd)> {
+        self.entries
+            .iter_mut()
+            .find(|(m, _)| *m == method)
+            .map(|(_, d)| &mut **d)
+    }
+}
+
+impl<'d, const M: usize> Default for Decompressors<'d, M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Event adapter decompressing the `LocalFileData` of [`PassiveParser`](crate::PassiveParser)
+/// entries having a registered decompressor, through an output buffer of `B` bytes.
+///
+/// The `offset` of the forwarded data events is then the offset in the decompressed data.
+/// Entries without decompressor are forwarded untouched, decompression errors are reported
+/// as `ParsingError` events, as well as output going past the declared uncompressed size.
+pub struct DecompressingHandler<'d, const M: usize = 4, const B: usize = 1024> {
+    decompressors: Decompressors<'d, M>,
+    output: [u8; B],
+    /// the current entry is decompressed
+    active: Option<CompressMethod>,
+    offset: usize,
+    /// declared uncompressed size of the current entry, unknown until its data descriptor
+    uncompressed_size: Option<u64>,
+}
+
+impl<'d, const M: usize, const B: usize> DecompressingHandler<'d, M, B> {
+    pub fn new(decompressors: Decompressors<'d, M>) -> Self {
+        Self {
+            decompressors,
+            output: [0; B],
+            active: None,
+            offset: 0,
+            uncompressed_size: None,
+        }
+    }
+
+    /// Handle a parser event, calling `on_event` with the decompressed events.
+    ///
+    /// Returns false as soon as `on_event` does, to be returned to the parser.
+    pub fn handle<const N: usize, F>(&mut self, evt: ParserEvent<'_, '_, N>, mut on_event: F) -> bool
+    where
+        F: for<'b, 'c> FnMut(ParserEvent<'b, 'c, N>) -> bool,
+    {
+        match evt {
+            ParserEvent::LocalFileHeader(file_index, info) => {
+                self.active = None;
+                self.offset = 0;
+                // both are zero in the local header when a data descriptor follows the data
+                let sizes_known = info.compressed_size > 0 || info.uncompressed_size > 0;
+                self.uncompressed_size = sizes_known.then_some(info.uncompressed_size);
+                if let Some(decompressor) = self.decompressors.get(info.compression_method) {
+                    match decompressor.init(info.compression_method) {
+                        Ok(()) => self.active = Some(info.compression_method),
+                        Err(err) => return on_event(ParserEvent::ParsingError(file_index, err)) && on_event(evt),
+                    }
+                }
+                on_event(evt)
+            }
+            ParserEvent::LocalFileData { file_index, data, .. } if self.active.is_some() => {
+                self.decompress(file_index, data, false, &mut on_event)
+            }
+            ParserEvent::LocalFileDataDescriptor(file_index, descriptor) if self.active.is_some() => {
+                self.uncompressed_size = Some(descriptor.uncompressed_size);
+                if self.offset as u64 > descriptor.uncompressed_size {
+                    self.active = None;
+                    let err = ParsingError::UncompressedSizeExceeded(descriptor.uncompressed_size);
+                    return on_event(ParserEvent::ParsingError(file_index, err)) && on_event(evt);
+                }
+                on_event(evt)
+            }
+            ParserEvent::LocalFileEnd(file_index) if self.active.is_some() => {
+                self.decompress(file_index, &[], true, &mut on_event) && on_event(evt)
+            }
+            _ => on_event(evt),
+        }
+    }
+
+    fn decompress<const N: usize, F>(&mut self, file_index: i32, mut input: &[u8], last: bool, on_event: &mut F) -> bool
+    where
+        F: for<'b, 'c> FnMut(ParserEvent<'b, 'c, N>) -> bool,
+    {
+        let Some(method) = self.active else {
+            return true;
+        };
+        let Some(decompressor) = self.decompressors.get(method) else {
+            return true;
+        };
+        loop {
+            let res = if last {
+                decompressor.finish(input, &mut self.output)
+            } else {
+                decompressor.feed(input, &mut self.output)
+            };
+            let (consumed, produced) = match res {
+                Ok(res) => res,
+                Err(err) => {
+                    // give up on this entry
+                    self.active = None;
+                    return on_event(ParserEvent::ParsingError(file_index, err));
+                }
+            };
+            input = &input[consumed..];
+            if let Some(size) = self.uncompressed_size.filter(|&size| (self.offset + produced) as u64 > size) {
+                // give up on this entry as well
+                self.active = None;
+                return on_event(ParserEvent::ParsingError(file_index, ParsingError::UncompressedSizeExceeded(size)));
+            }
+            if produced > 0 {
+                let offset = self.offset;
+                self.offset += produced;
+                if !on_event(ParserEvent::LocalFileData { file_index, offset, data: &self.output[..produced] }) {
+                    return false;
+                }
+            } else if input.is_empty() || consumed == 0 {
+                return true;
+            }
+        }
+    }
+}
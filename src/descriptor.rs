@@ -0,0 +1,163 @@
+//! Data descriptors, following the data of the entries whose sizes were unknown when the local
+//! header was written (general purpose bit 3)
+
+use core::cmp;
+
+use crate::{le_u32, Crc32, ParsingError, Read, Seek, SeekFrom};
+
+/// Optional signature of a data descriptor
+pub(crate) const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x08074b50;
+/// Longest data descriptor: signature, CRC-32 and ZIP64 sizes
+pub const MAX_DATA_DESCRIPTOR_LEN: usize = 24;
+
+/// CRC-32 and sizes of an entry, written after its data
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct DataDescriptor {
+    pub crc32: u32,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+}
+
+impl DataDescriptor {
+    /// Decode a descriptor without its signature, with 8 bytes sizes if `zip64`
+    pub(crate) fn from_bytes(bytes: &[u8], zip64: bool) -> Self {
+        let size = |index: usize| if zip64 {
+            (le_u32(bytes, 4 + index * 8) as u64) | (le_u32(bytes, 8 + index * 8) as u64) << 32
+        } else {
+            le_u32(bytes, 4 + index * 4) as u64
+        };
+        Self {
+            crc32: le_u32(bytes, 0),
+            compressed_size: size(0),
+            uncompressed_size: size(1),
+        }
+    }
+
+    /// Read the descriptor at `offset` of `stream`, right after the entry data
+    pub(crate) fn read_from<S: Read + Seek>(stream: &mut S, offset: u64, zip64: bool) -> Result<Self, ParsingError> {
+        stream.seek(SeekFrom::Start(offset))?;
+        // the central directory follows, so the longest form can be read either way
+        let len = if zip64 { MAX_DATA_DESCRIPTOR_LEN } else { 16 };
+        let mut buf = [0u8; MAX_DATA_DESCRIPTOR_LEN];
+        let mut i = 0;
+        while i < len {
+            match stream.read(&mut buf[i..len])? {
+                0 => return Err(ParsingError::StreamEnding),
+                n => i += n,
+            }
+        }
+        let record = if le_u32(&buf, 0) == DATA_DESCRIPTOR_SIGNATURE { &buf[4..] } else { &buf[..] };
+        Ok(Self::from_bytes(record, zip64))
+    }
+}
+
+/// Where the data descriptor of an entry was found in the data fed to [`DescriptorScanner::scan`]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Scan {
+    /// leading held back bytes which are file data
+    pub held_data: usize,
+    /// leading input bytes which are file data
+    pub input_data: usize,
+    /// input bytes to consume, the descriptor included
+    pub consumed: usize,
+    pub descriptor: Option<DataDescriptor>,
+}
+
+/// Looks for the data descriptor ending the data of an entry of unknown size.
+///
+/// The last bytes received may be the descriptor, so they are held back until the following
+/// bytes tell otherwise. A descriptor, with or without its signature, is only accepted if its
+/// compressed size matches the data before it. The CRC-32 covers the uncompressed plaintext, so
+/// it is only checked as well for stored entries which are not encrypted.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DescriptorScanner {
+    /// bytes held back, following the file data handed out so far
+    pub held: heapless::Vec<u8, MAX_DATA_DESCRIPTOR_LEN>,
+    /// the sizes take 8 bytes, as the local header had the ZIP64 extended information
+    pub zip64: bool,
+    /// the entry is stored and not encrypted, its data is what the CRC-32 covers
+    pub stored: bool,
+}
+
+impl DescriptorScanner {
+    /// Get ready for another entry
+    pub fn reset(&mut self, zip64: bool, stored: bool) {
+        self.held.clear();
+        self.zip64 = zip64;
+        self.stored = stored;
+    }
+
+    /// Descriptor lengths, with and without the signature
+    fn descriptor_lens(&self) -> (usize, usize) {
+        let unsigned = if self.zip64 { 20 } else { 12 };
+        (unsigned + 4, unsigned)
+    }
+
+    /// Look for the descriptor in the held back bytes followed by `input`, `data_len` and `crc32`
+    /// being those of the file data handed out so far, `engine` computing the CRC32.
+    ///
+    /// Once the data before the returned `held_data` and `input_data` is handed out, call
+    /// [`DescriptorScanner::advance`] unless a descriptor was found.
+    pub fn scan(&self, data_len: u64, crc32: u32, input: &[u8], engine: &dyn Crc32) -> Scan {
+        let held = self.held.len();
+        let byte = |index: usize| if index < held { self.held[index] } else { input[index - held] };
+        let (signed_len, unsigned_len) = self.descriptor_lens();
+
+        for end in held + 1..=held + input.len() {
+            for (len, signed) in [(signed_len, true), (unsigned_len, false)] {
+                if end < len {
+                    continue;
+                }
+                let mut record = [0u8; MAX_DATA_DESCRIPTOR_LEN];
+                for (i, b) in record[..len].iter_mut().enumerate() {
+                    *b = byte(end - len + i);
+                }
+                let record = if signed {
+                    if le_u32(&record, 0) != DATA_DESCRIPTOR_SIGNATURE {
+                        continue;
+                    }
+                    &record[4..len]
+                } else {
+                    &record[..len]
+                };
+
+                let descriptor = DataDescriptor::from_bytes(record, self.zip64);
+                let data_end = end - len;
+                if descriptor.compressed_size != data_len + data_end as u64 {
+                    continue;
+                }
+                let held_data = cmp::min(held, data_end);
+                let input_data = data_end - held_data;
+                let matches = !self.stored || descriptor.uncompressed_size == descriptor.compressed_size
+                    && engine.update(engine.update(crc32, &self.held[..held_data]), &input[..input_data]) == descriptor.crc32;
+                if matches {
+                    return Scan {
+                        held_data,
+                        input_data,
+                        consumed: end - held,
+                        descriptor: Some(descriptor),
+                    };
+                }
+            }
+        }
+
+        // hold back what may start a descriptor
+        let data_end = (held + input.len()).saturating_sub(signed_len);
+        let held_data = cmp::min(held, data_end);
+        Scan {
+            held_data,
+            input_data: data_end - held_data,
+            consumed: input.len(),
+            descriptor: None,
+        }
+    }
+
+    /// Drop the `held_data` bytes handed out, and hold back the input bytes scanned past the data
+    pub fn advance(&mut self, held_data: usize, held_input: &[u8]) {
+        let remaining = self.held.len() - held_data;
+        self.held.copy_within(held_data.., 0);
+        self.held.truncate(remaining);
+        // never more than the longest descriptor
+        let _ = self.held.extend_from_slice(held_input);
+    }
+}
@@ -0,0 +1,18 @@
+//! Diagnostics of the parsers, through `log` or `defmt` when enabled and dropped otherwise
+
+/// Emit a diagnostic at `$level` (`error`, `warn`, `info`, `debug` or `trace`).
+///
+/// The message must be understood by both `log` and `defmt`: plain `{}` placeholders, and
+/// arguments implementing `Display` and `defmt::Format`, e.g. integers.
+macro_rules! diag {
+    ($level:ident, $fmt:literal $(, $arg:expr)* $(,)?) => {{
+        #[cfg(feature = "log")]
+        log::$level!($fmt $(, $arg)*);
+        #[cfg(feature = "defmt")]
+        defmt::$level!($fmt $(, $arg)*);
+        #[cfg(not(any(feature = "log", feature = "defmt")))]
+        {
+            $(let _ = &$arg;)*
+        }
+    }};
+}
@@ -0,0 +1,411 @@
+//! Records of the extra fields of the local and central directory file headers
+
+use core::cmp;
+
+use crate::{crc32_update, le_u16, le_u32, CompressMethod, ParserEvent, ParsingError, Read, Seek, SeekFrom};
+
+/// Extra field bytes kept by [`PassiveParser`](crate::PassiveParser) and
+/// [`HybridParser`](crate::HybridParser) for their extra field events
+pub const MAX_EXTRA_FIELD_LEN: usize = 256;
+/// Header ID and data size of a record
+const RECORD_HEADER_LEN: usize = 4;
+/// Header ID of the extended timestamp extra field, written by Info-ZIP on Unix
+const EXTENDED_TIMESTAMP_EXTRA_FIELD_ID: u16 = 0x5455;
+/// Header ID of the NTFS extra field, written by Windows archivers
+const NTFS_EXTRA_FIELD_ID: u16 = 0x000A;
+/// Header ID of the Info-ZIP Unix extra field holding the owner, with sizes of any length
+const UNIX_OWNER_EXTRA_FIELD_ID: u16 = 0x7875;
+/// Header ID of the Info-ZIP Unicode path extra field
+const UNICODE_PATH_EXTRA_FIELD_ID: u16 = 0x7075;
+/// Header ID of the Info-ZIP Unicode comment extra field
+const UNICODE_COMMENT_EXTRA_FIELD_ID: u16 = 0x6375;
+/// Header ID of the WinZip AES extra field
+const AES_EXTRA_FIELD_ID: u16 = 0x9901;
+/// Tag of the NTFS attribute holding the modification, access and creation times
+const NTFS_TIMES_TAG: u16 = 0x0001;
+/// Seconds between 1601-01-01, the NTFS epoch, and 1970-01-01
+const NTFS_EPOCH_OFFSET: i64 = 11_644_473_600;
+/// NTFS times count 100 nanoseconds intervals
+const NTFS_TICKS_PER_SECOND: u64 = 10_000_000;
+
+/// Moment relative to the Unix epoch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UnixTime {
+    /// Seconds since 1970-01-01 UTC, negative before
+    pub seconds: i64,
+    /// Nanoseconds after `seconds`, below one billion
+    pub nanoseconds: u32,
+}
+
+impl UnixTime {
+    /// Convert an NTFS time, the number of 100 nanoseconds intervals since 1601-01-01 UTC
+    pub fn from_ntfs(ticks: u64) -> Self {
+        Self {
+            seconds: (ticks / NTFS_TICKS_PER_SECOND) as i64 - NTFS_EPOCH_OFFSET,
+            nanoseconds: (ticks % NTFS_TICKS_PER_SECOND) as u32 * 100,
+        }
+    }
+}
+
+/// Times of the extended timestamp extra field (header ID 0x5455), with a 1 second resolution.
+///
+/// The central directory one only has the modification time, even when its flags tell otherwise.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ExtendedTimestamp {
+    pub modified: Option<UnixTime>,
+    pub accessed: Option<UnixTime>,
+    pub created: Option<UnixTime>,
+}
+
+impl ExtendedTimestamp {
+    /// Decode the data of an extended timestamp extra field record, `None` if it is empty
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        let (&flags, mut times) = data.split_first()?;
+        let mut time = |bit: u8| {
+            if flags & (1 << bit) == 0 || times.len() < 4 {
+                return None;
+            }
+            let seconds = le_u32(times, 0) as i32 as i64;
+            times = &times[4..];
+            Some(UnixTime { seconds, nanoseconds: 0 })
+        };
+        Some(Self {
+            modified: time(0),
+            accessed: time(1),
+            created: time(2),
+        })
+    }
+}
+
+/// Times of the NTFS extra field (header ID 0x000A), with a 100 nanoseconds resolution
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct NtfsTimestamps {
+    pub modified: UnixTime,
+    pub accessed: UnixTime,
+    pub created: UnixTime,
+}
+
+impl NtfsTimestamps {
+    /// Decode the data of an NTFS extra field record, `None` if it has no times attribute
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        // 4 reserved bytes, then the attributes
+        let mut attributes = data.get(4..)?;
+        while let Some(header) = attributes.get(..RECORD_HEADER_LEN) {
+            let len = RECORD_HEADER_LEN + le_u16(header, 2) as usize;
+            let attribute = attributes.get(..len)?;
+            if le_u16(header, 0) == NTFS_TIMES_TAG {
+                if attribute.len() < RECORD_HEADER_LEN + 24 {
+                    return None;
+                }
+                let time = |index: usize| {
+                    let offset = RECORD_HEADER_LEN + index * 8;
+                    let ticks = (le_u32(attribute, offset) as u64) | (le_u32(attribute, offset + 4) as u64) << 32;
+                    UnixTime::from_ntfs(ticks)
+                };
+                return Some(Self {
+                    modified: time(0),
+                    accessed: time(1),
+                    created: time(2),
+                });
+            }
+            attributes = &attributes[len..];
+        }
+        None
+    }
+}
+
+/// Iterator over the `(header_id, data)` records of an extra field.
+///
+/// A record running past the end of the extra field yields
+/// [`ParsingError::InvalidExtraField`] with its offset, and ends the iteration.
+#[derive(Debug, Clone, Copy)]
+pub struct ExtraFields<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+/// Owner of an entry, from the Info-ZIP Unix extra field (header ID 0x7875)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct UnixOwner {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl UnixOwner {
+    /// Decode the data of a Unix extra field record, `None` if its version is unknown, it is
+    /// truncated or an ID doesn't fit in 32 bits
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        // version 1, then the size and the value of each ID
+        if data.first() != Some(&1) {
+            return None;
+        }
+        let mut rest = &data[1..];
+        let mut id = || {
+            let (&size, value) = rest.split_first()?;
+            let value = value.get(..size as usize)?;
+            rest = &rest[1 + size as usize..];
+            let mut id = 0u32;
+            for (i, &b) in value.iter().enumerate() {
+                if i >= 4 {
+                    if b != 0 {
+                        return None;
+                    }
+                } else {
+                    id |= (b as u32) << (i * 8);
+                }
+            }
+            Some(id)
+        };
+        let uid = id()?;
+        let gid = id()?;
+        Some(Self { uid, gid })
+    }
+}
+
+/// Key length of a WinZip AES encrypted entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AesStrength {
+    Aes128 = 1,
+    Aes192 = 2,
+    Aes256 = 3,
+}
+
+impl AesStrength {
+    pub fn key_bits(&self) -> u16 {
+        match self {
+            Self::Aes128 => 128,
+            Self::Aes192 => 192,
+            Self::Aes256 => 256,
+        }
+    }
+}
+
+/// Parameters of a WinZip AES encrypted entry, from the AES extra field (header ID 0x9901)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AesExtraField {
+    /// 1 for AE-1, 2 for AE-2 whose CRC-32 is left out
+    pub vendor_version: u16,
+    /// "AE"
+    pub vendor_id: [u8; 2],
+    pub strength: AesStrength,
+    /// Compression method of the data once decrypted
+    pub compression_method: CompressMethod,
+}
+
+impl AesExtraField {
+    /// Decode the data of an AES extra field record, `None` if it is too short or its strength
+    /// is unknown
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        if data.len() < 7 {
+            return None;
+        }
+        let strength = match data[4] {
+            1 => AesStrength::Aes128,
+            2 => AesStrength::Aes192,
+            3 => AesStrength::Aes256,
+            _ => return None,
+        };
+        Some(Self {
+            vendor_version: le_u16(data, 0),
+            vendor_id: [data[2], data[3]],
+            strength,
+            compression_method: CompressMethod::from(le_u16(data, 5)),
+        })
+    }
+}
+
+impl<'a> ExtraFields<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    /// The raw extra field
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Data of the first record with `header_id`, if it is well-formed
+    pub fn find(&self, header_id: u16) -> Option<&'a [u8]> {
+        (*self)
+            .map_while(Result::ok)
+            .find(|(id, _)| *id == header_id)
+            .map(|(_, data)| data)
+    }
+
+    /// UTF-8 name of the Info-ZIP Unicode path extra field, if there is one written for
+    /// `header_name`, the name as stored in the header
+    pub fn unicode_path(&self, header_name: &[u8]) -> Option<&'a [u8]> {
+        self.find(UNICODE_PATH_EXTRA_FIELD_ID)
+            .and_then(|data| unicode_data(data, header_name))
+    }
+
+    /// UTF-8 comment of the Info-ZIP Unicode comment extra field, if there is one written for
+    /// `header_comment`, the file comment as stored in the central directory file header
+    pub fn unicode_comment(&self, header_comment: &[u8]) -> Option<&'a [u8]> {
+        self.find(UNICODE_COMMENT_EXTRA_FIELD_ID)
+            .and_then(|data| unicode_data(data, header_comment))
+    }
+
+    /// Parameters of the AES extra field, if there is one
+    pub fn aes(&self) -> Option<AesExtraField> {
+        self.find(AES_EXTRA_FIELD_ID).and_then(AesExtraField::from_bytes)
+    }
+
+    /// Owner of the Unix extra field, if there is one
+    pub fn unix_owner(&self) -> Option<UnixOwner> {
+        self.find(UNIX_OWNER_EXTRA_FIELD_ID).and_then(UnixOwner::from_bytes)
+    }
+
+    /// Times of the extended timestamp extra field, if there is one
+    pub fn extended_timestamp(&self) -> Option<ExtendedTimestamp> {
+        self.find(EXTENDED_TIMESTAMP_EXTRA_FIELD_ID).and_then(ExtendedTimestamp::from_bytes)
+    }
+
+    /// Times of the NTFS extra field, if there is one with the times attribute
+    pub fn ntfs_timestamps(&self) -> Option<NtfsTimestamps> {
+        self.find(NTFS_EXTRA_FIELD_ID).and_then(NtfsTimestamps::from_bytes)
+    }
+}
+
+impl<'a> Iterator for ExtraFields<'a> {
+    type Item = Result<(u16, &'a [u8]), ParsingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let remaining = &self.data[self.offset..];
+        if remaining.is_empty() {
+            return None;
+        }
+        let len = match remaining.get(..RECORD_HEADER_LEN) {
+            Some(header) => RECORD_HEADER_LEN + le_u16(header, 2) as usize,
+            None => usize::MAX,
+        };
+        let offset = self.offset;
+        match remaining.get(..len) {
+            Some(record) => {
+                self.offset += len;
+                Some(Ok((le_u16(record, 0), &record[RECORD_HEADER_LEN..])))
+            }
+            None => {
+                self.offset = self.data.len();
+                Some(Err(ParsingError::InvalidExtraField(offset)))
+            }
+        }
+    }
+}
+
+/// Header the extra field of an event comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExtraFieldLocation {
+    LocalFileHeader,
+    CentralFileHeader,
+}
+
+/// Decoder of the records of an extra field, e.g. a vendor-specific one, plugged in with
+/// [`ExtraFieldHandlers::register`]
+pub trait ExtraFieldHandler {
+    /// Decode the `data` of a record of entry `file_index`
+    fn handle(&mut self, file_index: i32, location: ExtraFieldLocation, data: &[u8]) -> Result<(), ParsingError>;
+}
+
+/// Record decoding by a callback, e.g. `ExtraFieldFn(|index, location, data| { ...; Ok(()) })`
+pub struct ExtraFieldFn<F>(pub F);
+
+impl<F> ExtraFieldHandler for ExtraFieldFn<F>
+where
+    F: FnMut(i32, ExtraFieldLocation, &[u8]) -> Result<(), ParsingError>,
+{
+    fn handle(&mut self, file_index: i32, location: ExtraFieldLocation, data: &[u8]) -> Result<(), ParsingError> {
+        (self.0)(file_index, location, data)
+    }
+}
+
+/// Event adapter handing the extra field records of [`PassiveParser`](crate::PassiveParser) or
+/// [`HybridParser`](crate::HybridParser) entries to the handler registered for their header ID,
+/// holding up to `M` handlers.
+///
+/// The events are forwarded untouched, handler errors are reported as `ParsingError` events
+/// right before the extra field event.
+pub struct ExtraFieldHandlers<'h, const M: usize = 4> {
+    entries: heapless::Vec<(u16, &'h mut dyn ExtraFieldHandler), M>,
+}
+
+impl<'h, const M: usize> ExtraFieldHandlers<'h, M> {
+    pub fn new() -> Self {
+        Self { entries: heapless::Vec::new() }
+    }
+
+    /// Use `handler` for the records with `header_id`, replacing the previous one if any.
+    ///
+    /// Fails with [`ParsingError::BufferTooSmall`] when `M` handlers are already registered.
+    pub fn register(&mut self, header_id: u16, handler: &'h mut dyn ExtraFieldHandler) -> Result<(), ParsingError> {
+        if let Some(entry) = self.entries.iter_mut().find(|(id, _)| *id == header_id) {
+            entry.1 = handler;
+            return Ok(());
+        }
+        self.entries
+            .push((header_id, handler))
+            .map_err(|_| ParsingError::BufferTooSmall)
+    }
+
+    /// Handle a parser event, then forward it to `on_event`.
+    ///
+    /// Returns false as soon as `on_event` does, to be returned to the parser.
+    pub fn handle<const N: usize, F>(&mut self, evt: ParserEvent<'_, '_, N>, mut on_event: F) -> bool
+    where
+        F: for<'b, 'c> FnMut(ParserEvent<'b, 'c, N>) -> bool,
+    {
+        let (file_index, location, fields) = match evt {
+            ParserEvent::LocalFileExtraField(i, fields) => (i, ExtraFieldLocation::LocalFileHeader, fields),
+            ParserEvent::CentralFileExtraField(i, fields) => (i, ExtraFieldLocation::CentralFileHeader, fields),
+            _ => return on_event(evt),
+        };
+        // malformed records are left to the extra field event
+        for (header_id, data) in fields.map_while(Result::ok) {
+            let Some((_, handler)) = self.entries.iter_mut().find(|(id, _)| *id == header_id) else {
+                continue;
+            };
+            if let Err(err) = handler.handle(file_index, location, data) {
+                if !on_event(ParserEvent::ParsingError(file_index, err)) {
+                    return false;
+                }
+            }
+        }
+        on_event(evt)
+    }
+}
+
+impl<'h, const M: usize> Default for ExtraFieldHandlers<'h, M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// UTF-8 value of an Info-ZIP Unicode extra field record, if its version is known and its
+/// CRC-32 matches `header_value`. A mismatch means the header value was changed by a tool
+/// unaware of the extra field, which is then stale.
+fn unicode_data<'a>(data: &'a [u8], header_value: &[u8]) -> Option<&'a [u8]> {
+    // version 1, then the CRC-32 of the header value
+    if data.len() < 5 || data[0] != 1 || le_u32(data, 1) != crc32_update(0, header_value) {
+        return None;
+    }
+    Some(&data[5..])
+}
+
+/// Read the `len` bytes at `offset` of `stream` into `buf`, as much as fits, e.g. an extra field
+pub(crate) fn read_at<'b, S: Read + Seek>(
+    stream: &mut S,
+    offset: u64,
+    len: usize,
+    buf: &'b mut [u8],
+) -> Result<&'b [u8], ParsingError> {
+    let len = cmp::min(len, buf.len());
+    stream.seek(SeekFrom::Start(offset))?;
+    let mut i = 0;
+    while i < len {
+        match stream.read(&mut buf[i..len])? {
+            0 => return Err(ParsingError::StreamEnding),
+            n => i += n,
+        }
+    }
+    Ok(&buf[..len])
+}
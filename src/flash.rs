@@ -0,0 +1,135 @@
+//! Extracting entries straight into NOR flash through `embedded-storage`
+
+use core::cmp;
+
+use embedded_storage::nor_flash::{NorFlash, NorFlashError, NorFlashErrorKind};
+
+use crate::{extract_with_buffer, LocalFileOps, ParsingError};
+
+impl From<NorFlashErrorKind> for ParsingError {
+    fn from(kind: NorFlashErrorKind) -> Self {
+        ParsingError::Flash(kind)
+    }
+}
+
+/// Sequential writer of entry data into NOR flash.
+///
+/// Erases the flash lazily one erase block ahead of the data, and keeps up to `B` bytes
+/// buffered so every flash write is aligned to `WRITE_SIZE`. `B` must be a multiple of the
+/// flash `WRITE_SIZE`. Feed it the data of [`ParserEvent::LocalFileData`](crate::ParserEvent)
+/// events, or use [`extract_entry_to_flash`] for entries of a seeking parser.
+pub struct FlashWriter<'f, F: NorFlash, const B: usize = 256> {
+    flash: &'f mut F,
+    base_address: u32,
+    /// bytes written to the flash, not counting the buffered ones
+    written: u32,
+    /// flash is erased from `base_address` up to here
+    erased_end: u32,
+    buffer: [u8; B],
+    buffer_len: usize,
+}
+
+impl<'f, F: NorFlash, const B: usize> FlashWriter<'f, F, B> {
+    /// `base_address` must be aligned to the flash `ERASE_SIZE`
+    pub fn new(flash: &'f mut F, base_address: u32) -> Result<Self, ParsingError> {
+        if (base_address as usize) % F::ERASE_SIZE != 0 || B == 0 || B % F::WRITE_SIZE != 0 {
+            return Err(NorFlashErrorKind::NotAligned.into());
+        }
+        Ok(Self {
+            flash,
+            base_address,
+            written: 0,
+            erased_end: base_address,
+            buffer: [0; B],
+            buffer_len: 0,
+        })
+    }
+
+    /// Bytes accepted so far
+    pub fn position(&self) -> u64 {
+        self.written as u64 + self.buffer_len as u64
+    }
+
+    pub fn write(&mut self, mut data: &[u8]) -> Result<(), ParsingError> {
+        if self.buffer_len > 0 {
+            let len = cmp::min(B - self.buffer_len, data.len());
+            self.buffer[self.buffer_len..self.buffer_len + len].copy_from_slice(&data[..len]);
+            self.buffer_len += len;
+            data = &data[len..];
+            if self.buffer_len < B {
+                return Ok(());
+            }
+            self.write_buffer()?;
+        }
+
+        // aligned data goes to the flash without copying
+        let aligned = data.len() - data.len() % F::WRITE_SIZE;
+        if aligned >= B {
+            self.program(0, aligned, data)?;
+            data = &data[aligned..];
+        }
+
+        self.buffer[..data.len()].copy_from_slice(data);
+        self.buffer_len = data.len();
+        Ok(())
+    }
+
+    /// Write out the buffered data, padding the last write unit with `0xFF`.
+    ///
+    /// Returns the number of data bytes written, padding not included.
+    pub fn finish(mut self) -> Result<u64, ParsingError> {
+        let len = self.position();
+        let padded = self.buffer_len.div_ceil(F::WRITE_SIZE) * F::WRITE_SIZE;
+        self.buffer[self.buffer_len..padded].fill(0xFF);
+        self.buffer_len = padded;
+        self.write_buffer()?;
+        Ok(len)
+    }
+
+    fn write_buffer(&mut self) -> Result<(), ParsingError> {
+        let len = self.buffer_len;
+        self.buffer_len = 0;
+        let buffer = self.buffer;
+        self.program(0, len, &buffer)
+    }
+
+    fn program(&mut self, from: usize, to: usize, data: &[u8]) -> Result<(), ParsingError> {
+        if from == to {
+            return Ok(());
+        }
+        let address = self.base_address + self.written;
+        let end = address
+            .checked_add((to - from) as u32)
+            .ok_or(ParsingError::Flash(NorFlashErrorKind::OutOfBounds))?;
+        if end > self.erased_end {
+            let erase_end = (end as usize).div_ceil(F::ERASE_SIZE) * F::ERASE_SIZE;
+            let erase_end = u32::try_from(erase_end).map_err(|_| NorFlashErrorKind::OutOfBounds)?;
+            self.flash.erase(self.erased_end, erase_end).map_err(|e| e.kind())?;
+            self.erased_end = erase_end;
+        }
+        self.flash.write(address, &data[from..to]).map_err(|e| e.kind())?;
+        self.written += (to - from) as u32;
+        Ok(())
+    }
+}
+
+/// Extract `entry` into `flash` at `base_address`, using `scratch` as the read buffer.
+///
+/// `progress` is called after every chunk with `(bytes_written, entry_size)`.
+/// Returns the number of bytes written.
+pub fn extract_entry_to_flash<F: NorFlash>(
+    entry: &mut impl LocalFileOps,
+    flash: &mut F,
+    base_address: u32,
+    scratch: &mut [u8],
+    mut progress: impl FnMut(u64, u64),
+) -> Result<u64, ParsingError> {
+    let size = entry.file_size();
+    let mut writer = FlashWriter::<F>::new(flash, base_address)?;
+    extract_with_buffer(entry, scratch, |chunk| {
+        writer.write(chunk)?;
+        progress(writer.position(), size);
+        Ok::<_, ParsingError>(())
+    })?;
+    writer.finish()
+}
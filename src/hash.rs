@@ -0,0 +1,85 @@
+//! Hashing entry data while it is read
+
+use crate::{LocalFileOps, ParsingError};
+
+use core::str::Utf8Error;
+
+/// Minimal hashing interface, implement it to plug any hash function into [`HashingReader`].
+///
+/// With the `digest` feature it is implemented for every `digest::Digest`.
+pub trait DataHasher {
+    type Output;
+
+    fn update(&mut self, data: &[u8]);
+
+    fn finalize(self) -> Self::Output;
+}
+
+#[cfg(feature = "digest")]
+impl<D: digest::Digest> DataHasher for D {
+    type Output = digest::Output<D>;
+
+    fn update(&mut self, data: &[u8]) {
+        digest::Digest::update(self, data);
+    }
+
+    fn finalize(self) -> Self::Output {
+        digest::Digest::finalize(self)
+    }
+}
+
+/// Entry reader hashing all the data read through it exactly once,
+/// so extracting an entry and hashing it takes a single pass.
+pub struct HashingReader<R, H> {
+    inner: R,
+    hasher: H,
+}
+
+/// Entry reader computing the SHA-256 of the data read through it
+#[cfg(feature = "sha2")]
+pub type Sha256Reader<R> = HashingReader<R, sha2::Sha256>;
+
+impl<R: LocalFileOps, H: DataHasher> HashingReader<R, H> {
+    pub fn with_hasher(inner: R, hasher: H) -> Self {
+        Self { inner, hasher }
+    }
+
+    /// Hash of the data read so far
+    pub fn finalize(self) -> H::Output {
+        self.hasher.finalize()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: LocalFileOps, H: DataHasher + Default> HashingReader<R, H> {
+    pub fn new(inner: R) -> Self {
+        Self::with_hasher(inner, H::default())
+    }
+}
+
+impl<R: LocalFileOps, H: DataHasher> LocalFileOps for HashingReader<R, H> {
+    fn file_name(&self) -> Result<&str, Utf8Error> {
+        self.inner.file_name()
+    }
+
+    fn file_size(&self) -> u64 {
+        self.inner.file_size()
+    }
+
+    fn has_directory_attribute(&self) -> bool {
+        self.inner.has_directory_attribute()
+    }
+
+    fn is_dir(&self) -> bool {
+        self.inner.is_dir()
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
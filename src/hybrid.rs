@@ -0,0 +1,239 @@
+//! Parser streaming the archive until the source turns out to be seekable
+
+use core::cmp;
+
+use crate::{
+    checked_offset, CentralDirEnd, CentralFileHeader, EntrySizes, HeaderType, LocalFileHeader, ParserEvent,
+    ParserState, ParsingError, PassiveParser, Read, Seek, SeekFrom, Zip64CentralDirEnd,
+    CENTRAL_FILE_HEADER_LEN, FLAG_DATA_DESCRIPTOR, LOCAL_FILE_HEADER_LEN,
+};
+
+/// Parser starting sequentially, then switching to the central directory once the stream
+/// reports its length, e.g. an HTTP body whose range support is only known after a while.
+///
+/// Events are the same as [`PassiveParser`] ones whatever the mode: the local files in order,
+/// then the central directory file headers, then [`ParserEvent::ArchiveEnd`]. After the switch,
+/// the remaining local files are located by the central directory and fed to the same parser,
+/// with its CRC-32, strict mode and inflate settings, in the order of the central directory. The
+/// switch only happens between two local files, the entries before it in the stream are not
+/// delivered again.
+pub struct HybridParser<'a, S: Read + Seek, const N: usize = 128> {
+    stream: &'a mut S,
+    passive: PassiveParser<N>,
+    /// the stream length was probed, don't try again
+    probed: bool,
+    seeking: bool,
+    /// data preceding the archive, shifting the local header offsets
+    prepended_len: u64,
+}
+
+impl<'a, S: Read + Seek, const N: usize> HybridParser<'a, S, N> {
+    /// `stream` must be at the start of the archive
+    pub fn new(stream: &'a mut S) -> Self {
+        Self::with_parser(stream, PassiveParser::new())
+    }
+
+    /// Go through `stream` with `parser`, e.g. a strict one
+    pub fn with_parser(stream: &'a mut S, parser: PassiveParser<N>) -> Self {
+        Self {
+            stream,
+            passive: parser,
+            probed: false,
+            seeking: false,
+            prepended_len: 0,
+        }
+    }
+
+    /// Whether the parser switched to the central directory
+    pub fn is_seeking(&self) -> bool {
+        self.seeking
+    }
+
+    /// Parse the whole archive, reading the stream through `scratch`.
+    ///
+    /// Stops early, returning `Ok`, when `on_event` returns false.
+    pub fn run<F>(&mut self, scratch: &mut [u8], mut on_event: F) -> Result<(), ParsingError>
+    where
+        F: for<'b, 'c> FnMut(ParserEvent<'b, 'c, N>) -> bool,
+    {
+        if scratch.is_empty() {
+            return Err(ParsingError::BufferTooSmall);
+        }
+        loop {
+            if let Some(end) = self.probe() {
+                self.seeking = true;
+                self.prepended_len = end.prepended_len;
+                return self.run_seeking(end, scratch, &mut on_event);
+            }
+
+            // stop reading at the end of the file data, so the switch can happen right after
+            let len = match self.passive.state {
+                ParserState::RecvLocalFileData if self.passive.file_data_index < self.passive.file_data_len => {
+                    cmp::min(scratch.len(), self.passive.file_data_len - self.passive.file_data_index)
+                }
+                _ => scratch.len(),
+            };
+            let n = self.stream.read(&mut scratch[..len])?;
+            if self.passive.is_finished() {
+                return Ok(());
+            }
+            if n == 0 {
+                return if self.passive.is_at_archive_end() { Ok(()) } else { Err(ParsingError::StreamEnding) };
+            }
+            if self.passive.parse_data(&scratch[..n], |evt, _| on_event(evt)).is_err() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// The end of central directory record, once the stream length is known between two local files
+    fn probe(&mut self) -> Option<CentralDirEnd> {
+        let between_local_files = match self.passive.state {
+            ParserState::RecvHeader(HeaderType::HeaderSignature, _) => self.passive.buffer.is_empty(),
+            ParserState::RecvLocalFileData => self.passive.file_data_index >= self.passive.file_data_len,
+            _ => false,
+        } && !self.passive.central_dir_started;
+        if self.probed || !between_local_files {
+            return None;
+        }
+        let stream_len = self.stream.stream_len()?;
+        self.probed = true;
+
+        // the zip file comment may follow the record
+        let end = CentralDirEnd::find(self.stream, stream_len).and_then(|(offset, mut end)| {
+            let mut next_record_offset = offset;
+            if let Some((zip64_offset, zip64_end)) = Zip64CentralDirEnd::read_from(self.stream, offset) {
+                if zip64_end.has_encrypted_central_dir() {
+                    // keep streaming, the local headers tell it as well
+                    return None;
+                }
+                end.apply_zip64(&zip64_end);
+                next_record_offset = zip64_offset;
+            }
+            end.apply_prepended_data(next_record_offset);
+            Some(end)
+        });
+        if end.is_none() {
+            // keep streaming from where we were
+            let _ = self.stream.seek(SeekFrom::Start(self.passive.stream_offset()));
+        }
+        end
+    }
+
+    fn run_seeking<F>(&mut self, end: CentralDirEnd, scratch: &mut [u8], on_event: &mut F) -> Result<(), ParsingError>
+    where
+        F: for<'b, 'c> FnMut(ParserEvent<'b, 'c, N>) -> bool,
+    {
+        // index of the next local file for the parser
+        let mut index = self.passive.localfile_index;
+        if matches!(self.passive.state, ParserState::RecvLocalFileData) {
+            // the data of the last streamed file is complete, its end comes with the next record
+            index += 1;
+        }
+        // the local files before were streamed, the central directory may list them in any order
+        let streamed_end = self.passive.stream_offset();
+        let entries = i32::try_from(end.total_entries_this_disk).map_err(|_| ParsingError::Overflow)?;
+
+        // the remaining local files
+        let mut offset = end.central_directory_offset;
+        for _ in 0..entries {
+            let (header, sizes) = self.read_central_header(offset)?;
+            offset = checked_offset(offset, header.len() as u64)?;
+            if sizes.local_header_offset < streamed_end {
+                continue;
+            }
+            // up to the end of its data, its end is reported with the next record as when streaming
+            let (data_end, data_descriptor) = self.local_data_end(&sizes, end.central_directory_offset)?;
+            let bound = if data_descriptor { end.central_directory_offset } else { data_end };
+            let descriptor = |evt: &ParserEvent<'_, '_, N>| matches!(evt, ParserEvent::LocalFileDataDescriptor(i, _) if *i == index);
+            if !self.feed_from(sizes.local_header_offset, Some(bound), scratch, on_event, descriptor)? {
+                return Ok(());
+            }
+            let passive = &self.passive;
+            let data_received = passive.localfile_index == index
+                && matches!(passive.state, ParserState::RecvLocalFileData)
+                && !passive.data_descriptor
+                && passive.file_data_index >= passive.file_data_len;
+            if !data_received {
+                // the local header disagrees with the central directory
+                return Err(ParsingError::EntryOutOfBounds(bound));
+            }
+            index += 1;
+        }
+
+        // then the central directory
+        let archive_end = |evt: &ParserEvent<'_, '_, N>| matches!(evt, ParserEvent::ArchiveEnd(..));
+        self.feed_from(end.central_directory_offset, None, scratch, on_event, archive_end)?;
+        Ok(())
+    }
+
+    /// Feed the stream from `offset` to the parser until `bound`, or until it emits the event
+    /// `last` matches, returns false if the handler cancelled or the parser stopped
+    fn feed_from<F, L>(
+        &mut self,
+        offset: u64,
+        bound: Option<u64>,
+        scratch: &mut [u8],
+        on_event: &mut F,
+        last: L,
+    ) -> Result<bool, ParsingError>
+    where
+        F: for<'b, 'c> FnMut(ParserEvent<'b, 'c, N>) -> bool,
+        L: Fn(&ParserEvent<'_, '_, N>) -> bool,
+    {
+        self.stream.seek(SeekFrom::Start(offset))?;
+        // where the records are, for the offsets it reports
+        self.passive.stream_offset = offset;
+        loop {
+            if self.passive.is_finished() {
+                return Ok(false);
+            }
+            let len = match bound {
+                Some(bound) => cmp::min(scratch.len() as u64, bound.saturating_sub(self.passive.stream_offset)) as usize,
+                None => scratch.len(),
+            };
+            if len == 0 {
+                return Ok(true);
+            }
+            let n = self.stream.read(&mut scratch[..len])?;
+            if n == 0 {
+                return Err(ParsingError::StreamEnding);
+            }
+            let (mut done, mut cancelled) = (false, false);
+            let _ = self.passive.parse_data(&scratch[..n], |evt, _| {
+                done = last(&evt);
+                cancelled = !on_event(evt);
+                !done && !cancelled
+            });
+            if done || cancelled {
+                return Ok(!cancelled);
+            }
+        }
+    }
+
+    /// Central directory file header at `offset`, with the sizes of its entry
+    fn read_central_header(&mut self, offset: u64) -> Result<(CentralFileHeader, EntrySizes), ParsingError> {
+        let mut buf = [0u8; CENTRAL_FILE_HEADER_LEN];
+        self.stream.seek(SeekFrom::Start(offset))?;
+        self.stream.read_exact(&mut buf)?;
+        let header = CentralFileHeader::from_bytes(&buf).ok_or(ParsingError::InvalidCentralFileHeader)?;
+        let mut sizes = header.read_sizes(self.stream, offset)?;
+        sizes.local_header_offset = checked_offset(sizes.local_header_offset, self.prepended_len)?;
+        Ok((header, sizes))
+    }
+
+    /// End of the data of a local file, of the size given by the central directory, which must
+    /// be before `data_bound`, and whether a data descriptor follows it
+    fn local_data_end(&mut self, sizes: &EntrySizes, data_bound: u64) -> Result<(u64, bool), ParsingError> {
+        let mut buf = [0u8; LOCAL_FILE_HEADER_LEN];
+        self.stream.seek(SeekFrom::Start(sizes.local_header_offset))?;
+        self.stream.read_exact(&mut buf)?;
+        let local_header = LocalFileHeader::from_bytes(&buf).ok_or(ParsingError::InvalidLocalFileHeader)?;
+        let file_data_offset = checked_offset(sizes.local_header_offset, local_header.len() as u64)?;
+        let data_end = checked_offset(file_data_offset, sizes.compressed_size)?;
+        if data_end > data_bound {
+            return Err(ParsingError::EntryOutOfBounds(data_end));
+        }
+        Ok((data_end, local_header.general_purpose_bit_flag & FLAG_DATA_DESCRIPTOR != 0))
+    }
+}
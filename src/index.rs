@@ -0,0 +1,178 @@
+//! Owned archive metadata, for comparing and listing archives
+
+use std::collections::HashMap;
+use std::string::String;
+use std::vec::Vec;
+
+use crate::{CompressMethod, DosDateTime, EntryMetadata};
+use crate::{Read, Seek, SeekingParser};
+
+/// Name and metadata of an entry
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IndexEntry {
+    pub name: String,
+    pub metadata: EntryMetadata,
+}
+
+/// Metadata of all the entries of an archive, in central directory order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchiveIndex {
+    entries: Vec<IndexEntry>,
+}
+
+impl ArchiveIndex {
+    /// Collect the metadata of all the entries the parser yields
+    pub fn build<S: Read + Seek, const N: usize>(parser: SeekingParser<'_, S, N>) -> Self {
+        parser
+            .map(|file| IndexEntry {
+                name: String::from_utf8_lossy(file.info.file_name_bytes()).into_owned(),
+                metadata: file.metadata,
+            })
+            .collect()
+    }
+
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    pub fn get(&self, name: &str) -> Option<&IndexEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    pub fn push(&mut self, entry: IndexEntry) {
+        self.entries.push(entry);
+    }
+}
+
+impl FromIterator<IndexEntry> for ArchiveIndex {
+    fn from_iter<T: IntoIterator<Item = IndexEntry>>(iter: T) -> Self {
+        Self {
+            entries: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Differences between two archives, see [`diff`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchiveDiff<'a> {
+    /// Entries only in the new archive
+    pub added: Vec<&'a IndexEntry>,
+    /// Entries only in the old archive
+    pub removed: Vec<&'a IndexEntry>,
+    /// Pattern: (old_entry, new_entry)
+    pub modified: Vec<(&'a IndexEntry, &'a IndexEntry)>,
+}
+
+impl ArchiveDiff<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// Compare the entries of archive `a` (old) with archive `b` (new) by name.
+///
+/// An entry present in both is modified if its CRC, uncompressed size or modification time differs.
+pub fn diff<'a>(a: &'a ArchiveIndex, b: &'a ArchiveIndex) -> ArchiveDiff<'a> {
+    let old: HashMap<&str, &IndexEntry> = a.entries.iter().map(|entry| (entry.name.as_str(), entry)).collect();
+    let new: HashMap<&str, &IndexEntry> = b.entries.iter().map(|entry| (entry.name.as_str(), entry)).collect();
+
+    let mut result = ArchiveDiff::default();
+    for entry in &b.entries {
+        match old.get(entry.name.as_str()) {
+            None => result.added.push(entry),
+            Some(old_entry) if is_modified(&old_entry.metadata, &entry.metadata) => {
+                result.modified.push((old_entry, entry))
+            }
+            Some(_) => {}
+        }
+    }
+    result.removed = a.entries
+        .iter()
+        .filter(|entry| !new.contains_key(entry.name.as_str()))
+        .collect();
+    result
+}
+
+fn is_modified(old: &EntryMetadata, new: &EntryMetadata) -> bool {
+    old.crc32 != new.crc32
+        || old.uncompressed_size != new.uncompressed_size
+        || old.last_modified != new.last_modified
+}
+
+/// One entry of a detailed listing, like `zipinfo -v` prints it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListingRow<'a> {
+    pub name: &'a str,
+    pub compression_method: CompressMethod,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+    pub crc32: u32,
+    pub general_purpose_bit_flag: u16,
+    pub version_made_by: u16,
+    pub version_needed_to_extract: u16,
+    /// Host system, the high byte of `version_made_by`
+    pub host_system: u8,
+    pub last_modified: DosDateTime,
+}
+
+impl ListingRow<'_> {
+    /// Space saved by the compression in percent, negative if the entry grew
+    pub fn savings_percent(&self) -> f64 {
+        savings_percent(self.compressed_size, self.uncompressed_size)
+    }
+}
+
+/// Sums over all the entries of a listing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ListingTotals {
+    pub entries: usize,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+}
+
+impl ListingTotals {
+    /// Space saved by the compression in percent, negative if the archive grew
+    pub fn savings_percent(&self) -> f64 {
+        savings_percent(self.compressed_size, self.uncompressed_size)
+    }
+}
+
+/// Detailed listing of an archive, see [`ArchiveIndex::listing`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Listing<'a> {
+    pub rows: Vec<ListingRow<'a>>,
+    pub totals: ListingTotals,
+}
+
+impl ArchiveIndex {
+    /// Detailed listing of all the entries plus the archive totals
+    pub fn listing(&self) -> Listing<'_> {
+        let mut listing = Listing::default();
+        for entry in &self.entries {
+            let metadata = &entry.metadata;
+            listing.rows.push(ListingRow {
+                name: &entry.name,
+                compression_method: metadata.compression_method,
+                compressed_size: metadata.compressed_size,
+                uncompressed_size: metadata.uncompressed_size,
+                crc32: metadata.crc32,
+                general_purpose_bit_flag: metadata.general_purpose_bit_flag,
+                version_made_by: metadata.version_made_by,
+                version_needed_to_extract: metadata.version_needed_to_extract,
+                host_system: (metadata.version_made_by >> 8) as u8,
+                last_modified: metadata.last_modified,
+            });
+            listing.totals.entries += 1;
+            listing.totals.compressed_size += metadata.compressed_size;
+            listing.totals.uncompressed_size += metadata.uncompressed_size;
+        }
+        listing
+    }
+}
+
+fn savings_percent(compressed_size: u64, uncompressed_size: u64) -> f64 {
+    if uncompressed_size == 0 {
+        return 0.0;
+    }
+    (1.0 - compressed_size as f64 / uncompressed_size as f64) * 100.0
+}
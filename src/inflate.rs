@@ -0,0 +1,120 @@
+//! Deflate (method 8) decompression backed by `miniz_oxide`
+
+use core::cmp;
+use core::fmt;
+
+use miniz_oxide::inflate::core::{decompress, inflate_flags, DecompressorOxide};
+use miniz_oxide::inflate::TINFLStatus;
+
+use crate::{CompressMethod, Decompressor, ParsingError};
+
+/// Deflate history window, the output buffer of the decompressor wraps around it
+const WINDOW_SIZE: usize = 32 * 1024;
+
+/// Streaming raw deflate decompressor, needing no allocation.
+///
+/// It holds the 32 KiB history window, so it weighs about 43 KiB.
+pub struct Inflater {
+    state: DecompressorOxide,
+    window: [u8; WINDOW_SIZE],
+    /// next write position in the window
+    position: usize,
+    /// bytes before `position` not handed out yet
+    pending: usize,
+    done: bool,
+}
+
+impl Inflater {
+    pub fn new() -> Self {
+        Self {
+            state: DecompressorOxide::new(),
+            window: [0; WINDOW_SIZE],
+            position: 0,
+            pending: 0,
+            done: false,
+        }
+    }
+
+    /// Get ready for another deflate stream
+    pub fn reset(&mut self) {
+        self.state.init();
+        self.position = 0;
+        self.pending = 0;
+        self.done = false;
+    }
+
+    /// The end of the deflate stream was reached and all the data handed out
+    pub fn is_done(&self) -> bool {
+        self.done && self.pending == 0
+    }
+
+    /// Decompress `input` into `output`, returns `(consumed, produced)`.
+    ///
+    /// `more_input` tells whether more compressed data follows `input`. Decompressed data not
+    /// fitting in `output` is kept for the next call, which may pass an empty `input`.
+    pub fn inflate(&mut self, input: &[u8], output: &mut [u8], more_input: bool) -> Result<(usize, usize), ParsingError> {
+        let mut consumed = 0;
+        let mut produced = 0;
+        let mut needs_input = false;
+        loop {
+            if self.pending > 0 {
+                let start = self.position - self.pending;
+                let len = cmp::min(self.pending, output.len() - produced);
+                output[produced..produced + len].copy_from_slice(&self.window[start..start + len]);
+                self.pending -= len;
+                produced += len;
+            }
+            if self.pending > 0 || produced == output.len() || self.done || needs_input {
+                return Ok((consumed, produced));
+            }
+
+            if self.position == WINDOW_SIZE {
+                self.position = 0;
+            }
+            let flags = if more_input { inflate_flags::TINFL_FLAG_HAS_MORE_INPUT } else { 0 };
+            let (status, read, written) = decompress(&mut self.state, &input[consumed..], &mut self.window, self.position, flags);
+            consumed += read;
+            self.position += written;
+            self.pending = written;
+            match status {
+                TINFLStatus::Done => self.done = true,
+                TINFLStatus::NeedsMoreInput => needs_input = true,
+                TINFLStatus::HasMoreOutput => {}
+                _ => return Err(ParsingError::DecompressionFailed),
+            }
+        }
+    }
+}
+
+impl Default for Inflater {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Inflater {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Inflater")
+            .field("pending", &self.pending)
+            .field("done", &self.done)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Decompressor for Inflater {
+    fn init(&mut self, method: CompressMethod) -> Result<(), ParsingError> {
+        if method != CompressMethod::Deflated {
+            return Err(ParsingError::UnsupportedCompressMethod(method));
+        }
+        self.reset();
+        Ok(())
+    }
+
+    fn feed(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), ParsingError> {
+        self.inflate(input, output, true)
+    }
+
+    fn finish(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), ParsingError> {
+        self.inflate(input, output, false)
+    }
+}
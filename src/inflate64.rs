@@ -0,0 +1,84 @@
+//! Deflate64 (method 9) decompression backed by the `deflate64` crate
+
+use core::fmt;
+use std::boxed::Box;
+
+use deflate64::InflaterManaged;
+
+use crate::{CompressMethod, Decompressor, ParsingError};
+
+/// Streaming Deflate64 decompressor.
+///
+/// Deflate64 is deflate with a 64 KiB window and longer matches, the decompressor state takes
+/// about 270 KiB, allocated on the first entry.
+#[derive(Default)]
+pub struct Inflater64 {
+    state: Option<Box<InflaterManaged>>,
+}
+
+impl Inflater64 {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get ready for another Deflate64 stream
+    pub fn reset(&mut self) {
+        match &mut self.state {
+            Some(state) => **state = InflaterManaged::new(),
+            None => self.state = Some(Box::new(InflaterManaged::new())),
+        }
+    }
+
+    /// The end of the Deflate64 stream was reached and all the data handed out
+    pub fn is_done(&self) -> bool {
+        self.state.as_ref().is_some_and(|state| state.finished())
+    }
+
+    /// Decompress `input` into `output`, returns `(consumed, produced)`.
+    ///
+    /// Decompressed data not fitting in `output` is kept for the next call, which may pass an
+    /// empty `input`. `last` tells that `input` ends the compressed data.
+    pub fn decompress(&mut self, input: &[u8], output: &mut [u8], last: bool) -> Result<(usize, usize), ParsingError> {
+        let state = self.state.get_or_insert_with(|| Box::new(InflaterManaged::new()));
+        if state.finished() {
+            return Ok((0, 0));
+        }
+
+        let res = state.inflate(input, output);
+        if res.data_error {
+            return Err(ParsingError::DecompressionFailed);
+        }
+        let stalled = res.bytes_consumed == 0 && res.bytes_written == 0 && !output.is_empty();
+        if last && stalled && !state.input_finished() {
+            // the stream is truncated
+            return Err(ParsingError::DecompressionFailed);
+        }
+        Ok((res.bytes_consumed, res.bytes_written))
+    }
+}
+
+impl fmt::Debug for Inflater64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Inflater64")
+            .field("done", &self.is_done())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Decompressor for Inflater64 {
+    fn init(&mut self, method: CompressMethod) -> Result<(), ParsingError> {
+        if method != CompressMethod::Deflate64 {
+            return Err(ParsingError::UnsupportedCompressMethod(method));
+        }
+        self.reset();
+        Ok(())
+    }
+
+    fn feed(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), ParsingError> {
+        self.decompress(input, output, false)
+    }
+
+    fn finish(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), ParsingError> {
+        self.decompress(input, output, true)
+    }
+}
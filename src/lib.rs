@@ -1,37 +1,31 @@
 //! Zip file format parser implemented by rust, supports stream parsing, `no_std` environment.
 //!
-//! The [`Parser`] will search central directory at the end of zip file if [`Seek`] is available.
+//! The [`AutoParser`] will search central directory at the end of zip file if [`Seek`] is available.
 //! Also, It supports sequence read parsing when [`Seek`] is not available.
 //! All types in std env implemented `std::io::Read` automatically implement [`Read`], and so is the trait [`Seek`].
 //!
 //! ## stream parsing
 //! ```
+//! use core::cell::RefCell;
+//!
 //! use zip_parser as zip;
 //! use zip::prelude::*;
 //!
 //! #[cfg(feature = "std")]
-//! fn parse<S: zip::Read + zip::Seek>(parser: Parser<S>) {
-//!     for (i, mut file) in parser.enumerate() {
-//!         println!("{}: {}({} Bytes)", i, unsafe { file.file_name() }, file.file_size());
-//!         let mut buf = Vec::new();
-//!         buf.resize(file.file_size() as usize, 0);
-//!         if let Ok(n) = file.read(&mut buf) {
-//!             println!("Data: {:02X?}", &buf[..n]);
-//!         } else {
-//!             println!("read failed");
-//!         }
-//!         println!();
+//! fn parse(path: &str) -> Result<(), ParsingError> {
+//!     let mut file = RefCell::new(std::fs::File::open(path)?);
+//!     let mut parser = AutoParser::<_, 128>::new(&mut file);
+//!     println!("central directory: {}", parser.is_seeking());
+//!     while let Some(mut file) = parser.next_entry()? {
+//!         let mut buf = [0u8; 16];
+//!         let n = file.read(&mut buf)?;
+//!         println!("{}({} Bytes): {:02X?}", file.file_name().unwrap_or("NoFileName"), file.file_size(), &buf[..n]);
 //!     }
-//! }
-//!
-//! #[cfg(feature = "std")]
-//! fn stdin_parsing() {
-//!     println!("*** get stream from stdin ***");
-//!     parse(Parser::new(std::io::stdin().lock()))
+//!     Ok(())
 //! }
 //! ```
-//! You just need to pass a stream which implements [`Read`] into the [`Parser::new()`](struct.Parser.html#method.new),
-//! then you can iterate over it. For more detail, see example `stream_parsing`.
+//! You just need to pass a stream which implements [`Read`] and [`Seek`] into [`AutoParser::new`],
+//! then you can go through the entries with [`Parser::next_entry`]. For more detail, see example `stream_parsing`.
 //!
 //! ## Example
 //! ### Stream_parsing
@@ -50,42 +44,226 @@
 //! In example [`stream_parsing`], there is a case for passive parsing:
 //! read data from a file and [`PassiveParser::feed_data`] to the parser.
 //!
+//! ## Features
+//! - `std`: implement [`Read`] and [`Seek`] for all `std::io` streams,
+//!   owned archive metadata (`ArchiveIndex`), detailed listings and archive comparison (`diff`).
+//! - `forbid-unsafe`: build the crate with `#![forbid(unsafe_code)]`.
+//!   `UringFile` submits reads to the kernel through unsafe code, so it is unavailable in this configuration.
+//! - `digest`: use any `digest::Digest` as a [`DataHasher`] for [`HashingReader`].
+//! - `sha2`: `Sha256Reader`, hashing entry data with SHA-256 as it is read.
+//! - `embedded-storage`: `FlashWriter` and `extract_entry_to_flash`, unpacking entries into NOR flash.
+//! - `io-uring`: `UringFile`, reading archives with positional io_uring reads on Linux.
+//! - `inflate`: `LocalFile::read` decompresses deflated entries, `Inflater` is also a [`Decompressor`]
+//!   for [`PassiveParser`] events, and `PassiveParser::with_inflate` inflates the data events.
+//!   The inflater takes about 43 KiB, held inline by `PassiveParser`, and by `LocalFile` without
+//!   `std`, which allocates it otherwise.
+//! - `zstd`: `LocalFile::read` decompresses Zstandard entries through `ZstdDecoder`, which needs an allocator.
+//! - `bzip2`: `LocalFile::read` decompresses BZIP2 entries through `Bzip2Decoder`, enables `std`.
+//! - `lzma`: `LocalFile::read` decompresses LZMA entries through `LzmaDecoder`, enables `std`.
+//! - `xz`: `LocalFile::read` decompresses XZ entries through `XzDecoder`, enables `std`.
+//! - `deflate64`: `LocalFile::read` decompresses Deflate64 entries through `Inflater64`, enables `std`.
+//! - `crc-check`: `LocalFile` reads fail with `ParsingError::CrcMismatch` at the end of an entry whose
+//!   data doesn't match its CRC-32, and [`PassiveParser`] reports it as a `ParsingError` event, unless
+//!   turned off with `with_crc_check`.
+//! - `time`: `DosDateTime::to_primitive_date_time` and `UnixTime::to_offset_date_time`.
+//! - `chrono`: `DosDateTime::to_naive_date_time` and `UnixTime::to_naive_date_time`.
+//! - `log`, `defmt`: report why [`SeekingParser`] stops listing the entries through these
+//!   loggers, the same error is kept by `SeekingParser::last_error`. Nothing is printed otherwise.
+//!
 
 #![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(feature = "forbid-unsafe", forbid(unsafe_code))]
+// a panic bricks an embedded target, malformed input must come out as a `ParsingError`
+#![cfg_attr(not(test), deny(clippy::unwrap_used, clippy::expect_used, clippy::panic))]
 #![allow(dead_code)]
 
+use core::cell::{RefCell, RefMut};
 use core::fmt::Display;
+use core::hash::{Hash, Hasher};
 use core::{
-    str, mem, ptr, slice, cmp,
+    str, cmp,
 };
-use core::marker::PhantomData;
 use core::convert::{TryFrom};
+use core::mem::MaybeUninit;
+use core::ops::Range;
 use core::str::Utf8Error;
 
 #[cfg(feature = "std")]
-use std::{io, vec::Vec};
+use std::{borrow::Cow, io, string::String, sync::Arc, vec::Vec};
+#[cfg(all(feature = "inflate", feature = "std"))]
+use std::boxed::Box;
+
+#[macro_use]
+mod diag;
+
+mod crc32;
+pub use crc32::{Crc32, SoftwareCrc32};
+use crc32::crc32_update;
+
+mod zip64;
+use zip64::{Zip64CentralDirEnd, Zip64CentralDirEndLocator, Zip64ExtraField, ZIP64_SATURATED};
+
+mod state;
+
+mod descriptor;
+pub use descriptor::DataDescriptor;
+use descriptor::{DescriptorScanner, MAX_DATA_DESCRIPTOR_LEN};
+
+mod crypto;
+pub use crypto::{PasswordFn, PasswordProvider, MAX_PASSWORD_LEN};
+use crypto::{ZipCrypto, ENCRYPTION_HEADER_LEN};
+
+mod cp437;
+pub use cp437::decode_cp437;
+#[cfg(feature = "std")]
+use cp437::cp437_char;
+
+mod extra;
+pub use extra::{
+    AesExtraField, AesStrength, ExtendedTimestamp, ExtraFieldFn, ExtraFieldHandler, ExtraFieldHandlers,
+    ExtraFieldLocation, ExtraFields, NtfsTimestamps, UnixOwner, UnixTime, MAX_EXTRA_FIELD_LEN,
+};
+use extra::read_at;
+
+#[cfg(any(feature = "time", feature = "chrono"))]
+mod datetime;
+
+mod pages;
+pub use pages::PageAligner;
+mod hybrid;
+pub use hybrid::HybridParser;
+mod pull;
+pub use pull::{PullEntry, PullParser};
+mod auto;
+pub use auto::{AutoEntry, AutoParser};
+mod sequential;
+pub use sequential::{SequentialEntry, SequentialParser};
+mod central;
+pub use central::{CentralDirectory, CentralEntry};
+mod regions;
+pub use regions::{ArchiveRegions, RegionReader};
+mod owned;
+pub use owned::OwnedSeekingParser;
+mod positional;
+pub use positional::{ReadAt, ReadAtCursor};
+mod cancel;
+pub use cancel::{CancelFn, CancelToken, Cancellable};
+
+mod overlap;
+pub use overlap::ClaimedRanges;
+mod context;
+pub use context::ErrorContext;
+
+mod decompress;
+pub use decompress::{DecompressingHandler, Decompressor, Decompressors};
+
+#[cfg(feature = "inflate")]
+mod inflate;
+#[cfg(feature = "inflate")]
+pub use inflate::Inflater;
+
+#[cfg(feature = "zstd")]
+extern crate alloc;
+#[cfg(feature = "zstd")]
+mod zstd;
+#[cfg(feature = "zstd")]
+pub use zstd::ZstdDecoder;
+
+#[cfg(feature = "bzip2")]
+mod bzip;
+#[cfg(feature = "bzip2")]
+pub use bzip::Bzip2Decoder;
+
+#[cfg(feature = "lzma")]
+mod lzma;
+#[cfg(feature = "lzma")]
+pub use lzma::LzmaDecoder;
+
+#[cfg(feature = "xz")]
+mod xz;
+#[cfg(feature = "xz")]
+pub use xz::XzDecoder;
+
+#[cfg(feature = "deflate64")]
+mod inflate64;
+#[cfg(feature = "deflate64")]
+pub use inflate64::Inflater64;
+
+#[cfg(all(feature = "io-uring", target_os = "linux", not(feature = "forbid-unsafe")))]
+mod uring;
+#[cfg(all(feature = "io-uring", target_os = "linux", not(feature = "forbid-unsafe")))]
+pub use uring::UringFile;
+
+#[cfg(feature = "embedded-storage")]
+mod flash;
+#[cfg(feature = "embedded-storage")]
+pub use flash::{extract_entry_to_flash, FlashWriter};
+
+mod hash;
+pub use hash::{DataHasher, HashingReader};
+
+mod verify;
+pub use verify::{EntryCheck, EntryReport, VerifyStats};
+#[cfg(feature = "sha2")]
+pub use hash::Sha256Reader;
+
+#[cfg(feature = "std")]
+mod index;
+#[cfg(feature = "std")]
+pub use index::{diff, ArchiveDiff, ArchiveIndex, IndexEntry, Listing, ListingRow, ListingTotals};
 
 /// Pure LocalFile header len, not include filename & extra field
-pub const LOCAL_FILE_HEADER_LEN: usize = mem::size_of::<LocalFileHeader>();
-pub const CENTRAL_FILE_HEADER_LEN: usize = mem::size_of::<CentralFileHeader>();
-pub const CENTRAL_DIR_END_LEN: usize = mem::size_of::<CentralDirEnd>();
+pub const LOCAL_FILE_HEADER_LEN: usize = 30;
+pub const CENTRAL_FILE_HEADER_LEN: usize = 46;
+pub const CENTRAL_DIR_END_LEN: usize = 22;
+/// Pure ZIP64 end of central directory record len, not include the extensible data sector
+pub const ZIP64_CENTRAL_DIR_END_LEN: usize = 56;
+pub const ZIP64_CENTRAL_DIR_END_LOCATOR_LEN: usize = 20;
+/// Longest zip file comment, its length being on 16 bits
+const MAX_COMMENT_LEN: usize = u16::MAX as usize;
+
+/// General purpose bit 0: the entry data is encrypted
+const FLAG_ENCRYPTED: u16 = 0x01;
+/// General purpose bit 3: the CRC-32 and sizes are in a data descriptor following the data
+const FLAG_DATA_DESCRIPTOR: u16 = 0x08;
+/// General purpose bit 6: PKWARE strong encryption
+const FLAG_STRONG_ENCRYPTION: u16 = 0x40;
+/// General purpose bit 11: the file name and comment are UTF-8
+const FLAG_UTF8: u16 = 0x0800;
+/// General purpose bit 13: the central directory is encrypted, the local header values are masked
+const FLAG_MASKED_HEADER: u16 = 0x2000;
+/// Internal file attributes bit 0: the entry is apparently text
+const INTERNAL_TEXT: u16 = 0x01;
+/// Version needed to extract an archive with an encrypted central directory
+const CENTRAL_DIR_ENCRYPTION_VERSION: u16 = 62;
 
 pub trait Read {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError>;
 
+    /// Fill the whole `buf`, returns its length.
+    ///
+    /// Fails with [`ParsingError::StreamEnding`] if the stream ends before, the bytes read so far
+    /// being then in `buf`.
     fn read_exact(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
         let mut i = 0;
         while i < buf.len() {
-            match self.read(&mut buf[i..]) {
-                Ok(n) => {
-                    i += n;
-                }
-                Err(e) => {
-                    return Err(e);
-                }
+            match self.read(&mut buf[i..])? {
+                0 => return Err(ParsingError::StreamEnding),
+                n => i += n,
             }
         }
-        Ok(0)
+        Ok(i)
+    }
+
+    /// Read into `bufs` one after the other, returns the length read.
+    ///
+    /// By default, only the first non-empty buffer is read into, streams reading many buffers at
+    /// once override it.
+    fn read_vectored(&mut self, bufs: &mut [&mut [u8]]) -> Result<usize, ParsingError> {
+        match bufs.iter_mut().find(|buf| !buf.is_empty()) {
+            Some(buf) => self.read(buf),
+            None => Ok(0),
+        }
     }
 }
 
@@ -93,7 +271,58 @@ pub trait Read {
 impl<T> Read for T where T: io::Read,
 {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
-        self.read(buf).or(Err(ParsingError::Generic))
+        Ok(self.read(buf)?)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [&mut [u8]]) -> Result<usize, ParsingError> {
+        let mut slices: Vec<_> = bufs.iter_mut().map(|buf| io::IoSliceMut::new(buf)).collect();
+        Ok(io::Read::read_vectored(self, &mut slices)?)
+    }
+}
+
+/// Sink of the entry data, see [`LocalFile::copy_to`]
+pub trait Write {
+    /// Write some of `buf`, returns the length written
+    fn write(&mut self, buf: &[u8]) -> Result<usize, ParsingError>;
+
+    /// Write the whole `buf`.
+    ///
+    /// Fails with [`ParsingError::BufferTooSmall`] if the sink takes no more bytes.
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<(), ParsingError> {
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => return Err(ParsingError::BufferTooSmall),
+                n => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), ParsingError> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Write for T where T: io::Write,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, ParsingError> {
+        Ok(self.write(buf)?)
+    }
+
+    fn flush(&mut self) -> Result<(), ParsingError> {
+        Ok(self.flush()?)
+    }
+}
+
+/// Takes the bytes which fit
+#[cfg(not(feature = "std"))]
+impl<const M: usize> Write for heapless::Vec<u8, M> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, ParsingError> {
+        let len = cmp::min(buf.len(), self.capacity() - self.len());
+        // within the capacity left
+        let _ = self.extend_from_slice(&buf[..len]);
+        Ok(len)
     }
 }
 
@@ -104,9 +333,9 @@ pub enum SeekFrom {
 }
 
 #[cfg(feature = "std")]
-impl Into<io::SeekFrom> for SeekFrom {
-    fn into(self) -> io::SeekFrom {
-        match self {
+impl From<SeekFrom> for io::SeekFrom {
+    fn from(pos: SeekFrom) -> Self {
+        match pos {
             SeekFrom::Start(n) => io::SeekFrom::Start(n),
             SeekFrom::Current(n) => io::SeekFrom::Current(n),
             SeekFrom::End(n) => io::SeekFrom::End(n),
@@ -114,17 +343,27 @@ impl Into<io::SeekFrom> for SeekFrom {
     }
 }
 
+#[cfg(feature = "std")]
+impl From<io::SeekFrom> for SeekFrom {
+    fn from(pos: io::SeekFrom) -> Self {
+        match pos {
+            io::SeekFrom::Start(n) => SeekFrom::Start(n),
+            io::SeekFrom::Current(n) => SeekFrom::Current(n),
+            io::SeekFrom::End(n) => SeekFrom::End(n),
+        }
+    }
+}
+
 pub trait Seek {
-    fn seek(&mut self, _pos: SeekFrom) -> Result<u64, &str> {
-        Err("unimplemented")
+    /// Move to `pos`, returns the new position from the start of the stream.
+    ///
+    /// Fails with [`ParsingError::InvalidStream`] by default, for streams which can't seek.
+    fn seek(&mut self, _pos: SeekFrom) -> Result<u64, ParsingError> {
+        Err(ParsingError::InvalidStream)
     }
 
-    fn rewind(&mut self) -> Result<(), &str> {
-        if self.seek(SeekFrom::Start(0)).is_ok() {
-            Ok(())
-        } else {
-            Err("seek to the beginning failed")
-        }
+    fn rewind(&mut self) -> Result<(), ParsingError> {
+        self.seek(SeekFrom::Start(0)).map(|_| ())
     }
 
     fn stream_len(&mut self) -> Option<u64> {
@@ -135,14 +374,14 @@ pub trait Seek {
 #[cfg(feature = "std")]
 impl<T: io::Seek> Seek for T
 {
-    fn seek(&mut self, pos: SeekFrom) -> Result<u64, &str> {
-        self.seek(pos.into()).or(Err("std.io.seek err"))
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, ParsingError> {
+        Ok(self.seek(pos.into())?)
     }
 
     fn stream_len(&mut self) -> Option<u64> {
-        let cur = self.stream_position().unwrap();
-        let size = self.seek(io::SeekFrom::End(0)).unwrap();
-        let _ = self.seek(io::SeekFrom::Start(cur));
+        let cur = self.stream_position().ok()?;
+        let size = self.seek(io::SeekFrom::End(0)).ok()?;
+        self.seek(io::SeekFrom::Start(cur)).ok()?;
         Some(size)
     }
 }
@@ -153,6 +392,10 @@ enum Signature {
     LocalFileHeader = 0x04034b50,
     CentralFileHeader = 0x02014b50,
     CentralDirEnd = 0x06054b50,
+    Zip64CentralDirEnd = 0x06064b50,
+    Zip64CentralDirEndLocator = 0x07064b50,
+    /// precedes an encrypted central directory
+    ArchiveExtraData = 0x08064b50,
 }
 
 impl TryFrom<u32> for Signature {
@@ -163,6 +406,9 @@ impl TryFrom<u32> for Signature {
             0x04034b50 => Ok(Signature::LocalFileHeader),
             0x02014b50 => Ok(Signature::CentralFileHeader),
             0x06054b50 => Ok(Signature::CentralDirEnd),
+            0x06064b50 => Ok(Signature::Zip64CentralDirEnd),
+            0x07064b50 => Ok(Signature::Zip64CentralDirEndLocator),
+            0x08064b50 => Ok(Signature::ArchiveExtraData),
             _ => Err(ParsingError::InvalidSignature),
         }
     }
@@ -187,7 +433,36 @@ impl TryFrom<&[u8]> for Signature {
     }
 }
 
-#[repr(packed)]
+#[inline]
+fn le_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+#[inline]
+fn le_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+/// Get `len` bytes at `offset` of a record, fails if the record is too short
+#[inline]
+fn record_field(record: &[u8], offset: usize, len: usize) -> Result<&[u8], ParsingError> {
+    offset
+        .checked_add(len)
+        .and_then(|end| record.get(offset..end))
+        .ok_or(ParsingError::DataNotEnough)
+}
+
+/// Borrow the stream shared by a [`SeekingParser`] and its entries, for one read
+fn borrow_stream<S>(stream: Option<&RefCell<S>>) -> Result<RefMut<'_, S>, ParsingError> {
+    stream.and_then(|stream| stream.try_borrow_mut().ok()).ok_or(ParsingError::InvalidStream)
+}
+
+/// `offset + len` of header fields, failing instead of wrapping around on crafted values
+#[inline]
+fn checked_offset(offset: u64, len: u64) -> Result<u64, ParsingError> {
+    offset.checked_add(len).ok_or(ParsingError::Overflow)
+}
+
 #[derive(Debug, Copy, Clone)]
 struct LocalFileHeader {
     signature: Signature,
@@ -205,30 +480,45 @@ struct LocalFileHeader {
 
 impl LocalFileHeader {
     pub fn len(&self) -> usize {
-        mem::size_of::<Self>() + self.file_name_length as usize + self.extra_field_length as usize
+        LOCAL_FILE_HEADER_LEN + self.file_name_length as usize + self.extra_field_length as usize
     }
 
-    pub unsafe fn from_bytes(ptr: &[u8]) -> Option<&Self> {
-        (ptr.as_ptr() as *const Self).as_ref().and_then(|h| {
-            if matches!(h.signature, Signature::LocalFileHeader) {
-                Some(h)
-            } else {
-                None
-            }
+    /// Decode the fixed part of the header, `None` if the data is too short or the signature mismatches
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < LOCAL_FILE_HEADER_LEN
+            || !matches!(Signature::try_from(bytes), Ok(Signature::LocalFileHeader)) {
+            return None;
+        }
+        Some(Self {
+            signature: Signature::LocalFileHeader,
+            version_needed_to_extract: le_u16(bytes, 4),
+            general_purpose_bit_flag: le_u16(bytes, 6),
+            compression_method: le_u16(bytes, 8),
+            last_mod_file_time: le_u16(bytes, 10),
+            last_mod_file_date: le_u16(bytes, 12),
+            crc32: le_u32(bytes, 14),
+            compressed_size: le_u32(bytes, 18),
+            uncompressed_size: le_u32(bytes, 22),
+            file_name_length: le_u16(bytes, 26),
+            extra_field_length: le_u16(bytes, 28),
         })
     }
 
-    pub unsafe fn get_extra_field(&self) -> &[u8] {
-        let base = self as *const Self as *const u8;
-        slice::from_raw_parts(
-            base.offset(mem::size_of::<Self>() as isize)
-                .offset(self.file_name_length as isize),
+    /// File name of the whole header `record`, which must start with the fixed part of this header
+    pub fn file_name<'r>(&self, record: &'r [u8]) -> Result<&'r [u8], ParsingError> {
+        record_field(record, LOCAL_FILE_HEADER_LEN, self.file_name_length as usize)
+    }
+
+    /// Extra field of the whole header `record`, which must start with the fixed part of this header
+    pub fn extra_field<'r>(&self, record: &'r [u8]) -> Result<&'r [u8], ParsingError> {
+        record_field(
+            record,
+            LOCAL_FILE_HEADER_LEN + self.file_name_length as usize,
             self.extra_field_length as usize,
         )
     }
 }
 
-#[repr(packed)]
 #[derive(Debug, Copy, Clone)]
 struct CentralFileHeader {
     signature: Signature,
@@ -252,73 +542,211 @@ struct CentralFileHeader {
 
 impl CentralFileHeader {
     pub fn len(&self) -> usize {
-        mem::size_of::<Self>()
+        CENTRAL_FILE_HEADER_LEN
             + self.file_name_length as usize
             + self.extra_field_length as usize
             + self.file_comment_length as usize
     }
 
     pub fn local_header_len(&self) -> usize {
-        mem::size_of::<LocalFileHeader>()
+        LOCAL_FILE_HEADER_LEN
             + self.file_name_length as usize
             + self.extra_field_length as usize
     }
 
-    pub unsafe fn from_bytes(bytes: &[u8]) -> Option<&Self> {
-        (bytes.as_ptr() as *const Self).as_ref().and_then(|h| {
-            if matches!(h.signature, Signature::CentralFileHeader) {
-                Some(h)
-            } else {
-                None
-            }
+    /// Decode the fixed part of the header, `None` if the data is too short or the signature mismatches
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < CENTRAL_FILE_HEADER_LEN
+            || !matches!(Signature::try_from(bytes), Ok(Signature::CentralFileHeader)) {
+            return None;
+        }
+        Some(Self {
+            signature: Signature::CentralFileHeader,
+            version_made_by: le_u16(bytes, 4),
+            version_needed_to_extract: le_u16(bytes, 6),
+            general_purpose_bit_flag: le_u16(bytes, 8),
+            compression_method: le_u16(bytes, 10),
+            last_mod_file_time: le_u16(bytes, 12),
+            last_mod_file_date: le_u16(bytes, 14),
+            crc32: le_u32(bytes, 16),
+            compressed_size: le_u32(bytes, 20),
+            uncompressed_size: le_u32(bytes, 24),
+            file_name_length: le_u16(bytes, 28),
+            extra_field_length: le_u16(bytes, 30),
+            file_comment_length: le_u16(bytes, 32),
+            disk_number_start: le_u16(bytes, 34),
+            internal_file_attributes: le_u16(bytes, 36),
+            external_file_attributes: le_u32(bytes, 38),
+            relative_offset_of_local_header: le_u32(bytes, 42),
         })
     }
 
-    pub unsafe fn get_extra_field(&self) -> &[u8] {
-        let base = self as *const Self as *const u8;
-        slice::from_raw_parts(
-            base.offset((mem::size_of::<Self>() + self.file_name_length as usize) as isize),
+    /// File name of the whole header `record`, which must start with the fixed part of this header
+    pub fn file_name<'r>(&self, record: &'r [u8]) -> Result<&'r [u8], ParsingError> {
+        record_field(record, CENTRAL_FILE_HEADER_LEN, self.file_name_length as usize)
+    }
+
+    /// Extra field of the whole header `record`, which must start with the fixed part of this header
+    pub fn extra_field<'r>(&self, record: &'r [u8]) -> Result<&'r [u8], ParsingError> {
+        record_field(
+            record,
+            CENTRAL_FILE_HEADER_LEN + self.file_name_length as usize,
             self.extra_field_length as usize,
         )
     }
 
-    pub unsafe fn get_file_comment<'a>(&self) -> Result<&'a str, Utf8Error> {
-        let base: *const u8 = self as *const Self as _;
-        let b = slice::from_raw_parts(
-            base.add(mem::size_of::<Self>() + self.file_name_length as usize + self.extra_field_length as usize),
+    /// File comment of the whole header `record`, which must start with the fixed part of this header
+    pub fn file_comment<'r>(&self, record: &'r [u8]) -> Result<&'r [u8], ParsingError> {
+        record_field(
+            record,
+            CENTRAL_FILE_HEADER_LEN + self.file_name_length as usize + self.extra_field_length as usize,
             self.file_comment_length as usize,
-        );
-        core::str::from_utf8(b)
+        )
+    }
+
+    /// Whether a size or the offset is saturated, the actual value being in the ZIP64 extended information
+    pub fn has_zip64_values(&self) -> bool {
+        self.compressed_size == ZIP64_SATURATED
+            || self.uncompressed_size == ZIP64_SATURATED
+            || self.relative_offset_of_local_header == ZIP64_SATURATED
+    }
+
+    /// Sizes and local header offset, the saturated ones taken from the extended information `zip64`
+    pub fn sizes(&self, zip64: &Zip64ExtraField) -> EntrySizes {
+        let mut sizes = EntrySizes {
+            compressed_size: self.compressed_size.into(),
+            uncompressed_size: self.uncompressed_size.into(),
+            local_header_offset: self.relative_offset_of_local_header.into(),
+        };
+        zip64.resolve(&mut [&mut sizes.uncompressed_size, &mut sizes.compressed_size, &mut sizes.local_header_offset]);
+        sizes
+    }
+
+    /// Same as [`CentralFileHeader::sizes`], the extra field being read from the header at `offset` of `stream` if needed
+    pub fn read_sizes<S: Read + Seek>(&self, stream: &mut S, offset: u64) -> Result<EntrySizes, ParsingError> {
+        let zip64 = if self.has_zip64_values() {
+            let extra_field_offset = checked_offset(offset, CENTRAL_FILE_HEADER_LEN as u64 + self.file_name_length as u64)?;
+            Zip64ExtraField::read_from(stream, extra_field_offset, self.extra_field_length as usize)?
+        } else {
+            Zip64ExtraField::new()
+        };
+        Ok(self.sizes(&zip64))
     }
 }
 
-#[repr(packed)]
+/// 64 bits sizes and offset of an entry, ZIP64 extended information included
+#[derive(Debug, Default, Copy, Clone)]
+struct EntrySizes {
+    compressed_size: u64,
+    uncompressed_size: u64,
+    local_header_offset: u64,
+}
+
 #[derive(Debug, Copy, Clone)]
 struct CentralDirEnd {
     signature: Signature,
-    number_of_disk: u16,
-    number_of_start_central_directory_disk: u16,
-    total_entries_this_disk: u16,
-    total_entries_all_disk: u16,
-    size_of_the_central_directory: u32,
-    central_directory_offset: u32,
+    // wider than in the record, to hold the ZIP64 values
+    number_of_disk: u32,
+    number_of_start_central_directory_disk: u32,
+    total_entries_this_disk: u64,
+    total_entries_all_disk: u64,
+    size_of_the_central_directory: u64,
+    central_directory_offset: u64,
     zip_file_comment_length: u16,
+    /// length of the data preceding the archive, see [`CentralDirEnd::apply_prepended_data`]
+    prepended_len: u64,
 }
 
 impl CentralDirEnd {
     pub fn len(&self) -> usize {
-        mem::size_of::<Self>() + self.zip_file_comment_length as usize
+        CENTRAL_DIR_END_LEN + self.zip_file_comment_length as usize
     }
 
-    pub unsafe fn from_bytes(bytes: &[u8]) -> Option<&Self> {
-        (bytes.as_ptr() as *const Self).as_ref().and_then(|h| {
-            if matches!(h.signature, Signature::CentralDirEnd) {
-                Some(h)
-            } else {
-                None
-            }
+    /// Decode the fixed part of the record, `None` if the data is too short or the signature mismatches
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < CENTRAL_DIR_END_LEN
+            || !matches!(Signature::try_from(bytes), Ok(Signature::CentralDirEnd)) {
+            return None;
+        }
+        Some(Self {
+            signature: Signature::CentralDirEnd,
+            number_of_disk: le_u16(bytes, 4).into(),
+            number_of_start_central_directory_disk: le_u16(bytes, 6).into(),
+            total_entries_this_disk: le_u16(bytes, 8).into(),
+            total_entries_all_disk: le_u16(bytes, 10).into(),
+            size_of_the_central_directory: le_u32(bytes, 12).into(),
+            central_directory_offset: le_u32(bytes, 16).into(),
+            zip_file_comment_length: le_u16(bytes, 20),
+            prepended_len: 0,
         })
     }
+
+    /// Look for the record backwards from the end of the `stream_len` bytes of `stream`, over
+    /// the zip file comment which may follow it. Returns the offset of the record along with it.
+    ///
+    /// A signature is only taken for the record if the comment length brings to the end of the
+    /// stream, as the comment or the central directory may hold the same bytes.
+    pub fn find<S: Read + Seek>(stream: &mut S, stream_len: u64) -> Option<(u64, Self)> {
+        const SIGNATURE_LEN: usize = 4;
+        let lowest = stream_len.saturating_sub((CENTRAL_DIR_END_LEN + MAX_COMMENT_LEN) as u64);
+        // the last offset which may hold the record
+        let mut candidate = stream_len.checked_sub(CENTRAL_DIR_END_LEN as u64)?;
+        let mut buf = [0u8; 256];
+        loop {
+            let end = candidate + SIGNATURE_LEN as u64;
+            let start = cmp::max(lowest, end.saturating_sub(buf.len() as u64));
+            let chunk = read_at(stream, start, (end - start) as usize, &mut buf).ok()?;
+            for i in (0..=chunk.len() - SIGNATURE_LEN).rev() {
+                if le_u32(chunk, i) != Signature::CentralDirEnd as u32 {
+                    continue;
+                }
+                let offset = start + i as u64;
+                let mut record = [0u8; CENTRAL_DIR_END_LEN];
+                let end = read_at(stream, offset, CENTRAL_DIR_END_LEN, &mut record).ok().and_then(Self::from_bytes);
+                if let Some(end) = end.filter(|end| offset + end.len() as u64 == stream_len) {
+                    return Some((offset, end));
+                }
+            }
+            if start == lowest {
+                return None;
+            }
+            candidate = start - 1;
+        }
+    }
+
+    /// Whether the entry count is saturated, the actual one being in the ZIP64 end of central directory record
+    pub fn has_zip64_entries(&self) -> bool {
+        self.total_entries_this_disk == u16::MAX as u64 || self.total_entries_all_disk == u16::MAX as u64
+    }
+
+    /// Take the values of the ZIP64 end of central directory record `end`
+    pub fn apply_zip64(&mut self, end: &Zip64CentralDirEnd) {
+        self.number_of_disk = end.number_of_disk;
+        self.number_of_start_central_directory_disk = end.number_of_start_central_directory_disk;
+        self.total_entries_this_disk = end.total_entries_this_disk;
+        self.total_entries_all_disk = end.total_entries_all_disk;
+        self.size_of_the_central_directory = end.size_of_the_central_directory;
+        self.central_directory_offset = end.central_directory_offset;
+    }
+
+    /// Take into account the data preceding the archive, e.g. the stub of a self-extracting
+    /// archive, whose offsets are then relative to the start of the archive rather than to the
+    /// stream. The central directory ends right before the record at `next_record_offset`, the
+    /// ZIP64 end of central directory record or this one: the difference with the end given by
+    /// this record is the length of that data.
+    ///
+    /// The central directory offset is shifted, the local header offsets must be shifted by
+    /// `prepended_len`.
+    pub fn apply_prepended_data(&mut self, next_record_offset: u64) {
+        let claimed_end = self.central_directory_offset.saturating_add(self.size_of_the_central_directory);
+        self.prepended_len = next_record_offset.saturating_sub(claimed_end);
+        self.central_directory_offset += self.prepended_len;
+    }
+
+    /// Zip file comment of the whole `record`, which must start with the fixed part of this record
+    pub fn zip_file_comment<'r>(&self, record: &'r [u8]) -> Result<&'r [u8], ParsingError> {
+        record_field(record, CENTRAL_DIR_END_LEN, self.zip_file_comment_length as usize)
+    }
 }
 
 pub trait LocalFileOps {
@@ -326,13 +754,67 @@ pub trait LocalFileOps {
 
     fn file_size(&self) -> u64;
 
+    /// Whether the external attributes of the central directory record mark the entry as a
+    /// directory, see [`EntryMetadata::has_directory_attribute`].
+    ///
+    /// By default false, for the entries read without their central directory record.
+    fn has_directory_attribute(&self) -> bool {
+        false
+    }
+
+    /// Whether the entry is a directory, to be created rather than extracted.
+    ///
+    /// By default, an entry whose name ends with a `/` or whose attributes tell so. The data
+    /// length is not looked at, [`LocalFileOps::file_size`] being the compressed size of some
+    /// entries.
+    fn is_dir(&self) -> bool {
+        self.file_name().is_ok_and(|name| name.ends_with('/')) || self.has_directory_attribute()
+    }
+
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError>;
 
-    fn read_exact(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError>;
+    /// Fill the whole `buf`, returns its length, or [`ParsingError::StreamEnding`] if the entry ends before
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        let mut i = 0;
+        while i < buf.len() {
+            match self.read(&mut buf[i..])? {
+                0 => return Err(ParsingError::StreamEnding),
+                n => i += n,
+            }
+        }
+        Ok(i)
+    }
+}
+
+/// Extract the whole `entry`, using only `scratch` as the buffer.
+///
+/// The data is handed over to `sink` chunk by chunk, each chunk at most `scratch.len()` bytes,
+/// so the memory used never depends on the entry size. Returns the number of bytes extracted.
+pub fn extract_with_buffer<F, E>(entry: &mut impl LocalFileOps, scratch: &mut [u8], mut sink: F) -> Result<u64, E>
+where
+    F: FnMut(&[u8]) -> Result<(), E>,
+    E: From<ParsingError>,
+{
+    if scratch.is_empty() {
+        return Err(ParsingError::BufferTooSmall.into());
+    }
+
+    let size = entry.file_size();
+    let mut extracted = 0u64;
+    while extracted < size {
+        let len = cmp::min(scratch.len() as u64, size - extracted) as usize;
+        let n = entry.read(&mut scratch[..len])?;
+        if n == 0 {
+            return Err(ParsingError::StreamEnding.into());
+        }
+        sink(&scratch[..n])?;
+        extracted += n as u64;
+    }
+    Ok(extracted)
 }
 
 /// Parser event for callback
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum ParserEvent<'b, 'c, const N: usize> {
     LocalFileHeader(i32, &'c LocalFileInfo<N>),
     LocalFileData{file_index: i32, offset: usize, data: &'b [u8]},
@@ -342,9 +824,81 @@ pub enum ParserEvent<'b, 'c, const N: usize> {
 
     /// Pattern: (local_file_index, consumed_bytes)
     UserCancel(i32, usize),
+
+    /// A central directory file header was received, the file name is truncated to `N` bytes.
+    /// Pattern: (central_file_index, info, metadata)
+    CentralFileHeader(i32, &'c LocalFileInfo<N>, EntryMetadata),
+
+    /// The end of central directory record was received.
+    /// Pattern: (archive_index, statistics)
+    ArchiveEnd(u32, ArchiveStats),
+
+    /// All the data of a deflated entry was inflated, sent before its `LocalFileEnd`
+    /// by [`PassiveParser::with_inflate`].
+    /// Pattern: (local_file_index, decompressed_len)
+    LocalFileInflated(i32, u64),
+
+    /// The data descriptor following the data of an entry with general purpose bit 3 set was
+    /// received, sent before its `LocalFileEnd`.
+    /// Pattern: (local_file_index, descriptor)
+    LocalFileDataDescriptor(i32, DataDescriptor),
+
+    /// The extra field of a local file header, sent after its `LocalFileHeader` if not empty.
+    /// Only its first [`MAX_EXTRA_FIELD_LEN`] bytes are kept.
+    /// Pattern: (local_file_index, records)
+    LocalFileExtraField(i32, ExtraFields<'b>),
+
+    /// The extra field of a central directory file header, sent after its `CentralFileHeader`
+    /// if not empty. Only its first [`MAX_EXTRA_FIELD_LEN`] bytes are kept.
+    /// Pattern: (central_file_index, records)
+    CentralFileExtraField(i32, ExtraFields<'b>),
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Summary of a parsed archive, reported by [`ParserEvent::ArchiveEnd`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ArchiveStats {
+    /// Local file entries seen
+    pub entries: u64,
+    /// Central directory file headers seen
+    pub central_records: u64,
+    /// Total entries declared by the end of central directory record
+    pub declared_entries: u64,
+    /// Local file data bytes streamed
+    pub compressed_bytes: u64,
+    /// Sum of the uncompressed sizes of the local file entries
+    pub uncompressed_bytes: u64,
+}
+
+impl ArchiveStats {
+    /// Whether the local entries, the central records and the declared entry count agree
+    pub fn is_consistent(&self) -> bool {
+        self.entries == self.central_records && self.central_records == self.declared_entries
+    }
+}
+
+/// Parser event not borrowing anything, queued by [`PassiveParser::feed_events`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum OwnedEvent<const N: usize> {
+    LocalFileHeader(i32, LocalFileInfo<N>),
+    /// The file data is `data[range]` of the data passed to `feed_events`
+    LocalFileData{file_index: i32, offset: usize, range: Range<usize>},
+    LocalFileEnd(i32),
+
+    ParsingError(i32, ParsingError),
+
+    CentralFileHeader(i32, LocalFileInfo<N>, EntryMetadata),
+    ArchiveEnd(u32, ArchiveStats),
+    LocalFileInflated(i32, u64),
+    LocalFileDataDescriptor(i32, DataDescriptor),
+    /// File data held back while looking for a data descriptor, it was part of the data passed
+    /// to an earlier `feed_events` call
+    LocalFileHeldData{file_index: i32, offset: usize, data: heapless::Vec<u8, MAX_DATA_DESCRIPTOR_LEN>},
+    /// Read the records with [`ExtraFields::new`]
+    LocalFileExtraField(i32, heapless::Vec<u8, MAX_EXTRA_FIELD_LEN>),
+    CentralFileExtraField(i32, heapless::Vec<u8, MAX_EXTRA_FIELD_LEN>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum ParsingError {
     /// Pattern: (local_file_index, filename_len)
     LocalFileNameTooLong(i32, usize),
@@ -370,6 +924,89 @@ pub enum ParsingError {
     InvalidSignature,
 
     DataNotEnough,
+
+    /// Disk numbers of a single-file archive are not all 0
+    DiskNumberMismatch,
+
+    /// The buffer provided by the caller can not hold the data
+    BufferTooSmall,
+
+    /// Entry data goes on past the declared uncompressed size.
+    /// Pattern: (uncompressed_size)
+    UncompressedSizeExceeded(u64),
+
+    /// Entry data ends before reaching the declared uncompressed size.
+    /// Pattern: (produced_size)
+    UncompressedSizeShort(u64),
+
+    /// A saved parser state is corrupted or from an incompatible version
+    InvalidSavedState,
+
+    /// Strict mode: a local file header follows the start of the central directory
+    LocalHeaderAfterCentralDirectory,
+
+    /// Strict mode: a record follows the end of central directory record
+    RecordAfterCentralDirEnd,
+
+    /// Strict mode, or the central directory ends before the entry count of the end of central
+    /// directory record: central directory records don't match the end of central directory record.
+    /// Pattern: (central_records, declared_entries)
+    CentralEntryCountMismatch(u64, u64),
+
+    /// The work was cancelled through a [`CancelToken`]
+    Cancelled,
+
+    /// The compressed data is corrupted or truncated
+    DecompressionFailed,
+
+    /// No decompressor available for the compression method
+    UnsupportedCompressMethod(CompressMethod),
+
+    /// The entry, or the whole central directory, is encrypted in a way the parser can't handle
+    UnsupportedEncryption(EncryptionKind),
+
+    /// The password provider gave up before a password passed the check of the encrypted entry.
+    /// Pattern: (wrong_passwords)
+    IncorrectPassword(u32),
+
+    /// An extra field record runs past the end of the extra field.
+    /// Pattern: (record_offset)
+    InvalidExtraField(usize),
+
+    /// The CRC-32 of the entry data read doesn't match the one of the central directory.
+    /// Pattern: (expected, computed)
+    CrcMismatch(u32, u32),
+
+    /// The local file header of the entry disagrees with its central directory record, see
+    /// `SeekingParser::with_strict`.
+    /// Pattern: (field)
+    LocalHeaderMismatch(HeaderField),
+
+    /// The lengths in the headers of an entry make its data run past the start of the central
+    /// directory, or past the stream length given to the parser.
+    /// Pattern: (data_end_offset)
+    EntryOutOfBounds(u64),
+
+    /// An offset or a length computed from the header fields doesn't fit, e.g. crafted to wrap
+    /// around, or an entry too large for `usize` on this target.
+    Overflow,
+
+    /// Two entries share bytes of the stream, see [`ClaimedRanges`].
+    /// Pattern: (claiming_file_index, file_index)
+    OverlappingEntries(usize, usize),
+
+    /// The sizes of the entry follow its data in a data descriptor, so the end of the data can't
+    /// be told without parsing it, see `SequentialParser`.
+    /// Pattern: (local_file_index)
+    UnknownDataLength(i32),
+
+    /// Writing to the flash failed
+    #[cfg(feature = "embedded-storage")]
+    Flash(embedded_storage::nor_flash::NorFlashErrorKind),
+
+    /// Reading a [`std::io::Read`] stream failed, the error is the source of this one
+    #[cfg(feature = "std")]
+    Io(IoError),
 }
 
 impl Display for ParsingError {
@@ -385,13 +1022,137 @@ impl Display for ParsingError {
             Self::InvalidStream => write!(f, "InvalidStream"),
             Self::InvalidSignature => write!(f, "InvalidSignature"),
             Self::DataNotEnough => write!(f, "DataNotEnough"),
+            Self::DiskNumberMismatch => write!(f, "DiskNumberMismatch"),
+            Self::BufferTooSmall => write!(f, "BufferTooSmall"),
+            Self::UncompressedSizeExceeded(n) => write!(f, "UncompressedSizeExceeded({})", n),
+            Self::UncompressedSizeShort(n) => write!(f, "UncompressedSizeShort({})", n),
+            Self::InvalidSavedState => write!(f, "InvalidSavedState"),
+            Self::LocalHeaderAfterCentralDirectory => write!(f, "LocalHeaderAfterCentralDirectory"),
+            Self::RecordAfterCentralDirEnd => write!(f, "RecordAfterCentralDirEnd"),
+            Self::CentralEntryCountMismatch(records, declared) => write!(f, "CentralEntryCountMismatch({}, {})", records, declared),
+            Self::Cancelled => write!(f, "Cancelled"),
+            Self::DecompressionFailed => write!(f, "DecompressionFailed"),
+            Self::UnsupportedCompressMethod(method) => write!(f, "UnsupportedCompressMethod({:?})", method),
+            Self::UnsupportedEncryption(kind) => write!(f, "UnsupportedEncryption({:?})", kind),
+            Self::IncorrectPassword(n) => write!(f, "IncorrectPassword({})", n),
+            Self::InvalidExtraField(offset) => write!(f, "InvalidExtraField({})", offset),
+            Self::CrcMismatch(expected, computed) => write!(f, "CrcMismatch({:08x}, {:08x})", expected, computed),
+            Self::LocalHeaderMismatch(field) => write!(f, "LocalHeaderMismatch({:?})", field),
+            Self::EntryOutOfBounds(end) => write!(f, "EntryOutOfBounds({})", end),
+            Self::Overflow => write!(f, "Overflow"),
+            Self::OverlappingEntries(claiming, i) => write!(f, "LocalFile #{}: OverlappingEntries({})", i, claiming),
+            Self::UnknownDataLength(i) => write!(f, "LocalFile #{}: UnknownDataLength", i),
+            #[cfg(feature = "embedded-storage")]
+            Self::Flash(kind) => write!(f, "Flash({})", kind),
+            #[cfg(feature = "std")]
+            Self::Io(ref err) => write!(f, "Io({})", err.get_ref()),
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ParsingError {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{}", defmt::Display2Format(self))
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParsingError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err.get_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// The error of a `std::io::Read` stream, unless it comes from a reader of the crate, whose
+/// original error is given back (see `From<ParsingError> for io::Error`)
+#[cfg(feature = "std")]
+impl From<io::Error> for ParsingError {
+    fn from(err: io::Error) -> Self {
+        match err.downcast::<ParsingError>() {
+            Ok(err) => err,
+            Err(err) => Self::Io(IoError(Arc::new(err))),
+        }
+    }
+}
+
+/// The error of the `std::io` traits implemented by the readers of the crate: the error of the
+/// stream as it was for [`ParsingError::Io`], [`io::ErrorKind::UnexpectedEof`] for
+/// [`ParsingError::StreamEnding`] and [`io::ErrorKind::InvalidData`] for the others
+#[cfg(feature = "std")]
+impl From<ParsingError> for io::Error {
+    fn from(err: ParsingError) -> Self {
+        match err {
+            ParsingError::Io(IoError(err)) => Arc::try_unwrap(err)
+                .unwrap_or_else(|shared| io::Error::new(shared.kind(), ParsingError::Io(IoError(shared)))),
+            ParsingError::StreamEnding => io::Error::new(io::ErrorKind::UnexpectedEof, err),
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
         }
     }
 }
 
+/// [`io::Error`] kept by [`ParsingError::Io`], shared so that the error can still be cloned.
+///
+/// Two of them are equal when their [`io::ErrorKind`] is.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct IoError(Arc<io::Error>);
+
+#[cfg(feature = "std")]
+impl IoError {
+    pub fn kind(&self) -> io::ErrorKind {
+        self.0.kind()
+    }
+
+    pub fn get_ref(&self) -> &io::Error {
+        &self.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl PartialEq for IoError {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind() == other.kind()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Eq for IoError {}
+
+#[cfg(feature = "std")]
+impl Hash for IoError {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.kind().hash(state);
+    }
+}
+
+/// Encryption reported by [`ParsingError::UnsupportedEncryption`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EncryptionKind {
+    /// PKWARE strong encryption of the entry data (general purpose bit 6)
+    Strong,
+    /// The central directory is encrypted and the local header values are masked (general
+    /// purpose bit 13), so the entries can't be told apart
+    CentralDirectory,
+}
+
+/// Field of a local file header reported by [`ParsingError::LocalHeaderMismatch`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HeaderField {
+    FileName,
+    CompressionMethod,
+    Crc32,
+    CompressedSize,
+    UncompressedSize,
+}
+
 #[repr(u8)]
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
 pub enum CompressMethod {
+    #[default]
     Uncompress = 0,
     Shrunk = 1,
 
@@ -402,6 +1163,8 @@ pub enum CompressMethod {
 
     Imploded = 6,
     Deflated = 8,
+    /// Deflate with a 64 KiB window
+    Deflate64 = 9,
     BZIP2 = 12,
     LZMA = 14,
 
@@ -420,6 +1183,9 @@ pub enum CompressMethod {
     /// JPEG variant
     JPEG = 96,
 
+    /// WinZip AES encryption, the actual method is in the AES extra field
+    Aes = 99,
+
     Unknown = 0xFF,
 }
 
@@ -434,6 +1200,7 @@ impl From<u16> for CompressMethod {
             5 => Self::Reduced4,
             6 => Self::Imploded,
             8 => Self::Deflated,
+            9 => Self::Deflate64,
             12 => Self::BZIP2,
             14 => Self::LZMA,
             19 => Self::LZ77z,
@@ -441,17 +1208,22 @@ impl From<u16> for CompressMethod {
             94 => Self::MP3,
             95 => Self::XZ,
             96 => Self::JPEG,
+            99 => Self::Aes,
             _ => Self::Unknown,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LocalFileInfo<const N: usize> {
     file_name_buffer: [u8; N],
     file_name_length: usize,
     extra_field_length: usize,
     file_data_offset: u64,
+    /// general purpose bit 0
+    encrypted: bool,
+    /// the name is UTF-8: general purpose bit 11, or taken from the Unicode path extra field
+    utf8_name: bool,
 
     pub compression_method: CompressMethod,
     pub compressed_size: u64,
@@ -474,13 +1246,108 @@ impl<const N: usize> LocalFileInfo<N> {
         self
     }
 
+    /// Set from the general purpose bit flag of the header
+    pub fn with_general_purpose_bit_flag(mut self, flag: u16) -> Self {
+        self.encrypted = flag & FLAG_ENCRYPTED != 0;
+        self.utf8_name = flag & FLAG_UTF8 != 0;
+        self
+    }
+
+    /// The entry data is encrypted, reading it gives the ciphertext unless the entry is unlocked
+    pub fn is_encrypted(&self) -> bool {
+        self.encrypted
+    }
+
     pub fn file_name(&self) -> Result<&str, Utf8Error> {
-        str::from_utf8(&self.file_name_buffer[..self.file_name_length])
+        str::from_utf8(self.file_name_bytes())
+    }
+
+    /// File name as stored in the header, or the one of the Unicode path extra field taking its place
+    pub fn file_name_bytes(&self) -> &[u8] {
+        &self.file_name_buffer[..self.file_name_length]
+    }
+
+    /// Whether the name is UTF-8, as flagged by general purpose bit 11 or taken from the Unicode
+    /// path extra field. Other names are in code page 437.
+    pub fn has_utf8_name(&self) -> bool {
+        self.utf8_name
+    }
+
+    /// File name decoded according to its encoding, through `buf` if it is in code page 437.
+    ///
+    /// A name flagged as UTF-8 which is not is taken as code page 437 as well.
+    pub fn decode_file_name<'s>(&'s self, buf: &'s mut [u8]) -> Result<&'s str, ParsingError> {
+        let name = self.file_name_bytes();
+        match str::from_utf8(name) {
+            Ok(name) if self.utf8_name || name.is_ascii() => Ok(name),
+            _ => decode_cp437(name, buf),
+        }
+    }
+
+    /// File name decoded according to its encoding, see [`LocalFileInfo::decode_file_name`]
+    #[cfg(feature = "std")]
+    pub fn decoded_file_name(&self) -> Cow<'_, str> {
+        let name = self.file_name_bytes();
+        match str::from_utf8(name) {
+            Ok(name) if self.utf8_name || name.is_ascii() => Cow::Borrowed(name),
+            _ => Cow::Owned(name.iter().map(|&byte| cp437_char(byte)).collect::<String>()),
+        }
+    }
+
+    /// Take the name of the Info-ZIP Unicode path extra field in `fields` instead of the header
+    /// one, unless the header name is UTF-8 already (general purpose bit 11) or the Unicode name
+    /// doesn't fit in `N` bytes
+    pub(crate) fn apply_unicode_path(&mut self, fields: ExtraFields<'_>) {
+        if self.utf8_name {
+            return;
+        }
+        match fields.unicode_path(self.file_name_bytes()) {
+            Some(name) if name.len() <= N => {
+                self.file_name_buffer[..name.len()].copy_from_slice(name);
+                self.file_name_length = name.len();
+                self.utf8_name = true;
+            }
+            _ => {}
+        }
     }
 
     pub fn file_size(&self) -> u64 {
         self.compressed_size
     }
+
+    /// Whether the entry is a directory: its name ends with a `/` and it has no data
+    pub fn is_dir(&self) -> bool {
+        self.file_name_bytes().ends_with(b"/") && self.uncompressed_size == 0
+    }
+}
+
+// Only the used part of the name buffer takes part in comparison and hashing
+impl<const N: usize> PartialEq for LocalFileInfo<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.file_name_buffer[..self.file_name_length] == other.file_name_buffer[..other.file_name_length]
+            && self.extra_field_length == other.extra_field_length
+            && self.file_data_offset == other.file_data_offset
+            && self.encrypted == other.encrypted
+            && self.utf8_name == other.utf8_name
+            && self.compression_method == other.compression_method
+            && self.compressed_size == other.compressed_size
+            && self.uncompressed_size == other.uncompressed_size
+    }
+}
+
+impl<const N: usize> Eq for LocalFileInfo<N> {}
+
+impl<const N: usize> Hash for LocalFileInfo<N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.file_name_buffer[..self.file_name_length].hash(state);
+        self.extra_field_length.hash(state);
+        self.file_data_offset.hash(state);
+        self.encrypted.hash(state);
+        self.utf8_name.hash(state);
+        self.compression_method.hash(state);
+        self.compressed_size.hash(state);
+        self.uncompressed_size.hash(state);
+    }
 }
 
 impl<const N: usize> Default for LocalFileInfo<N> {
@@ -490,6 +1357,8 @@ impl<const N: usize> Default for LocalFileInfo<N> {
             file_name_length: 0,
             extra_field_length: 0,
             file_data_offset: 0,
+            encrypted: false,
+            utf8_name: false,
             compression_method: CompressMethod::Uncompress,
             compressed_size: 0,
             uncompressed_size: 0,
@@ -497,773 +1366,6460 @@ impl<const N: usize> Default for LocalFileInfo<N> {
     }
 }
 
-/// File instance in the zip pack. You can get it by iterating over the [`Parser`].
-#[derive(Debug)]
-pub struct LocalFile<'a, S: Read + Seek, const N: usize> {
-    pub info: LocalFileInfo<N>,
-
-    stream: *mut S,
-    stream_origin: u64,
-    stream_position: u64,
-    _marker: PhantomData<&'a mut S>,
+/// Last modification time in MS-DOS format, as stored in the headers
+///
+/// The fields are ordered so that comparing two values compares the moments they represent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DosDateTime {
+    pub date: u16,
+    pub time: u16,
 }
 
-impl<'a, S: Read + Seek, const N: usize> LocalFile<'a, S, N> {
-    pub fn with_compression_method(mut self, method: CompressMethod) -> Self {
-        self.info.compression_method = method;
-        self
+impl DosDateTime {
+    pub fn new(date: u16, time: u16) -> Self {
+        Self { date, time }
     }
 
-    pub fn with_compressed_size(mut self, size: u64) -> Self {
-        self.info.compressed_size = size;
-        self
+    pub fn year(&self) -> u16 {
+        1980 + (self.date >> 9)
     }
 
-    pub fn with_uncompressed_size(mut self, size: u64) -> Self {
-        self.info.uncompressed_size = size;
-        self
+    pub fn month(&self) -> u8 {
+        ((self.date >> 5) & 0x0F) as u8
     }
 
-    pub fn with_stream(mut self, stream: &mut S) -> Self {
-        self.stream = stream;
-        self.stream_origin = stream.seek(SeekFrom::Current(0)).unwrap_or(0);
-        self.stream_position = self.stream_origin;
-        self
+    pub fn day(&self) -> u8 {
+        (self.date & 0x1F) as u8
     }
-}
 
-impl<'a, S: Read + Seek, const N: usize> Default for LocalFile<'a, S, N> {
-    fn default() -> Self {
-        Self {
-            info: Default::default(),
-            stream: ptr::null_mut(),
-            stream_origin: 0,
-            stream_position: 0,
-            _marker:  PhantomData::default(),
-        }
+    pub fn hour(&self) -> u8 {
+        (self.time >> 11) as u8
     }
-}
 
-impl<'a, S: Read + Seek, const N: usize> LocalFileOps for LocalFile<'a, S, N> {
-    fn file_name(&self) -> Result<&str, Utf8Error> {
-        self.info.file_name()
+    pub fn minute(&self) -> u8 {
+        ((self.time >> 5) & 0x3F) as u8
     }
 
-    fn file_size(&self) -> u64 {
-        self.info.file_size()
+    /// DOS time only has a 2 seconds resolution
+    pub fn second(&self) -> u8 {
+        ((self.time & 0x1F) * 2) as u8
     }
+}
 
-    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
-        unsafe {
-            let stream = self.stream
-                .as_mut()
-                .ok_or(ParsingError::InvalidStream)?;
+/// Host system of the archiver, the high byte of the version made by, telling how to read the
+/// external file attributes
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum HostSystem {
+    /// MS-DOS, and FAT file systems of the other systems
+    #[default]
+    Fat = 0,
+    Amiga = 1,
+    OpenVms = 2,
+    Unix = 3,
+    VmCms = 4,
+    AtariSt = 5,
+    /// OS/2 HPFS
+    Os2 = 6,
+    /// Classic Mac OS
+    Macintosh = 7,
+    ZSystem = 8,
+    CpM = 9,
+    /// Windows NTFS
+    Ntfs = 10,
+    Mvs = 11,
+    Vse = 12,
+    AcornRisc = 13,
+    Vfat = 14,
+    AlternateMvs = 15,
+    BeOs = 16,
+    Tandem = 17,
+    Os400 = 18,
+    /// macOS
+    Darwin = 19,
 
-            stream.seek(SeekFrom::Start(self.stream_position)).map_err(|_| ParsingError::InvalidStream)?;
-            let bytes_read = stream.read(buf)?;
-            self.stream_position += u64::try_from(bytes_read).map_err(|_| ParsingError::InvalidStream)?;
+    Unknown = 0xFF,
+}
 
-            Ok(bytes_read)
+impl From<u8> for HostSystem {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Fat,
+            1 => Self::Amiga,
+            2 => Self::OpenVms,
+            3 => Self::Unix,
+            4 => Self::VmCms,
+            5 => Self::AtariSt,
+            6 => Self::Os2,
+            7 => Self::Macintosh,
+            8 => Self::ZSystem,
+            9 => Self::CpM,
+            10 => Self::Ntfs,
+            11 => Self::Mvs,
+            12 => Self::Vse,
+            13 => Self::AcornRisc,
+            14 => Self::Vfat,
+            15 => Self::AlternateMvs,
+            16 => Self::BeOs,
+            17 => Self::Tandem,
+            18 => Self::Os400,
+            19 => Self::Darwin,
+            _ => Self::Unknown,
         }
     }
+}
 
-    fn read_exact(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
-        unsafe {
-            let stream = self.stream
-                .as_mut()
-                .ok_or(ParsingError::InvalidStream)?;
+/// MS-DOS attributes, the low byte of the external file attributes of entries archived on
+/// MS-DOS, OS/2 or Windows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct DosAttributes(pub u8);
 
-            stream.seek(SeekFrom::Start(self.stream_position)).map_err(|_| ParsingError::InvalidStream)?;
-            let bytes_read = stream.read_exact(buf)?;
-            self.stream_position += u64::try_from(bytes_read).map_err(|_| ParsingError::InvalidStream)?;
+impl DosAttributes {
+    pub const READ_ONLY: u8 = 0x01;
+    pub const HIDDEN: u8 = 0x02;
+    pub const SYSTEM: u8 = 0x04;
+    pub const DIRECTORY: u8 = 0x10;
+    pub const ARCHIVE: u8 = 0x20;
 
-            Ok(bytes_read)
-        }
+    pub fn is_read_only(&self) -> bool {
+        self.0 & Self::READ_ONLY != 0
     }
-}
 
-// #[cfg(feature = "std")]
-// impl<'a, S: Read + Seek> io::Read for LocalFile<'a, S> {
-//     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-//         todo!()
-//     }
-// }
+    pub fn is_hidden(&self) -> bool {
+        self.0 & Self::HIDDEN != 0
+    }
 
-pub trait Parser<S: Read> {
-    /// Creating an instance
-    fn new(stream: &mut S) -> Self;
-}
+    pub fn is_system(&self) -> bool {
+        self.0 & Self::SYSTEM != 0
+    }
 
-/// Zip file parser, creating it by [`new`](struct.Parser.html#method.new) method
-pub struct SeekingParser<'a, S: Read + Seek, const N: usize = 128> {
-    /// It will be None when no central directory was found
-    pub number_of_files: Option<usize>,
+    pub fn is_directory(&self) -> bool {
+        self.0 & Self::DIRECTORY != 0
+    }
 
-    central_directory_offset: u64,
-    /// offset relative to the central dir
-    next_entry_offset: u64,
+    /// Changed since the last backup
+    pub fn is_archive(&self) -> bool {
+        self.0 & Self::ARCHIVE != 0
+    }
+}
 
-    /// holding the file handle
-    stream: &'a mut S,
+/// Everything the central directory records about an entry
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct EntryMetadata {
+    pub version_made_by: u16,
+    pub version_needed_to_extract: u16,
+    pub general_purpose_bit_flag: u16,
+    pub compression_method: CompressMethod,
+    pub last_modified: DosDateTime,
+    pub crc32: u32,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+    pub disk_number_start: u16,
+    pub internal_file_attributes: u16,
+    pub external_file_attributes: u32,
+    /// Offset of the local file header in the stream
+    pub local_header_offset: u64,
+
+    /// Offset of the central file header in the stream
+    pub central_header_offset: u64,
+    /// Stream offset of the extra field in the central file header
+    pub extra_field_offset: u64,
+    pub extra_field_length: u16,
+    /// Stream offset of the file comment in the central file header
+    pub file_comment_offset: u64,
+    pub file_comment_length: u16,
 }
 
-impl<'a, S: Read + Seek, const N: usize> SeekingParser<'a, S, N> {
-    pub fn new(stream: &'a mut S) -> Self {
-        // seek to the start of central directory
-        let mut central_directory_offset = 0u64;
-        let mut number_of_files = None;
-        if let Some(stream_len) = stream.stream_len() {
-            const READ_LEN: usize = mem::size_of::<CentralDirEnd>();
-            if let Ok(_) = stream.seek(SeekFrom::Start(stream_len - READ_LEN as u64)) {
-                let mut buf = [0u8; READ_LEN];
-                if matches!(stream.read(&mut buf), Ok(n) if n == buf.len()) {
-                    if matches!(Signature::try_from(buf.as_slice()), Ok(Signature::CentralDirEnd)) {
-                        let central_dir = unsafe { CentralDirEnd::from_bytes(&buf).unwrap() };
-                        let _ = stream.seek(SeekFrom::Start(central_dir.central_directory_offset as u64));
-                        central_directory_offset = central_dir.central_directory_offset.into();
-                        number_of_files = Some(central_dir.total_entries_this_disk.into());
-                    } else {
-                        let _ = stream.rewind();
-                    }
-                } else {
-                    let _ = stream.rewind();
-                }
-            } else {
-                #[cfg(feature = "std")]
-                eprintln!("seek is unavailable, use SequentialParser instead");
+impl EntryMetadata {
+    /// The entry data is encrypted (general purpose bit 0)
+    pub fn is_encrypted(&self) -> bool {
+        self.general_purpose_bit_flag & FLAG_ENCRYPTED != 0
+    }
+
+    /// The archiver found the entry to be text (internal file attributes bit 0), e.g. to
+    /// translate its newlines
+    pub fn is_text(&self) -> bool {
+        self.internal_file_attributes & INTERNAL_TEXT != 0
+    }
+
+    /// Host system of the archiver, high byte of the version made by
+    pub fn host_system(&self) -> HostSystem {
+        HostSystem::from((self.version_made_by >> 8) as u8)
+    }
+
+    /// Unix file type and permission bits (`st_mode`), the high 16 bits of the external file
+    /// attributes, if the entry was archived on a Unix host which recorded them
+    pub fn unix_mode(&self) -> Option<u32> {
+        let mode = self.external_file_attributes >> 16;
+        match self.host_system() {
+            HostSystem::Unix | HostSystem::Darwin if mode != 0 => Some(mode),
+            _ => None,
+        }
+    }
+
+    /// The external file attributes tell the entry is a directory, as Unix file type or as
+    /// MS-DOS attribute
+    pub fn has_directory_attribute(&self) -> bool {
+        const UNIX_FILE_TYPE: u32 = 0o170000;
+        const UNIX_DIRECTORY: u32 = 0o040000;
+        self.unix_mode().is_some_and(|mode| mode & UNIX_FILE_TYPE == UNIX_DIRECTORY)
+            || self.dos_attributes().is_some_and(|attributes| attributes.is_directory())
+    }
+
+    /// MS-DOS attributes, the low byte of the external file attributes, if the entry was
+    /// archived on MS-DOS, OS/2 or Windows
+    pub fn dos_attributes(&self) -> Option<DosAttributes> {
+        match self.host_system() {
+            HostSystem::Fat | HostSystem::Os2 | HostSystem::Ntfs | HostSystem::Vfat => {
+                Some(DosAttributes(self.external_file_attributes as u8))
             }
-        } else {
-            #[cfg(feature = "std")]
-            eprintln!("seek is unavailable, use SequentialParser instead");
+            _ => None,
         }
+    }
 
+    fn from_central_header(header: &CentralFileHeader, sizes: &EntrySizes, central_header_offset: u64) -> Self {
+        let extra_field_offset = central_header_offset
+            + CENTRAL_FILE_HEADER_LEN as u64
+            + header.file_name_length as u64;
         Self {
-            stream,
-            central_directory_offset,
-            next_entry_offset: 0,
-            number_of_files,
+            version_made_by: header.version_made_by,
+            version_needed_to_extract: header.version_needed_to_extract,
+            general_purpose_bit_flag: header.general_purpose_bit_flag,
+            compression_method: header.compression_method.into(),
+            last_modified: DosDateTime::new(header.last_mod_file_date, header.last_mod_file_time),
+            crc32: header.crc32,
+            compressed_size: sizes.compressed_size,
+            uncompressed_size: sizes.uncompressed_size,
+            disk_number_start: header.disk_number_start,
+            internal_file_attributes: header.internal_file_attributes,
+            external_file_attributes: header.external_file_attributes,
+            local_header_offset: sizes.local_header_offset,
+            central_header_offset,
+            extra_field_offset,
+            extra_field_length: header.extra_field_length,
+            file_comment_offset: extra_field_offset + header.extra_field_length as u64,
+            file_comment_length: header.file_comment_length,
         }
     }
 }
 
-impl<'a, S: Read + Seek, const N: usize> Iterator for SeekingParser<'a, S, N> {
-    type Item = LocalFile<'a, S, N>;
+/// File instance in the zip pack. You can get it by iterating over the [`Parser`].
+///
+/// With the `inflate` feature and without `std`, it holds the [`Inflater`] of deflated entries,
+/// about 43 KiB, mind the stack. With `std`, the inflater is allocated on the first read instead.
+#[derive(Debug)]
+pub struct LocalFile<'a, S: Read + Seek, const N: usize> {
+    pub info: LocalFileInfo<N>,
+    /// Metadata from the central directory
+    pub metadata: EntryMetadata,
 
-    fn next(&mut self) -> Option<Self::Item> {
-        // seek read
-        let _ = self.stream.seek(
-            SeekFrom::Start(self.central_directory_offset + self.next_entry_offset)
-        );
-        let mut buf = [0u8; mem::size_of::<CentralFileHeader>()];
-        match self.stream.read(&mut buf) {
-            Ok(n) if n == buf.len() => {
-                if let Some(file_info) = unsafe { CentralFileHeader::from_bytes(&buf) } {
-                    // #[cfg(feature = "std")]
-                    // dbg!(file_info);
-                    let mut file = LocalFile::default()
-                        .with_compression_method(CompressMethod::from(file_info.compression_method))
-                        .with_compressed_size(file_info.compressed_size as u64)
-                        .with_uncompressed_size(file_info.uncompressed_size as u64)
-                        .with_stream(self.stream);
-                    if let Ok(n) = self
-                        .stream
-                        .read(&mut file.info.file_name_buffer[..file_info.file_name_length as usize]) {
-                        file.info.file_name_length = n;
-                    }
+    /// shared with the parser and the other entries, borrowed for each read
+    stream: Option<&'a RefCell<S>>,
+    stream_origin: u64,
+    stream_position: u64,
 
-                    // set next entry
-                    self.next_entry_offset += file_info.len() as u64;
+    /// length produced by the decompressor
+    decompressed: u64,
+    /// CRC-32 of the uncompressed data read so far
+    crc32: u32,
+    /// check the CRC-32 once all the uncompressed data is read
+    check_crc: bool,
+    crc_engine: &'static dyn Crc32,
+    /// the headers can't be trusted: out of bounds, or disagreeing in strict mode
+    unreadable: Option<ParsingError>,
+    /// index given by the parser, for the error contexts
+    file_index: i32,
+    /// a data descriptor follows the data (general purpose bit 3)
+    data_descriptor: bool,
+    /// the sizes of the data descriptor take 8 bytes
+    zip64_descriptor: bool,
+    /// decrypting the data, once unlocked
+    cipher: Option<ZipCrypto>,
+    /// built-in decompressor of deflated entries, allocated for the first one
+    #[cfg(all(feature = "inflate", feature = "std"))]
+    inflater: Option<Box<Inflater>>,
+    /// built-in decompressor of deflated entries, inline without an allocator
+    #[cfg(all(feature = "inflate", not(feature = "std")))]
+    inflater: Inflater,
+    /// built-in decompressor of Zstandard entries
+    #[cfg(feature = "zstd")]
+    zstd: ZstdDecoder,
+    /// built-in decompressor of BZIP2 entries
+    #[cfg(feature = "bzip2")]
+    bzip2: Bzip2Decoder,
+    /// built-in decompressor of LZMA entries
+    #[cfg(feature = "lzma")]
+    lzma: LzmaDecoder,
+    /// built-in decompressor of XZ entries
+    #[cfg(feature = "xz")]
+    xz: XzDecoder,
+    /// built-in decompressor of Deflate64 entries
+    #[cfg(feature = "deflate64")]
+    inflater64: Inflater64,
+}
 
-                    // seek to file data
-                    let mut local_header_buf = [0u8; mem::size_of::<LocalFileHeader>()];
-                    let _ = self.stream.seek(SeekFrom::Start(file_info.relative_offset_of_local_header as u64));
-                    if matches!(self.stream.read(&mut local_header_buf), Ok(n) if n == local_header_buf.len()) {
-                        if let Some(local_header) = unsafe { LocalFileHeader::from_bytes(&local_header_buf) } {
-                            file.info.file_data_offset = file_info.relative_offset_of_local_header as u64 + local_header.len() as u64;
-                            file.stream_origin = file.info.file_data_offset;
-                            file.stream_position = file.info.file_data_offset;
-                            Some(file)
-                        } else {
-                            #[cfg(feature = "std")]
-                            eprintln!("get LocalFileHeader from raw ptr({:02X?}) failed", local_header_buf);
-                            None
-                        }
-                    } else {
-                        #[cfg(feature = "std")]
-                        eprintln!("read local header failed");
-                        None
-                    }
-                } else {
-                    #[cfg(feature = "std")]
-                    eprintln!("get CentralFileHeader from raw ptr({:02X?}) failed", buf);
-                    None
-                }
-            }
-            Ok(_n) => {
-                #[cfg(feature = "std")]
-                eprintln!("no enough data: {}", _n);
-                None
-            }
-            Err(_e) => {
-                #[cfg(feature = "std")]
-                eprintln!("stream read err: {}", _e);
-                None
-            }
-        }
+impl<'a, S: Read + Seek, const N: usize> LocalFile<'a, S, N> {
+    pub fn with_compression_method(mut self, method: CompressMethod) -> Self {
+        self.info.compression_method = method;
+        self
+    }
+
+    pub fn with_compressed_size(mut self, size: u64) -> Self {
+        self.info.compressed_size = size;
+        self
+    }
+
+    pub fn with_uncompressed_size(mut self, size: u64) -> Self {
+        self.info.uncompressed_size = size;
+        self
+    }
+
+    /// Check the CRC-32 of the uncompressed data against [`EntryMetadata::crc32`] at the end of
+    /// the entry, on by default with the `crc-check` feature
+    pub fn with_crc_check(mut self, check: bool) -> Self {
+        self.check_crc = check;
+        self
+    }
+
+    /// Compute the CRC-32 with `engine` instead of [`SoftwareCrc32`]
+    pub fn with_crc_engine(mut self, engine: &'static dyn Crc32) -> Self {
+        self.crc_engine = engine;
+        self
     }
-}
 
-/*pub struct SequentialParser<'a, S: Read, const N: usize = 128> {
-    /// holding the file handle
-    stream: &'a mut S,
+    /// The entry data starts at the current position of `stream`
+    pub fn with_stream(mut self, stream: &'a RefCell<S>) -> Self {
+        self.stream = Some(stream);
+        self.stream_origin = stream.try_borrow_mut().ok().and_then(|mut stream| stream.seek(SeekFrom::Current(0)).ok()).unwrap_or(0);
+        self.stream_position = self.stream_origin;
+        self
+    }
 
-    /// signature buffer
-    buffer: [u8; LOCAL_FILE_HEADER_LEN],
-    data_len_in_buffer: usize,
+    /// The stream, borrowed until the result is dropped
+    fn stream(&self) -> Result<RefMut<'a, S>, ParsingError> {
+        borrow_stream(self.stream)
+    }
 }
 
-impl<'a, S: Read, const N: usize> SequentialParser<'a, S, N> {
-    pub fn new(stream: &'a mut S) -> Self {
+impl<'a, S: Read + Seek, const N: usize> Default for LocalFile<'a, S, N> {
+    fn default() -> Self {
         Self {
-            stream,
-            buffer: [0; LOCAL_FILE_HEADER_LEN],
-            data_len_in_buffer: 0,
+            info: Default::default(),
+            metadata: Default::default(),
+            stream: None,
+            stream_origin: 0,
+            stream_position: 0,
+            decompressed: 0,
+            crc32: 0,
+            check_crc: cfg!(feature = "crc-check"),
+            crc_engine: &SoftwareCrc32,
+            unreadable: None,
+            file_index: 0,
+            data_descriptor: false,
+            zip64_descriptor: false,
+            cipher: None,
+            #[cfg(all(feature = "inflate", feature = "std"))]
+            inflater: None,
+            #[cfg(all(feature = "inflate", not(feature = "std")))]
+            inflater: Inflater::new(),
+            #[cfg(feature = "zstd")]
+            zstd: ZstdDecoder::new(),
+            #[cfg(feature = "bzip2")]
+            bzip2: Bzip2Decoder::new(),
+            #[cfg(feature = "lzma")]
+            lzma: LzmaDecoder::new(),
+            #[cfg(feature = "xz")]
+            xz: XzDecoder::new(),
+            #[cfg(feature = "deflate64")]
+            inflater64: Inflater64::new(),
         }
     }
 }
 
-impl<'a, S: Read, const N: usize> Iterator for SequentialParser<'a, S, N> {
-    type Item = LocalFile<'a, S, N>;
+impl<'a, S: Read + Seek, const N: usize> LocalFileOps for LocalFile<'a, S, N> {
+    fn file_name(&self) -> Result<&str, Utf8Error> {
+        self.info.file_name()
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            // read enough data
-            let read_len = LOCAL_FILE_HEADER_LEN - self.data_len_in_buffer;
-            match self.stream.read(&mut self.buffer[self.data_len_in_buffer..]) {
-                Ok(n) => {
-                    if n == 0 {
-                        // FIXME: This will be an infinite loop if their is no more data
-                        continue;
-                    } else if n < read_len {
-                        self.data_len_in_buffer += n;
-                        continue;
-                    } else {
-                        self.data_len_in_buffer += n;
-                    }
-                }
-                Err(_e) => {
-                    #[cfg(feature = "std")]
-                    eprintln!("read local header failed({})", _e);
-                    return None;
-                }
-            }
-
-            // search LocalFileHeader
-            // search signature
-            let mut header_found = false;
-            for (i, _v) in self.buffer.iter().take(self.data_len_in_buffer - 3).enumerate() {
-                if self.buffer[i..i+4] == [0x50, 0x4b, 0x03, 0x04] {
-                    unsafe {
-                        let len = self.data_len_in_buffer - i;
-                        ptr::copy(self.buffer.as_ptr().add(i),
-                                  self.buffer.as_mut_ptr(),
-                                  len);
-                        self.data_len_in_buffer = len;
-                        header_found = true;
-                    }
-                    // #[cfg(feature = "std")]
-                    // println!("found signature at {}", i);
-                    break;
-                }
-            }
+    fn has_directory_attribute(&self) -> bool {
+        self.metadata.has_directory_attribute()
+    }
 
-            // save the last 3 bytes if header was not found
-            if header_found == false {
-                for i in 0..3 {
-                    self.buffer[i] = self.buffer[self.data_len_in_buffer - 3 + i];
-                }
-                self.data_len_in_buffer = 3;
-                continue;
-            }
+    /// The name ends with a `/`, or the attributes tell so, and the entry has no data
+    fn is_dir(&self) -> bool {
+        (self.info.file_name_bytes().ends_with(b"/") || self.metadata.has_directory_attribute())
+            && self.metadata.uncompressed_size == 0
+    }
 
-            // begin to parse if data is ready
-            if self.data_len_in_buffer >= LOCAL_FILE_HEADER_LEN {
-                break;
-            }
+    fn file_size(&self) -> u64 {
+        match self.info.compression_method {
+            #[cfg(feature = "inflate")]
+            CompressMethod::Deflated => self.info.uncompressed_size,
+            #[cfg(feature = "zstd")]
+            CompressMethod::Zstd => self.info.uncompressed_size,
+            #[cfg(feature = "bzip2")]
+            CompressMethod::BZIP2 => self.info.uncompressed_size,
+            #[cfg(feature = "lzma")]
+            CompressMethod::LZMA => self.info.uncompressed_size,
+            #[cfg(feature = "xz")]
+            CompressMethod::XZ => self.info.uncompressed_size,
+            #[cfg(feature = "deflate64")]
+            CompressMethod::Deflate64 => self.info.uncompressed_size,
+            // without the encryption header
+            CompressMethod::Uncompress if self.cipher.is_some() => self.info.uncompressed_size,
+            _ => self.info.file_size(),
         }
+    }
 
-        // parse header
-        if let Some(file_info) = unsafe { LocalFileHeader::from_bytes(&self.buffer) } {
-            // #[cfg(feature = "std")]
-            // dbg!(file_info);
-            let mut file = LocalFile::default()
-                .with_compression_method(CompressMethod::from(file_info.compression_method))
-                .with_compressed_size(file_info.compressed_size as u64)
-                .with_uncompressed_size(file_info.uncompressed_size as u64)
-                .with_stream(self.stream);
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        match self.info.compression_method {
+            CompressMethod::Uncompress => self.read_stored(buf),
+            #[cfg(feature = "inflate")]
+            CompressMethod::Deflated => self.read_decompressed(None, buf),
+            #[cfg(feature = "zstd")]
+            CompressMethod::Zstd => self.read_decompressed(None, buf),
+            #[cfg(feature = "bzip2")]
+            CompressMethod::BZIP2 => self.read_decompressed(None, buf),
+            #[cfg(feature = "lzma")]
+            CompressMethod::LZMA => self.read_decompressed(None, buf),
+            #[cfg(feature = "xz")]
+            CompressMethod::XZ => self.read_decompressed(None, buf),
+            #[cfg(feature = "deflate64")]
+            CompressMethod::Deflate64 => self.read_decompressed(None, buf),
+            _ => self.read_raw(buf),
+        }
+    }
+}
 
-            // read file name
-            match self.stream.read_exact(&mut file.info.file_name_buffer[..file_info.file_name_length as usize]) {
-                Ok(_) => file.info.file_name_length = file_info.file_name_length as usize,
-                Err(_e) => {
-                    #[cfg(feature = "std")]
-                    eprintln!("read filename failed: {}", _e);
-                },
-            }
+impl<'a, S: Read + Seek, const N: usize> LocalFile<'a, S, N> {
+    /// Read the data descriptor following the entry data, `None` if the entry has none.
+    ///
+    /// The sizes of the entry come from the central directory either way, the descriptor values
+    /// can be checked against them and [`EntryMetadata::crc32`].
+    pub fn data_descriptor(&mut self) -> Result<Option<DataDescriptor>, ParsingError> {
+        if !self.data_descriptor {
+            return Ok(None);
+        }
+        let data_end = self.stream_origin + self.info.compressed_size;
+        DataDescriptor::read_from(&mut *self.stream()?, data_end, self.zip64_descriptor).map(Some)
+    }
 
-            // drop extra field
-            {
-                let mut len = file_info.extra_field_length as usize;
-                let mut buf = [0u8; 16];
-                loop {
-                    let read_len = cmp::min(buf.len(), len);
-                    if let Ok(n) = self.stream.read(&mut buf[..read_len]) {
-                        len -= n;
-                        if len == 0 {
-                            break;
-                        }
-                    } else {
-                        #[cfg(feature = "std")]
-                        eprintln!("drop data read failed");
-                        return None;
-                    }
-                }
-            }
+    /// Records of the extra field of the central directory file header, read into `buf`
+    pub fn extra_fields<'b>(&mut self, buf: &'b mut [u8]) -> Result<ExtraFields<'b>, ParsingError> {
+        let len = self.metadata.extra_field_length as usize;
+        if buf.len() < len {
+            return Err(ParsingError::BufferTooSmall);
+        }
+        read_at(&mut *self.stream()?, self.metadata.extra_field_offset, len, buf).map(ExtraFields::new)
+    }
 
-            // reset for next header
-            self.data_len_in_buffer = 0;
+    /// File comment of the central directory file header read into `buf`, or the one of the
+    /// Unicode comment extra field taking its place unless the header comment is UTF-8 already
+    /// (general purpose bit 11)
+    pub fn file_comment<'b>(&mut self, buf: &'b mut [u8]) -> Result<&'b [u8], ParsingError> {
+        let len = self.metadata.file_comment_length as usize;
+        if buf.len() < len {
+            return Err(ParsingError::BufferTooSmall);
+        }
+        read_at(&mut *self.stream()?, self.metadata.file_comment_offset, len, buf)?;
+        if self.metadata.general_purpose_bit_flag & FLAG_UTF8 != 0 {
+            return Ok(&buf[..len]);
+        }
 
-            Some(file)
-        } else {
-            #[cfg(feature = "std")]
-            eprintln!("get LocalFileHeader from raw ptr({:02X?}) failed", self.buffer);
-            None
+        let mut extra_field = [0u8; MAX_EXTRA_FIELD_LEN];
+        match self.read_extra_field(false, &mut extra_field)?.unicode_comment(&buf[..len]) {
+            Some(comment) if comment.len() <= buf.len() => {
+                buf[..comment.len()].copy_from_slice(comment);
+                Ok(&buf[..comment.len()])
+            }
+            Some(_) => Err(ParsingError::BufferTooSmall),
+            None => Ok(&buf[..len]),
         }
     }
-}*/
 
+    /// AES encryption parameters of the central directory file header, if the entry is AES
+    /// encrypted ([`CompressMethod::Aes`]). The data can't be decrypted, but the actual
+    /// compression method is known.
+    pub fn aes_extra_field(&mut self) -> Result<Option<AesExtraField>, ParsingError> {
+        if self.info.compression_method != CompressMethod::Aes {
+            return Ok(None);
+        }
+        let mut buf = [0u8; MAX_EXTRA_FIELD_LEN];
+        Ok(self.read_extra_field(false, &mut buf)?.aes())
+    }
 
-#[derive(Debug, Clone, Copy)]
-enum HeaderType {
-    HeaderSignature,
-    LocalFileHeader,
-    CentralFileHeader,
-    CentralDirEnd,
-}
+    /// Times of the NTFS extra field of the central directory file header, if there is one
+    ///
+    /// Only the first [`MAX_EXTRA_FIELD_LEN`] bytes of the extra field are looked at.
+    pub fn ntfs_timestamps(&mut self) -> Result<Option<NtfsTimestamps>, ParsingError> {
+        let mut buf = [0u8; MAX_EXTRA_FIELD_LEN];
+        Ok(self.read_extra_field(false, &mut buf)?.ntfs_timestamps())
+    }
 
-#[derive(Debug, Clone, Copy)]
-enum ParserState {
-    RecvHeader(HeaderType, usize),
-    RecvCentralFileHeader,
-    RecvCentralDirEnd,
-    RecvLocalFileName,
-    RecvLocalFileExtraField,
-    RecvLocalFileData,
-}
+    /// Times of the extended timestamp extra field of the local file header, if there is one.
+    /// Unlike the central directory one, it may have the access and creation times.
+    pub fn extended_timestamp(&mut self) -> Result<Option<ExtendedTimestamp>, ParsingError> {
+        let mut buf = [0u8; MAX_EXTRA_FIELD_LEN];
+        Ok(self.read_extra_field(true, &mut buf)?.extended_timestamp())
+    }
 
-pub struct PassiveParser<const N: usize> {
-    /// header buffer
-    buffer: heapless::Vec<u8, CENTRAL_FILE_HEADER_LEN>,
+    /// Owner of the Unix extra field of the central directory file header, or of the local file
+    /// header if the central one has none
+    ///
+    /// Only the first [`MAX_EXTRA_FIELD_LEN`] bytes of each extra field are looked at.
+    pub fn unix_owner(&mut self) -> Result<Option<UnixOwner>, ParsingError> {
+        let mut buf = [0u8; MAX_EXTRA_FIELD_LEN];
+        if let Some(owner) = self.read_extra_field(false, &mut buf)?.unix_owner() {
+            return Ok(Some(owner));
+        }
+        Ok(self.read_extra_field(true, &mut buf)?.unix_owner())
+    }
 
-    #[cfg(feature = "std")]
-    zip_file_comment: Vec<u8>,
+    /// Records of the extra field of the local file header, read into `buf`
+    pub fn local_extra_fields<'b>(&mut self, buf: &'b mut [u8]) -> Result<ExtraFields<'b>, ParsingError> {
+        let (_, len) = self.local_extra_field()?;
+        if buf.len() < len {
+            return Err(ParsingError::BufferTooSmall);
+        }
+        self.read_extra_field(true, buf)
+    }
 
-    pub localfile_info: Option<LocalFileInfo<N>>,
+    /// Stream offset and length of the extra field of the local file header
+    fn local_extra_field(&mut self) -> Result<(u64, usize), ParsingError> {
+        let mut header = [0u8; LOCAL_FILE_HEADER_LEN];
+        read_at(&mut *self.stream()?, self.metadata.local_header_offset, header.len(), &mut header)?;
+        let header = LocalFileHeader::from_bytes(&header).ok_or(ParsingError::InvalidLocalFileHeader)?;
+        let offset = self.metadata.local_header_offset + LOCAL_FILE_HEADER_LEN as u64 + header.file_name_length as u64;
+        Ok((offset, header.extra_field_length as usize))
+    }
 
-    localfile_index: i32,
-    centralfile_index: i32,
+    /// The local or central extra field, truncated to the length of `buf`
+    fn read_extra_field<'b>(&mut self, local: bool, buf: &'b mut [u8]) -> Result<ExtraFields<'b>, ParsingError> {
+        let (offset, len) = if local {
+            self.local_extra_field()?
+        } else {
+            (self.metadata.extra_field_offset, self.metadata.extra_field_length as usize)
+        };
+        read_at(&mut *self.stream()?, offset, len, buf).map(ExtraFields::new)
+    }
 
-    file_name_len: usize,
-    file_name_index: usize,
+    /// Strongly encrypted data can't be handed out, not even raw
+    fn check_encryption(&self) -> Result<(), ParsingError> {
+        if self.metadata.general_purpose_bit_flag & FLAG_STRONG_ENCRYPTION != 0 {
+            return Err(ParsingError::UnsupportedEncryption(EncryptionKind::Strong));
+        }
+        Ok(())
+    }
 
-    extra_field_len: usize,
-    extra_field_index: usize,
+    /// Field of the local file header disagreeing with the central directory record, found by
+    /// a [`SeekingParser::with_strict`] parser. The data of such an entry can't be read.
+    pub fn header_mismatch(&self) -> Option<HeaderField> {
+        match self.unreadable {
+            Some(ParsingError::LocalHeaderMismatch(field)) => Some(field),
+            _ => None,
+        }
+    }
 
-    file_data_len: usize,
-    file_data_index: usize,
+    /// `error` returned by a read of the entry, along with the stream offset reached and the
+    /// index and name of the entry
+    pub fn error_context(&self, error: ParsingError) -> ErrorContext<N> {
+        ErrorContext::new(error, self.stream_position).with_entry(self.file_index, self.info.file_name_bytes())
+    }
 
-    central_file_header_index: usize,
-    central_file_header_len: usize,
+    /// The data can be read: the headers are trusted and no strong encryption is used
+    fn check_readable(&self) -> Result<(), ParsingError> {
+        if let Some(err) = &self.unreadable {
+            return Err(err.clone());
+        }
+        self.check_encryption()
+    }
 
-    central_dir_end_index: usize,
-    central_dir_end_len: usize,
+    /// Get ready to decrypt the entry, asking `provider` for passwords until one passes the check
+    /// of the encryption header. Entries which are not encrypted are left as is.
+    ///
+    /// Reading starts over from the beginning of the entry data afterwards.
+    pub fn unlock(&mut self, mut provider: impl PasswordProvider) -> Result<(), ParsingError> {
+        if !self.info.is_encrypted() {
+            return Ok(());
+        }
+        self.check_encryption()?;
 
-    state: ParserState,
-}
+        let mut header = [0u8; ENCRYPTION_HEADER_LEN];
+        let mut position = self.stream_origin;
+        let mut i = 0;
+        while i < header.len() {
+            match Self::read_stream(self.stream, &mut position, self.data_end(), &mut header[i..])? {
+                0 => return Err(ParsingError::StreamEnding),
+                n => i += n,
+            }
+        }
+        // high byte of the CRC-32, or of the modification time if the CRC-32 follows the data
+        let check = if self.data_descriptor {
+            (self.metadata.last_modified.time >> 8) as u8
+        } else {
+            (self.metadata.crc32 >> 24) as u8
+        };
 
-impl<const N: usize> PassiveParser<N> {
-    fn buffer_data_len(&self) -> usize {
-        self.buffer.len()
+        let mut password = [0u8; MAX_PASSWORD_LEN];
+        let mut attempt = 0;
+        loop {
+            let len = provider
+                .password(self.info.file_name_bytes(), attempt, &mut password)
+                .ok_or(ParsingError::IncorrectPassword(attempt))?;
+            let mut cipher = ZipCrypto::new(password.get(..len).ok_or(ParsingError::BufferTooSmall)?);
+            let mut decrypted = header;
+            cipher.decrypt(&mut decrypted);
+            if decrypted[ENCRYPTION_HEADER_LEN - 1] == check {
+                self.cipher = Some(cipher);
+                self.stream_position = position;
+                self.decompressed = 0;
+                return Ok(());
+            }
+            attempt += 1;
+        }
     }
 
-    fn buffer_data(&self) -> &[u8] {
-        self.buffer.as_slice()
+    /// Start of the entry data, past the encryption header once unlocked
+    fn data_start(&self) -> u64 {
+        match self.cipher {
+            Some(_) => self.stream_origin + ENCRYPTION_HEADER_LEN as u64,
+            None => self.stream_origin,
+        }
     }
 
-    fn append_bytes(&mut self, data: &[u8]) -> usize {
-        let len = if data.len() + self.buffer.len() > self.buffer.capacity() {
-            self.buffer.capacity() - self.buffer.len()
-        } else {
-            data.len()
-        };
-        self.buffer.extend_from_slice(&data[..len]).unwrap_or_else(|_| panic!());
-        len
+    fn data_end(&self) -> u64 {
+        // a crafted size is flagged by the parser already
+        self.stream_origin.saturating_add(self.info.compressed_size)
     }
 
-    pub fn new() -> Self {
-        Self::default()
+    /// Read the raw (maybe compressed) data, never past the end of the entry
+    fn read_raw(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        self.check_readable()?;
+        let data_end = self.data_end();
+        let n = Self::read_stream(self.stream, &mut self.stream_position, data_end, buf)?;
+        if let Some(cipher) = &mut self.cipher {
+            cipher.decrypt(&mut buf[..n]);
+        }
+        Ok(n)
     }
 
-    pub fn reset(&mut self) {
-        self.state = ParserState::RecvHeader(HeaderType::HeaderSignature, 4);
+    /// Read the stream from `position` up to `data_end` at most, taking the fields apart so the
+    /// built-in decompressors can be borrowed meanwhile
+    fn read_stream(stream: Option<&RefCell<S>>, position: &mut u64, data_end: u64, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        let len = cmp::min(buf.len() as u64, data_end.saturating_sub(*position)) as usize;
+        if len == 0 {
+            return Ok(0);
+        }
 
+        let mut stream = borrow_stream(stream)?;
+        stream.seek(SeekFrom::Start(*position))?;
+        let bytes_read = stream.read(&mut buf[..len])?;
+        *position += u64::try_from(bytes_read).map_err(|_| ParsingError::InvalidStream)?;
+
+        Ok(bytes_read)
+    }
+
+    /// Read the entry through `decompressor`, which must be the same for all the reads of the entry
+    pub fn read_with(&mut self, decompressor: &mut dyn Decompressor, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        self.read_decompressed(Some(decompressor), buf)
+    }
+
+    /// Read the entry through the decompressor registered for its compression method,
+    /// or as [`LocalFileOps::read`] does if there is none
+    pub fn read_using<const M: usize>(&mut self, decompressors: &mut Decompressors<'_, M>, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        match decompressors.get(self.info.compression_method) {
+            Some(decompressor) => self.read_decompressed(Some(decompressor), buf),
+            None => LocalFileOps::read(self, buf),
+        }
+    }
+
+    /// Stored data is its own output, it must match the declared uncompressed size
+    fn read_stored(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        let produced = self.stream_position - self.data_start();
+        if produced == 0 {
+            self.crc32 = 0;
+        }
+        let len = cmp::min(buf.len() as u64, self.info.uncompressed_size.saturating_sub(produced)) as usize;
+        if len == 0 && !buf.is_empty() {
+            return if self.stream_position < self.data_end() {
+                Err(ParsingError::UncompressedSizeExceeded(self.info.uncompressed_size))
+            } else {
+                self.check_crc().map(|_| 0)
+            };
+        }
+
+        let n = self.read_raw(&mut buf[..len])?;
+        if n == 0 && len > 0 {
+            return Err(ParsingError::UncompressedSizeShort(produced));
+        }
+        self.crc32 = self.crc_engine.update(self.crc32, &buf[..n]);
+        Ok(n)
+    }
+
+    /// Decompress the entry data, the built-in decompressor being used when `decompressor` is `None`.
+    /// The output must match the declared uncompressed size.
+    fn read_decompressed(&mut self, decompressor: Option<&mut dyn Decompressor>, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        self.check_readable()?;
+        let (data_start, data_end) = (self.data_start(), self.data_end());
+        let method = self.info.compression_method;
+        let decompressor = match decompressor {
+            Some(decompressor) => decompressor,
+            #[cfg(all(feature = "inflate", feature = "std"))]
+            None if method == CompressMethod::Deflated => &mut **self.inflater.get_or_insert_with(|| Box::new(Inflater::new())),
+            #[cfg(all(feature = "inflate", not(feature = "std")))]
+            None if method == CompressMethod::Deflated => &mut self.inflater,
+            #[cfg(feature = "zstd")]
+            None if method == CompressMethod::Zstd => &mut self.zstd,
+            #[cfg(feature = "bzip2")]
+            None if method == CompressMethod::BZIP2 => &mut self.bzip2,
+            #[cfg(feature = "lzma")]
+            None if method == CompressMethod::LZMA => &mut self.lzma,
+            #[cfg(feature = "xz")]
+            None if method == CompressMethod::XZ => &mut self.xz,
+            #[cfg(feature = "deflate64")]
+            None if method == CompressMethod::Deflate64 => &mut self.inflater64,
+            None => return Err(ParsingError::UnsupportedCompressMethod(method)),
+        };
+        if self.decompressed == 0 && self.stream_position == data_start {
+            decompressor.init(method)?;
+            self.crc32 = 0;
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let mut input = [0u8; 512];
+        loop {
+            let n = Self::read_stream(self.stream, &mut self.stream_position, data_end, &mut input)?;
+            if let Some(cipher) = &self.cipher {
+                // the keys only move past the input taken
+                cipher.clone().decrypt(&mut input[..n]);
+            }
+            let (consumed, produced) = if self.stream_position < data_end {
+                decompressor.feed(&input[..n], buf)?
+            } else {
+                decompressor.finish(&input[..n], buf)?
+            };
+            // give back the input the decompressor did not take
+            self.stream_position -= (n - consumed) as u64;
+            if let Some(cipher) = &mut self.cipher {
+                cipher.advance(&input[..consumed]);
+            }
+
+            self.decompressed += produced as u64;
+            if self.decompressed > self.info.uncompressed_size {
+                return Err(ParsingError::UncompressedSizeExceeded(self.info.uncompressed_size));
+            }
+            if produced > 0 {
+                self.crc32 = self.crc_engine.update(self.crc32, &buf[..produced]);
+                return Ok(produced);
+            }
+            if consumed == 0 && n > 0 {
+                return Err(ParsingError::DecompressionFailed);
+            }
+            if n == 0 {
+                // all the input is taken, and no output is left
+                return if self.decompressed < self.info.uncompressed_size {
+                    Err(ParsingError::UncompressedSizeShort(self.decompressed))
+                } else {
+                    self.check_crc().map(|_| 0)
+                };
+            }
+        }
+    }
+
+    /// [`LocalFileOps::read`] yields the uncompressed data, the entry being stored or having a
+    /// built-in decompressor
+    pub(crate) fn reads_uncompressed(&self) -> bool {
+        match self.info.compression_method {
+            CompressMethod::Uncompress => true,
+            #[cfg(feature = "inflate")]
+            CompressMethod::Deflated => true,
+            #[cfg(feature = "zstd")]
+            CompressMethod::Zstd => true,
+            #[cfg(feature = "bzip2")]
+            CompressMethod::BZIP2 => true,
+            #[cfg(feature = "lzma")]
+            CompressMethod::LZMA => true,
+            #[cfg(feature = "xz")]
+            CompressMethod::XZ => true,
+            #[cfg(feature = "deflate64")]
+            CompressMethod::Deflate64 => true,
+            _ => false,
+        }
+    }
+
+    /// End of the uncompressed data, its CRC-32 must match the central directory when checked
+    fn check_crc(&self) -> Result<(), ParsingError> {
+        if self.check_crc && self.crc32 != self.metadata.crc32 {
+            return Err(ParsingError::CrcMismatch(self.metadata.crc32, self.crc32));
+        }
+        Ok(())
+    }
+}
+
+impl<'a, S: Read + Seek, const N: usize> LocalFile<'a, S, N> {
+    /// Read the whole entry into a vector sized from [`LocalFileOps::file_size`], nothing of it
+    /// being read before.
+    ///
+    /// Fails with [`ParsingError::BufferTooSmall`] if the entry is larger than `max_len` bytes,
+    /// before anything is allocated, and with the read errors, e.g. the CRC-32 check at the end.
+    #[cfg(feature = "std")]
+    pub fn read_to_vec(&mut self, max_len: u64) -> Result<Vec<u8>, ParsingError> {
+        let size = self.file_size();
+        if size > max_len {
+            return Err(ParsingError::BufferTooSmall);
+        }
+        let mut data = vec![0u8; usize::try_from(size).map_err(|_| ParsingError::BufferTooSmall)?];
+        let mut i = 0;
+        while i < data.len() {
+            match LocalFileOps::read(self, &mut data[i..])? {
+                0 => return Err(ParsingError::UncompressedSizeShort(i as u64)),
+                n => i += n,
+            }
+        }
+        // the end of the data is where the checks happen
+        if LocalFileOps::read(self, &mut [0u8; 1])? > 0 {
+            return Err(ParsingError::UncompressedSizeExceeded(size));
+        }
+        Ok(data)
+    }
+
+    /// Write the whole entry to `sink` in chunks of 512 bytes, nothing of it being read before.
+    /// Returns the length written, which is [`LocalFileOps::file_size`].
+    ///
+    /// Fails with [`ParsingError::StreamEnding`] if the data ends before, and with the read
+    /// errors, e.g. the CRC-32 check at the end.
+    pub fn copy_to(&mut self, sink: &mut impl Write) -> Result<u64, ParsingError> {
+        let written = extract_with_buffer(self, &mut [0u8; 512], |chunk| sink.write_all(chunk))?;
+        // the end of the data is where the checks happen
+        if LocalFileOps::read(self, &mut [0u8; 1])? > 0 {
+            return Err(ParsingError::UncompressedSizeExceeded(written));
+        }
+        sink.flush()?;
+        Ok(written)
+    }
+
+    /// Reader of the uncompressed entry data, created by [`LocalFile::decompressed_reader`]
+    pub fn decompressed_reader(&mut self) -> DecompressedReader<'_, 'a, S, N> {
+        DecompressedReader { file: self }
+    }
+}
+
+/// Reader yielding the uncompressed data of a [`LocalFile`], never the raw compressed bytes.
+///
+/// Stored entries are read as is, the others through the built-in decompressor of their method
+/// (deflated ones with the `inflate` feature), failing with
+/// [`ParsingError::UnsupportedCompressMethod`] if there is none. With `std`, it implements
+/// `std::io::Read`, so it works with `io::copy` and the like.
+pub struct DecompressedReader<'r, 'a, S: Read + Seek, const N: usize> {
+    file: &'r mut LocalFile<'a, S, N>,
+}
+
+impl<'r, 'a, S: Read + Seek, const N: usize> DecompressedReader<'r, 'a, S, N> {
+    fn read_uncompressed(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        match self.file.info.compression_method {
+            CompressMethod::Uncompress => self.file.read_stored(buf),
+            _ => self.file.read_decompressed(None, buf),
+        }
+    }
+}
+
+impl<'r, 'a, S: Read + Seek, const N: usize> LocalFileOps for DecompressedReader<'r, 'a, S, N> {
+    fn file_name(&self) -> Result<&str, Utf8Error> {
+        self.file.file_name()
+    }
+
+    fn has_directory_attribute(&self) -> bool {
+        self.file.has_directory_attribute()
+    }
+
+    fn is_dir(&self) -> bool {
+        self.file.is_dir()
+    }
+
+    fn file_size(&self) -> u64 {
+        self.file.info.uncompressed_size
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        self.read_uncompressed(buf)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'r, 'a, S: Read + Seek, const N: usize> io::Read for DecompressedReader<'r, 'a, S, N> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_uncompressed(buf)
+            .map_err(io::Error::from)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<'r, 'a, S: Read + Seek, const N: usize> Read for DecompressedReader<'r, 'a, S, N> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        self.read_uncompressed(buf)
+    }
+}
+
+impl<'a, S: Read + Seek, const N: usize> LocalFile<'a, S, N> {
+    /// Move within the data of a stored entry, the position being relative to the start of the
+    /// data and clamped to its size.
+    ///
+    /// Fails with [`ParsingError::InvalidStream`] for compressed or encrypted entries. Moving
+    /// elsewhere than the current position or the start turns the CRC-32 check off, the data
+    /// being no longer read in one go.
+    fn seek_stored(&mut self, pos: SeekFrom) -> Result<u64, ParsingError> {
+        let size = self.stored_len()?;
+        let current = self.stream_position.saturating_sub(self.data_start());
+        let (base, offset) = match pos {
+            SeekFrom::Start(n) => (n, 0),
+            SeekFrom::End(n) => (size, n),
+            SeekFrom::Current(n) => (current, n),
+        };
+        let target = base.checked_add_signed(offset).unwrap_or(if offset < 0 { 0 } else { u64::MAX });
+        let target = cmp::min(target, size);
+        if target != current && target != 0 {
+            self.check_crc = false;
+        }
+        self.stream_position = self.data_start() + target;
+        Ok(target)
+    }
+
+    /// Read the data of a stored entry at `offset` from its start, without moving the position
+    /// of the entry. Returns the length read, 0 past the end of the data.
+    ///
+    /// Fails with [`ParsingError::InvalidStream`] for compressed or encrypted entries.
+    pub fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        let size = self.stored_len()?;
+        let data_start = self.data_start();
+        let mut position = data_start + cmp::min(offset, size);
+        Self::read_stream(self.stream, &mut position, data_start + size, buf)
+    }
+
+    /// Length of the data of a stored entry, which can be read anywhere
+    fn stored_len(&self) -> Result<u64, ParsingError> {
+        if self.info.compression_method != CompressMethod::Uncompress || self.info.is_encrypted() {
+            return Err(ParsingError::InvalidStream);
+        }
+        self.check_readable()?;
+        Ok(cmp::min(self.info.uncompressed_size, self.data_end().saturating_sub(self.data_start())))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, S: Read + Seek, const N: usize> io::Seek for LocalFile<'a, S, N> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.seek_stored(pos.into())
+            .map_err(io::Error::from)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<'a, S: Read + Seek, const N: usize> Seek for LocalFile<'a, S, N> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, ParsingError> {
+        self.seek_stored(pos)
+    }
+
+    fn stream_len(&mut self) -> Option<u64> {
+        Some(self.info.uncompressed_size)
+    }
+}
+
+impl<'a, S: Read + Seek, const N: usize> LocalFile<'a, S, N> {
+    /// Reader of the compressed entry data, created by [`LocalFile::raw_reader`]
+    pub fn raw_reader(&mut self) -> RawReader<'_, 'a, S, N> {
+        RawReader { file: self }
+    }
+}
+
+/// Reader yielding the data of a [`LocalFile`] as stored in the archive, never decompressed,
+/// e.g. to serve deflated data as is or to copy the entry into another archive.
+///
+/// It ends with the compressed size of the entry and goes on from where the entry was read up
+/// to. The data is decrypted once the entry is unlocked, otherwise an encrypted entry comes out
+/// as is, encryption header included. With `std`, it implements `std::io::Read`.
+pub struct RawReader<'r, 'a, S: Read + Seek, const N: usize> {
+    file: &'r mut LocalFile<'a, S, N>,
+}
+
+impl<'r, 'a, S: Read + Seek, const N: usize> LocalFileOps for RawReader<'r, 'a, S, N> {
+    fn file_name(&self) -> Result<&str, Utf8Error> {
+        self.file.file_name()
+    }
+
+    fn has_directory_attribute(&self) -> bool {
+        self.file.has_directory_attribute()
+    }
+
+    fn is_dir(&self) -> bool {
+        self.file.is_dir()
+    }
+
+    /// The compressed size, without the encryption header once unlocked
+    fn file_size(&self) -> u64 {
+        self.file.data_end().saturating_sub(self.file.data_start())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        self.file.read_raw(buf)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'r, 'a, S: Read + Seek, const N: usize> io::Read for RawReader<'r, 'a, S, N> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read_raw(buf)
+            .map_err(io::Error::from)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<'r, 'a, S: Read + Seek, const N: usize> Read for RawReader<'r, 'a, S, N> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        self.file.read_raw(buf)
+    }
+}
+
+impl<'a, S: Read + Seek, const N: usize> LocalFile<'a, S, N> {
+    /// Read the data of a stored entry into all of `bufs` at once if it goes on past them, as
+    /// [`LocalFileOps::read`] into the first non-empty buffer otherwise
+    fn read_vectored_data(&mut self, bufs: &mut [&mut [u8]]) -> Result<usize, ParsingError> {
+        let total = bufs.iter().map(|buf| buf.len() as u64).sum::<u64>();
+        let produced = self.stream_position.saturating_sub(self.data_start());
+        let vectored = match self.stored_len() {
+            Ok(size) => total > 0 && total <= size.saturating_sub(produced),
+            Err(_) => false,
+        };
+        if !vectored {
+            return match bufs.iter_mut().find(|buf| !buf.is_empty()) {
+                Some(buf) => LocalFileOps::read(self, buf),
+                None => Ok(0),
+            };
+        }
+
+        if produced == 0 {
+            self.crc32 = 0;
+        }
+        let n = {
+            let mut stream = self.stream()?;
+            stream.seek(SeekFrom::Start(self.stream_position))?;
+            stream.read_vectored(bufs)?
+        };
+        self.stream_position += n as u64;
+        let mut left = n;
+        for buf in bufs.iter() {
+            let len = cmp::min(left, buf.len());
+            self.crc32 = self.crc_engine.update(self.crc32, &buf[..len]);
+            left -= len;
+        }
+        Ok(n)
+    }
+}
+
+/// Reads as [`LocalFileOps::read`], ending with the data of the entry
+#[cfg(feature = "std")]
+impl<'a, S: Read + Seek, const N: usize> io::Read for LocalFile<'a, S, N> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        LocalFileOps::read(self, buf)
+            .map_err(io::Error::from)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        let mut bufs: Vec<&mut [u8]> = bufs.iter_mut().map(|buf| &mut **buf).collect();
+        self.read_vectored_data(&mut bufs)
+            .map_err(io::Error::from)
+    }
+}
+
+/// Reads as [`LocalFileOps::read`], ending with the data of the entry, e.g. to parse a nested
+/// archive
+#[cfg(not(feature = "std"))]
+impl<'a, S: Read + Seek, const N: usize> Read for LocalFile<'a, S, N> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        LocalFileOps::read(self, buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [&mut [u8]]) -> Result<usize, ParsingError> {
+        self.read_vectored_data(bufs)
+    }
+}
+
+/// Going through the entries of an archive read from `S`, whatever the parser, so that generic
+/// code can take any of them.
+pub trait Parser<S: Read> {
+    /// An entry along with its data, borrowing the parser if they share the stream position
+    type Entry<'e>: LocalFileOps where Self: 'e;
+
+    /// Next entry, `Ok(None)` at the end of the archive
+    fn next_entry(&mut self) -> Result<Option<Self::Entry<'_>>, ParsingError>;
+
+    /// Number of entries, if known before going through them
+    fn entry_count(&self) -> Option<u64> {
+        None
+    }
+
+    /// Go through the entries left until the one named `name`
+    fn find_entry(&mut self, name: &str) -> Result<Option<Self::Entry<'_>>, ParsingError>;
+
+    /// Hand each entry left to `f`, the entry being only usable within the call.
+    ///
+    /// The entry is seen through [`LocalFileOps`], so that `f` doesn't depend on the lifetime of
+    /// the entries of the parser.
+    ///
+    /// Stops at the first error, of the parser or returned by `f`.
+    fn for_each_entry<E, F>(&mut self, mut f: F) -> Result<(), E>
+    where
+        E: From<ParsingError>,
+        F: FnMut(&mut dyn LocalFileOps) -> Result<(), E>,
+    {
+        while let Some(mut entry) = self.next_entry()? {
+            f(&mut entry)?;
+        }
+        Ok(())
+    }
+
+    /// Run `f` on the entry named `name`, found as by [`Parser::find_entry`], `Ok(None)` if there is none
+    fn with_entry<T, F>(&mut self, name: &str, f: F) -> Result<Option<T>, ParsingError>
+    where
+        F: FnOnce(&mut dyn LocalFileOps) -> T,
+    {
+        Ok(self.find_entry(name)?.map(|mut entry| f(&mut entry)))
+    }
+}
+
+impl<'a, S: Read + Seek, const N: usize> Parser<S> for SeekingParser<'a, S, N> {
+    type Entry<'e> = LocalFile<'a, S, N> where Self: 'e;
+
+    fn next_entry(&mut self) -> Result<Option<LocalFile<'a, S, N>>, ParsingError> {
+        self.try_next()
+    }
+
+    /// Number of entries of the central directory
+    fn entry_count(&self) -> Option<u64> {
+        self.number_of_files
+    }
+
+    fn find_entry(&mut self, name: &str) -> Result<Option<LocalFile<'a, S, N>>, ParsingError> {
+        while let Some(file) = self.try_next()? {
+            if file.file_name() == Ok(name) {
+                return Ok(Some(file));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Zip file parser, creating it by [`new`](struct.Parser.html#method.new) method
+pub struct SeekingParser<'a, S: Read + Seek, const N: usize = 128> {
+    /// It will be None when no central directory was found
+    pub number_of_files: Option<u64>,
+
+    central_directory_offset: u64,
+    /// offset relative to the central dir
+    next_entry_offset: u64,
+
+    central_dir_end: Option<CentralDirEnd>,
+    /// stream offset of the end of central directory record
+    central_dir_end_offset: u64,
+    /// the central directory can't be listed
+    central_dir_encrypted: bool,
+    /// cross-check the local file headers with the central directory
+    strict: bool,
+    /// index of the next entry
+    file_index: i32,
+    /// why the iteration stopped early
+    last_error: Option<ErrorContext<N>>,
+    /// offsets of the central directory records relative to the central dir, from the first one
+    #[cfg(feature = "std")]
+    record_offsets: Vec<u64>,
+
+    /// holding the file handle, shared with the entries
+    stream: &'a RefCell<S>,
+}
+
+impl<'a, S: Read + Seek, const N: usize> SeekingParser<'a, S, N> {
+    /// Parse the archive of `stream`, which must not stay borrowed: the entries borrow it for
+    /// each of their reads.
+    pub fn new(stream: &'a RefCell<S>) -> Self {
+        // seek to the start of central directory
+        let mut central_directory_offset = 0u64;
+        let mut number_of_files = None;
+        let mut central_dir_end = None;
+        let mut central_dir_encrypted = false;
+        let mut central_dir_end_offset = 0u64;
+        let mut handle = stream.try_borrow_mut().ok();
+        if let Some((stream, stream_len)) = handle.as_deref_mut().and_then(|stream| stream.stream_len().map(|len| (stream, len))) {
+            if let Some((end_offset, mut central_dir)) = CentralDirEnd::find(stream, stream_len) {
+                // archives beyond the limits of the record have a ZIP64 one
+                let mut next_record_offset = end_offset;
+                if let Some((zip64_offset, zip64_end)) = Zip64CentralDirEnd::read_from(stream, end_offset) {
+                    central_dir.apply_zip64(&zip64_end);
+                    central_dir_encrypted = zip64_end.has_encrypted_central_dir();
+                    next_record_offset = zip64_offset;
+                }
+                central_dir.apply_prepended_data(next_record_offset);
+                let _ = stream.seek(SeekFrom::Start(central_dir.central_directory_offset));
+                central_directory_offset = central_dir.central_directory_offset;
+                central_dir_end_offset = end_offset;
+                number_of_files = Some(central_dir.total_entries_this_disk);
+                central_dir_end = Some(central_dir);
+            } else {
+                let _ = stream.rewind();
+            }
+        } else {
+            diag!(warn, "the stream length is unknown, the central directory can't be found");
+        }
+        drop(handle);
+
+        Self {
+            stream,
+            central_directory_offset,
+            next_entry_offset: 0,
+            number_of_files,
+            central_dir_end,
+            central_dir_end_offset,
+            central_dir_encrypted,
+            strict: false,
+            file_index: 0,
+            last_error: None,
+            #[cfg(feature = "std")]
+            record_offsets: Vec::new(),
+        }
+    }
+
+    /// Why the last call to `next` returned `None` before the last entry declared by the central
+    /// directory: the error, the stream offset of the faulty record and, past the central
+    /// directory file header, the index and name of the entry.
+    pub fn last_error(&self) -> Option<&ErrorContext<N>> {
+        self.last_error.as_ref()
+    }
+
+    /// Next entry, telling a corrupted entry, a short read or a failed seek apart from the end
+    /// of the central directory, which is `Ok(None)`. See [`SeekingParser::last_error`] for where
+    /// the error happened.
+    ///
+    /// The entry after a corrupted local file header can still be listed by calling it again,
+    /// while the errors of the central directory keep being returned, unless they come from the
+    /// stream and it recovers, e.g. from `std::io::ErrorKind::WouldBlock`.
+    pub fn try_next(&mut self) -> Result<Option<LocalFile<'a, S, N>>, ParsingError> {
+        match self.next() {
+            Some(file) => Ok(Some(file)),
+            None => self.last_error.as_ref().map_or(Ok(None), |context| Err(context.error.clone())),
+        }
+    }
+
+    /// End the iteration early because of `context`, kept for [`SeekingParser::last_error`]
+    fn stop<T>(&mut self, context: ErrorContext<N>) -> Option<T> {
+        diag!(warn, "listing the entries failed: {}", context);
+        self.last_error = Some(context);
+        None
+    }
+
+    /// The iteration ended for good, not an error
+    fn all_listed(&self) -> bool {
+        self.number_of_files.is_none_or(|n| self.file_index as u64 >= n)
+    }
+
+    /// The entries from the first one, through a new parser sharing the stream and the central
+    /// directory found by this one, e.g. to extract some entries after listing them all.
+    ///
+    /// Each entry seeks to its central directory record, so the iterators can be interleaved.
+    pub fn entries(&self) -> Self {
+        Self {
+            next_entry_offset: 0,
+            file_index: 0,
+            last_error: None,
+            #[cfg(feature = "std")]
+            record_offsets: self.record_offsets.clone(),
+            ..*self
+        }
+    }
+
+    /// The entry `index` of the central directory, `Ok(None)` past the last one. The iteration
+    /// goes on from where it was.
+    ///
+    /// The central directory records before it are walked on the first call, their offsets being
+    /// kept under `std` so that the following calls seek right to the record.
+    pub fn entry(&mut self, index: usize) -> Result<Option<LocalFile<'a, S, N>>, ParsingError> {
+        self.check_encryption()?;
+        if self.number_of_files.is_none_or(|n| index as u64 >= n) {
+            return Ok(None);
+        }
+        let file_index = i32::try_from(index).map_err(|_| ParsingError::Overflow)?;
+        let next_entry_offset = self.record_offset(index)?;
+        let mut parser = Self {
+            next_entry_offset,
+            file_index,
+            last_error: None,
+            #[cfg(feature = "std")]
+            record_offsets: Vec::new(),
+            ..*self
+        };
+        parser.try_next()
+    }
+
+    /// Offset of the central directory record `index` relative to the central dir, walking the
+    /// records from the closest one known
+    fn record_offset(&mut self, index: usize) -> Result<u64, ParsingError> {
+        let mut known = (0, 0);
+        if index >= self.file_index as usize {
+            known = (self.file_index as usize, self.next_entry_offset);
+        }
         #[cfg(feature = "std")]
-        { self.zip_file_comment = Vec::new(); }
+        match self.record_offsets.get(index) {
+            Some(&offset) => return Ok(offset),
+            None if self.record_offsets.len() > known.0 + 1 => {
+                known = (self.record_offsets.len() - 1, self.record_offsets[self.record_offsets.len() - 1]);
+            }
+            None => {}
+        }
+
+        let (mut i, mut offset) = known;
+        let mut stream = borrow_stream(Some(self.stream))?;
+        let mut buf = [0u8; CENTRAL_FILE_HEADER_LEN];
+        while i < index {
+            stream.seek(SeekFrom::Start(checked_offset(self.central_directory_offset, offset)?))?;
+            stream.read_exact(&mut buf)?;
+            let header = CentralFileHeader::from_bytes(&buf).ok_or(ParsingError::InvalidCentralFileHeader)?;
+            offset = checked_offset(offset, header.len() as u64)?;
+            i += 1;
+            #[cfg(feature = "std")]
+            self.cache_record_offset(i, offset);
+        }
+        Ok(offset)
+    }
+
+    /// Keep the offset of the record `index`, if those before it are known
+    #[cfg(feature = "std")]
+    fn cache_record_offset(&mut self, index: usize, offset: u64) {
+        if index == self.record_offsets.len() {
+            self.record_offsets.push(offset);
+        }
+    }
+
+    /// Cross-check the local file header of each entry with its central directory record: file
+    /// name, compression method, CRC-32 and sizes, the latter being skipped for entries with a
+    /// data descriptor. The mismatching field is given by [`LocalFile::header_mismatch`] and the
+    /// reads of the entry fail with [`ParsingError::LocalHeaderMismatch`].
+    ///
+    /// Archives from untrusted sources may disagree on purpose, so that tools trusting either
+    /// record see different contents.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// First field of the `local` header, at `metadata.local_header_offset`, disagreeing with the
+    /// central directory record of the entry, whose name is the `central_name_len` bytes at
+    /// `central_name_offset`
+    fn header_mismatch(stream: &mut S, local: &LocalFileHeader, central_name_offset: u64, central_name_len: u64, metadata: &EntryMetadata) -> Option<HeaderField> {
+        let name_offset = metadata.local_header_offset + LOCAL_FILE_HEADER_LEN as u64;
+        if local.file_name_length as u64 != central_name_len
+            || Self::same_bytes(stream, name_offset, central_name_offset, central_name_len) != Ok(true)
+        {
+            return Some(HeaderField::FileName);
+        }
+        if CompressMethod::from(local.compression_method) != metadata.compression_method {
+            return Some(HeaderField::CompressionMethod);
+        }
+        if local.general_purpose_bit_flag & FLAG_DATA_DESCRIPTOR != 0 {
+            // the values follow the data
+            return None;
+        }
+        if local.crc32 != metadata.crc32 {
+            return Some(HeaderField::Crc32);
+        }
+        let mut uncompressed_size = local.uncompressed_size as u64;
+        let mut compressed_size = local.compressed_size as u64;
+        let extra_field_offset = name_offset + local.file_name_length as u64;
+        if let Ok(zip64) = Zip64ExtraField::read_from(stream, extra_field_offset, local.extra_field_length as usize) {
+            zip64.resolve(&mut [&mut uncompressed_size, &mut compressed_size]);
+        }
+        if compressed_size != metadata.compressed_size {
+            return Some(HeaderField::CompressedSize);
+        }
+        if uncompressed_size != metadata.uncompressed_size {
+            return Some(HeaderField::UncompressedSize);
+        }
+        None
+    }
+
+    /// Whether the `len` bytes at `a` and at `b` in `stream` are the same, compared a chunk at a
+    /// time so that their length is not bounded
+    fn same_bytes(stream: &mut S, a: u64, b: u64, len: u64) -> Result<bool, ParsingError> {
+        let mut chunk_a = [0u8; 64];
+        let mut chunk_b = [0u8; 64];
+        let mut done = 0;
+        while done < len {
+            let n = cmp::min(len - done, chunk_a.len() as u64) as usize;
+            let chunk_a = read_at(stream, a.saturating_add(done), n, &mut chunk_a)?;
+            if chunk_a != read_at(stream, b.saturating_add(done), n, &mut chunk_b)? {
+                return Ok(false);
+            }
+            done += n as u64;
+        }
+        Ok(true)
+    }
+
+    /// Zip file comment following the end of central directory record, read into `buf`
+    pub fn archive_comment<'b>(&mut self, buf: &'b mut [u8]) -> Result<&'b [u8], ParsingError> {
+        let end = self.central_dir_end.ok_or(ParsingError::InvalidCentralDirEnd)?;
+        let len = end.zip_file_comment_length as usize;
+        if buf.len() < len {
+            return Err(ParsingError::BufferTooSmall);
+        }
+        let offset = self.central_dir_end_offset + CENTRAL_DIR_END_LEN as u64;
+        read_at(&mut *borrow_stream(Some(self.stream))?, offset, len, buf)
+    }
+
+    /// Check the central directory is not encrypted, no entry is listed if it is
+    pub fn check_encryption(&self) -> Result<(), ParsingError> {
+        if self.central_dir_encrypted {
+            return Err(ParsingError::UnsupportedEncryption(EncryptionKind::CentralDirectory));
+        }
+        Ok(())
+    }
+
+    /// Check the disk fields of the end of central directory record.
+    ///
+    /// Spanned archives are not supported, so all disk numbers must be 0 and this disk must
+    /// hold all the entries. A mismatch usually means a truncated or spliced archive.
+    pub fn check_disk_numbers(&self) -> Result<(), ParsingError> {
+        match self.central_dir_end {
+            Some(end) if end.number_of_disk != 0
+                || end.number_of_start_central_directory_disk != 0
+                || end.total_entries_this_disk != end.total_entries_all_disk => Err(ParsingError::DiskNumberMismatch),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<'a, S: Read + Seek, const N: usize> SeekingParser<'a, S, N> {
+    /// Open the entry of `entry`, listed by a [`CentralDirectory`], without reading its central
+    /// directory record again
+    pub fn open(&self, entry: &CentralEntry<N>) -> Result<LocalFile<'a, S, N>, ParsingError> {
+        self.open_entry(entry).map_err(|context| context.error)
+    }
+
+    /// Read the next central directory record, `None` at the end of the central directory or
+    /// after an error, kept for [`SeekingParser::last_error`]
+    fn next_record(&mut self) -> Option<CentralEntry<N>> {
+        self.last_error = None;
+        if self.central_dir_encrypted {
+            let err = ParsingError::UnsupportedEncryption(EncryptionKind::CentralDirectory);
+            return self.stop(ErrorContext::new(err, self.central_directory_offset));
+        }
+        // whatever follows the last record
+        if self.all_listed() {
+            return None;
+        }
+        if let Some(end) = self.central_dir_end.filter(|end| self.next_entry_offset >= end.size_of_the_central_directory) {
+            // the records are over before the count
+            let err = ParsingError::CentralEntryCountMismatch(self.file_index as u64, end.total_entries_this_disk);
+            return self.stop(ErrorContext::new(err, self.central_directory_offset.saturating_add(self.next_entry_offset)));
+        }
+
+        // seek read
+        let cell = self.stream;
+        let Ok(mut stream) = cell.try_borrow_mut() else {
+            return self.stop(ErrorContext::new(ParsingError::InvalidStream, self.central_directory_offset));
+        };
+        let stream = &mut *stream;
+        let Some(central_header_offset) = self.central_directory_offset.checked_add(self.next_entry_offset) else {
+            return self.stop(ErrorContext::new(ParsingError::Overflow, self.central_directory_offset));
+        };
+        if let Err(err) = stream.seek(SeekFrom::Start(central_header_offset)) {
+            return self.stop(ErrorContext::new(err, central_header_offset));
+        }
+        let mut buf = [0u8; CENTRAL_FILE_HEADER_LEN];
+        match stream.read(&mut buf) {
+            Ok(n) if n == buf.len() => {
+                if let Some(file_info) = CentralFileHeader::from_bytes(&buf) {
+                    // #[cfg(feature = "std")]
+                    // dbg!(file_info);
+                    let mut info = LocalFileInfo::default()
+                        .with_general_purpose_bit_flag(file_info.general_purpose_bit_flag)
+                        .with_compression_method(CompressMethod::from(file_info.compression_method));
+                    if file_info.disk_number_start != 0 {
+                        // spanned archives are unsupported
+                        return self.stop(ErrorContext::new(ParsingError::DiskNumberMismatch, central_header_offset));
+                    }
+                    // the name is truncated to `N` bytes
+                    let name_len = cmp::min(file_info.file_name_length as usize, N);
+                    if let Ok(n) = stream.read(&mut info.file_name_buffer[..name_len]) {
+                        info.file_name_length = n;
+                    }
+
+                    // sizes and offset beyond 4 GiB are in the extra field
+                    let mut sizes = match file_info.read_sizes(stream, central_header_offset) {
+                        Ok(sizes) => sizes,
+                        Err(err) => return self.stop(ErrorContext::new(err, central_header_offset)),
+                    };
+                    let prepended_len = self.central_dir_end.map_or(0, |end| end.prepended_len);
+                    let Some(local_header_offset) = sizes.local_header_offset.checked_add(prepended_len) else {
+                        return self.stop(ErrorContext::new(ParsingError::Overflow, central_header_offset));
+                    };
+                    sizes.local_header_offset = local_header_offset;
+                    info.compressed_size = sizes.compressed_size;
+                    info.uncompressed_size = sizes.uncompressed_size;
+                    let metadata = EntryMetadata::from_central_header(&file_info, &sizes, central_header_offset);
+                    let mut extra_field = [0u8; MAX_EXTRA_FIELD_LEN];
+                    if let Ok(extra_field) = read_at(
+                        stream,
+                        metadata.extra_field_offset,
+                        file_info.extra_field_length as usize,
+                        &mut extra_field,
+                    ) {
+                        info.apply_unicode_path(ExtraFields::new(extra_field));
+                    }
+
+                    // set next entry
+                    #[cfg(feature = "std")]
+                    self.cache_record_offset(self.file_index as usize, self.next_entry_offset);
+                    self.next_entry_offset += file_info.len() as u64;
+                    let entry = CentralEntry { info, metadata, file_index: self.file_index };
+                    self.file_index += 1;
+                    Some(entry)
+                } else {
+                    self.stop(ErrorContext::new(ParsingError::InvalidCentralFileHeader, central_header_offset))
+                }
+            }
+            Ok(_) => self.stop(ErrorContext::new(ParsingError::StreamEnding, central_header_offset)),
+            Err(err) => self.stop(ErrorContext::new(err, central_header_offset)),
+        }
+    }
+
+    /// Read the local file header of `entry` to get to its data
+    fn open_entry(&self, entry: &CentralEntry<N>) -> Result<LocalFile<'a, S, N>, ErrorContext<N>> {
+        let local_header_offset = entry.metadata.local_header_offset;
+        let context = |err| ErrorContext::new(err, local_header_offset).with_entry(entry.file_index, entry.info.file_name_bytes());
+        let mut stream = borrow_stream(Some(self.stream)).map_err(context)?;
+        let stream = &mut *stream;
+        // the data offset is set below
+        let mut file = LocalFile {
+            info: entry.info.clone(),
+            metadata: entry.metadata,
+            file_index: entry.file_index,
+            stream: Some(self.stream),
+            ..LocalFile::default()
+        };
+
+        // seek to file data
+        let mut local_header_buf = [0u8; LOCAL_FILE_HEADER_LEN];
+        stream.seek(SeekFrom::Start(local_header_offset)).map_err(context)?;
+        if !matches!(stream.read(&mut local_header_buf), Ok(n) if n == local_header_buf.len()) {
+            return Err(context(ParsingError::StreamEnding));
+        }
+        let local_header = LocalFileHeader::from_bytes(&local_header_buf).ok_or_else(|| context(ParsingError::InvalidLocalFileHeader))?;
+        file.info.file_data_offset = local_header_offset + local_header.len() as u64;
+        if local_header.general_purpose_bit_flag & FLAG_DATA_DESCRIPTOR != 0 {
+            // the descriptor has 8 bytes sizes along with the ZIP64 extended information
+            let extra_field_offset = local_header_offset
+                + LOCAL_FILE_HEADER_LEN as u64
+                + local_header.file_name_length as u64;
+            file.data_descriptor = true;
+            file.zip64_descriptor = Zip64ExtraField::read_from(stream, extra_field_offset, local_header.extra_field_length as usize)
+                .is_ok_and(|field| field.is_present());
+        }
+        match checked_offset(file.info.file_data_offset, file.info.compressed_size) {
+            Err(err) => file.unreadable = Some(err),
+            Ok(data_end) if self.central_dir_end.is_some() && data_end > self.central_directory_offset => {
+                // the data would run into the central directory
+                file.unreadable = Some(ParsingError::EntryOutOfBounds(data_end));
+            }
+            _ => {}
+        }
+        if file.unreadable.is_none() && self.strict {
+            // the name of the record, which the Unicode path extra field may have replaced
+            let name_offset = entry.metadata.central_header_offset + CENTRAL_FILE_HEADER_LEN as u64;
+            let name_len = entry.metadata.extra_field_offset.saturating_sub(name_offset);
+            file.unreadable = Self::header_mismatch(stream, &local_header, name_offset, name_len, &file.metadata)
+                .map(ParsingError::LocalHeaderMismatch);
+        }
+        file.stream_origin = file.info.file_data_offset;
+        file.stream_position = file.info.file_data_offset;
+        Ok(file)
+    }
+}
+
+impl<'a, S: Read + Seek, const N: usize> Iterator for SeekingParser<'a, S, N> {
+    type Item = LocalFile<'a, S, N>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.next_record()?;
+        match self.open_entry(&entry) {
+            Ok(file) => Some(file),
+            Err(context) => self.stop(context),
+        }
+    }
+
+    /// The entries left according to the end of central directory record, bounded by the
+    /// headers its central directory size can hold, none without a central directory
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let fitting = self.central_dir_end
+            .map_or(0, |end| end.size_of_the_central_directory / CENTRAL_FILE_HEADER_LEN as u64);
+        let left = cmp::min(self.number_of_files.unwrap_or(0), fitting).saturating_sub(self.file_index as u64);
+        let left = usize::try_from(left).unwrap_or(usize::MAX);
+        (left, Some(left))
+    }
+}
+
+/// The length is the count of the end of central directory record, the iteration stops before
+/// on a corrupted archive, see [`SeekingParser::last_error`].
+impl<S: Read + Seek, const N: usize> ExactSizeIterator for SeekingParser<'_, S, N> {}
+
+#[derive(Debug, Clone, Copy)]
+enum HeaderType {
+    HeaderSignature,
+    LocalFileHeader,
+    CentralFileHeader,
+    CentralDirEnd,
+    Zip64CentralDirEnd,
+    Zip64CentralDirEndLocator,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, Copy)]
+enum ParserState {
+    RecvHeader(HeaderType, usize),
+    RecvCentralFileHeader,
+    RecvCentralDirEnd,
+    /// extensible data sector of the ZIP64 end of central directory record, skipped
+    RecvZip64CentralDirEnd,
+    RecvLocalFileName,
+    RecvLocalFileExtraField,
+    RecvLocalFileData,
+    /// listing-only mode after the end of central directory record, or encrypted central
+    /// directory, nothing is consumed anymore
+    Finished,
+}
+
+pub struct PassiveParser<const N: usize> {
+    /// header buffer
+    buffer: heapless::Vec<u8, MAX_RECORD_LEN>,
+
+    #[cfg(feature = "std")]
+    zip_file_comment: Vec<u8>,
+
+    pub localfile_info: Option<LocalFileInfo<N>>,
+
+    localfile_index: i32,
+    centralfile_index: i32,
+
+    file_name_len: usize,
+    file_name_index: usize,
+
+    extra_field_len: usize,
+    extra_field_index: usize,
+    /// ZIP64 extended information of the extra field being received, local or central
+    zip64_extra: Zip64ExtraField,
+    /// start of the extra field being received, local or central
+    extra_field: heapless::Vec<u8, MAX_EXTRA_FIELD_LEN>,
+
+    file_data_len: usize,
+    file_data_index: usize,
+
+    central_file_header_index: usize,
+    central_file_header_len: usize,
+
+    central_dir_end_index: usize,
+    central_dir_end_len: usize,
+
+    /// fixed part and file name of the central directory file header being received
+    central_header: [u8; CENTRAL_FILE_HEADER_LEN],
+    central_file_name: [u8; N],
+    central_header_offset: u64,
+
+    /// bytes consumed since the start of the stream
+    stream_offset: u64,
+    /// CRC32 of the current local file data so far
+    crc32: u32,
+    /// CRC32 of the uncompressed data of the current entry, from its local header or data descriptor
+    expected_crc32: u32,
+    /// report entries whose data doesn't match their CRC32
+    check_crc: bool,
+    crc_engine: &'static dyn Crc32,
+
+    /// reset after each archive and keep parsing the following one
+    multi_archive: bool,
+    archive_index: u32,
+    stats: ArchiveStats,
+
+    /// report badly ordered records
+    strict: bool,
+    central_dir_started: bool,
+    archive_ended: bool,
+
+    /// skip the local file data and stop after the end of central directory record
+    listing_only: bool,
+    /// length of the whole stream, if known
+    stream_len: Option<u64>,
+    /// where the last `ParsingError` event was found
+    last_error: Option<ErrorContext<N>>,
+
+    /// the data of the current entry ends with a data descriptor, its length is unknown
+    data_descriptor: bool,
+    descriptor_scanner: DescriptorScanner,
+
+    /// run the data of deflated entries through `inflater`
+    #[cfg(feature = "inflate")]
+    inflate: bool,
+    /// the current entry is being inflated
+    #[cfg(feature = "inflate")]
+    inflating: bool,
+    /// the current entry failed to inflate, the rest of its data is dropped
+    #[cfg(feature = "inflate")]
+    inflate_failed: bool,
+    /// decompressed length of the current entry
+    #[cfg(feature = "inflate")]
+    inflated: usize,
+    /// CRC32 of the decompressed data of the current entry so far
+    #[cfg(feature = "inflate")]
+    inflated_crc32: u32,
+    #[cfg(feature = "inflate")]
+    inflater: Inflater,
+
+    state: ParserState,
+}
+
+/// Longest fixed part of the records received by [`PassiveParser`]
+const MAX_RECORD_LEN: usize = ZIP64_CENTRAL_DIR_END_LEN;
+
+/// Output chunk of [`PassiveParser::with_inflate`], the length of its `LocalFileData` events at most
+#[cfg(feature = "inflate")]
+pub(crate) const INFLATE_CHUNK_LEN: usize = 512;
+
+/// Snapshot of a [`PassiveParser`] taken with [`PassiveParser::checkpoint`]
+///
+/// To resume, restore a parser from it and feed the stream from `stream_offset` on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Checkpoint<const N: usize> {
+    /// Offset of the stream to continue feeding data from
+    pub stream_offset: u64,
+    pub file_index: i32,
+    /// Offset within the local file data, only meaningful inside an entry
+    pub data_offset: u64,
+    /// CRC32 of the local file data before `data_offset`
+    pub crc32: u32,
+    /// Header of the entry being received, `None` between entries
+    pub localfile_info: Option<LocalFileInfo<N>>,
+
+    expected_crc32: u32,
+    centralfile_index: i32,
+    file_data_len: usize,
+    archive_index: u32,
+    stats: ArchiveStats,
+    central_dir_started: bool,
+}
+
+impl<const N: usize> PassiveParser<N> {
+    fn buffer_data_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    fn buffer_data(&self) -> &[u8] {
+        self.buffer.as_slice()
+    }
+
+    fn append_bytes(&mut self, data: &[u8]) -> usize {
+        let len = cmp::min(data.len(), self.buffer.capacity() - self.buffer.len());
+        // fits by construction, nothing is appended otherwise
+        self.buffer.extend_from_slice(&data[..len]).map_or(0, |_| len)
+    }
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reset(&mut self) {
+        self.state = ParserState::RecvHeader(HeaderType::HeaderSignature, 4);
+
+        #[cfg(feature = "std")]
+        { self.zip_file_comment = Vec::new(); }
+
+        self.buffer.clear();
+
+        self.localfile_info = None;
+        self.localfile_index = 0;
+        self.centralfile_index = 0;
+
+        self.file_name_index = 0;
+        self.file_name_len = 0;
+
+        self.extra_field_index = 0;
+        self.extra_field_len = 0;
+        self.zip64_extra.reset();
+        self.extra_field.clear();
+
+        self.file_data_index = 0;
+        self.file_data_len = 0;
+
+        self.central_file_header_index = 0;
+        self.central_file_header_len = 0;
+
+        self.central_dir_end_index = 0;
+        self.central_dir_end_len = 0;
+
+        self.central_header = [0; CENTRAL_FILE_HEADER_LEN];
+        self.central_file_name = [0; N];
+        self.central_header_offset = 0;
+
+        self.stream_offset = 0;
+        self.crc32 = 0;
+        self.expected_crc32 = 0;
+        self.last_error = None;
+
+        self.archive_index = 0;
+        self.stats = ArchiveStats::default();
+
+        self.central_dir_started = false;
+        self.archive_ended = false;
+
+        self.data_descriptor = false;
+        self.descriptor_scanner.reset(false, false);
+
+        #[cfg(feature = "inflate")]
+        {
+            self.inflating = false;
+            self.inflate_failed = false;
+            self.inflated = 0;
+            self.inflated_crc32 = 0;
+        }
+    }
+
+    /// Check the ordering of the records: local file headers, then the central directory, then
+    /// the end of central directory record. Violations are reported as `ParsingError` events.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Only list the archive: `LocalFileData` events are not emitted, the data is just skipped,
+    /// and the parser stops consuming data after the end of central directory record.
+    ///
+    /// The listing comes from the `LocalFileHeader` and `CentralFileHeader` events, the latter
+    /// having the sizes and CRC32 of entries written with a data descriptor. In multi-archive
+    /// mode, the parser goes on with the next archive instead of stopping.
+    pub fn with_listing_only(mut self, listing_only: bool) -> Self {
+        self.listing_only = listing_only;
+        self
+    }
+
+    /// Length of the whole stream, when known: an entry whose data would run past it is reported
+    /// with [`ParsingError::EntryOutOfBounds`] and ends the parsing, instead of the rest of the
+    /// stream being taken as its data.
+    pub fn with_stream_len(mut self, len: u64) -> Self {
+        self.stream_len = Some(len);
+        self
+    }
+
+    /// Check the CRC32 of the entries at their end, a mismatch is reported as a `ParsingError` event
+    /// with [`ParsingError::CrcMismatch`] before `LocalFileEnd`. On by default with the `crc-check`
+    /// feature.
+    ///
+    /// Only the stored entries and the inflated ones (see `with_inflate`) can be checked, not the
+    /// encrypted ones, nor those whose data is skipped by `with_listing_only`.
+    pub fn with_crc_check(mut self, check: bool) -> Self {
+        self.check_crc = check;
+        self
+    }
+
+    /// Compute the CRC32 with `engine` instead of [`SoftwareCrc32`]. The engine is not part of the
+    /// checkpoints and saved states, give it again to the restored parser.
+    pub fn with_crc_engine(mut self, engine: &'static dyn Crc32) -> Self {
+        self.crc_engine = engine;
+        self
+    }
+
+    /// Inflate the data of deflated entries: their `LocalFileData` events carry decompressed data,
+    /// at most 512 bytes at a time, the offsets being in the decompressed data. Then
+    /// [`ParserEvent::LocalFileInflated`] reports the decompressed length before `LocalFileEnd`.
+    ///
+    /// The end of the deflate stream ends the data of the entries having a data descriptor. An
+    /// inflated length other than the uncompressed size is reported as a `ParsingError` event,
+    /// which takes the place of `LocalFileInflated` as do the other inflate errors.
+    ///
+    /// Other entries, and the encrypted deflated ones, are reported as usual.
+    /// The inflater state is not saved, so `checkpoint` and `save_state` are unavailable inside a
+    /// deflated entry, and `feed_events` ignores the mode, queuing the raw data.
+    #[cfg(feature = "inflate")]
+    pub fn with_inflate(mut self, inflate: bool) -> Self {
+        self.inflate = inflate;
+        self
+    }
+
+    /// Keep the following bytes of the extra field, as long as they fit
+    fn keep_extra_field(&mut self, bytes: &[u8]) {
+        let len = cmp::min(bytes.len(), self.extra_field.capacity() - self.extra_field.len());
+        let _ = self.extra_field.extend_from_slice(&bytes[..len]);
+    }
+
+    /// Whether the current entry is run through the inflater
+    fn is_inflating(&self) -> bool {
+        #[cfg(feature = "inflate")]
+        { self.inflate && self.inflating }
+        #[cfg(not(feature = "inflate"))]
+        { false }
+    }
+
+    /// Whether the inflater is yet to find the end of the deflate stream of the current entry
+    fn is_running_inflater(&self) -> bool {
+        #[cfg(feature = "inflate")]
+        { self.is_inflating() && !self.listing_only && !self.inflate_failed && !self.inflater.is_done() }
+        #[cfg(not(feature = "inflate"))]
+        { false }
+    }
+
+    /// Whether the parser stopped after the end of the archive in listing-only mode, or on an
+    /// encrypted central directory
+    pub fn is_finished(&self) -> bool {
+        matches!(self.state, ParserState::Finished)
+    }
+
+    /// Whether the data fed so far ends with a whole archive, or the parser stopped, so that the
+    /// stream may end here
+    pub(crate) fn is_at_archive_end(&self) -> bool {
+        // multi-archive mode resets the parser after each archive
+        let between_archives = self.archive_index > 0
+            && matches!(self.state, ParserState::RecvHeader(HeaderType::HeaderSignature, _))
+            && self.buffer.is_empty()
+            && self.localfile_info.is_none()
+            && !self.central_dir_started;
+        self.is_finished() || self.archive_ended || between_archives
+    }
+
+    /// Ordering violation of a record starting with `signature`, only checked in strict mode
+    fn check_order(&self, signature: Signature) -> Option<ParsingError> {
+        if !self.strict {
+            None
+        } else if self.archive_ended {
+            Some(ParsingError::RecordAfterCentralDirEnd)
+        } else if self.central_dir_started && matches!(signature, Signature::LocalFileHeader) {
+            Some(ParsingError::LocalHeaderAfterCentralDirectory)
+        } else {
+            None
+        }
+    }
+
+    /// Parse concatenated archives: after the end of central directory record of an archive, the
+    /// parser resets itself and goes on with the next archive in the stream.
+    ///
+    /// Each archive ends with a [`ParserEvent::ArchiveEnd`], and file indices restart from 0. Its
+    /// comment stays available through [`PassiveParser::file_comment`] until the next archive
+    /// starts.
+    pub fn with_multi_archive(mut self, multi_archive: bool) -> Self {
+        self.multi_archive = multi_archive;
+        self
+    }
+
+    /// Index of the archive being parsed, only increases in multi-archive mode
+    pub fn archive_index(&self) -> u32 {
+        self.archive_index
+    }
+
+    /// Finish the current archive, returns its index and statistics
+    fn end_archive(&mut self) -> (u32, ArchiveStats) {
+        let archive_index = self.archive_index;
+        let mut stats = self.stats;
+        stats.central_records = self.centralfile_index as u64;
+        if self.multi_archive {
+            let stream_offset = self.stream_offset;
+            // readable after the `ArchiveEnd` event, until the next archive starts
+            #[cfg(feature = "std")]
+            let comment = core::mem::take(&mut self.zip_file_comment);
+            self.reset();
+            #[cfg(feature = "std")]
+            { self.zip_file_comment = comment; }
+            self.stream_offset = stream_offset;
+            self.archive_index = archive_index + 1;
+        } else {
+            self.central_dir_end_index = 0;
+            self.central_dir_end_len = 0;
+            self.state = if self.listing_only {
+                ParserState::Finished
+            } else {
+                ParserState::RecvHeader(HeaderType::HeaderSignature, 4)
+            };
+            self.archive_ended = true;
+        }
+        (archive_index, stats)
+    }
+
+    /// Bytes consumed since the start of the stream
+    pub fn stream_offset(&self) -> u64 {
+        self.stream_offset
+    }
+
+    /// Where the error of the last `ParsingError` event was found: the stream offset right after
+    /// the faulty bytes and, inside a local file, its index and name so far.
+    ///
+    /// Only the errors found by the parser itself are kept, not the ones of the handlers.
+    pub fn last_error(&self) -> Option<&ErrorContext<N>> {
+        self.last_error.as_ref()
+    }
+
+    /// `ParsingError` event of `err`, keeping its context for [`PassiveParser::last_error`]
+    fn error_event<'b, 'c>(&mut self, err: ParsingError, processed: usize) -> ParserEvent<'b, 'c, N> {
+        let mut context = ErrorContext::new(err.clone(), self.stream_offset + processed as u64);
+        match self.state {
+            // the entry info is the one of the previous entry until the header is parsed
+            ParserState::RecvHeader(HeaderType::LocalFileHeader, _) => context.file_index = Some(self.localfile_index),
+            ParserState::RecvLocalFileName | ParserState::RecvLocalFileExtraField | ParserState::RecvLocalFileData => {
+                let name = self.localfile_info.as_ref().map_or(&[][..], |info| info.file_name_bytes());
+                context = context.with_entry(self.localfile_index, name);
+            }
+            _ => {}
+        }
+        self.last_error = Some(context);
+        ParserEvent::ParsingError(self.localfile_index, err)
+    }
+
+    /// Snapshot the progress, so parsing can be resumed later by [`PassiveParser::restore`].
+    ///
+    /// Only available inside local file data or between records, `None` otherwise. The data of an
+    /// entry ending with a data descriptor doesn't count, as the bytes held back aren't saved.
+    pub fn checkpoint(&self) -> Option<Checkpoint<N>> {
+        let in_file_data = match self.state {
+            ParserState::RecvLocalFileData if self.is_inflating() || self.data_descriptor => return None,
+            ParserState::RecvLocalFileData => true,
+            ParserState::RecvHeader(HeaderType::HeaderSignature, _) if self.buffer.is_empty() => false,
+            _ => return None,
+        };
+        Some(Checkpoint {
+            stream_offset: self.stream_offset,
+            file_index: self.localfile_index,
+            data_offset: if in_file_data { self.file_data_index as u64 } else { 0 },
+            crc32: if in_file_data { self.crc32 } else { 0 },
+            localfile_info: if in_file_data { self.localfile_info.clone() } else { None },
+            expected_crc32: if in_file_data { self.expected_crc32 } else { 0 },
+            centralfile_index: self.centralfile_index,
+            file_data_len: if in_file_data { self.file_data_len } else { 0 },
+            archive_index: self.archive_index,
+            stats: self.stats,
+            central_dir_started: self.central_dir_started,
+        })
+    }
+
+    /// Create a parser continuing from `checkpoint`, feed it the stream from `checkpoint.stream_offset`
+    pub fn restore(checkpoint: &Checkpoint<N>) -> Self {
+        let mut parser = Self {
+            stream_offset: checkpoint.stream_offset,
+            localfile_index: checkpoint.file_index,
+            centralfile_index: checkpoint.centralfile_index,
+            archive_index: checkpoint.archive_index,
+            stats: checkpoint.stats,
+            central_dir_started: checkpoint.central_dir_started,
+            ..Self::default()
+        };
+        if let Some(info) = &checkpoint.localfile_info {
+            parser.state = ParserState::RecvLocalFileData;
+            parser.localfile_info = Some(info.clone());
+            parser.file_data_index = checkpoint.data_offset as usize;
+            parser.file_data_len = checkpoint.file_data_len;
+            parser.crc32 = checkpoint.crc32;
+            parser.expected_crc32 = checkpoint.expected_crc32;
+        }
+        parser
+    }
+
+    pub fn localfile_index(&self) -> i32 {
+        self.localfile_index
+    }
+
+    pub fn file_comment(&self) -> Result<&str, Utf8Error> {
+        #[cfg(feature = "std")]
+        { str::from_utf8(self.zip_file_comment.as_slice()) }
+        #[cfg(not(feature = "std"))]
+        { Ok("") }
+    }
+
+    pub fn feed_data<F>(&mut self, data: &[u8], mut on_event: F)
+    where
+        F: for<'b, 'c> FnMut(ParserEvent<'b, 'c, N>) -> bool,
+    {
+        // report consumed len
+        if let Err(n) = self.parse_data(data, |evt, _| on_event(evt)) {
+            on_event(ParserEvent::UserCancel(-1, n));
+        }
+    }
+
+    /// Parse everything `reader` yields, decoding the records straight out of its buffer
+    /// with `fill_buf`/`consume`, returns the number of bytes consumed.
+    ///
+    /// Stops early, after a `UserCancel` event, if the handler cancels.
+    #[cfg(feature = "std")]
+    pub fn feed_buf_read<R, F>(&mut self, reader: &mut R, mut on_event: F) -> io::Result<u64>
+    where
+        R: io::BufRead,
+        F: for<'b, 'c> FnMut(ParserEvent<'b, 'c, N>) -> bool,
+    {
+        let mut consumed = 0;
+        loop {
+            let data = reader.fill_buf()?;
+            if data.is_empty() || self.is_finished() {
+                return Ok(consumed);
+            }
+            match self.parse_data(data, |evt, _| on_event(evt)) {
+                Ok(n) => {
+                    reader.consume(n);
+                    consumed += n as u64;
+                }
+                Err(n) => {
+                    reader.consume(n);
+                    consumed += n as u64;
+                    on_event(ParserEvent::UserCancel(-1, consumed as usize));
+                    return Ok(consumed);
+                }
+            }
+        }
+    }
+
+    /// Feed several discontiguous pieces of the stream at once, e.g. the two halves of a ring buffer.
+    ///
+    /// Same as calling [`PassiveParser::feed_data`] on each slice in order, but a cancellation
+    /// stops at once and `UserCancel` reports the bytes consumed over all the slices.
+    pub fn feed_data_vectored<F>(&mut self, data: &[&[u8]], mut on_event: F)
+    where
+        F: for<'b, 'c> FnMut(ParserEvent<'b, 'c, N>) -> bool,
+    {
+        let mut consumed = 0;
+        for slice in data {
+            match self.parse_data(slice, |evt, _| on_event(evt)) {
+                Ok(n) => consumed += n,
+                Err(n) => {
+                    on_event(ParserEvent::UserCancel(-1, consumed + n));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Parse `data` without a callback, queuing the events into `events` instead.
+    ///
+    /// Returns `(consumed_bytes, event_count)`, the first `event_count` slots of `events` are
+    /// initialized. Parsing stops when the queue is full, feed `data[consumed_bytes..]` again
+    /// after handling the events.
+    pub fn feed_events(&mut self, data: &[u8], events: &mut [MaybeUninit<OwnedEvent<N>>]) -> (usize, usize) {
+        if events.is_empty() {
+            return (0, 0);
+        }
+        // the inflated data is not part of `data`
+        #[cfg(feature = "inflate")]
+        let inflate = core::mem::replace(&mut self.inflate, false);
+
+        let mut count = 0;
+        let res = self.parse_data(data, |evt, position| {
+            let evt = match evt {
+                ParserEvent::LocalFileHeader(i, info) => OwnedEvent::LocalFileHeader(i, info.clone()),
+                ParserEvent::LocalFileData { file_index, offset, data: held } if !data.as_ptr_range().contains(&held.as_ptr()) => {
+                    OwnedEvent::LocalFileHeldData {
+                        file_index,
+                        offset,
+                        data: heapless::Vec::from_slice(held).unwrap_or_default(),
+                    }
+                }
+                ParserEvent::LocalFileData { file_index, offset, data } => OwnedEvent::LocalFileData {
+                    file_index,
+                    offset,
+                    range: position..position + data.len(),
+                },
+                ParserEvent::LocalFileEnd(i) => OwnedEvent::LocalFileEnd(i),
+                ParserEvent::ParsingError(i, e) => OwnedEvent::ParsingError(i, e),
+                ParserEvent::CentralFileHeader(i, info, metadata) => OwnedEvent::CentralFileHeader(i, info.clone(), metadata),
+                ParserEvent::ArchiveEnd(i, stats) => OwnedEvent::ArchiveEnd(i, stats),
+                ParserEvent::LocalFileInflated(i, len) => OwnedEvent::LocalFileInflated(i, len),
+                ParserEvent::LocalFileDataDescriptor(i, descriptor) => OwnedEvent::LocalFileDataDescriptor(i, descriptor),
+                ParserEvent::LocalFileExtraField(i, records) => {
+                    OwnedEvent::LocalFileExtraField(i, heapless::Vec::from_slice(records.as_bytes()).unwrap_or_default())
+                }
+                ParserEvent::CentralFileExtraField(i, records) => {
+                    OwnedEvent::CentralFileExtraField(i, heapless::Vec::from_slice(records.as_bytes()).unwrap_or_default())
+                }
+                ParserEvent::UserCancel(..) => return true,
+            };
+            events[count].write(evt);
+            count += 1;
+            count < events.len()
+        });
+        #[cfg(feature = "inflate")]
+        { self.inflate = inflate; }
+        match res {
+            Ok(n) | Err(n) => (n, count),
+        }
+    }
+
+    /// Parse `data`, returns `Err` with the consumed length if the handler cancelled.
+    ///
+    /// Besides the event, the handler gets the offset in `data` where the event data starts.
+    fn parse_data<F>(&mut self, data: &[u8], mut on_event: F) -> Result<usize, usize>
+    where
+        F: for<'b, 'c> FnMut(ParserEvent<'b, 'c, N>, usize) -> bool,
+    {
+        struct BufferData<'a> {
+            count: usize,
+            data: &'a [u8],
+        }
+
+        impl<'a> BufferData<'a> {
+            pub fn new(data: &'a [u8]) -> Self {
+                BufferData {
+                    count: 0,
+                    data,
+                }
+            }
+
+            pub fn peek_data(&self, len: usize) -> &[u8] {
+                &self.data[self.count..self.count + len]
+            }
+
+            /// Processed some data, which length is `len`
+            pub fn proccessed(&mut self, len: usize) {
+                self.count += len;
+            }
+
+            pub fn proccessed_data_len(&self) -> usize {
+                self.count
+            }
+
+            pub fn unproccessed_data_len(&self) -> usize {
+                self.data.len() - self.count
+            }
+        }
+
+        let mut buffer_data = BufferData::new(data);
+        let res: Result<usize, usize> = loop {
+            // All data have being processed
+            if buffer_data.proccessed_data_len() >= data.len() {
+                break Ok(data.len());
+            }
+            let mut continue_parsing = true;
+            match self.state {
+                ParserState::RecvHeader(header_type, header_len) => {
+                    // a record fully in `data` is decoded in place, otherwise it is queued in the buffer
+                    let in_place = self.buffer.is_empty() && buffer_data.unproccessed_data_len() >= header_len;
+                    let start = buffer_data.proccessed_data_len();
+                    if in_place {
+                        // a valid signature is part of the record header that follows
+                        if !matches!(header_type, HeaderType::HeaderSignature) {
+                            buffer_data.proccessed(header_len);
+                        }
+                    } else {
+                        // queue data
+                        let len = cmp::min(
+                            header_len - self.buffer_data_len(),
+                            buffer_data.unproccessed_data_len(),
+                        );
+                        buffer_data.proccessed(self.append_bytes(buffer_data.peek_data(len)));
+
+                        // check data len in buffer
+                        if self.buffer_data_len() < header_len {
+                            continue;
+                        }
+                    }
+                    let record: &[u8] = if in_place {
+                        &data[start..start + header_len]
+                    } else {
+                        &self.buffer
+                    };
+
+                    match header_type {
+                        HeaderType::HeaderSignature => {
+                            // parse signature type
+                            match Signature::try_from(record) {
+                                Err(err) => {
+                                    if in_place {
+                                        buffer_data.proccessed(header_len);
+                                    }
+                                    continue_parsing = on_event(self.error_event(err, buffer_data.proccessed_data_len()), buffer_data.proccessed_data_len());
+                                    self.buffer.clear();
+                                }
+                                Ok(sig) => {
+                                    // first record of the archive, the comment is that of the previous one
+                                    #[cfg(feature = "std")]
+                                    if self.localfile_info.is_none() && !self.central_dir_started {
+                                        self.zip_file_comment.clear();
+                                    }
+                                    if let Some(err) = self.check_order(sig) {
+                                        continue_parsing = on_event(self.error_event(err, buffer_data.proccessed_data_len()), buffer_data.proccessed_data_len());
+                                    }
+                                    if !matches!(sig, Signature::LocalFileHeader) {
+                                        self.central_dir_started = true;
+                                    }
+                                    match sig {
+                                        Signature::LocalFileHeader => self.state = ParserState::RecvHeader(HeaderType::LocalFileHeader, LOCAL_FILE_HEADER_LEN),
+                                        Signature::CentralFileHeader => self.state = ParserState::RecvHeader(HeaderType::CentralFileHeader, CENTRAL_FILE_HEADER_LEN),
+                                        Signature::CentralDirEnd => self.state = ParserState::RecvHeader(HeaderType::CentralDirEnd, CENTRAL_DIR_END_LEN),
+                                        Signature::Zip64CentralDirEnd => {
+                                            self.state = ParserState::RecvHeader(HeaderType::Zip64CentralDirEnd, ZIP64_CENTRAL_DIR_END_LEN)
+                                        }
+                                        Signature::Zip64CentralDirEndLocator => {
+                                            self.state = ParserState::RecvHeader(HeaderType::Zip64CentralDirEndLocator, ZIP64_CENTRAL_DIR_END_LOCATOR_LEN)
+                                        }
+                                        Signature::ArchiveExtraData => {
+                                            // nothing after it can be parsed
+                                            let err = ParsingError::UnsupportedEncryption(EncryptionKind::CentralDirectory);
+                                            continue_parsing = on_event(self.error_event(err, buffer_data.proccessed_data_len()), buffer_data.proccessed_data_len());
+                                            self.state = ParserState::Finished;
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                        HeaderType::LocalFileHeader => {
+                            // parse header
+                            if let Some(file_info) = LocalFileHeader::from_bytes(record) {
+                                // #[cfg(feature = "std")]
+                                // dbg!(file_info);
+
+                                // header is ready
+                                self.state = ParserState::RecvLocalFileName;
+
+                                self.file_name_index = 0;
+                                self.file_name_len = file_info.file_name_length as usize;
+                                self.extra_field_index = 0;
+                                self.extra_field_len = file_info.extra_field_length as usize;
+                                self.zip64_extra.reset();
+                                self.extra_field.clear();
+                                self.file_data_index = 0;
+                                self.file_data_len = file_info.compressed_size as usize;
+                                self.data_descriptor = file_info.general_purpose_bit_flag & FLAG_DATA_DESCRIPTOR != 0;
+                                self.crc32 = 0;
+                                self.expected_crc32 = file_info.crc32;
+
+                                self.stats.entries += 1;
+
+                                // The data size in buffer must equal to LOCAL_FILE_HEADER_LEN
+                                let localfile_info = LocalFileInfo::default()
+                                    .with_general_purpose_bit_flag(file_info.general_purpose_bit_flag)
+                                    .with_compression_method(CompressMethod::from(file_info.compression_method))
+                                    .with_compressed_size(file_info.compressed_size as u64)
+                                    .with_uncompressed_size(file_info.uncompressed_size as u64);
+                                self.localfile_info.replace(localfile_info);
+
+                                if file_info.general_purpose_bit_flag & FLAG_MASKED_HEADER != 0 {
+                                    // the data length is unknown, nothing after it can be parsed
+                                    let err = ParsingError::UnsupportedEncryption(EncryptionKind::CentralDirectory);
+                                    continue_parsing = on_event(self.error_event(err, buffer_data.proccessed_data_len()), buffer_data.proccessed_data_len());
+                                    self.state = ParserState::Finished;
+                                } else if file_info.general_purpose_bit_flag & FLAG_STRONG_ENCRYPTION != 0 {
+                                    let err = ParsingError::UnsupportedEncryption(EncryptionKind::Strong);
+                                    continue_parsing = on_event(self.error_event(err, buffer_data.proccessed_data_len()), buffer_data.proccessed_data_len());
+                                } else if self.file_name_len > N {
+                                    // only the first `N` bytes of the name are kept
+                                    let err = ParsingError::LocalFileNameTooLong(self.localfile_index, self.file_name_len);
+                                    continue_parsing = on_event(self.error_event(err, buffer_data.proccessed_data_len()), buffer_data.proccessed_data_len());
+                                }
+                            } else {
+                                // #[cfg(feature = "std")]
+                                // eprintln!("get LocalFileHeader from raw ptr({:02X?}) failed", self.buffer);
+
+                                let err = ParsingError::InvalidLocalFileHeader;
+                                continue_parsing = on_event(self.error_event(err, buffer_data.proccessed_data_len()), buffer_data.proccessed_data_len());
+                            }
+                            // drop all data
+                            self.buffer.clear();
+                        }
+                        HeaderType::CentralFileHeader => {
+                            // parse
+                            if let Some(header) = CentralFileHeader::from_bytes(record) {
+                                self.central_file_header_len = header.len();
+                                self.central_file_header_index = header_len;
+                                self.central_header.copy_from_slice(record);
+                                self.zip64_extra.reset();
+                                self.extra_field.clear();
+                                self.central_header_offset = self.stream_offset
+                                    + buffer_data.proccessed_data_len() as u64
+                                    - CENTRAL_FILE_HEADER_LEN as u64;
+                            } else {
+                                self.central_header = [0; CENTRAL_FILE_HEADER_LEN];
+                                // nothing follows the fixed part, the entry is skipped
+                                self.central_file_header_len = header_len;
+                                self.central_file_header_index = header_len;
+                                let err = ParsingError::InvalidCentralFileHeader;
+                                continue_parsing = on_event(self.error_event(err, buffer_data.proccessed_data_len()), buffer_data.proccessed_data_len());
+                            }
+                            // drop all data
+                            self.buffer.clear();
+
+                            self.state = ParserState::RecvCentralFileHeader;
+                        }
+                        HeaderType::CentralDirEnd => {
+                            // parse
+                            if let Some(header) = CentralDirEnd::from_bytes(record) {
+                                self.central_dir_end_len = header.len();
+                                self.central_dir_end_index = header_len;
+                                // otherwise the count came with the ZIP64 end of central directory record
+                                if !header.has_zip64_entries() {
+                                    self.stats.declared_entries = header.total_entries_all_disk;
+                                }
+                                if self.strict && self.centralfile_index as u64 != self.stats.declared_entries {
+                                    let err = ParsingError::CentralEntryCountMismatch(self.centralfile_index as u64, self.stats.declared_entries);
+                                    continue_parsing = on_event(self.error_event(err, buffer_data.proccessed_data_len()), buffer_data.proccessed_data_len());
+                                }
+                            } else {
+                                let err = ParsingError::InvalidCentralDirEnd;
+                                continue_parsing = on_event(self.error_event(err, buffer_data.proccessed_data_len()), buffer_data.proccessed_data_len());
+                            }
+                            // drop all data
+                            self.buffer.clear();
+
+                            self.state = ParserState::RecvCentralDirEnd;
+                            // without a comment, the archive ends right here
+                            if self.central_dir_end_index >= self.central_dir_end_len {
+                                let (archive_index, stats) = self.end_archive();
+                                continue_parsing = continue_parsing
+                                    && on_event(ParserEvent::ArchiveEnd(archive_index, stats), buffer_data.proccessed_data_len());
+                            }
+                        }
+                        HeaderType::Zip64CentralDirEnd => {
+                            // parse
+                            if let Some(header) = Zip64CentralDirEnd::from_bytes(record) {
+                                self.central_dir_end_len = cmp::max(header.len(), header_len as u64) as usize;
+                                self.central_dir_end_index = header_len;
+                                self.stats.declared_entries = header.total_entries_all_disk;
+                                self.state = ParserState::RecvZip64CentralDirEnd;
+                            } else {
+                                let err = ParsingError::InvalidCentralDirEnd;
+                                continue_parsing = on_event(self.error_event(err, buffer_data.proccessed_data_len()), buffer_data.proccessed_data_len());
+                                self.state = ParserState::RecvHeader(HeaderType::HeaderSignature, 4);
+                            }
+                            // drop all data
+                            self.buffer.clear();
+                        }
+                        HeaderType::Zip64CentralDirEndLocator => {
+                            // nothing to keep, the ZIP64 record was received already
+                            if Zip64CentralDirEndLocator::from_bytes(record).is_none() {
+                                let err = ParsingError::InvalidCentralDirEnd;
+                                continue_parsing = on_event(self.error_event(err, buffer_data.proccessed_data_len()), buffer_data.proccessed_data_len());
+                            }
+                            // drop all data
+                            self.buffer.clear();
+
+                            self.state = ParserState::RecvHeader(HeaderType::HeaderSignature, 4);
+                        }
+                    }
+                }
+                ParserState::RecvLocalFileName => {
+                    // if header is ready
+                    let Some(info) = self.localfile_info.as_mut() else {
+                        let err = ParsingError::LocalFileHeaderNotRecved(self.localfile_index);
+                        continue_parsing = on_event(self.error_event(err, buffer_data.proccessed_data_len()), buffer_data.proccessed_data_len());
+                        self.state = ParserState::Finished;
+                        if !continue_parsing {
+                            break Err(buffer_data.proccessed_data_len());
+                        }
+                        continue;
+                    };
+
+                    // save filename
+                    if self.file_name_index >= self.file_name_len {
+                        info.file_name_length = cmp::min(self.file_name_len, N);
+
+                        self.state = ParserState::RecvLocalFileExtraField;
+                    } else {
+                        let len = cmp::min(
+                            self.file_name_len - self.file_name_index,
+                            buffer_data.unproccessed_data_len(),
+                        );
+                        // names longer than `N` bytes are truncated
+                        let kept = cmp::min(self.file_name_index, N)..cmp::min(self.file_name_index + len, N);
+                        info.file_name_buffer[kept.clone()]
+                            .copy_from_slice(&buffer_data.peek_data(len)[..kept.len()]);
+                        self.file_name_index += len;
+
+                        // count processed data
+                        buffer_data.proccessed(len);
+                    }
+                }
+                ParserState::RecvLocalFileExtraField => {
+                    if self.extra_field_index >= self.extra_field_len {
+                        // the name state reports a missing header
+                        let Some(info) = self.localfile_info.as_mut() else {
+                            self.state = ParserState::RecvLocalFileName;
+                            continue;
+                        };
+                        // sizes beyond 4 GiB are in the extra field
+                        self.zip64_extra.resolve(&mut [&mut info.uncompressed_size, &mut info.compressed_size]);
+                        // usize::MAX on overflow, flagged below unless a data descriptor ends the data
+                        let fits = usize::try_from(info.compressed_size).is_ok();
+                        self.file_data_len = usize::try_from(info.compressed_size).unwrap_or(usize::MAX);
+                        self.stats.uncompressed_bytes = self.stats.uncompressed_bytes.saturating_add(info.uncompressed_size);
+                        if self.data_descriptor {
+                            // the data ends where the descriptor is found
+                            self.file_data_len = usize::MAX;
+                            // the CRC-32 is that of the plaintext
+                            let stored = info.compression_method == CompressMethod::Uncompress && !info.is_encrypted();
+                            self.descriptor_scanner.reset(self.zip64_extra.is_present(), stored);
+                        }
+
+                        #[cfg(feature = "inflate")]
+                        {
+                            self.inflating = info.compression_method == CompressMethod::Deflated
+                                && !info.is_encrypted();
+                            self.inflate_failed = false;
+                            self.inflated = 0;
+                            self.inflated_crc32 = 0;
+                            if self.inflate && self.inflating {
+                                self.inflater.reset();
+                            }
+                        }
+                        let data_end = (self.stream_offset + buffer_data.proccessed_data_len() as u64).checked_add(self.file_data_len as u64);
+                        let err = match data_end {
+                            _ if self.data_descriptor => None,
+                            Some(data_end) if fits => {
+                                // the rest of the stream would be taken as the entry data
+                                self.stream_len.filter(|&len| data_end > len).map(|_| ParsingError::EntryOutOfBounds(data_end))
+                            }
+                            _ => Some(ParsingError::Overflow),
+                        };
+                        if let Some(err) = err {
+                            continue_parsing = on_event(self.error_event(err, buffer_data.proccessed_data_len()), buffer_data.proccessed_data_len());
+                            self.state = ParserState::Finished;
+                            if !continue_parsing {
+                                break Err(buffer_data.proccessed_data_len());
+                            }
+                            continue;
+                        }
+
+                        let Some(info) = self.localfile_info.as_mut() else {
+                            self.state = ParserState::RecvLocalFileName;
+                            continue;
+                        };
+                        info.apply_unicode_path(ExtraFields::new(&self.extra_field));
+                        continue_parsing = on_event(ParserEvent::LocalFileHeader(self.localfile_index, info), buffer_data.proccessed_data_len());
+                        if continue_parsing && self.extra_field_len > 0 {
+                            continue_parsing = on_event(
+                                ParserEvent::LocalFileExtraField(self.localfile_index, ExtraFields::new(&self.extra_field)),
+                                buffer_data.proccessed_data_len(),
+                            );
+                        }
+
+                        self.state = ParserState::RecvLocalFileData;
+                    } else {
+                        let len = cmp::min(
+                            self.extra_field_len - self.extra_field_index,
+                            buffer_data.unproccessed_data_len(),
+                        );
+                        self.zip64_extra.feed(buffer_data.peek_data(len));
+                        self.keep_extra_field(buffer_data.peek_data(len));
+                        self.extra_field_index += len;
+
+                        // count processed data
+                        buffer_data.proccessed(len);
+                    }
+                }
+                // the end of the deflate stream is where the descriptor starts
+                ParserState::RecvLocalFileData if self.data_descriptor && !self.is_running_inflater() => {
+                    let input = buffer_data.peek_data(buffer_data.unproccessed_data_len());
+                    let scan = self.descriptor_scanner.scan(self.file_data_index as u64, self.crc32, input, self.crc_engine);
+                    if scan.held_data > 0 {
+                        // the data held back turned out to be file data
+                        let data = &self.descriptor_scanner.held[..scan.held_data];
+                        if !self.listing_only && !self.is_inflating() {
+                            continue_parsing = on_event(
+                                ParserEvent::LocalFileData {
+                                    file_index: self.localfile_index,
+                                    offset: self.file_data_index,
+                                    data,
+                                },
+                                buffer_data.proccessed_data_len(),
+                            );
+                        }
+                        self.crc32 = self.crc_engine.update(self.crc32, data);
+                        self.file_data_index += scan.held_data;
+                        self.stats.compressed_bytes += scan.held_data as u64;
+                        self.descriptor_scanner.advance(scan.held_data, &[]);
+                    } else if scan.input_data > 0 {
+                        let data = buffer_data.peek_data(scan.input_data);
+                        if !self.listing_only && !self.is_inflating() {
+                            continue_parsing = on_event(
+                                ParserEvent::LocalFileData {
+                                    file_index: self.localfile_index,
+                                    offset: self.file_data_index,
+                                    data,
+                                },
+                                buffer_data.proccessed_data_len(),
+                            );
+                        }
+                        self.crc32 = self.crc_engine.update(self.crc32, data);
+                        self.file_data_index += scan.input_data;
+                        self.stats.compressed_bytes += scan.input_data as u64;
+                        buffer_data.proccessed(scan.input_data);
+                    } else if let Some(descriptor) = scan.descriptor {
+                        buffer_data.proccessed(scan.consumed);
+                        self.data_descriptor = false;
+                        self.descriptor_scanner.reset(false, false);
+                        self.file_data_len = self.file_data_index;
+                        if let Some(info) = self.localfile_info.as_mut() {
+                            info.compressed_size = descriptor.compressed_size;
+                            info.uncompressed_size = descriptor.uncompressed_size;
+                        }
+                        self.stats.uncompressed_bytes = self.stats.uncompressed_bytes.saturating_add(descriptor.uncompressed_size);
+                        self.expected_crc32 = descriptor.crc32;
+                        continue_parsing = on_event(
+                            ParserEvent::LocalFileDataDescriptor(self.localfile_index, descriptor),
+                            buffer_data.proccessed_data_len(),
+                        );
+                    } else {
+                        // may be the start of the descriptor
+                        self.descriptor_scanner.advance(0, input);
+                        buffer_data.proccessed(scan.consumed);
+                    }
+                }
+                ParserState::RecvLocalFileData if self.listing_only => {
+                    if self.file_data_index >= self.file_data_len {
+                        continue_parsing = on_event(ParserEvent::LocalFileEnd(self.localfile_index), buffer_data.proccessed_data_len());
+
+                        self.localfile_index += 1;
+                        self.state = ParserState::RecvHeader(HeaderType::HeaderSignature, 4);
+                    } else {
+                        // skip the data
+                        let len = cmp::min(
+                            self.file_data_len - self.file_data_index,
+                            buffer_data.unproccessed_data_len(),
+                        );
+                        self.file_data_index += len;
+                        self.stats.compressed_bytes += len as u64;
+                        buffer_data.proccessed(len);
+                    }
+                }
+                #[cfg(feature = "inflate")]
+                ParserState::RecvLocalFileData if self.is_inflating() => {
+                    let mut out = [0u8; INFLATE_CHUNK_LEN];
+                    let remaining = self.file_data_len - self.file_data_index;
+                    let len = cmp::min(remaining, buffer_data.unproccessed_data_len());
+                    let res = if self.inflate_failed {
+                        Ok((0, 0))
+                    } else {
+                        self.inflater.inflate(buffer_data.peek_data(len), &mut out, len < remaining)
+                    };
+                    match res {
+                        Ok((consumed, produced)) if consumed > 0 || produced > 0 => {
+                            self.crc32 = self.crc_engine.update(self.crc32, buffer_data.peek_data(consumed));
+                            self.file_data_index += consumed;
+                            self.stats.compressed_bytes += consumed as u64;
+                            buffer_data.proccessed(consumed);
+
+                            // the sizes are only known after the data with a data descriptor
+                            let size = self.localfile_info.as_ref()
+                                .filter(|_| !self.data_descriptor)
+                                .map_or(u64::MAX, |info| info.uncompressed_size);
+                            let kept = cmp::min(produced as u64, size.saturating_sub(self.inflated as u64)) as usize;
+                            if kept > 0 {
+                                continue_parsing = on_event(
+                                    ParserEvent::LocalFileData {
+                                        file_index: self.localfile_index,
+                                        offset: self.inflated,
+                                        data: &out[..kept],
+                                    },
+                                    buffer_data.proccessed_data_len(),
+                                );
+                                self.inflated += kept;
+                                self.inflated_crc32 = self.crc_engine.update(self.inflated_crc32, &out[..kept]);
+                            }
+                            if kept < produced {
+                                // the rest of the data is dropped
+                                self.inflate_failed = true;
+                                let err = ParsingError::UncompressedSizeExceeded(size);
+                                continue_parsing = continue_parsing
+                                    && on_event(self.error_event(err, buffer_data.proccessed_data_len()), buffer_data.proccessed_data_len());
+                            }
+                        }
+                        res if !self.inflate_failed && !self.inflater.is_done() => {
+                            // broken or truncated deflate stream
+                            self.inflate_failed = true;
+                            let err = res.err().unwrap_or(ParsingError::DecompressionFailed);
+                            continue_parsing = on_event(self.error_event(err, buffer_data.proccessed_data_len()), buffer_data.proccessed_data_len());
+                        }
+                        _ if remaining > 0 => {
+                            // past the end of the deflate stream, or of a failed one
+                            self.crc32 = self.crc_engine.update(self.crc32, buffer_data.peek_data(len));
+                            self.file_data_index += len;
+                            self.stats.compressed_bytes += len as u64;
+                            buffer_data.proccessed(len);
+                        }
+                        _ => {
+                            // `LocalFileEnd` follows as for the other entries
+                            let size = self.localfile_info.as_ref().map_or(0, |info| info.uncompressed_size);
+                            let inflated = self.inflated as u64;
+                            let err = match () {
+                                _ if self.inflate_failed => None,
+                                _ if self.check_crc && self.inflated_crc32 != self.expected_crc32 => {
+                                    Some(ParsingError::CrcMismatch(self.expected_crc32, self.inflated_crc32))
+                                }
+                                _ if inflated > size => Some(ParsingError::UncompressedSizeExceeded(size)),
+                                _ if inflated < size => Some(ParsingError::UncompressedSizeShort(inflated)),
+                                _ => None,
+                            };
+                            if let Some(err) = err {
+                                // reported once, even if parsing stops here
+                                self.inflate_failed = true;
+                                continue_parsing = on_event(self.error_event(err, buffer_data.proccessed_data_len()), buffer_data.proccessed_data_len());
+                            } else {
+                                self.inflating = false;
+                                // a failed entry ends with its error
+                                if !self.inflate_failed {
+                                    continue_parsing = on_event(
+                                        ParserEvent::LocalFileInflated(self.localfile_index, self.inflated as u64),
+                                        buffer_data.proccessed_data_len(),
+                                    );
+                                }
+                            }
+                        }
+                    }
+                }
+                ParserState::RecvLocalFileData => {
+                    if self.file_data_index >= self.file_data_len {
+                        // the data of stored entries is the uncompressed data
+                        let stored = self.localfile_info.as_ref()
+                            .is_some_and(|info| info.compression_method == CompressMethod::Uncompress && !info.is_encrypted());
+                        if self.check_crc && stored && self.crc32 != self.expected_crc32 {
+                            let err = ParsingError::CrcMismatch(self.expected_crc32, self.crc32);
+                            // reported once, even if parsing stops here
+                            self.expected_crc32 = self.crc32;
+                            continue_parsing = on_event(self.error_event(err, buffer_data.proccessed_data_len()), buffer_data.proccessed_data_len());
+                        } else {
+                            continue_parsing = on_event(ParserEvent::LocalFileEnd(self.localfile_index), buffer_data.proccessed_data_len());
+
+                            self.localfile_index += 1;
+                            self.state = ParserState::RecvHeader(HeaderType::HeaderSignature, 4);
+                        }
+                    } else {
+                        // process
+                        let len = cmp::min(
+                            self.file_data_len - self.file_data_index,
+                            buffer_data.unproccessed_data_len(),
+                        );
+                        continue_parsing = on_event(
+                            ParserEvent::LocalFileData{
+                                file_index: self.localfile_index,
+                                offset: self.file_data_index,
+                                data: buffer_data.peek_data(len),
+                            },
+                            buffer_data.proccessed_data_len(),
+                        );
+                        self.crc32 = self.crc_engine.update(self.crc32, buffer_data.peek_data(len));
+                        self.file_data_index += len;
+                        self.stats.compressed_bytes += len as u64;
+
+                        // count processed data
+                        buffer_data.proccessed(len);
+                    }
+                }
+                ParserState::RecvCentralFileHeader => {
+                    if self.central_file_header_index >= self.central_file_header_len {
+                        if let Some(header) = CentralFileHeader::from_bytes(&self.central_header) {
+                            let name_len = cmp::min(header.file_name_length as usize, N);
+                            let sizes = header.sizes(&self.zip64_extra);
+                            let mut info = LocalFileInfo::default()
+                                .with_general_purpose_bit_flag(header.general_purpose_bit_flag)
+                                .with_compression_method(CompressMethod::from(header.compression_method))
+                                .with_compressed_size(sizes.compressed_size)
+                                .with_uncompressed_size(sizes.uncompressed_size);
+                            info.file_name_buffer[..name_len].copy_from_slice(&self.central_file_name[..name_len]);
+                            info.file_name_length = name_len;
+                            info.extra_field_length = header.extra_field_length as usize;
+                            info.apply_unicode_path(ExtraFields::new(&self.extra_field));
+                            let metadata = EntryMetadata::from_central_header(&header, &sizes, self.central_header_offset);
+                            continue_parsing = on_event(
+                                ParserEvent::CentralFileHeader(self.centralfile_index, &info, metadata),
+                                buffer_data.proccessed_data_len(),
+                            );
+                            if continue_parsing && header.extra_field_length > 0 {
+                                continue_parsing = on_event(
+                                    ParserEvent::CentralFileExtraField(self.centralfile_index, ExtraFields::new(&self.extra_field)),
+                                    buffer_data.proccessed_data_len(),
+                                );
+                            }
+                        }
+
+                        self.centralfile_index += 1;
+                        self.central_file_header_index = 0;
+                        self.central_file_header_len = 0;
+                        self.state = ParserState::RecvHeader(HeaderType::HeaderSignature, 4);
+                    } else {
+                        let len = cmp::min(
+                            self.central_file_header_len - self.central_file_header_index,
+                            buffer_data.unproccessed_data_len(),
+                        );
+
+                        // keep the part of the file name fitting in `N` bytes
+                        let name_end = cmp::min(
+                            CENTRAL_FILE_HEADER_LEN + N,
+                            CENTRAL_FILE_HEADER_LEN + le_u16(&self.central_header, 28) as usize,
+                        );
+                        let from = self.central_file_header_index;
+                        let to = cmp::min(from + len, name_end);
+                        if from < to {
+                            self.central_file_name[from - CENTRAL_FILE_HEADER_LEN..to - CENTRAL_FILE_HEADER_LEN]
+                                .copy_from_slice(buffer_data.peek_data(to - from));
+                        }
+
+                        // and the extended information of the extra field
+                        let extra_start = CENTRAL_FILE_HEADER_LEN + le_u16(&self.central_header, 28) as usize;
+                        let extra_end = extra_start + le_u16(&self.central_header, 30) as usize;
+                        let from = cmp::max(self.central_file_header_index, extra_start);
+                        let to = cmp::min(self.central_file_header_index + len, extra_end);
+                        if from < to {
+                            let data = buffer_data.peek_data(to - self.central_file_header_index);
+                            self.zip64_extra.feed(&data[from - self.central_file_header_index..]);
+                            self.keep_extra_field(&data[from - self.central_file_header_index..]);
+                        }
+
+                        self.central_file_header_index += len;
+                        buffer_data.proccessed(len);
+                    }
+                }
+                ParserState::Finished => break Ok(buffer_data.proccessed_data_len()),
+                ParserState::RecvZip64CentralDirEnd => {
+                    if self.central_dir_end_index >= self.central_dir_end_len {
+                        self.central_dir_end_index = 0;
+                        self.central_dir_end_len = 0;
+                        self.state = ParserState::RecvHeader(HeaderType::HeaderSignature, 4);
+                    } else {
+                        // skip the extensible data sector
+                        let len = cmp::min(
+                            self.central_dir_end_len - self.central_dir_end_index,
+                            buffer_data.unproccessed_data_len(),
+                        );
+                        self.central_dir_end_index += len;
+                        buffer_data.proccessed(len);
+                    }
+                }
+                ParserState::RecvCentralDirEnd => {
+                    let len = cmp::min(
+                        self.central_dir_end_len - self.central_dir_end_index,
+                        buffer_data.unproccessed_data_len(),
+                    );
+
+                    #[cfg(feature = "std")]
+                    self.zip_file_comment.extend(buffer_data.peek_data(len));
+
+                    self.central_dir_end_index += len;
+                    buffer_data.proccessed(len);
+
+                    if self.central_dir_end_index >= self.central_dir_end_len {
+                        let (archive_index, stats) = self.end_archive();
+                        continue_parsing = on_event(ParserEvent::ArchiveEnd(archive_index, stats), buffer_data.proccessed_data_len());
+                    }
+                }
+            }
+            if !continue_parsing {
+                break Err(buffer_data.proccessed_data_len());
+            }
+        };
+
+        match res {
+            Ok(n) | Err(n) => self.stream_offset += n as u64,
+        }
+        res
+    }
+}
+
+impl<const N: usize> Default for PassiveParser<N> {
+    fn default() -> Self {
+        Self {
+            state: ParserState::RecvHeader(HeaderType::HeaderSignature, 4),
+
+            #[cfg(feature = "std")]
+            zip_file_comment: Vec::new(),
+
+            buffer: Default::default(),
+
+            localfile_info: None,
+            localfile_index: 0,
+            centralfile_index: 0,
+
+            file_name_index: 0,
+            file_name_len: 0,
+
+            extra_field_index: 0,
+            extra_field_len: 0,
+            zip64_extra: Zip64ExtraField::new(),
+            extra_field: heapless::Vec::new(),
+
+            file_data_index: 0,
+            file_data_len: 0,
+
+            central_file_header_index: 0,
+            central_file_header_len: 0,
+
+            central_dir_end_index: 0,
+            central_dir_end_len: 0,
+
+            central_header: [0; CENTRAL_FILE_HEADER_LEN],
+            central_file_name: [0; N],
+            central_header_offset: 0,
+
+            stream_offset: 0,
+            crc32: 0,
+            expected_crc32: 0,
+            check_crc: cfg!(feature = "crc-check"),
+            crc_engine: &SoftwareCrc32,
+
+            multi_archive: false,
+            archive_index: 0,
+            stats: ArchiveStats::default(),
+
+            strict: false,
+            central_dir_started: false,
+            archive_ended: false,
+
+            listing_only: false,
+            stream_len: None,
+            last_error: None,
+
+            data_descriptor: false,
+            descriptor_scanner: DescriptorScanner::default(),
+
+            #[cfg(feature = "inflate")]
+            inflate: false,
+            #[cfg(feature = "inflate")]
+            inflating: false,
+            #[cfg(feature = "inflate")]
+            inflate_failed: false,
+            #[cfg(feature = "inflate")]
+            inflated: 0,
+            #[cfg(feature = "inflate")]
+            inflated_crc32: 0,
+            #[cfg(feature = "inflate")]
+            inflater: Inflater::new(),
+        }
+    }
+}
+
+/// Prelude of zip_parser
+pub mod prelude {
+    pub use crate::{
+        LocalFileOps,
+        Parser, ParsingError, ErrorContext, ParserEvent, OwnedEvent, Checkpoint, ArchiveStats,
+        SequentialParser, PassiveParser, HybridParser, PullParser, AutoParser,
+    };
+
+    pub use crate::{LocalFile, SeekingParser, OwnedSeekingParser};
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+
+    use crate::{CompressMethod, LocalFileHeader, LocalFileInfo, ParserEvent, ParsingError, PassiveParser};
+    use crate::{Seek, SeekFrom};
+
+    /// In-memory stream, the blanket `std::io` impls are only there with the `std` feature
+    struct MemStream {
+        data: Vec<u8>,
+        position: usize,
+        /// `stream_len` is unknown for that many reads
+        len_unknown_reads: usize,
+    }
+
+    impl MemStream {
+        fn new(data: Vec<u8>) -> Self {
+            Self { data, position: 0, len_unknown_reads: 0 }
+        }
+
+        /// test.zip with its zip file comment stripped
+        fn test_zip_without_comment() -> Self {
+            let mut data = std::fs::read("test.zip").unwrap();
+            let comment_len = u16::from_le_bytes([data[data.len() - 23], data[data.len() - 22]]) as usize;
+            data.truncate(data.len() - comment_len);
+            let len = data.len();
+            data[len - 2..].copy_from_slice(&[0, 0]);
+            Self::new(data)
+        }
+    }
+
+    impl crate::Read for MemStream {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+            // past the end once seeked there, as `std::io::Cursor`
+            let len = buf.len().min(self.data.len().saturating_sub(self.position));
+            if len > 0 {
+                buf[..len].copy_from_slice(&self.data[self.position..self.position + len]);
+            }
+            self.position += len;
+            self.len_unknown_reads = self.len_unknown_reads.saturating_sub(1);
+            Ok(len)
+        }
+    }
+
+    impl Seek for MemStream {
+        fn seek(&mut self, pos: SeekFrom) -> Result<u64, ParsingError> {
+            let position = match pos {
+                SeekFrom::Start(n) => n as i64,
+                SeekFrom::End(n) => self.data.len() as i64 + n,
+                SeekFrom::Current(n) => self.position as i64 + n,
+            };
+            if position < 0 {
+                return Err(ParsingError::InvalidStream);
+            }
+            self.position = position as usize;
+            Ok(self.position as u64)
+        }
+
+        fn stream_len(&mut self) -> Option<u64> {
+            if self.len_unknown_reads > 0 {
+                return None;
+            }
+            Some(self.data.len() as u64)
+        }
+    }
+
+    #[test]
+    fn parse_file_list() {
+        let data = std::fs::read("test.zip").unwrap();
+        let mut names = Vec::new();
+        let mut parser = PassiveParser::<128>::new();
+        parser.feed_data(&data, |evt| {
+            if let ParserEvent::LocalFileHeader(_, file) = evt {
+                names.push(file.file_name().unwrap().to_string());
+            }
+            true
+        });
+        assert_eq!(names, ["README.md", "Cargo.toml", "src/lib.rs"]);
+        #[cfg(feature = "std")]
+        assert_eq!(parser.file_comment(), Ok("Zip file parsing test"));
+    }
+
+    #[test]
+    fn local_file_info_eq_ignores_unused_name_buffer() {
+        let mut a = LocalFileInfo::<8>::default().with_compression_method(CompressMethod::Deflated);
+        let mut b = a.clone();
+        a.file_name_buffer[..3].copy_from_slice(b"a.c");
+        a.file_name_length = 3;
+        b.file_name_buffer.copy_from_slice(b"a.cstale");
+        b.file_name_length = 3;
+        assert_eq!(a, b);
+
+        let set: HashSet<_> = [a, b].into_iter().collect();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn entries_share_the_stream() {
+        use crate::{LocalFileOps, SeekingParser};
+
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let mut files: Vec<_> = SeekingParser::<_, 128>::new(&stream).collect();
+        assert_eq!(files.len(), 3);
+
+        // reading an entry doesn't disturb the others
+        let (readme, cargo) = files.split_at_mut(1);
+        let (mut a, mut b) = ([0u8; 8], [0u8; 8]);
+        assert_eq!(readme[0].read(&mut a), Ok(8));
+        assert_eq!(cargo[0].read(&mut b), Ok(8));
+        assert_eq!(readme[0].read(&mut a[4..]), Ok(4));
+        assert_eq!(a[..4], stream.borrow().data[67..71]);
+        assert_eq!(a[4..], stream.borrow().data[75..79]);
+
+        // the stream borrowed elsewhere is reported instead of read
+        let _held = stream.borrow_mut();
+        assert_eq!(files[0].read(&mut a), Err(ParsingError::InvalidStream));
+    }
+
+    #[test]
+    fn parsers_share_the_parser_trait() {
+        use crate::{LocalFileOps, Parser, PullParser, SeekingParser};
+
+        /// Names and data lengths of the entries, reading the data `chunk` bytes at a time
+        fn entries<S: crate::Read, P: Parser<S>>(mut parser: P, chunk: usize) -> Result<Vec<(String, usize)>, ParsingError> {
+            let mut entries = Vec::new();
+            while let Some(mut entry) = parser.next_entry()? {
+                let mut buf = vec![0u8; chunk];
+                let mut len = 0;
+                loop {
+                    match entry.read(&mut buf)? {
+                        0 => break,
+                        n => len += n,
+                    }
+                }
+                entries.push((entry.file_name().unwrap().to_string(), len));
+            }
+            Ok(entries)
+        }
+
+        let expected = [("README.md".to_string(), 359), ("Cargo.toml".to_string(), 534), ("src/lib.rs".to_string(), 40643)];
+        let data = std::fs::read("test.zip").unwrap();
+        let stream = RefCell::new(MemStream::new(data.clone()));
+        assert_eq!(SeekingParser::<_, 128>::new(&stream).entry_count(), Some(3));
+        assert_eq!(entries(SeekingParser::<_, 128>::new(&stream), 100), Ok(expected.to_vec()));
+        for chunk in [1, 100, 4096] {
+            let mut stream = MemStream::new(data.clone());
+            assert_eq!(entries(PullParser::<_, 128, 64>::new(&mut stream), chunk), Ok(expected.to_vec()));
+        }
+
+        // the data held back before a data descriptor may not fit the buffer of a read
+        let mut stream = MemStream::new(std::fs::read("test_descriptor.zip").unwrap());
+        let lens = entries(PullParser::<_, 128, 64>::new(&mut stream), 5).unwrap();
+        assert_eq!(lens.iter().map(|(_, len)| *len).collect::<Vec<_>>(), [218, 76, 5132]);
+
+        // inflated chunks are longer than the buffer of a read
+        #[cfg(feature = "inflate")]
+        for chunk in [1, 7, 100] {
+            let mut stream = MemStream::new(std::fs::read("test_deflated.zip").unwrap());
+            let parser = PullParser::<_, 128, 64>::with_parser(&mut stream, PassiveParser::new().with_inflate(true));
+            let expected = [("README.md".to_string(), 359), ("lorem.txt".to_string(), 108890)];
+            assert_eq!(entries(parser, chunk), Ok(expected.to_vec()));
+        }
+
+        let mut stream = MemStream::new(data.clone());
+        let mut parser = PullParser::<_, 128>::new(&mut stream);
+        let mut file = parser.find_entry("Cargo.toml").unwrap().unwrap();
+        let mut buf = [0u8; 9];
+        assert_eq!(file.read_exact(&mut buf), Ok(9));
+        assert_eq!(&buf, b"[package]");
+        assert!(parser.find_entry("README.md").unwrap().is_none());
+
+        // a cut stream isn't taken for the end of the archive
+        let mut stream = MemStream::new(data[..500].to_vec());
+        assert_eq!(entries(PullParser::<_, 128>::new(&mut stream), 100), Err(ParsingError::StreamEnding));
+    }
+
+    #[test]
+    fn entries_can_be_listed_again() {
+        use crate::{LocalFileOps, SeekingParser};
+
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let mut parser = SeekingParser::<_, 128>::new(&stream);
+        let names = parser.entries().map(|file| file.file_name().unwrap().to_string()).collect::<Vec<_>>();
+        assert_eq!(names, ["README.md", "Cargo.toml", "src/lib.rs"]);
+        assert_eq!(parser.entries().count(), 3);
+
+        // the parser itself and the other iterators are left where they were
+        let mut first = parser.next().unwrap();
+        let mut entries = parser.entries();
+        let mut again = entries.next().unwrap();
+        assert_eq!(parser.next().unwrap().file_name(), Ok("Cargo.toml"));
+        assert_eq!(entries.next().unwrap().file_name(), Ok("Cargo.toml"));
+        let (mut a, mut b) = ([0u8; 11], [0u8; 11]);
+        assert_eq!(first.read_exact(&mut a), Ok(11));
+        assert_eq!(again.read_exact(&mut b), Ok(11));
+        assert_eq!(a, b);
+        assert_eq!(&a, b"# zip_parse");
+    }
+
+    #[test]
+    fn entry_count_is_the_iterator_length() {
+        use crate::SeekingParser;
+
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let mut parser = SeekingParser::<_, 128>::new(&stream);
+        assert_eq!(parser.len(), 3);
+        parser.next().unwrap();
+        assert_eq!(parser.size_hint(), (2, Some(2)));
+        assert_eq!(parser.entries().len(), 3);
+        parser.by_ref().for_each(drop);
+        assert_eq!(parser.len(), 0);
+
+        // no central directory
+        let stream = RefCell::new(MemStream::new(std::fs::read("test.zip").unwrap()[..500].to_vec()));
+        let parser = SeekingParser::<_, 128>::new(&stream);
+        assert_eq!(parser.len(), 0);
+        assert_eq!(parser.count(), 0);
+    }
+
+    #[test]
+    fn entry_count_is_bounded_by_the_central_directory_size() {
+        use crate::{SeekingParser, CENTRAL_DIR_END_LEN};
+
+        let mut data = MemStream::test_zip_without_comment().data;
+        let end = data.len() - CENTRAL_DIR_END_LEN;
+        data[end + 8..end + 12].copy_from_slice(&[0xff; 4]);
+        let stream = RefCell::new(MemStream::new(data));
+        let mut parser = SeekingParser::<_, 128>::new(&stream);
+        assert!((3..=stream.borrow().data.len() / 46).contains(&parser.len()));
+        assert_eq!(parser.by_ref().count(), 3);
+        assert!(parser.try_next().is_err());
+    }
+
+    #[test]
+    fn listing_stops_at_the_entry_count() {
+        use crate::{SeekingParser, CENTRAL_DIR_END_LEN};
+
+        let data = MemStream::test_zip_without_comment().data;
+        let end = data.len() - CENTRAL_DIR_END_LEN;
+        for (count, listed, error) in [
+            (2, 2, None),
+            (3, 3, None),
+            (4, 3, Some(ParsingError::CentralEntryCountMismatch(3, 4))),
+        ] {
+            let mut data = data.clone();
+            data[end + 8..end + 10].copy_from_slice(&(count as u16).to_le_bytes());
+            data[end + 10..end + 12].copy_from_slice(&(count as u16).to_le_bytes());
+            let stream = RefCell::new(MemStream::new(data));
+            let mut parser = SeekingParser::<_, 128>::new(&stream);
+            assert_eq!(parser.by_ref().count(), listed);
+            assert_eq!(parser.try_next().err(), error);
+        }
+    }
+
+    #[test]
+    fn entries_are_used_within_a_closure() {
+        use crate::{AutoParser, LocalFileOps, Parser, ParsingError, SeekingParser};
+
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let mut parser = SeekingParser::<_, 128>::new(&stream);
+        let mut sizes = [0u64; 3];
+        let mut i = 0;
+        parser
+            .for_each_entry(|entry| {
+                sizes[i] = entry.file_size();
+                i += 1;
+                Ok::<_, ParsingError>(())
+            })
+            .unwrap();
+        assert_eq!(sizes, [359, 534, 40643]);
+
+        let mut stream = RefCell::new(MemStream::test_zip_without_comment());
+        let mut parser = AutoParser::<_, 128>::new(&mut stream);
+        let mut buf = [0u8; 11];
+        let read = parser.with_entry("README.md", |entry| entry.read_exact(&mut buf)).unwrap();
+        assert_eq!(read, Some(Ok(11)));
+        assert_eq!(&buf, b"# zip_parse");
+        assert_eq!(parser.with_entry("README.md", |entry| entry.file_size()), Ok(None));
+
+        // an error of the closure stops the listing
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let mut parser = SeekingParser::<_, 128>::new(&stream);
+        let result = parser.for_each_entry(|entry| match entry.file_name() {
+            Ok("Cargo.toml") => Err(ParsingError::StreamEnding),
+            _ => Ok(()),
+        });
+        assert_eq!(result, Err(ParsingError::StreamEnding));
+        assert_eq!(parser.next().unwrap().file_name(), Ok("src/lib.rs"));
+    }
+
+    #[test]
+    fn entries_are_read_through_positional_reads() {
+        use crate::{LocalFileOps, ReadAt, ReadAtCursor, SeekingParser};
+
+        let data = MemStream::test_zip_without_comment().data;
+        let mut buf = [0u8; 4];
+        assert_eq!(data.as_slice().read_at(data.len() as u64 - 2, &mut buf), Ok(2));
+        assert_eq!(data.as_slice().read_at(data.len() as u64 + 2, &mut buf), Ok(0));
+
+        // each parser has its own cursor over the same data
+        let first = RefCell::new(ReadAtCursor::new(data.as_slice()));
+        let second = RefCell::new(ReadAtCursor::new(data.as_slice()));
+        let mut readme = SeekingParser::<_, 128>::new(&first).next().unwrap();
+        let mut lib = SeekingParser::<_, 128>::new(&second).entry(2).unwrap().unwrap();
+        let (mut head, mut lib_head) = ([0u8; 11], [0u8; 11]);
+        assert_eq!(readme.read_exact(&mut head[..5]), Ok(5));
+        assert_eq!(lib.read_exact(&mut lib_head), Ok(11));
+        assert_eq!(readme.read_exact(&mut head[5..]), Ok(6));
+        assert_eq!(&head, b"# zip_parse");
+        assert_eq!(lib.file_name(), Ok("src/lib.rs"));
+
+        std::thread::scope(|scope| {
+            let data = data.as_slice();
+            let sizes = [(0, 359), (1, 534), (2, 40643)].map(|(index, size)| {
+                scope.spawn(move || {
+                    let parser = SeekingParser::<_, 128>::take(ReadAtCursor::new(data));
+                    let size_read = parser.parser().entry(index).unwrap().unwrap().file_size();
+                    (size_read, size)
+                })
+            });
+            for handle in sizes {
+                let (read, expected) = handle.join().unwrap();
+                assert_eq!(read, expected);
+            }
+        });
+    }
+
+    #[test]
+    fn parser_can_own_the_stream() {
+        use crate::{LocalFileOps, OwnedSeekingParser, Parser, SeekingParser};
+
+        fn open(data: Vec<u8>) -> OwnedSeekingParser<MemStream, 128> {
+            SeekingParser::<_, 128>::take(MemStream::new(data))
+        }
+
+        let mut parser = open(MemStream::test_zip_without_comment().data);
+        assert_eq!(parser.entry_count(), Some(3));
+        let mut file = parser.next_entry().unwrap().unwrap();
+        let mut buf = [0u8; 11];
+        assert_eq!(file.read_exact(&mut buf), Ok(11));
+        assert_eq!(&buf, b"# zip_parse");
+        // the borrowing parser goes on from there, without moving this one
+        assert_eq!(parser.parser().next().unwrap().file_name(), Ok("Cargo.toml"));
+        assert_eq!(parser.parser().entry(2).unwrap().unwrap().file_name(), Ok("src/lib.rs"));
+        assert_eq!(parser.find_entry("src/lib.rs").unwrap().unwrap().file_size(), 40643);
+        assert!(parser.next_entry().unwrap().is_none());
+        assert!(parser.last_error().is_none());
+        assert_eq!(parser.into_inner().data, MemStream::test_zip_without_comment().data);
+
+        let mut parser = open(MemStream::test_zip_without_comment().data[..500].to_vec());
+        assert_eq!(parser.entry_count(), None);
+        assert!(parser.next_entry().unwrap().is_none());
+    }
+
+    #[test]
+    fn entries_are_opened_by_index() {
+        use crate::{LocalFileOps, SeekingParser};
+
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let mut parser = SeekingParser::<_, 128>::new(&stream);
+        assert_eq!(parser.entry(2).unwrap().unwrap().file_name(), Ok("src/lib.rs"));
+        let mut file = parser.entry(0).unwrap().unwrap();
+        assert_eq!(file.file_name(), Ok("README.md"));
+        assert!(parser.entry(3).unwrap().is_none());
+        // the iteration isn't moved
+        assert_eq!(parser.next().unwrap().file_name(), Ok("README.md"));
+        assert_eq!(parser.entry(1).unwrap().unwrap().file_name(), Ok("Cargo.toml"));
+        assert_eq!(parser.next().unwrap().file_name(), Ok("Cargo.toml"));
+        let mut buf = [0u8; 11];
+        assert_eq!(file.read_exact(&mut buf), Ok(11));
+        assert_eq!(&buf, b"# zip_parse");
+
+        // a corrupted record on the way
+        let mut stream = MemStream::test_zip_without_comment();
+        let central_directory_offset = stream.data.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+        stream.data[central_directory_offset] = 0;
+        let stream = RefCell::new(stream);
+        let mut parser = SeekingParser::<_, 128>::new(&stream);
+        assert_eq!(parser.entry(1).err(), Some(ParsingError::InvalidCentralFileHeader));
+    }
+
+    #[test]
+    fn central_directory_is_read_once() {
+        use crate::{CentralDirectory, LocalFileOps, SeekingParser};
+
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let mut parser = SeekingParser::<_, 128>::new(&stream);
+        parser.next().unwrap();
+        let directory = CentralDirectory::<128>::read(&parser).unwrap();
+        let names = directory.entries().iter().map(|entry| entry.info.file_name().unwrap()).collect::<Vec<_>>();
+        assert_eq!(names, ["README.md", "Cargo.toml", "src/lib.rs"]);
+        assert_eq!(directory.entries()[2].metadata.uncompressed_size, 40643);
+
+        // the central directory isn't read anymore
+        stream.borrow_mut().data[directory.entries()[0].metadata.central_header_offset as usize] = 0;
+        let readme = directory.get("README.md").unwrap();
+        for _ in 0..2 {
+            let mut file = parser.open(readme).unwrap();
+            assert_eq!(file.info.file_data_offset, 67);
+            let mut buf = [0u8; 11];
+            assert_eq!(file.read_exact(&mut buf), Ok(11));
+            assert_eq!(&buf, b"# zip_parse");
+        }
+        assert_eq!(parser.open(&directory.entries()[1]).unwrap().file_size(), 534);
+        #[cfg(feature = "std")]
+        {
+            let directory = directory.clone().with_name_index();
+            assert_eq!(directory.get("src/lib.rs"), directory.entries().get(2));
+            assert!(directory.get("src").is_none());
+        }
+        assert_eq!(CentralDirectory::<128>::read(&parser), Err(ParsingError::InvalidCentralFileHeader));
+
+        #[cfg(not(feature = "std"))]
+        {
+            let stream = RefCell::new(MemStream::test_zip_without_comment());
+            let parser = SeekingParser::<_, 128>::new(&stream);
+            assert_eq!(CentralDirectory::<128, 2>::read(&parser), Err(ParsingError::BufferTooSmall));
+            assert_eq!(CentralDirectory::<128, 3>::read(&parser).unwrap().len(), 3);
+        }
+    }
+
+    #[test]
+    fn prepended_data_is_skipped() {
+        use crate::{HybridParser, LocalFileOps, SeekingParser};
+
+        for (archive, first) in [("test.zip", "README.md"), ("test_zip64.zip", "hello.txt")] {
+            let archive = std::fs::read(archive).unwrap();
+            let mut data = b"#!/bin/sh\nexit 0\n".repeat(40);
+            let prepended_len = data.len() as u64;
+            data.extend_from_slice(&archive);
+
+            let stream = RefCell::new(MemStream::new(archive.clone()));
+            let expected = SeekingParser::<_, 128>::new(&stream).map(|file| file.metadata.local_header_offset).collect::<Vec<_>>();
+            let stream = RefCell::new(MemStream::new(data.clone()));
+            let mut parser = SeekingParser::<_, 128>::new(&stream);
+            let mut file = parser.next().unwrap();
+            assert_eq!(file.file_name(), Ok(first));
+            assert_eq!(file.metadata.local_header_offset, prepended_len);
+            let mut buf = [0u8; 4];
+            assert_eq!(file.read_exact(&mut buf), Ok(4));
+            assert_eq!(&buf, &archive[file.info.file_data_offset as usize - prepended_len as usize..][..4]);
+            let offsets = parser.entries().map(|file| file.metadata.local_header_offset - prepended_len).collect::<Vec<_>>();
+            assert_eq!(offsets, expected);
+
+            let mut stream = MemStream::new(data);
+            let mut parser = HybridParser::<_, 128>::new(&mut stream);
+            let mut headers = 0;
+            parser.run(&mut [0u8; 512], |evt| {
+                headers += matches!(evt, ParserEvent::LocalFileHeader(..)) as usize;
+                true
+            }).unwrap();
+            assert!(parser.is_seeking());
+            assert_eq!(headers, expected.len());
+        }
+    }
+
+    #[test]
+    fn regions_around_the_entries_are_located() {
+        use crate::{ArchiveRegions, SeekingParser, CENTRAL_DIR_END_LEN};
+
+        let archive = MemStream::test_zip_without_comment().data;
+        let stream = RefCell::new(MemStream::new(archive.clone()));
+        let parser = SeekingParser::<_, 128>::new(&stream);
+        assert!(parser.regions().unwrap().is_empty());
+
+        // a stub before the archive, a block before the central directory, which the end of
+        // central directory record accounts for
+        let central_directory_offset = archive.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+        let mut data = b"stub".to_vec();
+        data.extend_from_slice(&archive[..central_directory_offset]);
+        data.extend_from_slice(b"signing block");
+        data.extend_from_slice(&archive[central_directory_offset..]);
+        let end = data.len() - CENTRAL_DIR_END_LEN;
+        data[end + 16..end + 20].copy_from_slice(&(central_directory_offset as u32 + 13).to_le_bytes());
+
+        let stream = RefCell::new(MemStream::new(data));
+        let parser = SeekingParser::<_, 128>::new(&stream);
+        let regions = parser.regions().unwrap();
+        let block_offset = 4 + central_directory_offset as u64;
+        assert_eq!(regions, ArchiveRegions { prefix: 0..4, before_central_directory: block_offset..block_offset + 13 });
+        assert_eq!(parser.entries().count(), 3);
+
+        let mut reader = parser.region_reader(regions.before_central_directory);
+        let mut buf = [0u8; 32];
+        let mut block = Vec::new();
+        loop {
+            match crate::Read::read(&mut reader, &mut buf).unwrap() {
+                0 => break,
+                n => block.extend_from_slice(&buf[..n]),
+            }
+        }
+        assert_eq!(block, b"signing block");
+        assert_eq!(reader.remaining(), 0);
+
+        let stream = RefCell::new(MemStream::new(archive[..500].to_vec()));
+        assert_eq!(SeekingParser::<_, 128>::new(&stream).regions(), Err(ParsingError::InvalidCentralDirEnd));
+    }
+
+    #[test]
+    fn sequential_parser_ends_with_the_stream() {
+        use crate::{LocalFileOps, Parser, SequentialParser};
+
+        let data = std::fs::read("test.zip").unwrap();
+        let mut stream = MemStream::new(data.clone());
+        let mut parser = SequentialParser::<_, 128>::new(&mut stream);
+        let mut names = Vec::new();
+        while let Some(mut file) = parser.next_entry().unwrap() {
+            names.push(file.file_name().unwrap().to_string());
+            // the data left unread is skipped
+            if file.file_name() == Ok("README.md") {
+                let mut buf = [0u8; 11];
+                assert_eq!(file.read_exact(&mut buf), Ok(11));
+                assert_eq!(&buf, b"# zip_parse");
+                assert_eq!(file.info().file_data_offset, 67);
+            }
+        }
+        assert_eq!(names, ["README.md", "Cargo.toml", "src/lib.rs"]);
+        assert!(parser.next_entry().unwrap().is_none());
+        assert!(parser.last_error().is_none());
+
+        // the local files alone, the stream ending after the last one
+        let mut stream = MemStream::new(data[..data.len() - 200].to_vec());
+        let mut parser = SequentialParser::<_, 128>::new(&mut stream);
+        let mut file = parser.find_entry("src/lib.rs").unwrap().unwrap();
+        let mut buf = vec![0u8; 40643];
+        assert_eq!(file.read_exact(&mut buf), Ok(40643));
+        assert_eq!(file.read(&mut buf), Ok(0));
+
+        // a cut stream isn't taken for the end of the archive
+        let mut stream = MemStream::new(data[..500].to_vec());
+        let mut parser = SequentialParser::<_, 128>::new(&mut stream);
+        assert!(parser.find_entry("Cargo.toml").unwrap().is_some());
+        assert!(matches!(parser.next_entry(), Err(ParsingError::StreamEnding)));
+        assert_eq!(parser.last_error().unwrap().file_index, Some(1));
+        assert!(parser.next_entry().unwrap().is_none());
+
+        let mut stream = MemStream::new(std::fs::read("test_descriptor.zip").unwrap());
+        let mut parser = SequentialParser::<_, 128>::new(&mut stream);
+        assert!(matches!(parser.next_entry(), Err(ParsingError::UnknownDataLength(0))));
+    }
+
+    #[test]
+    fn auto_parser_picks_the_central_directory_when_it_can() {
+        use crate::{AutoParser, LocalFileOps, Parser};
+
+        fn names(parser: &mut AutoParser<'_, MemStream, 128>) -> Vec<String> {
+            let mut names = Vec::new();
+            while let Some(file) = parser.next_entry().unwrap() {
+                names.push(file.file_name().unwrap().to_string());
+            }
+            names
+        }
+
+        let data = std::fs::read("test.zip").unwrap();
+        let mut stream = RefCell::new(MemStream::new(data.clone()));
+        let mut parser = AutoParser::<_, 128>::new(&mut stream);
+        assert!(parser.is_seeking());
+        assert_eq!(parser.entry_count(), Some(3));
+        assert_eq!(names(&mut parser), ["README.md", "Cargo.toml", "src/lib.rs"]);
+
+        // a stream without a length is read in order
+        let mut stream = MemStream::new(data.clone());
+        stream.len_unknown_reads = usize::MAX;
+        let mut stream = RefCell::new(stream);
+        let mut parser = AutoParser::<_, 128>::new(&mut stream);
+        assert!(!parser.is_seeking());
+        assert_eq!(parser.entry_count(), None);
+        assert_eq!(names(&mut parser), ["README.md", "Cargo.toml", "src/lib.rs"]);
+
+        // so is an archive whose central directory was cut, from where the probe found it
+        let mut stream = RefCell::new(MemStream::new(data[..1000].to_vec()));
+        let mut parser = AutoParser::<_, 128>::new(&mut stream);
+        assert!(!parser.is_seeking());
+        let mut file = parser.find_entry("README.md").unwrap().unwrap();
+        let mut buf = [0u8; 359];
+        assert_eq!(file.read_exact(&mut buf), Ok(359));
+        assert!(buf.starts_with(b"# zip_parser"));
+    }
+
+    #[test]
+    fn seeking_parser_attaches_central_metadata() {
+        use crate::SeekingParser;
+
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let parser = SeekingParser::<_, 128>::new(&stream);
+        assert_eq!(parser.check_disk_numbers(), Ok(()));
+        let files: Vec<_> = parser
+            .map(|file| (file.info.file_name().unwrap().to_string(), file.metadata))
+            .collect();
+        assert_eq!(files.len(), 3);
+
+        let (name, metadata) = &files[1];
+        assert_eq!(name, "Cargo.toml");
+        assert_eq!(metadata.crc32, 0x03716d34);
+        assert_eq!(metadata.uncompressed_size, 534);
+        assert_eq!(metadata.version_made_by >> 8, 3);
+        assert_eq!(metadata.external_file_attributes >> 16, 0o100644);
+        let modified = metadata.last_modified;
+        assert_eq!((modified.year(), modified.month(), modified.day()), (2022, 10, 25));
+    }
+
+    #[test]
+    fn spliced_disk_numbers_are_detected() {
+        use crate::SeekingParser;
+
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        // total_entries_this_disk of the end of central directory record
+        let len = stream.borrow().data.len();
+        stream.borrow_mut().data[len - 14] = 2;
+        let parser = SeekingParser::<_, 128>::new(&stream);
+        assert_eq!(parser.check_disk_numbers(), Err(ParsingError::DiskNumberMismatch));
+    }
+
+    #[test]
+    fn read_exact_stops_at_the_end() {
+        use crate::Read;
+
+        let mut stream = MemStream::new(b"0123456789".to_vec());
+        let mut buf = [0u8; 6];
+        assert_eq!(stream.read_exact(&mut buf), Ok(6));
+        assert_eq!(&buf, b"012345");
+        assert_eq!(stream.read_exact(&mut buf), Err(ParsingError::StreamEnding));
+        assert_eq!(&buf[..4], b"6789");
+        assert_eq!(stream.read_exact(&mut []), Ok(0));
+    }
+
+    #[test]
+    #[cfg(feature = "sha2")]
+    fn sha256_reader_hashes_read_data() {
+        use crate::{LocalFileOps, SeekingParser, Sha256Reader};
+
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let file = SeekingParser::<_, 128>::new(&stream).next().unwrap();
+        let mut reader = Sha256Reader::new(file);
+        let mut buf = vec![0; reader.file_size() as usize];
+        reader.read_exact(&mut buf).unwrap();
+        let hex: String = reader.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex, "f37924b8eb9ae2b946c1f4fbfd4c704f99d9c91d4be1d102c1234e21343fc5d4");
+    }
+
+    #[test]
+    fn hashing_reader_accepts_custom_hasher() {
+        use crate::{DataHasher, HashingReader, LocalFileOps, SeekingParser};
+
+        #[derive(Default)]
+        struct ByteCount(usize);
+
+        impl DataHasher for ByteCount {
+            type Output = usize;
+
+            fn update(&mut self, data: &[u8]) {
+                self.0 += data.len();
+            }
+
+            fn finalize(self) -> usize {
+                self.0
+            }
+        }
+
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let file = SeekingParser::<_, 128>::new(&stream).nth(1).unwrap();
+        let mut reader = HashingReader::<_, ByteCount>::new(file);
+        let mut buf = [0; 100];
+        reader.read(&mut buf).unwrap();
+        reader.read_exact(&mut buf[..10]).unwrap();
+        assert_eq!(reader.finalize(), 110);
+    }
+
+    #[test]
+    fn extract_with_buffer_uses_only_the_scratch_buffer() {
+        use crate::{extract_with_buffer, SeekingParser};
+
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let mut file = SeekingParser::<_, 128>::new(&stream).next().unwrap();
+        let mut scratch = [0; 64];
+        let mut data = Vec::new();
+        let extracted = extract_with_buffer(&mut file, &mut scratch, |chunk| {
+            assert!(chunk.len() <= 64);
+            data.extend_from_slice(chunk);
+            Ok::<_, ParsingError>(())
+        });
+        assert_eq!(extracted, Ok(359));
+        assert!(data.starts_with(b"# zip_parser"));
+    }
+
+    #[test]
+    fn stored_reads_enforce_uncompressed_size() {
+        use crate::{LocalFileOps, SeekingParser};
+
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let mut files = SeekingParser::<_, 128>::new(&stream);
+        let mut buf = vec![0; 1024];
+
+        let mut file = files.next().unwrap();
+        assert_eq!(file.read(&mut buf), Ok(359));
+        assert_eq!(file.read(&mut buf), Ok(0));
+
+        let mut file = files.next().unwrap().with_uncompressed_size(500);
+        assert_eq!(file.read(&mut buf), Ok(500));
+        assert_eq!(file.read(&mut buf), Err(ParsingError::UncompressedSizeExceeded(500)));
+
+        let mut file = files.next().unwrap().with_compressed_size(100);
+        assert_eq!(file.read(&mut buf), Ok(100));
+        assert_eq!(file.read(&mut buf), Err(ParsingError::UncompressedSizeShort(100)));
+    }
+
+    #[test]
+    fn crc_is_checked_at_the_end_of_the_entry() {
+        use crate::{LocalFileOps, SeekingParser};
+
+        fn read_to_end(file: &mut impl LocalFileOps) -> Result<usize, ParsingError> {
+            let mut buf = [0u8; 1024];
+            let mut len = 0;
+            loop {
+                match file.read(&mut buf)? {
+                    0 => return Ok(len),
+                    n => len += n,
+                }
+            }
+        }
+
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let mut files = SeekingParser::<_, 128>::new(&stream);
+
+        let mut file = files.next().unwrap().with_crc_check(true);
+        assert_eq!(read_to_end(&mut file), Ok(359));
+
+        let mut file = files.next().unwrap().with_crc_check(true);
+        let crc32 = file.metadata.crc32;
+        file.metadata.crc32 ^= 1;
+        assert_eq!(read_to_end(&mut file), Err(ParsingError::CrcMismatch(crc32 ^ 1, crc32)));
+
+        // the same error through the `Read` trait of the crate, with or without `std`
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let mut file = SeekingParser::<_, 128>::new(&stream).nth(1).unwrap().with_crc_check(true);
+        file.metadata.crc32 ^= 1;
+        let mut buf = [0u8; 1024];
+        let result = loop {
+            match crate::Read::read(&mut file, &mut buf) {
+                Ok(0) => break Ok(()),
+                Ok(_) => {}
+                Err(err) => break Err(err),
+            }
+        };
+        assert_eq!(result, Err(ParsingError::CrcMismatch(crc32 ^ 1, crc32)));
+
+        let mut file = files.next().unwrap().with_crc_check(false);
+        file.metadata.crc32 = 0;
+        assert!(read_to_end(&mut file).is_ok());
+
+        #[cfg(feature = "inflate")]
+        {
+            let stream = RefCell::new(MemStream::new(std::fs::read("test_deflated.zip").unwrap()));
+            let mut file = SeekingParser::<_, 128>::new(&stream).nth(1).unwrap().with_crc_check(true);
+            // the inflater is allocated on the first read with `std`
+            #[cfg(feature = "std")]
+            assert!(core::mem::size_of_val(&file) < core::mem::size_of::<crate::Inflater>());
+            file.metadata.crc32 = 0;
+            assert_eq!(read_to_end(&mut file), Err(ParsingError::CrcMismatch(0, 0xaed34b37)));
+        }
+    }
+
+    #[test]
+    fn passive_parser_reports_crc_mismatches() {
+        fn parse(data: &[u8], parser: PassiveParser<128>) -> Vec<(i32, Option<ParsingError>)> {
+            let mut parser = parser.with_crc_check(true);
+            let mut events = Vec::new();
+            for chunk in data.chunks(100) {
+                parser.feed_data(chunk, |evt| {
+                    match evt {
+                        ParserEvent::ParsingError(i, err) => events.push((i, Some(err))),
+                        ParserEvent::LocalFileEnd(i) => events.push((i, None)),
+                        _ => {}
+                    }
+                    true
+                });
+            }
+            events
+        }
+
+        let mut data = std::fs::read("test.zip").unwrap();
+        assert_eq!(parse(&data, PassiveParser::new()), [(0, None), (1, None), (2, None)]);
+        // CRC-32 of the first entry in its local header
+        data[14] ^= 1;
+        assert_eq!(
+            parse(&data, PassiveParser::new()),
+            [(0, Some(ParsingError::CrcMismatch(0xa3d1389d, 0xa3d1389c))), (0, None), (1, None), (2, None)],
+        );
+        assert_eq!(parse(&data, PassiveParser::new().with_listing_only(true)), [(0, None), (1, None), (2, None)]);
+
+        #[cfg(feature = "inflate")]
+        {
+            let mut data = std::fs::read("test_deflated.zip").unwrap();
+            data[14] ^= 1;
+            assert_eq!(
+                parse(&data, PassiveParser::new().with_inflate(true)),
+                [(0, Some(ParsingError::CrcMismatch(0xa3d1389d, 0xa3d1389c))), (0, None), (1, None)],
+            );
+            // the compressed data can't be checked
+            assert_eq!(parse(&data, PassiveParser::new()), [(0, None), (1, None)]);
+        }
+    }
+
+    #[test]
+    fn strict_mode_cross_checks_the_local_headers() {
+        use crate::{HeaderField, LocalFileOps, SeekingParser};
+
+        for name in ["test.zip", "test_zip64.zip", "test_descriptor.zip", "test_unicode_path.zip", "test_zipcrypto.zip"] {
+            let stream = RefCell::new(MemStream::new(std::fs::read(name).unwrap()));
+            for file in SeekingParser::<_, 128>::new(&stream).with_strict(true) {
+                assert_eq!(file.header_mismatch(), None, "{name}");
+            }
+        }
+
+        // fields of the first local file header
+        for (offset, field) in [
+            (8, HeaderField::CompressionMethod),
+            (14, HeaderField::Crc32),
+            (18, HeaderField::CompressedSize),
+            (22, HeaderField::UncompressedSize),
+            (30, HeaderField::FileName),
+        ] {
+            let mut data = std::fs::read("test.zip").unwrap();
+            data[offset] ^= 1;
+            let stream = RefCell::new(MemStream::new(data));
+            let mut file = SeekingParser::<_, 128>::new(&stream).next().unwrap();
+            assert_eq!(file.header_mismatch(), None);
+            assert_eq!(file.read(&mut [0u8; 16]), Ok(16));
+
+            let mut file = SeekingParser::<_, 128>::new(&stream).with_strict(true).next().unwrap();
+            assert_eq!(file.header_mismatch(), Some(field));
+            assert_eq!(file.read(&mut [0u8; 16]), Err(ParsingError::LocalHeaderMismatch(field)));
+        }
+
+        // names longer than N are compared in full
+        let data = std::fs::read("test.zip").unwrap();
+        let stream = RefCell::new(MemStream::new(data.clone()));
+        let files = SeekingParser::<_, 4>::new(&stream).with_strict(true).collect::<Vec<_>>();
+        assert_eq!(files.len(), 3);
+        assert!(files.iter().all(|file| file.header_mismatch().is_none()));
+        let mut data = data;
+        // last byte of "README.md"
+        data[38] ^= 1;
+        let stream = RefCell::new(MemStream::new(data));
+        let file = SeekingParser::<_, 4>::new(&stream).with_strict(true).next().unwrap();
+        assert_eq!(file.header_mismatch(), Some(HeaderField::FileName));
+    }
+
+    #[test]
+    fn header_lengths_are_bounded() {
+        let data = std::fs::read("test.zip").unwrap();
+
+        // names longer than N are truncated, and reported once by the passive parser
+        let mut parser = PassiveParser::<8>::new();
+        let mut events = Vec::new();
+        parser.feed_data(&data, |evt| {
+            match evt {
+                ParserEvent::LocalFileHeader(_, info) => events.push(Ok(info.file_name().unwrap().to_string())),
+                ParserEvent::ParsingError(_, err) => events.push(Err(err)),
+                _ => {}
+            }
+            true
+        });
+        assert_eq!(events[..2], [Err(ParsingError::LocalFileNameTooLong(0, 9)), Ok("README.m".to_string())]);
+        assert_eq!(events.len(), 6);
+
+        // a compressed size running past the stream
+        let mut bad_size = data.clone();
+        bad_size[18..22].copy_from_slice(&0x10000u32.to_le_bytes());
+        for (data, errors) in [(&data, vec![]), (&bad_size, vec![ParsingError::EntryOutOfBounds(39 + 28 + 0x10000)])] {
+            let mut parser = PassiveParser::<128>::new().with_stream_len(data.len() as u64);
+            let mut errors_seen = Vec::new();
+            let mut headers = 0;
+            parser.feed_data(data, |evt| {
+                match evt {
+                    ParserEvent::LocalFileHeader(..) => headers += 1,
+                    ParserEvent::ParsingError(_, err) => errors_seen.push(err),
+                    _ => {}
+                }
+                true
+            });
+            assert_eq!(errors_seen, errors);
+            assert_eq!(headers, if errors.is_empty() { 3 } else { 0 });
+        }
+
+        {
+            use crate::{HybridParser, LocalFileOps, SeekingParser};
+
+            let stream = RefCell::new(MemStream::new(data.clone()));
+            let names: Vec<_> = SeekingParser::<_, 8>::new(&stream)
+                .map(|file| file.info.file_name().unwrap().to_string())
+                .collect();
+            assert_eq!(names, ["README.m", "Cargo.to", "src/lib."]);
+
+            // the central compressed size of the first entry runs into the central directory
+            let mut data = data.clone();
+            let central = data.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+            data[central + 20..central + 24].copy_from_slice(&0x10000u32.to_le_bytes());
+            let stream = RefCell::new(MemStream::new(data.clone()));
+            let mut file = SeekingParser::<_, 128>::new(&stream).next().unwrap();
+            assert_eq!(file.read(&mut [0u8; 16]), Err(ParsingError::EntryOutOfBounds(39 + 28 + 0x10000)));
+
+            // the end of central directory record must end the stream to seek
+            let comment_len = u16::from_le_bytes([data[data.len() - 23], data[data.len() - 22]]) as usize;
+            data.truncate(data.len() - comment_len);
+            let len = data.len();
+            data[len - 2..].copy_from_slice(&[0, 0]);
+            let mut stream = MemStream::new(data);
+            let mut parser = HybridParser::<_, 128>::new(&mut stream);
+            assert_eq!(parser.run(&mut [0u8; 512], |_| true), Err(ParsingError::EntryOutOfBounds(39 + 28 + 0x10000)));
+        }
+    }
+
+    #[test]
+    fn wrapping_sizes_are_rejected() {
+        let data = std::fs::read("test_zip64.zip").unwrap();
+        let huge = (u64::MAX - 10).to_le_bytes();
+
+        // the ZIP64 compressed size of hello.txt, in its local header
+        let mut local = data.clone();
+        local[60..68].copy_from_slice(&huge);
+        let mut errors = Vec::new();
+        let mut headers = 0;
+        PassiveParser::<128>::new().feed_data(&local, |evt| {
+            match evt {
+                ParserEvent::LocalFileHeader(..) => headers += 1,
+                ParserEvent::ParsingError(_, err) => errors.push(err),
+                _ => {}
+            }
+            true
+        });
+        assert_eq!(errors, [ParsingError::Overflow]);
+        assert_eq!(headers, 0);
+
+        {
+            use crate::{HybridParser, LocalFileOps, SeekingParser};
+
+            // and in its central directory file header
+            let mut central = data.clone();
+            central[534..542].copy_from_slice(&huge);
+            let stream = RefCell::new(MemStream::new(central.clone()));
+            let mut file = SeekingParser::<_, 128>::new(&stream).next().unwrap();
+            assert_eq!(file.read(&mut [0u8; 16]), Err(ParsingError::Overflow));
+
+            let mut stream = MemStream::new(central);
+            let mut parser = HybridParser::<_, 128>::new(&mut stream);
+            assert_eq!(parser.run(&mut [0u8; 512], |_| true), Err(ParsingError::Overflow));
+        }
+    }
+
+    #[test]
+    fn corrupted_archives_never_panic() {
+        use crate::{HybridParser, LocalFileOps, SeekingParser};
+
+        let data = MemStream::test_zip_without_comment().data;
+        // the local header of README.md, and the central directory with its end record
+        let offsets = (0..100).chain(data.len() - 300..data.len());
+        for (offset, value) in offsets.flat_map(|offset| [(offset, 0x00), (offset, 0xff)]) {
+            let mut corrupted = data.clone();
+            corrupted[offset] = value;
+
+            for chunk in [7, corrupted.len()] {
+                let mut parser = PassiveParser::<16>::new();
+                for input in corrupted.chunks(chunk) {
+                    parser.feed_data(input, |_| true);
+                }
+            }
+
+            let stream = RefCell::new(MemStream::new(corrupted.clone()));
+            let mut parser = SeekingParser::<_, 16>::new(&stream);
+            while let Ok(Some(mut file)) = parser.try_next() {
+                let mut buf = [0u8; 512];
+                while matches!(file.read(&mut buf), Ok(n) if n > 0) {}
+            }
+
+            let mut stream = MemStream::new(corrupted);
+            let _ = HybridParser::<_, 16>::new(&mut stream).run(&mut [0u8; 64], |_| true);
+        }
+
+        // the data of an entry without its header is reported
+        let mut parser = PassiveParser::<128>::new();
+        parser.state = crate::ParserState::RecvLocalFileName;
+        let mut errors = Vec::new();
+        parser.feed_data(b"README.md", |evt| {
+            if let ParserEvent::ParsingError(_, err) = evt {
+                errors.push(err);
+            }
+            true
+        });
+        assert_eq!(errors, [ParsingError::LocalFileHeaderNotRecved(0)]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn io_errors_are_kept_as_sources() {
+        use std::error::Error;
+
+        struct Denied;
+
+        impl std::io::Read for Denied {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "no access"))
+            }
+        }
+
+        // seeking isn't supported
+        impl Seek for Denied {}
+
+        let err = crate::Read::read(&mut Denied, &mut [0u8; 16]).unwrap_err();
+        assert!(matches!(&err, ParsingError::Io(io) if io.kind() == std::io::ErrorKind::PermissionDenied));
+        assert_eq!(err.source().unwrap().to_string(), "no access");
+        assert_eq!(err.to_string(), "Io(no access)");
+
+        let context = crate::ErrorContext::<128>::new(err.clone(), 0);
+        assert_eq!(context.source().unwrap().to_string(), err.to_string());
+
+        // and so are the seek errors
+        let mut cursor = std::io::Cursor::new([0u8; 16]);
+        let err = crate::Seek::seek(&mut cursor, SeekFrom::Current(-1)).unwrap_err();
+        assert!(matches!(&err, ParsingError::Io(io) if io.kind() == std::io::ErrorKind::InvalidInput));
+        assert_eq!(crate::Seek::seek(&mut cursor, SeekFrom::End(-4)), Ok(12));
+        assert_eq!(crate::Seek::rewind(&mut cursor), Ok(()));
+        assert_eq!(Denied.seek(SeekFrom::Start(0)), Err(ParsingError::InvalidStream));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn stream_errors_reach_the_caller() {
+        use std::io::{Cursor, ErrorKind};
+
+        use crate::{LocalFileOps, SeekingParser};
+
+        /// Fails the next read with `error`
+        struct Flaky {
+            inner: Cursor<Vec<u8>>,
+            error: Option<ErrorKind>,
+        }
+
+        impl std::io::Read for Flaky {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                match self.error.take() {
+                    Some(kind) => Err(kind.into()),
+                    None => std::io::Read::read(&mut self.inner, buf),
+                }
+            }
+        }
+
+        impl std::io::Seek for Flaky {
+            fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+                std::io::Seek::seek(&mut self.inner, pos)
+            }
+        }
+
+        let kind = |err: ParsingError| match err {
+            ParsingError::Io(io) => Some(io.kind()),
+            _ => None,
+        };
+        let stream = RefCell::new(Flaky { inner: Cursor::new(std::fs::read("test.zip").unwrap()), error: None });
+        let mut parser = SeekingParser::<_, 128>::new(&stream);
+        stream.borrow_mut().error = Some(ErrorKind::WouldBlock);
+        assert_eq!(parser.try_next().map(|_| ()).map_err(kind), Err(Some(ErrorKind::WouldBlock)));
+
+        // nothing was lost, the same entry is listed once the stream is ready
+        let mut file = parser.try_next().unwrap().unwrap();
+        assert_eq!(file.file_name(), Ok("README.md"));
+        stream.borrow_mut().error = Some(ErrorKind::Interrupted);
+        let mut buf = [0u8; 12];
+        assert_eq!(file.read(&mut buf).map_err(kind), Err(Some(ErrorKind::Interrupted)));
+        assert_eq!(file.read(&mut buf), Ok(12));
+        assert_eq!(&buf, b"# zip_parser");
+
+        // the std readers keep the kind, `read_exact` goes on after an interruption
+        stream.borrow_mut().error = Some(ErrorKind::WouldBlock);
+        assert_eq!(std::io::Read::read(&mut file, &mut buf).map_err(|err| err.kind()), Err(ErrorKind::WouldBlock));
+        stream.borrow_mut().error = Some(ErrorKind::Interrupted);
+        std::io::Read::read_exact(&mut file, &mut buf).unwrap();
+        assert_eq!(&buf, b"\nZip file fo");
+    }
+
+    #[test]
+    fn try_next_tells_errors_from_the_end() {
+        use crate::SeekingParser;
+
+        fn names(data: Vec<u8>) -> Vec<Result<Option<String>, ParsingError>> {
+            let stream = RefCell::new(MemStream::new(data));
+            let mut files = SeekingParser::<_, 128>::new(&stream);
+            let mut names = Vec::new();
+            loop {
+                let next = files.try_next().map(|file| file.map(|file| file.info.file_name().unwrap().to_string()));
+                let done = matches!(next, Ok(None));
+                names.push(next);
+                if done {
+                    return names;
+                }
+            }
+        }
+
+        let data = std::fs::read("test.zip").unwrap();
+        assert_eq!(names(data.clone()), [
+            Ok(Some("README.md".to_string())),
+            Ok(Some("Cargo.toml".to_string())),
+            Ok(Some("src/lib.rs".to_string())),
+            Ok(None),
+        ]);
+
+        // the local file header of Cargo.toml
+        let mut data = data;
+        let second = data.windows(4).enumerate().filter(|(_, w)| *w == b"PK\x03\x04").nth(1).unwrap().0;
+        data[second + 3] = 0;
+        assert_eq!(names(data), [
+            Ok(Some("README.md".to_string())),
+            Err(ParsingError::InvalidLocalFileHeader),
+            Ok(Some("src/lib.rs".to_string())),
+            Ok(None),
+        ]);
+    }
+
+    #[test]
+    #[cfg(feature = "log")]
+    fn diagnostics_go_through_log() {
+        use crate::SeekingParser;
+        use std::sync::Mutex;
+
+        static LOGGED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+        struct Capture;
+
+        impl log::Log for Capture {
+            fn enabled(&self, _metadata: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record) {
+                LOGGED.lock().unwrap().push(format!("{} {}", record.level(), record.args()));
+            }
+
+            fn flush(&self) {}
+        }
+
+        static CAPTURE: Capture = Capture;
+        log::set_logger(&CAPTURE).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let mut data = std::fs::read("test.zip").unwrap();
+        let central = data.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+        data[central + 3] = 0;
+        let stream = RefCell::new(MemStream::new(data));
+        assert_eq!(SeekingParser::<_, 128>::new(&stream).count(), 0);
+        // other tests may log as well
+        let expected = format!("WARN listing the entries failed: InvalidCentralFileHeader at offset {:#x}", central);
+        assert!(LOGGED.lock().unwrap().contains(&expected));
+    }
+
+    #[test]
+    fn errors_tell_where_they_happened() {
+        let data = std::fs::read("test.zip").unwrap();
+
+        // a record with an unknown signature before the archive
+        let mut prefixed = b"PK\x05\x05".to_vec();
+        prefixed.extend_from_slice(&data);
+        let mut parser = PassiveParser::<128>::new();
+        parser.feed_data(&prefixed, |_| true);
+        let context = parser.last_error().unwrap();
+        assert_eq!((&context.error, context.offset, context.file_index), (&ParsingError::InvalidSignature, 4, None));
+
+        // the data of README.md, right after its extra field
+        let mut corrupted = data.clone();
+        corrupted[67] ^= 1;
+        let mut parser = PassiveParser::<128>::new().with_crc_check(true);
+        parser.feed_data(&corrupted, |_| true);
+        let context = parser.last_error().unwrap();
+        assert!(matches!(context.error, ParsingError::CrcMismatch(0xa3d1389c, _)));
+        assert_eq!((context.offset, context.file_index, context.file_name()), (67 + 359, Some(0), Ok("README.md")));
+        assert!(context.to_string().ends_with(" at offset 0x1aa, entry #0 (README.md)"));
+
+        {
+            use crate::{LocalFileOps, SeekingParser};
+
+            let stream = RefCell::new(MemStream::new(corrupted));
+            let mut files = SeekingParser::<_, 128>::new(&stream);
+            let mut file = files.next().unwrap().with_crc_check(true);
+            let err = loop {
+                match file.read(&mut [0u8; 64]) {
+                    Ok(0) => panic!("the CRC-32 was not checked"),
+                    Ok(_) => {}
+                    Err(err) => break err,
+                }
+            };
+            let context = file.error_context(err);
+            assert_eq!((context.offset, context.file_index, context.file_name()), (67 + 359, Some(0), Ok("README.md")));
+            assert_eq!(files.count(), 2);
+
+            // the iteration ends at the end of the central directory, that's no error
+            let stream = RefCell::new(MemStream::new(data.clone()));
+            let mut files = SeekingParser::<_, 128>::new(&stream);
+            assert_eq!(files.by_ref().count(), 3);
+            assert_eq!(files.last_error(), None);
+
+            let mut data = data;
+            let second = data.windows(4).enumerate().filter(|(_, w)| *w == b"PK\x01\x02").nth(1).unwrap().0;
+            data[second + 3] = 0;
+            let stream = RefCell::new(MemStream::new(data));
+            let mut files = SeekingParser::<_, 128>::new(&stream);
+            assert_eq!(files.by_ref().count(), 1);
+            let context = files.last_error().unwrap();
+            assert_eq!((&context.error, context.offset, context.file_index), (&ParsingError::InvalidCentralFileHeader, second as u64, None));
+        }
+    }
+
+    #[test]
+    fn overlapping_entries_are_detected() {
+        use crate::{ClaimedRanges, SeekingParser};
+
+        let claim_all = |data: Vec<u8>| {
+            let stream = RefCell::new(MemStream::new(data));
+            let mut claimed = ClaimedRanges::<4>::new();
+            SeekingParser::<_, 128>::new(&stream)
+                .enumerate()
+                .try_for_each(|(i, file)| claimed.claim_entry(i, &file))
+        };
+
+        let mut data = std::fs::read("test.zip").unwrap();
+        assert_eq!(claim_all(data.clone()), Ok(()));
+
+        // the second central directory record points at the first local file header
+        let central = data.windows(4).enumerate().filter(|(_, w)| *w == b"PK\x01\x02").nth(1).unwrap().0;
+        data[central + 42..central + 46].copy_from_slice(&[0; 4]);
+        assert_eq!(claim_all(data), Err(ParsingError::OverlappingEntries(0, 1)));
+
+        let mut claimed = ClaimedRanges::<2>::new();
+        claimed.claim(0, 0..10).unwrap();
+        claimed.claim(1, 10..20).unwrap();
+        assert_eq!(claimed.claim(2, 20..30), Err(ParsingError::BufferTooSmall));
+        claimed.clear();
+        assert_eq!(claimed.claim(2, 5..15), Ok(()));
+    }
+
+    #[test]
+    fn verify_all_tests_every_entry() {
+        use crate::{EntryCheck, SeekingParser, VerifyStats};
+
+        let verify = |data: Vec<u8>| {
+            let stream = RefCell::new(MemStream::new(data));
+            let mut checks = Vec::new();
+            let stats = SeekingParser::<_, 128>::new(&stream).verify_all(|report| {
+                assert_eq!(report.file_index, checks.len());
+                checks.push((report.data_len, report.check.clone()));
+            });
+            (stats, checks)
+        };
+
+        let mut data = std::fs::read("test.zip").unwrap();
+        let (stats, checks) = verify(data.clone());
+        assert_eq!(stats, VerifyStats { entries: 3, passed: 3, skipped: 0, failed: 0, declared_entries: Some(3) });
+        assert!(stats.is_ok());
+        assert_eq!(checks, [(359, EntryCheck::Passed), (534, EntryCheck::Passed), (40643, EntryCheck::Passed)]);
+
+        // first byte of the README.md data
+        let start = data.windows(12).position(|w| w == b"# zip_parser").unwrap();
+        data[start] ^= 0x20;
+        let (stats, checks) = verify(data);
+        assert_eq!(stats.failed, 1);
+        assert!(!stats.is_ok());
+        assert!(matches!(checks[0], (359, EntryCheck::Failed(ParsingError::CrcMismatch(0xa3d1389c, _)))));
+
+        let (stats, checks) = verify(std::fs::read("test_zipcrypto.zip").unwrap());
+        assert_eq!(stats.skipped, stats.entries);
+        assert!(checks.iter().all(|(len, check)| *len == 0 && *check == EntryCheck::Encrypted));
+
+        let (stats, checks) = verify(std::fs::read("test_deflated.zip").unwrap());
+        assert_eq!(stats.entries, 2);
+        #[cfg(feature = "inflate")]
+        assert_eq!(checks, [(359, EntryCheck::Passed), (108890, EntryCheck::Passed)]);
+        #[cfg(not(feature = "inflate"))]
+        assert_eq!(checks, [(0, EntryCheck::Unsupported(CompressMethod::Deflated)), (0, EntryCheck::Unsupported(CompressMethod::Deflated))]);
+    }
+
+    #[test]
+    fn crc_engine_is_pluggable() {
+        use crate::{Crc32, SoftwareCrc32};
+
+        /// CRC-32 unit computing nothing, as if broken
+        struct Stuck;
+        impl Crc32 for Stuck {
+            fn update(&self, _crc: u32, _data: &[u8]) -> u32 {
+                0
+            }
+        }
+
+        assert_eq!(SoftwareCrc32.update(SoftwareCrc32.update(0, b"zip_"), b"parser"), crate::crc32_update(0, b"zip_parser"));
+
+        let data = std::fs::read("test.zip").unwrap();
+        let mut errors = Vec::new();
+        let mut parser = PassiveParser::<128>::new().with_crc_check(true).with_crc_engine(&Stuck);
+        parser.feed_data(&data, |evt| {
+            if let ParserEvent::ParsingError(i, err) = evt {
+                errors.push((i, err));
+            }
+            true
+        });
+        assert_eq!(
+            errors,
+            [
+                (0, ParsingError::CrcMismatch(0xa3d1389c, 0)),
+                (1, ParsingError::CrcMismatch(0x03716d34, 0)),
+                (2, ParsingError::CrcMismatch(0x08ec4d9f, 0)),
+            ],
+        );
+
+        {
+            use crate::{LocalFileOps, SeekingParser};
+
+            let stream = RefCell::new(MemStream::test_zip_without_comment());
+            let mut file = SeekingParser::<_, 128>::new(&stream).next().unwrap()
+                .with_crc_check(true)
+                .with_crc_engine(&Stuck);
+            let mut buf = [0u8; 1024];
+            assert_eq!(file.read(&mut buf), Ok(359));
+            assert_eq!(file.read(&mut buf), Err(ParsingError::CrcMismatch(0xa3d1389c, 0)));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn archive_index_lists_and_diffs() {
+        use crate::{diff, ArchiveIndex, IndexEntry, SeekingParser};
+
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let old = ArchiveIndex::build(SeekingParser::<_, 128>::new(&stream));
+        assert!(diff(&old, &old).is_empty());
+
+        let mut new: ArchiveIndex = old.entries()[1..].iter().cloned().collect();
+        new.push(IndexEntry { name: "LICENSE".into(), metadata: Default::default() });
+        let mut lib = new.entries()[1].clone();
+        lib.metadata.crc32 ^= 1;
+        let new: ArchiveIndex = [new.entries()[0].clone(), lib, new.entries()[2].clone()].into_iter().collect();
+
+        let listing = old.listing();
+        assert_eq!(listing.rows[2].name, "src/lib.rs");
+        assert_eq!(listing.rows[2].host_system, 3);
+        assert_eq!(listing.totals.entries, 3);
+        assert_eq!(listing.totals.uncompressed_size, 41536);
+        assert_eq!(listing.totals.savings_percent(), 0.0);
+
+        let changes = diff(&old, &new);
+        assert_eq!(changes.added, [new.get("LICENSE").unwrap()]);
+        assert_eq!(changes.removed, [old.get("README.md").unwrap()]);
+        assert_eq!(changes.modified, [(old.get("src/lib.rs").unwrap(), new.get("src/lib.rs").unwrap())]);
+    }
+
+    #[test]
+    fn passive_parser_resumes_from_checkpoint() {
+        let data = std::fs::read("test.zip").unwrap();
+        let collect = |parser: &mut PassiveParser<128>, data: &[u8], out: &mut Vec<u8>, limit: usize| {
+            parser.feed_data(data, |evt| match evt {
+                ParserEvent::LocalFileData { file_index: 2, data, .. } => {
+                    out.extend_from_slice(data);
+                    out.len() < limit
+                }
+                _ => true,
+            });
+        };
+
+        let mut first_half = Vec::new();
+        let mut parser = PassiveParser::<128>::new();
+        for chunk in data.chunks(1000) {
+            collect(&mut parser, chunk, &mut first_half, 20000);
+            if first_half.len() >= 20000 {
+                break;
+            }
+        }
+        let checkpoint = parser.checkpoint().unwrap();
+        assert_eq!(checkpoint.file_index, 2);
+        assert_eq!(checkpoint.data_offset, first_half.len() as u64);
+
+        let mut second_half = Vec::new();
+        let mut parser = PassiveParser::restore(&checkpoint);
+        collect(&mut parser, &data[checkpoint.stream_offset as usize..], &mut second_half, usize::MAX);
+        let lib_rs = [first_half, second_half].concat();
+        assert_eq!(lib_rs.len(), 40643);
+        assert_eq!(crate::crc32_update(0, &lib_rs), 0x08ec4d9f);
+        assert_eq!(parser.crc32, 0x08ec4d9f);
+    }
+
+    #[test]
+    fn passive_parser_state_survives_save_and_load() {
+        let data = std::fs::read("test.zip").unwrap();
+        let record = |parser: &mut PassiveParser<128>, data: &[u8], events: &mut Vec<String>| {
+            parser.feed_data(data, |evt| {
+                events.push(format!("{:?}", evt));
+                true
+            });
+        };
+
+        let mut restored = Vec::new();
+        let mut parser = PassiveParser::<128>::new();
+        let mut blob = [0u8; 1024];
+        for chunk in data.chunks(7) {
+            record(&mut parser, chunk, &mut restored);
+            let len = parser.save_state(&mut blob).unwrap();
+            parser = PassiveParser::load_state(&blob[..len]).unwrap();
+        }
+
+        let mut events = Vec::new();
+        let mut parser = PassiveParser::<128>::new();
+        for chunk in data.chunks(7) {
+            record(&mut parser, chunk, &mut events);
+        }
+        assert_eq!(restored, events);
+        assert_eq!(PassiveParser::<128>::load_state(&blob[..10]).err(), Some(ParsingError::InvalidSavedState));
+    }
+
+    #[test]
+    fn corrupted_saved_state_is_rejected_or_parsed() {
+        use crate::CENTRAL_DIR_END_LEN;
+
+        let data = MemStream::test_zip_without_comment().data;
+        let end = data.len() - CENTRAL_DIR_END_LEN;
+        let central_directory = u32::from_le_bytes(data[end + 16..end + 20].try_into().unwrap()) as usize;
+        // within a local header, its name and data, a central header and its name, the end record
+        for at in [10, 40, 100, central_directory + 10, central_directory + 50, end + 10] {
+            let mut parser = PassiveParser::<128>::new();
+            parser.feed_data(&data[..at], |_| true);
+            let mut blob = [0u8; 1024];
+            let len = parser.save_state(&mut blob).unwrap();
+            for index in 0..len {
+                for corrupt in [|byte: u8| byte ^ 1, |_| 0xff] {
+                    let mut blob = blob;
+                    blob[index] = corrupt(blob[index]);
+                    // either rejected, or the rest is parsed without panicking nor looping
+                    if let Ok(mut parser) = PassiveParser::<128>::load_state(&blob[..len]) {
+                        parser.feed_data(&data[at..], |_| true);
+                    }
+                }
+            }
+        }
+
+        let mut parser = PassiveParser::<128>::new();
+        parser.feed_data(&data[..2], |_| true);
+        let mut blob = [0u8; 1024];
+        let len = parser.save_state(&mut blob).unwrap();
+        // the state length, right after the magic, tag and header type
+        blob[6] = 0;
+        assert_eq!(PassiveParser::<128>::load_state(&blob[..len]).err(), Some(ParsingError::InvalidSavedState));
+    }
+
+    #[test]
+    #[cfg(not(feature = "forbid-unsafe"))]
+    fn feed_events_queues_owned_events() {
+        use core::mem::MaybeUninit;
+
+        use crate::OwnedEvent;
+
+        let data = std::fs::read("test.zip").unwrap();
+        let mut parser = PassiveParser::<128>::new();
+        let mut queue: [MaybeUninit<OwnedEvent<128>>; 2] = [MaybeUninit::uninit(), MaybeUninit::uninit()];
+        let mut names = Vec::new();
+        let mut readme = Vec::new();
+        let mut input = &data[..];
+        while !input.is_empty() {
+            let (consumed, count) = parser.feed_events(input, &mut queue);
+            for slot in &queue[..count] {
+                match unsafe { slot.assume_init_ref() } {
+                    OwnedEvent::LocalFileHeader(_, info) => names.push(info.file_name().unwrap().to_string()),
+                    OwnedEvent::LocalFileData { file_index: 0, range, .. } => readme.extend_from_slice(&input[range.clone()]),
+                    _ => {}
+                }
+            }
+            input = &input[consumed..];
+        }
+        assert_eq!(names, ["README.md", "Cargo.toml", "src/lib.rs"]);
+        assert!(readme.starts_with(b"# zip_parser") && readme.len() == 359);
+    }
+
+    #[test]
+    fn feed_data_vectored_matches_contiguous_feed() {
+        let data = std::fs::read("test.zip").unwrap();
+        let mut events = Vec::new();
+        let mut parser = PassiveParser::<128>::new();
+        parser.feed_data(&data, |evt| {
+            if !matches!(evt, ParserEvent::LocalFileData { .. }) {
+                events.push(format!("{:?}", evt));
+            }
+            true
+        });
+
+        let mut vectored = Vec::new();
+        let mut parser = PassiveParser::<128>::new();
+        let (head, tail) = data.split_at(1000);
+        parser.feed_data_vectored(&[head, tail], |evt| {
+            if !matches!(evt, ParserEvent::LocalFileData { .. }) {
+                vectored.push(format!("{:?}", evt));
+            }
+            true
+        });
+        assert_eq!(events, vectored);
+
+        let mut parser = PassiveParser::<128>::new();
+        let mut cancelled = None;
+        parser.feed_data_vectored(&[head, tail], |evt| match evt {
+            ParserEvent::LocalFileHeader(1, _) => false,
+            ParserEvent::UserCancel(_, n) => {
+                cancelled = Some(n);
+                true
+            }
+            _ => true,
+        });
+        // README.md header + name + extra field + data, then the next header, name and extra field
+        assert_eq!(cancelled, Some(30 + 9 + 28 + 359 + 30 + 10 + 28));
+    }
+
+    #[test]
+    fn multi_archive_mode_parses_concatenated_archives() {
+        let data = std::fs::read("test.zip").unwrap();
+        let stream = [data.as_slice(), data.as_slice()].concat();
+
+        let mut parser = PassiveParser::<128>::new().with_multi_archive(true);
+        let mut events = Vec::new();
+        for chunk in stream.chunks(100) {
+            parser.feed_data(chunk, |evt| {
+                match evt {
+                    ParserEvent::LocalFileHeader(i, _) => events.push((i, false)),
+                    ParserEvent::ArchiveEnd(i, stats) => {
+                        assert!(stats.is_consistent());
+                        assert_eq!(stats.entries, 3);
+                        assert_eq!(stats.compressed_bytes, 359 + 534 + 40643);
+                        assert_eq!(stats.uncompressed_bytes, 359 + 534 + 40643);
+                        events.push((i as i32, true));
+                    }
+                    ParserEvent::ParsingError(_, err) => panic!("{:?}", err),
+                    _ => {}
+                }
+                true
+            });
+        }
+        assert_eq!(events, [
+            (0, false), (1, false), (2, false), (0, true),
+            (0, false), (1, false), (2, false), (1, true),
+        ]);
+        assert_eq!(parser.archive_index(), 2);
+        assert_eq!(parser.stream_offset(), stream.len() as u64);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn multi_archive_mode_keeps_the_comment_until_the_next_archive() {
+        let data = std::fs::read("test.zip").unwrap();
+        let mut parser = PassiveParser::<128>::new().with_multi_archive(true);
+        parser.feed_data(&data, |_| true);
+        assert_eq!(parser.archive_index(), 1);
+        assert_eq!(parser.file_comment(), Ok("Zip file parsing test"));
+
+        parser.feed_data(&data[..4], |_| true);
+        assert_eq!(parser.file_comment(), Ok(""));
+        parser.feed_data(&data[4..], |_| true);
+        assert_eq!(parser.file_comment(), Ok("Zip file parsing test"));
+    }
+
+    #[test]
+    fn strict_mode_reports_misordered_records() {
+        fn findings(stream: &[u8]) -> Vec<ParsingError> {
+            let mut parser = PassiveParser::<128>::new().with_strict(true);
+            let mut errors = Vec::new();
+            parser.feed_data(stream, |evt| {
+                if let ParserEvent::ParsingError(_, err) = evt {
+                    errors.push(err);
+                }
+                true
+            });
+            errors
+        }
+
+        let data = std::fs::read("test.zip").unwrap();
+        assert_eq!(findings(&data), []);
+
+        let concatenated = [data.as_slice(), data.as_slice()].concat();
+        assert_eq!(findings(&concatenated)[0], ParsingError::RecordAfterCentralDirEnd);
+
+        let mut miscounted = data.clone();
+        let total_entries = data.len() - 21 - 22 + 10;
+        miscounted[total_entries] = 4;
+        assert_eq!(findings(&miscounted), [ParsingError::CentralEntryCountMismatch(3, 4)]);
+
+        // move the first central record in front of the local file headers
+        let central_start = data.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+        let central_len = 46 + 9 + 24;
+        let reordered = [
+            &data[central_start..central_start + central_len],
+            &data[..central_start],
+            &data[central_start + central_len..],
+        ].concat();
+        assert_eq!(findings(&reordered)[0], ParsingError::LocalHeaderAfterCentralDirectory);
+    }
+
+    #[test]
+    fn listing_only_mode_skips_data_and_stops_at_the_end() {
+        let data = std::fs::read("test.zip").unwrap();
+        let stream = [data.as_slice(), b"trailing junk"].concat();
+
+        let mut parser = PassiveParser::<128>::new().with_listing_only(true);
+        let mut central = Vec::new();
+        for chunk in stream.chunks(64) {
+            parser.feed_data(chunk, |evt| {
+                match evt {
+                    ParserEvent::LocalFileData { .. } => panic!("data delivered in listing-only mode"),
+                    ParserEvent::ParsingError(_, err) => panic!("{:?}", err),
+                    ParserEvent::CentralFileHeader(i, info, metadata) => {
+                        central.push((i, info.file_name().unwrap().to_string(), metadata.crc32, metadata.central_header_offset));
+                    }
+                    _ => {}
+                }
+                true
+            });
+        }
+        assert!(parser.is_finished());
+        assert_eq!(parser.stream_offset(), data.len() as u64);
+
+        let central_start = data.windows(4).position(|w| w == b"PK\x01\x02").unwrap() as u64;
+        assert_eq!(central, [
+            (0, "README.md".to_string(), 0xa3d1389c, central_start),
+            (1, "Cargo.toml".to_string(), 0x03716d34, central_start + 46 + 9 + 24),
+            (2, "src/lib.rs".to_string(), 0x08ec4d9f, central_start + 2 * (46 + 24) + 9 + 10),
+        ]);
+    }
+
+    #[test]
+    fn zip64_extended_information_is_used() {
+        fn record(evt: ParserEvent<128>, events: &mut Vec<String>) {
+            match evt {
+                ParserEvent::LocalFileHeader(_, info) => {
+                    events.push(format!("local {} {}", info.compressed_size, info.uncompressed_size))
+                }
+                ParserEvent::LocalFileData { data, .. } => events.push(format!("data {}", data.len())),
+                ParserEvent::CentralFileHeader(_, info, metadata) => events.push(format!(
+                    "central {} {} {}",
+                    info.compressed_size, info.uncompressed_size, metadata.local_header_offset,
+                )),
+                ParserEvent::ParsingError(_, err) => events.push(format!("{:?}", err)),
+                _ => {}
+            }
+        }
+
+        let data = std::fs::read("test_zip64.zip").unwrap();
+        let mut events = Vec::new();
+        PassiveParser::<128>::new().feed_data(&data, |evt| {
+            record(evt, &mut events);
+            true
+        });
+        assert_eq!(events, [
+            "local 280 280", "data 280", "local 62 1800", "data 62",
+            "central 280 280 0", "central 62 1800 348",
+        ]);
+
+        // the extra fields arrive in pieces, across saved states
+        let mut restored = Vec::new();
+        let mut parser = PassiveParser::<128>::new();
+        let mut blob = [0u8; 1024];
+        for byte in data.chunks(1) {
+            parser.feed_data(byte, |evt| {
+                record(evt, &mut restored);
+                true
+            });
+            let len = parser.save_state(&mut blob).unwrap();
+            parser = PassiveParser::load_state(&blob[..len]).unwrap();
+        }
+        restored.dedup();
+        assert_eq!(restored, [
+            "local 280 280", "data 1", "local 62 1800", "data 1",
+            "central 280 280 0", "central 62 1800 348",
+        ]);
+
+        let mut seeking = Vec::new();
+        let stream = RefCell::new(MemStream::new(data));
+        crate::HybridParser::<_, 128>::new(&mut *stream.borrow_mut()).run(&mut [0u8; 512], |evt| {
+            record(evt, &mut seeking);
+            true
+        }).unwrap();
+        assert_eq!(seeking, events);
+
+        {
+            use crate::{LocalFileOps, SeekingParser};
+
+            let mut files = SeekingParser::<_, 128>::new(&stream);
+            let mut file = files.next().unwrap();
+            assert_eq!((file.info.compressed_size, file.file_size()), (280, 280));
+            assert_eq!(file.info.file_data_offset, 30 + 9 + 29);
+            let mut content = [0u8; 280];
+            file.read_exact(&mut content).unwrap();
+            assert_eq!(&content[..14], b"Hello, ZIP64!\n");
+            let file = files.next().unwrap();
+            assert_eq!((file.info.compressed_size, file.info.uncompressed_size), (62, 1800));
+            assert_eq!(file.metadata.local_header_offset, 348);
+        }
+    }
+
+    #[test]
+    fn oversized_zip64_block_is_fed_in_pieces() {
+        // stored "abc" whose ZIP64 block is 40 bytes, longer than the values it can hold
+        let mut data = Vec::new();
+        data.extend_from_slice(b"PK\x03\x04\x2d\x00\x00\x00\x00\x00\x00\x00\x00\x00");
+        data.extend_from_slice(&0x352441c2u32.to_le_bytes());
+        data.extend_from_slice(&[0xFF; 8]);
+        data.extend_from_slice(&[1, 0, 44, 0]);
+        data.push(b'a');
+        data.extend_from_slice(&[1, 0, 40, 0]);
+        data.extend_from_slice(&3u64.to_le_bytes());
+        data.extend_from_slice(&3u64.to_le_bytes());
+        data.extend_from_slice(&[0xAA; 24]);
+        data.extend_from_slice(b"abc");
+
+        let mut sizes = Vec::new();
+        let mut parser = PassiveParser::<128>::new();
+        for byte in data.chunks(1) {
+            parser.feed_data(byte, |evt| {
+                if let ParserEvent::LocalFileHeader(_, info) = evt {
+                    sizes.push((info.compressed_size, info.uncompressed_size));
+                }
+                true
+            });
+        }
+        assert_eq!(sizes, [(3, 3)]);
+    }
+
+    #[test]
+    fn zip64_end_of_central_directory_gives_the_entry_count() {
+        let data = std::fs::read("test_zip64_end.zip").unwrap();
+        let mut events = Vec::new();
+        PassiveParser::<128>::new().with_strict(true).feed_data(&data, |evt| {
+            match evt {
+                ParserEvent::ParsingError(_, err) => events.push(format!("{:?}", err)),
+                ParserEvent::ArchiveEnd(_, stats) => {
+                    assert!(stats.is_consistent());
+                    events.push(format!("end {}", stats.declared_entries));
+                }
+                _ => {}
+            }
+            true
+        });
+        assert_eq!(events, ["end 2"]);
+
+        // a count past u32::MAX is kept as is
+        let mut large = data.clone();
+        let zip64_end = large.windows(4).rposition(|w| w == b"PK\x06\x06").unwrap();
+        large[zip64_end + 32..zip64_end + 40].copy_from_slice(&0x1_0000_0002u64.to_le_bytes());
+        let mut events = Vec::new();
+        PassiveParser::<128>::new().with_strict(true).feed_data(&large, |evt| {
+            match evt {
+                ParserEvent::ParsingError(_, err) => events.push(format!("{:?}", err)),
+                ParserEvent::ArchiveEnd(_, stats) => events.push(format!("end {}", stats.declared_entries)),
+                _ => {}
+            }
+            true
+        });
+        assert_eq!(events, ["CentralEntryCountMismatch(2, 4294967298)", "end 4294967298"]);
+
+        let stream = RefCell::new(MemStream::new(data));
+        let mut names = Vec::new();
+        crate::HybridParser::<_, 128>::new(&mut *stream.borrow_mut()).run(&mut [0u8; 512], |evt| {
+            if let ParserEvent::CentralFileHeader(_, info, _) = evt {
+                names.push(info.file_name().unwrap().to_string());
+            }
+            true
+        }).unwrap();
+        assert_eq!(names, ["README.md", "lorem.txt"]);
+
+        {
+            let parser = crate::SeekingParser::<_, 128>::new(&stream);
+            assert_eq!(parser.number_of_files, Some(2));
+            assert_eq!(parser.check_disk_numbers(), Ok(()));
+            let sizes: Vec<_> = parser.map(|file| file.info.uncompressed_size).collect();
+            assert_eq!(sizes, [359, 108890]);
+        }
+    }
+
+    #[test]
+    fn data_descriptors_end_entries_of_unknown_size() {
+        use crate::DataDescriptor;
+
+        let data = std::fs::read("test_descriptor.zip").unwrap();
+        let expected = [
+            DataDescriptor { crc32: 0xa3d1389c, compressed_size: 218, uncompressed_size: 359 },
+            DataDescriptor { crc32: 0x8d24fb90, compressed_size: 76, uncompressed_size: 76 },
+            DataDescriptor { crc32: 0xaed34b37, compressed_size: 5132, uncompressed_size: 108890 },
+        ];
+
+        for chunk_len in [1, 7, 100, data.len()] {
+            let mut files: Vec<Vec<u8>> = Vec::new();
+            let mut descriptors = Vec::new();
+            let mut parser = PassiveParser::<128>::new().with_strict(true);
+            let mut blob = [0u8; 1024];
+            for chunk in data.chunks(chunk_len) {
+                parser.feed_data(chunk, |evt| {
+                    match evt {
+                        ParserEvent::LocalFileHeader(..) => files.push(Vec::new()),
+                        ParserEvent::LocalFileData { file_index, offset, data } => {
+                            assert_eq!(offset, files[file_index as usize].len());
+                            files[file_index as usize].extend_from_slice(data);
+                        }
+                        ParserEvent::LocalFileDataDescriptor(i, descriptor) => descriptors.push((i, descriptor)),
+                        ParserEvent::ParsingError(_, err) => panic!("{:?}", err),
+                        ParserEvent::ArchiveEnd(_, stats) => {
+                            assert!(stats.is_consistent());
+                            assert_eq!(stats.uncompressed_bytes, 359 + 76 + 108890);
+                        }
+                        _ => {}
+                    }
+                    true
+                });
+                let len = parser.save_state(&mut blob).unwrap();
+                parser = PassiveParser::load_state(&blob[..len]).unwrap();
+            }
+            assert_eq!(descriptors, [(0, expected[0]), (1, expected[1]), (2, expected[2])]);
+            let lens: Vec<_> = files.iter().map(|file| file.len() as u64).collect();
+            assert_eq!(lens, [218, 76, 5132]);
+            assert_eq!(crate::crc32_update(0, &files[1]), 0x8d24fb90);
+        }
 
-        self.buffer.clear();
+        // the data held back is queued by value
+        #[cfg(not(feature = "forbid-unsafe"))]
+        {
+            use core::mem::MaybeUninit;
+
+            use crate::OwnedEvent;
+
+            let mut parser = PassiveParser::<128>::new();
+            let mut queue = [const { MaybeUninit::<OwnedEvent<128>>::uninit() }; 4];
+            let mut stored = Vec::new();
+            for mut input in data.chunks(10) {
+                while !input.is_empty() {
+                    let (consumed, count) = parser.feed_events(input, &mut queue);
+                    for slot in &queue[..count] {
+                        match unsafe { slot.assume_init_ref() } {
+                            OwnedEvent::LocalFileData { file_index: 1, range, .. } => stored.extend_from_slice(&input[range.clone()]),
+                            OwnedEvent::LocalFileHeldData { file_index: 1, data, .. } => stored.extend_from_slice(data),
+                            _ => {}
+                        }
+                    }
+                    input = &input[consumed..];
+                }
+            }
+            assert_eq!(crate::crc32_update(0, &stored), 0x8d24fb90);
+        }
 
-        self.localfile_info = None;
-        self.localfile_index = 0;
-        self.centralfile_index = 0;
+        let mut descriptors = Vec::new();
+        let mut stream = MemStream::new(data);
+        crate::HybridParser::<_, 128>::new(&mut stream).run(&mut [0u8; 512], |evt| {
+            if let ParserEvent::LocalFileDataDescriptor(_, descriptor) = evt {
+                descriptors.push(descriptor);
+            }
+            true
+        }).unwrap();
+        assert_eq!(descriptors, expected);
+    }
 
-        self.file_name_index = 0;
-        self.file_name_len = 0;
+    #[test]
+    fn seeking_parser_reads_data_descriptors() {
+        use crate::{DataDescriptor, LocalFileOps, SeekingParser};
+
+        let stream = RefCell::new(MemStream::new(std::fs::read("test_descriptor.zip").unwrap()));
+        let mut descriptors = Vec::new();
+        for mut file in SeekingParser::<_, 128>::new(&stream) {
+            // the sizes come from the central directory, the local header has zeros
+            let descriptor = file.data_descriptor().unwrap().unwrap();
+            assert_eq!(descriptor.crc32, file.metadata.crc32);
+            assert_eq!(descriptor.compressed_size, file.info.compressed_size);
+            assert_eq!(descriptor.uncompressed_size, file.info.uncompressed_size);
+            descriptors.push(descriptor);
+
+            if file.file_name() == Ok("fake.bin") {
+                let mut content = [0u8; 77];
+                assert_eq!(file.read(&mut content), Ok(76));
+                assert_eq!(file.read(&mut content), Ok(0));
+                assert_eq!(crate::crc32_update(0, &content[..76]), descriptor.crc32);
+            }
+        }
+        assert_eq!(descriptors, [
+            DataDescriptor { crc32: 0xa3d1389c, compressed_size: 218, uncompressed_size: 359 },
+            DataDescriptor { crc32: 0x8d24fb90, compressed_size: 76, uncompressed_size: 76 },
+            DataDescriptor { crc32: 0xaed34b37, compressed_size: 5132, uncompressed_size: 108890 },
+        ]);
+
+        let stream = RefCell::new(MemStream::new(std::fs::read("test_deflated.zip").unwrap()));
+        let mut file = SeekingParser::<_, 128>::new(&stream).next().unwrap();
+        assert_eq!(file.data_descriptor(), Ok(None));
+    }
 
-        self.extra_field_index = 0;
-        self.extra_field_len = 0;
+    #[test]
+    fn encrypted_entries_are_flagged() {
+        let data = std::fs::read("test_encrypted.zip").unwrap();
+        let mut flags = Vec::new();
+        PassiveParser::<128>::new().feed_data(&data, |evt| {
+            match evt {
+                ParserEvent::LocalFileHeader(_, info) => flags.push(("local", info.is_encrypted())),
+                ParserEvent::CentralFileHeader(_, info, metadata) => {
+                    assert_eq!(info.is_encrypted(), metadata.is_encrypted());
+                    flags.push(("central", info.is_encrypted()));
+                }
+                _ => {}
+            }
+            true
+        });
+        assert_eq!(flags, [("local", true), ("local", false), ("central", true), ("central", false)]);
+
+        {
+            let stream = RefCell::new(MemStream::new(data));
+            let files: Vec<_> = crate::SeekingParser::<_, 128>::new(&stream)
+                .map(|file| (file.info.is_encrypted(), file.metadata.is_encrypted()))
+                .collect();
+            assert_eq!(files, [(true, true), (false, false)]);
+        }
+    }
 
-        self.file_data_index = 0;
-        self.file_data_len = 0;
+    #[test]
+    fn unsupported_encryption_is_reported() {
+        use crate::EncryptionKind;
+
+        fn errors(data: &[u8]) -> (Vec<(i32, ParsingError)>, bool) {
+            let mut errors = Vec::new();
+            let mut parser = PassiveParser::<128>::new();
+            parser.feed_data(data, |evt| {
+                if let ParserEvent::ParsingError(i, err) = evt {
+                    errors.push((i, err));
+                }
+                true
+            });
+            (errors, parser.is_finished())
+        }
 
-        self.central_file_header_index = 0;
-        self.central_file_header_len = 0;
+        // the strongly encrypted entry is skipped, the following one is parsed
+        let strong = std::fs::read("test_strong_encryption.zip").unwrap();
+        const STRONG_ERROR: ParsingError = ParsingError::UnsupportedEncryption(EncryptionKind::Strong);
+        assert_eq!(errors(&strong), (vec![(0, STRONG_ERROR)], false));
+
+        // nothing follows an encrypted central directory, starting from the masked local header
+        // or from the archive extra data record
+        let masked = std::fs::read("test_encrypted_central_dir.zip").unwrap();
+        const MASKED_ERROR: ParsingError = ParsingError::UnsupportedEncryption(EncryptionKind::CentralDirectory);
+        assert_eq!(errors(&masked), (vec![(0, MASKED_ERROR)], true));
+        assert_eq!(errors(&masked[95..]), (vec![(0, MASKED_ERROR)], true));
+
+        for (data, expected) in [(strong, STRONG_ERROR), (masked, MASKED_ERROR)] {
+            let mut stream = MemStream::new(data);
+            let mut errors = Vec::new();
+            crate::HybridParser::<_, 128>::new(&mut stream).run(&mut [0u8; 64], |evt| {
+                if let ParserEvent::ParsingError(i, err) = evt {
+                    errors.push((i, err));
+                }
+                true
+            }).unwrap();
+            assert_eq!(errors, [(0, expected)]);
+        }
 
-        self.central_dir_end_index = 0;
-        self.central_dir_end_len = 0;
+        {
+            use crate::{LocalFileOps, SeekingParser};
+
+            let stream = RefCell::new(MemStream::new(std::fs::read("test_strong_encryption.zip").unwrap()));
+            let mut files = SeekingParser::<_, 128>::new(&stream);
+            assert_eq!(files.check_encryption(), Ok(()));
+            let mut buf = [0u8; 64];
+            let mut file = files.next().unwrap();
+            assert!(file.info.is_encrypted());
+            assert_eq!(file.read(&mut buf), Err(STRONG_ERROR));
+            let mut file = files.next().unwrap();
+            assert_eq!(file.read(&mut buf), Ok(7));
+            assert_eq!(&buf[..7], b"public\n");
+
+            let stream = RefCell::new(MemStream::new(std::fs::read("test_encrypted_central_dir.zip").unwrap()));
+            let mut files = SeekingParser::<_, 128>::new(&stream);
+            assert_eq!(files.check_encryption(), Err(MASKED_ERROR));
+            assert!(files.next().is_none());
+        }
     }
 
-    pub fn localfile_index(&self) -> i32 {
-        self.localfile_index
+    #[test]
+    fn password_provider_unlocks_encrypted_entries() {
+        use crate::{LocalFileOps, PasswordFn, SeekingParser};
+
+        let stream = RefCell::new(MemStream::new(std::fs::read("test_zipcrypto.zip").unwrap()));
+        let mut files = SeekingParser::<_, 128>::new(&stream);
+        let mut file = files.next().unwrap();
+        let mut asked = Vec::new();
+        let passwords = ["letmein", "hunter2"];
+        file.unlock(PasswordFn(|name: &[u8], attempt| {
+            asked.push((name.to_vec(), attempt));
+            passwords.get(attempt as usize)
+        })).unwrap();
+        assert_eq!(asked, [(b"secret.txt".to_vec(), 0), (b"secret.txt".to_vec(), 1)]);
+        let mut content = [0u8; 16];
+        assert_eq!(file.file_size(), 11);
+        assert_eq!(file.read(&mut content), Ok(11));
+        assert_eq!(&content[..11], b"top secret\n");
+
+        // the provider gives up, or hands over a password too long
+        let mut file = files.next().unwrap();
+        assert_eq!(file.unlock(PasswordFn(|_: &[u8], _| None::<&str>)), Err(ParsingError::IncorrectPassword(0)));
+        assert_eq!(
+            file.unlock(PasswordFn(|_: &[u8], attempt| (attempt < 3).then_some("letmein"))),
+            Err(ParsingError::IncorrectPassword(3)),
+        );
+        assert_eq!(file.unlock(PasswordFn(|_: &[u8], _| Some([b'x'; 200]))), Err(ParsingError::BufferTooSmall));
+
+        #[cfg(feature = "inflate")]
+        {
+            file.unlock(PasswordFn(|_: &[u8], _| Some("hunter2"))).unwrap();
+            let mut reader = file.decompressed_reader();
+            let mut lorem = Vec::new();
+            let mut buf = [0u8; 100];
+            loop {
+                match LocalFileOps::read(&mut reader, &mut buf).unwrap() {
+                    0 => break,
+                    n => lorem.extend_from_slice(&buf[..n]),
+                }
+            }
+            assert_eq!(lorem.len(), 22972);
+            assert_eq!(crate::crc32_update(0, &lorem), 0x495dbd08);
+        }
+
+        // nothing to do on entries which are not encrypted
+        let stream = RefCell::new(MemStream::new(std::fs::read("test_encrypted.zip").unwrap()));
+        let mut file = SeekingParser::<_, 128>::new(&stream).nth(1).unwrap();
+        file.unlock(PasswordFn(|_: &[u8], _| -> Option<&str> { panic!() })).unwrap();
+        assert_eq!(file.read(&mut content), Ok(7));
     }
 
-    pub fn file_comment(&self) -> Result<&str, Utf8Error> {
-        #[cfg(feature = "std")]
-        { str::from_utf8(self.zip_file_comment.as_slice()) }
-        #[cfg(not(feature = "std"))]
-        { Ok("") }
+    #[test]
+    fn extra_field_records_are_iterated() {
+        use crate::ExtraFields;
+
+        fn records(fields: ExtraFields) -> Vec<(u16, usize)> {
+            fields.map(|record| record.map(|(id, data)| (id, data.len())).unwrap()).collect()
+        }
+
+        let data = std::fs::read("test.zip").unwrap();
+        let local = [(0x5455, 9), (0x7875, 11)];
+        let central = [(0x5455, 5), (0x7875, 11)];
+        let mut passive = Vec::new();
+        PassiveParser::<128>::new().feed_data(&data, |evt| {
+            match evt {
+                ParserEvent::LocalFileExtraField(i, fields) => passive.push(("local", i, records(fields))),
+                ParserEvent::CentralFileExtraField(i, fields) => passive.push(("central", i, records(fields))),
+                _ => {}
+            }
+            true
+        });
+        assert_eq!(passive.len(), 6);
+        for (kind, i, records) in &passive {
+            assert_eq!(records, if *kind == "local" { &local } else { &central }, "{} #{}", kind, i);
+        }
+
+        // the seeking mode reads them from the stream
+        let data = std::fs::read("test_zip64.zip").unwrap();
+        let collect = |events: &mut Vec<_>, evt: ParserEvent<128>| match evt {
+            ParserEvent::LocalFileExtraField(i, fields) => events.push(("local", i, records(fields))),
+            ParserEvent::CentralFileExtraField(i, fields) => events.push(("central", i, records(fields))),
+            _ => {}
+        };
+        let mut passive = Vec::new();
+        PassiveParser::<128>::new().feed_data(&data, |evt| {
+            collect(&mut passive, evt);
+            true
+        });
+        let stream = RefCell::new(MemStream::new(data));
+        let mut hybrid = Vec::new();
+        {
+            let mut handle = stream.borrow_mut();
+            let mut parser = crate::HybridParser::<_, 128>::new(&mut *handle);
+            parser.run(&mut [0u8; 64], |evt| {
+                collect(&mut hybrid, evt);
+                true
+            }).unwrap();
+            assert!(parser.is_seeking());
+        }
+        assert_eq!(hybrid, passive);
+        assert_eq!(hybrid.len(), 4);
+
+        {
+            let mut file = crate::SeekingParser::<_, 128>::new(&stream).next().unwrap();
+            let mut buf = [0u8; 64];
+            assert_eq!(records(file.extra_fields(&mut buf).unwrap()), [(0x5455, 5), (0x0001, 24)]);
+            assert_eq!(records(file.local_extra_fields(&mut buf).unwrap()), [(0x5455, 5), (0x0001, 16)]);
+            assert_eq!(file.extra_fields(&mut buf).unwrap().find(0x0001).map(|data| data.len()), Some(24));
+            assert_eq!(file.extra_fields(&mut [0u8; 8]).err(), Some(ParsingError::BufferTooSmall));
+        }
+
+        // records running past the end are reported, and end the iteration
+        let malformed = [0x55, 0x54, 0x01, 0x00, 0x00, 0x75, 0x78, 0x08, 0x00, 0x01];
+        let mut fields = ExtraFields::new(&malformed);
+        assert_eq!(fields.next(), Some(Ok((0x5455, &[0u8][..]))));
+        assert_eq!(fields.next(), Some(Err(ParsingError::InvalidExtraField(5))));
+        assert_eq!(fields.next(), None);
+        assert_eq!(ExtraFields::new(&malformed[..7]).find(0x7875), None);
+        assert_eq!(ExtraFields::new(&[0x01]).next(), Some(Err(ParsingError::InvalidExtraField(0))));
     }
 
-    pub fn feed_data<F>(&mut self, data: &[u8], mut on_event: F)
-    where
-        F: for<'b, 'c> FnMut(ParserEvent<'b, 'c, N>) -> bool,
-    {
-        struct BufferData<'a> {
-            count: usize,
-            data: &'a [u8],
+    #[test]
+    fn ntfs_timestamps_are_decoded() {
+        let data = std::fs::read("test_ntfs.zip").unwrap();
+        let expected = crate::NtfsTimestamps {
+            modified: crate::UnixTime { seconds: 1704164645, nanoseconds: 123456700 },
+            accessed: crate::UnixTime { seconds: 1704164705, nanoseconds: 0 },
+            created: crate::UnixTime { seconds: 1704161045, nanoseconds: 500 },
+        };
+
+        let mut timestamps = Vec::new();
+        PassiveParser::<128>::new().feed_data(&data, |evt| {
+            match evt {
+                ParserEvent::LocalFileExtraField(_, fields) | ParserEvent::CentralFileExtraField(_, fields) => {
+                    timestamps.push(fields.ntfs_timestamps());
+                }
+                _ => {}
+            }
+            true
+        });
+        assert_eq!(timestamps, [Some(expected); 2]);
+
+        {
+            let stream = RefCell::new(MemStream::new(data));
+            let mut file = crate::SeekingParser::<_, 128>::new(&stream).next().unwrap();
+            assert_eq!(file.ntfs_timestamps(), Ok(Some(expected)));
         }
 
-        impl<'a> BufferData<'a> {
-            pub fn new(data: &'a [u8]) -> Self {
-                BufferData {
-                    count: 0,
-                    data,
+        // before the Unix epoch, and without the times attribute
+        assert_eq!(crate::UnixTime::from_ntfs(5), crate::UnixTime { seconds: -11644473600, nanoseconds: 500 });
+        let other_attribute = [0, 0, 0, 0, 0x02, 0x00, 0x01, 0x00, 0xFF];
+        assert_eq!(crate::NtfsTimestamps::from_bytes(&other_attribute), None);
+        assert_eq!(crate::NtfsTimestamps::from_bytes(&other_attribute[..6]), None);
+    }
+
+    #[test]
+    fn unix_owner_is_decoded() {
+        let expected = crate::UnixOwner { uid: 0x0303EB, gid: 0x030201 };
+        let mut owners = Vec::new();
+        let data = std::fs::read("test.zip").unwrap();
+        PassiveParser::<128>::new().feed_data(&data, |evt| {
+            match evt {
+                ParserEvent::LocalFileExtraField(_, fields) | ParserEvent::CentralFileExtraField(_, fields) => {
+                    owners.push(fields.unix_owner());
                 }
+                _ => {}
             }
+            true
+        });
+        assert_eq!(owners, [Some(expected); 6]);
+
+        // only the local header has it there
+        {
+            let stream = RefCell::new(MemStream::new(std::fs::read("test_unix_owner.zip").unwrap()));
+            let mut file = crate::SeekingParser::<_, 128>::new(&stream).next().unwrap();
+            assert_eq!(file.extra_fields(&mut [0u8; 0]).unwrap().unix_owner(), None);
+            assert_eq!(file.unix_owner(), Ok(Some(crate::UnixOwner { uid: 1000, gid: 0x12345678 })));
+        }
 
-            pub fn peek_data(&self, len: usize) -> &[u8] {
-                &self.data[self.count..self.count + len]
+        // IDs wider than 32 bits are fine as long as they fit
+        let wide = [1, 8, 0xE8, 0x03, 0, 0, 0, 0, 0, 0, 1, 0];
+        assert_eq!(crate::UnixOwner::from_bytes(&wide), Some(crate::UnixOwner { uid: 1000, gid: 0 }));
+        let too_wide = [1, 5, 0, 0, 0, 0, 1, 1, 0];
+        assert_eq!(crate::UnixOwner::from_bytes(&too_wide), None);
+        assert_eq!(crate::UnixOwner::from_bytes(&wide[..11]), None);
+        assert_eq!(crate::UnixOwner::from_bytes(&[2, 1, 0, 1, 0]), None);
+    }
+
+    #[test]
+    fn unicode_path_replaces_the_header_name() {
+        // "café.txt" with its CP437 name in the header, then a stale field over a renamed entry
+        let data = std::fs::read("test_unicode_path.zip").unwrap();
+        let expected = ["café.txt".as_bytes(), b"na\x8bve.txt"];
+        let mut local = Vec::new();
+        let mut central = Vec::new();
+        PassiveParser::<128>::new().feed_data(&data, |evt| {
+            match evt {
+                ParserEvent::LocalFileHeader(_, info) => local.push(info.file_name_bytes().to_vec()),
+                ParserEvent::CentralFileHeader(_, info, _) => central.push(info.file_name_bytes().to_vec()),
+                _ => {}
             }
+            true
+        });
+        assert_eq!(local, expected);
+        assert_eq!(central, expected);
+
+        let mut stream = MemStream::new(data.clone());
+        let mut hybrid = Vec::new();
+        let mut parser = crate::HybridParser::<_, 128>::new(&mut stream);
+        parser.run(&mut [0u8; 64], |evt| {
+            if let ParserEvent::CentralFileHeader(_, info, _) = evt {
+                hybrid.push(info.file_name_bytes().to_vec());
+            }
+            true
+        }).unwrap();
+        assert!(parser.is_seeking());
+        assert_eq!(hybrid, expected);
+
+        {
+            let stream = RefCell::new(MemStream::new(data.clone()));
+            let mut parser = crate::SeekingParser::<_, 128>::new(&stream);
+            assert_eq!(parser.next().unwrap().info.file_name(), Ok("café.txt"));
+            assert!(parser.next().unwrap().info.file_name().is_err());
+        }
 
-            /// Processed some data, which length is `len`
-            pub fn proccessed(&mut self, len: usize) {
-                self.count += len;
+        // the Unicode name must fit, the header name is kept otherwise
+        let mut names = Vec::new();
+        let first_entry = &data[..63];
+        PassiveParser::<8>::new().feed_data(first_entry, |evt| {
+            if let ParserEvent::LocalFileHeader(_, info) = evt {
+                names.push(info.file_name_bytes().to_vec());
             }
+            true
+        });
+        assert_eq!(names, [b"caf\x82.txt"]);
+    }
 
-            pub fn proccessed_data_len(&self) -> usize {
-                self.count
+    #[test]
+    fn legacy_names_are_decoded_from_cp437() {
+        let mut names = Vec::new();
+        PassiveParser::<128>::new().feed_data(&std::fs::read("test_unicode_path.zip").unwrap(), |evt| {
+            if let ParserEvent::CentralFileHeader(_, info, _) = evt {
+                let mut buf = [0u8; 32];
+                names.push((info.has_utf8_name(), info.decode_file_name(&mut buf).unwrap().to_string()));
+                assert_eq!(info.decode_file_name(&mut [0u8; 4]).is_ok(), info.has_utf8_name());
+                #[cfg(feature = "std")]
+                assert_eq!(info.decoded_file_name(), names.last().unwrap().1);
             }
+            true
+        });
+        // the first name comes from the Unicode path extra field
+        assert_eq!(names, [(true, "café.txt".to_string()), (false, "naïve.txt".to_string())]);
+
+        let mut buf = [0u8; 16];
+        assert_eq!(crate::decode_cp437(b"\x9b5 \xab\xe3", &mut buf), Ok("¢5 ½π"));
+        assert_eq!(crate::decode_cp437(b"\xdb\xdb", &mut [0u8; 5]), Err(ParsingError::BufferTooSmall));
+    }
 
-            pub fn unproccessed_data_len(&self) -> usize {
-                self.data.len() - self.count
+    #[test]
+    fn unicode_comment_replaces_the_file_comment() {
+        let fields = [0x75, 0x63, 0x09, 0x00, 0x01, 0x1D, 0xA9, 0x59, 0xFC, b'h', 0xC3, 0xA9, b'!'];
+        assert_eq!(crate::ExtraFields::new(&fields).unicode_comment(b"h\x82!"), Some("hé!".as_bytes()));
+        assert_eq!(crate::ExtraFields::new(&fields).unicode_comment(b"he!"), None);
+
+        {
+            let stream = RefCell::new(MemStream::new(std::fs::read("test_unicode_comment.zip").unwrap()));
+            let mut parser = crate::SeekingParser::<_, 128>::new(&stream);
+            let mut file = parser.next().unwrap();
+            assert_eq!(file.file_comment(&mut [0u8; 64]), Ok("déjà vu".as_bytes()));
+            // the header comment fits, not the Unicode one
+            assert_eq!(file.file_comment(&mut [0u8; 8]), Err(ParsingError::BufferTooSmall));
+            // UTF-8 already
+            let mut file = parser.next().unwrap();
+            assert_eq!(file.file_comment(&mut [0u8; 64]), Ok("déjà lu".as_bytes()));
+        }
+    }
+
+    #[test]
+    fn aes_extra_field_is_decoded() {
+        let data = std::fs::read("test_aes.zip").unwrap();
+        let expected = crate::AesExtraField {
+            vendor_version: 2,
+            vendor_id: *b"AE",
+            strength: crate::AesStrength::Aes256,
+            compression_method: CompressMethod::Deflated,
+        };
+        let mut fields = Vec::new();
+        PassiveParser::<128>::new().feed_data(&data, |evt| {
+            match evt {
+                ParserEvent::LocalFileHeader(_, info) => assert_eq!(info.compression_method, CompressMethod::Aes),
+                ParserEvent::LocalFileExtraField(_, extra) | ParserEvent::CentralFileExtraField(_, extra) => {
+                    fields.push(extra.aes());
+                }
+                _ => {}
             }
+            true
+        });
+        assert_eq!(fields, [Some(expected); 2]);
+        assert_eq!(expected.strength.key_bits(), 256);
+
+        {
+            let stream = RefCell::new(MemStream::new(data));
+            let mut file = crate::SeekingParser::<_, 128>::new(&stream).next().unwrap();
+            assert!(file.info.is_encrypted());
+            assert_eq!(file.aes_extra_field(), Ok(Some(expected)));
         }
 
-        let mut buffer_data = BufferData::new(data);
-        let res: Result<usize, usize> = loop {
-            // All data have being processed
-            if buffer_data.proccessed_data_len() >= data.len() {
-                break Ok(data.len());
+        assert_eq!(crate::AesExtraField::from_bytes(&[2, 0, b'A', b'E', 4, 8, 0]), None);
+        assert_eq!(crate::AesExtraField::from_bytes(&[2, 0, b'A', b'E', 1, 8]), None);
+    }
+
+    #[test]
+    fn entry_times_are_converted() {
+        let data = std::fs::read("test.zip").unwrap();
+        let modified = crate::UnixTime { seconds: 1642158913, nanoseconds: 0 };
+        let accessed = crate::UnixTime { seconds: 1642572365, nanoseconds: 0 };
+        let mut timestamps = Vec::new();
+        let mut last_modified = None;
+        PassiveParser::<128>::new().feed_data(&data, |evt| {
+            match evt {
+                ParserEvent::LocalFileExtraField(0, fields) | ParserEvent::CentralFileExtraField(0, fields) => {
+                    timestamps.push(fields.extended_timestamp().unwrap());
+                }
+                ParserEvent::CentralFileHeader(0, _, metadata) => last_modified = Some(metadata.last_modified),
+                _ => {}
             }
-            let mut continue_parsing = true;
-            match self.state {
-                ParserState::RecvHeader(header_type, header_len) => {
-                    // queue data
-                    let len = cmp::min(
-                        header_len - self.buffer_data_len(),
-                        buffer_data.unproccessed_data_len(),
-                    );
-                    buffer_data.proccessed(self.append_bytes(buffer_data.peek_data(len)));
+            true
+        });
+        assert_eq!(timestamps, [
+            crate::ExtendedTimestamp { modified: Some(modified), accessed: Some(accessed), created: None },
+            crate::ExtendedTimestamp { modified: Some(modified), accessed: None, created: None },
+        ]);
+        assert_eq!(crate::ExtendedTimestamp::from_bytes(&[]), None);
+        let last_modified = last_modified.unwrap();
+
+        #[cfg(feature = "time")]
+        {
+            let date = time::Date::from_calendar_date(2022, time::Month::January, 14).unwrap();
+            assert_eq!(last_modified.to_primitive_date_time(), date.with_hms(19, 15, 14).ok());
+            assert_eq!(modified.to_offset_date_time(), date.with_hms(11, 15, 13).ok().map(|time| time.assume_utc()));
+            assert_eq!(crate::DosDateTime::new(0, 0).to_primitive_date_time(), None);
+        }
+        #[cfg(feature = "chrono")]
+        {
+            let date = chrono::NaiveDate::from_ymd_opt(2022, 1, 14).unwrap();
+            assert_eq!(last_modified.to_naive_date_time(), date.and_hms_opt(19, 15, 14));
+            let ntfs = crate::UnixTime { seconds: 1642158913, nanoseconds: 500 };
+            assert_eq!(ntfs.to_naive_date_time(), date.and_hms_nano_opt(11, 15, 13, 500));
+            assert_eq!(crate::DosDateTime::new(0, 0).to_naive_date_time(), None);
+        }
+        let _ = last_modified;
+    }
 
-                    // check data len in buffer
-                    if self.buffer_data_len() < header_len {
-                        continue;
+    #[test]
+    fn extra_field_handlers_decode_records() {
+        use crate::{ExtraFieldFn, ExtraFieldHandlers, ExtraFieldLocation};
+
+        let data = std::fs::read("test.zip").unwrap();
+        let mut owners = Vec::new();
+        let mut owner_handler = ExtraFieldFn(|i, location, data: &[u8]| {
+            owners.push((i, location, crate::UnixOwner::from_bytes(data).unwrap().uid));
+            Ok(())
+        });
+        let mut failing = ExtraFieldFn(|_, _, _: &[u8]| Err(ParsingError::InvalidExtraField(0)));
+        let mut handlers = ExtraFieldHandlers::<2>::new();
+        handlers.register(0x7875, &mut owner_handler).unwrap();
+        handlers.register(0x5455, &mut failing).unwrap();
+        let mut unused = ExtraFieldFn(|_, _, _: &[u8]| Ok(()));
+        assert_eq!(handlers.register(0x000A, &mut unused).err(), Some(ParsingError::BufferTooSmall));
+
+        let mut errors = 0;
+        let mut extra_fields = 0;
+        PassiveParser::<128>::new().feed_data(&data, |evt| handlers.handle(evt, |evt| {
+            match evt {
+                ParserEvent::ParsingError(_, ParsingError::InvalidExtraField(0)) => errors += 1,
+                ParserEvent::LocalFileExtraField(..) | ParserEvent::CentralFileExtraField(..) => extra_fields += 1,
+                _ => {}
+            }
+            true
+        }));
+        drop(handlers);
+        assert_eq!((errors, extra_fields), (6, 6));
+        assert_eq!(owners, [
+            (0, ExtraFieldLocation::LocalFileHeader, 0x0303EB),
+            (1, ExtraFieldLocation::LocalFileHeader, 0x0303EB),
+            (2, ExtraFieldLocation::LocalFileHeader, 0x0303EB),
+            (0, ExtraFieldLocation::CentralFileHeader, 0x0303EB),
+            (1, ExtraFieldLocation::CentralFileHeader, 0x0303EB),
+            (2, ExtraFieldLocation::CentralFileHeader, 0x0303EB),
+        ]);
+    }
+
+    #[test]
+    fn seeking_parser_reads_the_archive_comment() {
+        let stream = RefCell::new(MemStream::new(std::fs::read("test.zip").unwrap()));
+        let mut parser = crate::SeekingParser::<_, 128>::new(&stream);
+        assert_eq!(parser.number_of_files, Some(3));
+        assert_eq!(parser.archive_comment(&mut [0u8; 64]), Ok(&b"Zip file parsing test"[..]));
+        assert_eq!(parser.archive_comment(&mut [0u8; 8]), Err(ParsingError::BufferTooSmall));
+        assert_eq!(parser.count(), 3);
+
+        // a signature in the comment whose length doesn't bring to the end is not the record
+        let mut data = std::fs::read("test_dir.zip").unwrap();
+        let len = data.len();
+        data[len - 2..].copy_from_slice(&26u16.to_le_bytes());
+        data.extend_from_slice(&[0x50, 0x4b, 0x05, 0x06]);
+        data.extend_from_slice(&[0u8; 22]);
+        let stream = RefCell::new(MemStream::new(data));
+        let mut parser = crate::SeekingParser::<_, 128>::new(&stream);
+        assert_eq!(parser.number_of_files, Some(2));
+        assert_eq!(parser.archive_comment(&mut [0u8; 64]).unwrap().len(), 26);
+
+        // without any record
+        let stream = RefCell::new(MemStream::new(vec![0u8; 70000]));
+        let mut parser = crate::SeekingParser::<_, 128>::new(&stream);
+        assert_eq!(parser.number_of_files, None);
+        assert_eq!(parser.archive_comment(&mut [0u8; 64]), Err(ParsingError::InvalidCentralDirEnd));
+    }
+
+    #[test]
+    fn host_system_is_decoded() {
+        use crate::{EntryMetadata, HostSystem};
+
+        let mut hosts = Vec::new();
+        PassiveParser::<128>::new().feed_data(&std::fs::read("test.zip").unwrap(), |evt| {
+            if let ParserEvent::CentralFileHeader(_, _, metadata) = evt {
+                hosts.push(metadata.host_system());
+            }
+            true
+        });
+        assert_eq!(hosts, [HostSystem::Unix; 3]);
+
+        let host = |version_made_by| EntryMetadata { version_made_by, ..Default::default() }.host_system();
+        assert_eq!(host(0x0014), HostSystem::Fat);
+        assert_eq!(host(0x0A3F), HostSystem::Ntfs);
+        assert_eq!(host(0x1314), HostSystem::Darwin);
+        assert_eq!(host(0x2A14), HostSystem::Unknown);
+    }
+
+    #[test]
+    fn unix_mode_comes_from_unix_hosts() {
+        let mut modes = Vec::new();
+        PassiveParser::<128>::new().feed_data(&std::fs::read("test.zip").unwrap(), |evt| {
+            if let ParserEvent::CentralFileHeader(_, _, metadata) = evt {
+                modes.push(metadata.unix_mode());
+            }
+            true
+        });
+        assert_eq!(modes, [Some(0o100644); 3]);
+
+        // made on MS-DOS
+        let metadata = crate::EntryMetadata { version_made_by: 0x0014, external_file_attributes: 0x8020_0020, ..Default::default() };
+        assert_eq!(metadata.unix_mode(), None);
+        let metadata = crate::EntryMetadata { version_made_by: 0x1314, external_file_attributes: 0o40755 << 16, ..Default::default() };
+        assert_eq!(metadata.unix_mode(), Some(0o40755));
+    }
+
+    #[test]
+    fn dos_attributes_come_from_dos_and_windows_hosts() {
+        let metadata = crate::EntryMetadata { version_made_by: 0x0A3F, external_file_attributes: 0x23, ..Default::default() };
+        let attributes = metadata.dos_attributes().unwrap();
+        assert!(attributes.is_read_only() && attributes.is_hidden() && attributes.is_archive());
+        assert!(!attributes.is_system() && !attributes.is_directory());
+        let metadata = crate::EntryMetadata { version_made_by: 0x0014, external_file_attributes: 0x14, ..Default::default() };
+        let attributes = metadata.dos_attributes().unwrap();
+        assert!(attributes.is_system() && attributes.is_directory());
+
+        // the low byte means nothing for sure on Unix hosts
+        let mut attributes = Vec::new();
+        PassiveParser::<128>::new().feed_data(&std::fs::read("test.zip").unwrap(), |evt| {
+            if let ParserEvent::CentralFileHeader(_, _, metadata) = evt {
+                attributes.push(metadata.dos_attributes());
+            }
+            true
+        });
+        assert_eq!(attributes, [None; 3]);
+    }
+
+    #[test]
+    fn text_entries_are_flagged() {
+        let mut text = Vec::new();
+        PassiveParser::<128>::new().feed_data(&std::fs::read("test_dir.zip").unwrap(), |evt| {
+            if let ParserEvent::CentralFileHeader(_, _, metadata) = evt {
+                text.push((metadata.internal_file_attributes, metadata.is_text()));
+            }
+            true
+        });
+        assert_eq!(text, [(0, false), (1, true)]);
+    }
+
+    #[test]
+    fn directory_entries_are_detected() {
+        let data = std::fs::read("test_dir.zip").unwrap();
+        let mut dirs = Vec::new();
+        PassiveParser::<128>::new().feed_data(&data, |evt| {
+            if let ParserEvent::LocalFileHeader(_, info) = evt {
+                dirs.push(info.is_dir());
+            }
+            true
+        });
+        assert_eq!(dirs, [true, false]);
+
+        {
+            use crate::{LocalFile, LocalFileOps};
+
+            let stream = RefCell::new(MemStream::new(data));
+            let dirs: Vec<_> = crate::SeekingParser::<_, 128>::new(&stream).map(|file| file.is_dir()).collect();
+            assert_eq!(dirs, [true, false]);
+
+            // the attributes are enough without the trailing `/`
+            let mut file = LocalFile::<MemStream, 128>::default();
+            file.metadata.version_made_by = 0x0314;
+            file.metadata.external_file_attributes = 0o40755 << 16;
+            assert!(file.is_dir());
+            file.metadata.uncompressed_size = 1;
+            assert!(!file.is_dir());
+
+            // the streamed entries agree with their local header
+            let mut stream = MemStream::new(std::fs::read("test_dir.zip").unwrap());
+            let mut parser = crate::PullParser::<_, 128>::new(&mut stream);
+            let mut dirs = Vec::new();
+            while let Some(entry) = crate::Parser::next_entry(&mut parser).unwrap() {
+                assert_eq!(entry.is_dir(), entry.info().is_dir());
+                dirs.push(entry.is_dir());
+            }
+            assert_eq!(dirs, [true, false]);
+        }
+
+        // the default goes by the name and the attributes, not by the data length
+        use crate::LocalFileOps;
+        struct Entry(&'static str, bool, u64);
+        impl LocalFileOps for Entry {
+            fn file_name(&self) -> Result<&str, core::str::Utf8Error> {
+                Ok(self.0)
+            }
+            fn file_size(&self) -> u64 {
+                self.2
+            }
+            fn has_directory_attribute(&self) -> bool {
+                self.1
+            }
+            fn read(&mut self, _: &mut [u8]) -> Result<usize, ParsingError> {
+                Ok(0)
+            }
+        }
+        assert!(Entry("dir/", false, 2).is_dir());
+        assert!(Entry("dir", true, 0).is_dir());
+        assert!(!Entry("dir", false, 0).is_dir());
+    }
+
+    #[test]
+    fn hybrid_parser_switches_to_seeking() {
+        use crate::HybridParser;
+
+        fn record(evt: ParserEvent<128>, events: &mut Vec<String>, files: &mut Vec<Vec<u8>>) {
+            match evt {
+                ParserEvent::LocalFileData { file_index, data, .. } => files[file_index as usize].extend_from_slice(data),
+                evt => {
+                    if let ParserEvent::LocalFileHeader(..) = evt {
+                        files.push(Vec::new());
                     }
+                    events.push(format!("{:?}", evt));
+                }
+            }
+        }
 
-                    match header_type {
-                        HeaderType::HeaderSignature => {
-                            // parse signature type
-                            match Signature::try_from(self.buffer.as_ref()) {
-                                Err(err) => {
-                                    continue_parsing = on_event(ParserEvent::ParsingError(self.localfile_index, err));
-                                    self.buffer.clear();
-                                }
-                                Ok(sig) => {
-                                    match sig {
-                                        Signature::LocalFileHeader => self.state = ParserState::RecvHeader(HeaderType::LocalFileHeader, LOCAL_FILE_HEADER_LEN),
-                                        Signature::CentralFileHeader => self.state = ParserState::RecvHeader(HeaderType::CentralFileHeader, CENTRAL_FILE_HEADER_LEN),
-                                        Signature::CentralDirEnd => self.state = ParserState::RecvHeader(HeaderType::CentralDirEnd, CENTRAL_DIR_END_LEN),
-                                    }
-                                }
-                            }
-                        }
-                        HeaderType::LocalFileHeader => {
-                            // parse header
-                            if let Some(file_info) = unsafe {
-                                LocalFileHeader::from_bytes(&self.buffer)
-                            } {
-                                // #[cfg(feature = "std")]
-                                // dbg!(file_info);
+        let data = MemStream::test_zip_without_comment().data;
+        let mut expected = (Vec::new(), Vec::new());
+        PassiveParser::<128>::new().feed_data(&data, |evt| {
+            record(evt, &mut expected.0, &mut expected.1);
+            true
+        });
+
+        for len_unknown_reads in [0, 2, 10, 1000] {
+            let mut stream = MemStream::test_zip_without_comment();
+            stream.len_unknown_reads = len_unknown_reads;
+            let mut parser = HybridParser::<_, 128>::new(&mut stream);
+            let mut actual = (Vec::new(), Vec::new());
+            parser.run(&mut [0u8; 300], |evt| {
+                record(evt, &mut actual.0, &mut actual.1);
+                true
+            }).unwrap();
+            assert_eq!(parser.is_seeking(), len_unknown_reads < 1000);
+            assert_eq!(actual, expected);
+        }
+
+        // the end of central directory record is found before the zip file comment
+        let mut stream = MemStream::new(std::fs::read("test.zip").unwrap());
+        let mut parser = HybridParser::<_, 128>::new(&mut stream);
+        let mut actual = (Vec::new(), Vec::new());
+        parser.run(&mut [0u8; 300], |evt| {
+            record(evt, &mut actual.0, &mut actual.1);
+            true
+        }).unwrap();
+        assert!(parser.is_seeking());
+        assert_eq!(actual.1, expected.1);
+    }
+
+    #[test]
+    fn hybrid_parser_checks_the_entries_after_the_switch() {
+        use crate::HybridParser;
+
+        fn parser() -> PassiveParser<128> {
+            let parser = PassiveParser::new().with_strict(true).with_crc_check(true);
+            #[cfg(feature = "inflate")]
+            let parser = parser.with_inflate(true);
+            parser
+        }
+        fn record(evt: ParserEvent<128>, events: &mut Vec<String>, data: &mut Vec<u8>) {
+            match evt {
+                ParserEvent::LocalFileData { data: chunk, .. } => data.extend_from_slice(chunk),
+                evt => events.push(format!("{:?}", evt)),
+            }
+        }
+
+        // a corrupted byte in the data of README.md
+        let mut corrupted = MemStream::test_zip_without_comment().data;
+        let readme = corrupted.windows(11).position(|w| w == b"# zip_parse").unwrap();
+        corrupted[readme] = b'!';
+        let inflated = if cfg!(feature = "inflate") { "LocalFileInflated" } else { "LocalFileEnd" };
+        for (data, checked) in [(corrupted, "CrcMismatch"), (std::fs::read("test_deflated.zip").unwrap(), inflated)] {
+            let mut expected = (Vec::new(), Vec::new());
+            parser().feed_data(&data, |evt| {
+                record(evt, &mut expected.0, &mut expected.1);
+                true
+            });
+            assert!(expected.0.iter().any(|evt| evt.contains(checked)));
+
+            for len_unknown_reads in [0, 3, 1000] {
+                let mut stream = MemStream::new(data.clone());
+                stream.len_unknown_reads = len_unknown_reads;
+                let mut parser = HybridParser::with_parser(&mut stream, parser());
+                let mut actual = (Vec::new(), Vec::new());
+                parser.run(&mut [0u8; 300], |evt| {
+                    record(evt, &mut actual.0, &mut actual.1);
+                    true
+                }).unwrap();
+                assert_eq!(actual, expected);
+            }
+        }
+
+        // a central directory listing the entries in the reverse order of the local files
+        let mut data = MemStream::test_zip_without_comment().data;
+        let end = data.len() - 22;
+        let size = u32::from_le_bytes(data[end + 12..end + 16].try_into().unwrap()) as usize;
+        let start = u32::from_le_bytes(data[end + 16..end + 20].try_into().unwrap()) as usize;
+        let mut records = Vec::new();
+        let mut offset = start;
+        while offset < start + size {
+            let field = |at: usize| u16::from_le_bytes([data[offset + at], data[offset + at + 1]]) as usize;
+            let len = 46 + field(28) + field(30) + field(32);
+            records.push(data[offset..offset + len].to_vec());
+            offset += len;
+        }
+        records.reverse();
+        data.splice(start..start + size, records.concat());
+        for (len_unknown_reads, names) in [
+            (0, ["src/lib.rs", "Cargo.toml", "README.md"]),
+            (2, ["README.md", "src/lib.rs", "Cargo.toml"]),
+            (1000, ["README.md", "Cargo.toml", "src/lib.rs"]),
+        ] {
+            let mut stream = MemStream::new(data.clone());
+            stream.len_unknown_reads = len_unknown_reads;
+            let mut hybrid = HybridParser::with_parser(&mut stream, parser());
+            let mut headers = Vec::new();
+            hybrid.run(&mut [0u8; 300], |evt| {
+                match evt {
+                    ParserEvent::LocalFileHeader(i, info) => headers.push((i, info.file_name().unwrap().to_string())),
+                    ParserEvent::ParsingError(_, err) => panic!("{err:?}"),
+                    _ => {}
+                }
+                true
+            }).unwrap();
+            assert_eq!(headers, names.iter().enumerate().map(|(i, name)| (i as i32, name.to_string())).collect::<Vec<_>>());
+        }
+
+        // a stream ending before the end of the archive
+        let mut stream = MemStream::test_zip_without_comment();
+        stream.data.truncate(1000);
+        stream.len_unknown_reads = usize::MAX;
+        let mut parser = HybridParser::<_, 128>::new(&mut stream);
+        assert_eq!(parser.run(&mut [0u8; 300], |_| true), Err(ParsingError::StreamEnding));
+
+        // an entry count beyond the file indices
+        let mut data = std::fs::read("test_zip64_end.zip").unwrap();
+        let zip64_end = data.windows(4).rposition(|w| w == b"PK\x06\x06").unwrap();
+        data[zip64_end + 24..zip64_end + 40].copy_from_slice(&[0xff; 16]);
+        let mut stream = MemStream::new(data);
+        let mut parser = HybridParser::<_, 128>::new(&mut stream);
+        assert_eq!(parser.run(&mut [0u8; 300], |_| true), Err(ParsingError::Overflow));
+    }
+
+    #[test]
+    fn cancellable_stream_aborts_parsing() {
+        use crate::{Cancellable, HybridParser};
+        use core::sync::atomic::{AtomicBool, Ordering};
+
+        let cancel = AtomicBool::new(false);
+        let mut inner = MemStream::test_zip_without_comment();
+        inner.len_unknown_reads = usize::MAX;
+        let mut stream = Cancellable::new(inner, &cancel);
+        let mut parser = HybridParser::<_, 128>::new(&mut stream);
+        let mut data_events = 0;
+        let res = parser.run(&mut [0u8; 100], |evt| {
+            if let ParserEvent::LocalFileData { .. } = evt {
+                data_events += 1;
+                cancel.store(true, Ordering::Relaxed);
+            }
+            true
+        });
+        assert_eq!(res, Err(ParsingError::Cancelled));
+        assert_eq!(data_events, 1);
+    }
+
+    #[cfg(all(feature = "io-uring", target_os = "linux", not(feature = "forbid-unsafe")))]
+    #[test]
+    fn uring_file_reads_ranges() {
+        use crate::{Read, UringFile};
+
+        let data = std::fs::read("test.zip").unwrap();
+        let mut file = UringFile::open("test.zip").unwrap();
+        let (mut head, mut tail) = ([0u8; 30], [0u8; 22]);
+        file.read_ranges(&mut [(0, &mut head[..]), (data.len() as u64 - 43, &mut tail[..])]).unwrap();
+        assert_eq!(head, data[..30]);
+        assert_eq!(tail, data[data.len() - 43..data.len() - 21]);
+        assert_eq!(file.read_at(data.len() as u64 - 5, &mut head), Ok(5));
+        assert_eq!(file.read_ranges(&mut [(data.len() as u64 - 5, &mut head[..])]), Err(ParsingError::StreamEnding));
+
+        let mut read = Vec::new();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        let mut buf = [0u8; 4096];
+        loop {
+            match file.read(&mut buf).unwrap() {
+                0 => break,
+                n => read.extend_from_slice(&buf[..n]),
+            }
+        }
+        assert_eq!(read, data);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn feed_buf_read_matches_feed_data() {
+        let data = std::fs::read("test.zip").unwrap();
+        let record = |evt: ParserEvent<128>, events: &mut Vec<String>| {
+            if let ParserEvent::LocalFileData { file_index, offset, data } = evt {
+                events.push(format!("{} {} {}", file_index, offset, crate::crc32_update(0, data)));
+            } else {
+                events.push(format!("{:?}", evt));
+            }
+        };
+
+        let mut expected = Vec::new();
+        PassiveParser::<128>::new().feed_data(&data, |evt| {
+            record(evt, &mut expected);
+            true
+        });
+
+        let mut reader = std::io::BufReader::with_capacity(data.len() + 1, data.as_slice());
+        let mut events = Vec::new();
+        let consumed = PassiveParser::<128>::new().feed_buf_read(&mut reader, |evt| {
+            record(evt, &mut events);
+            true
+        }).unwrap();
+        assert_eq!(consumed, data.len() as u64);
+        assert_eq!(events, expected);
+
+        // records split over refills still go through the parser buffer
+        let mut reader = std::io::BufReader::with_capacity(64, data.as_slice());
+        let mut names = Vec::new();
+        PassiveParser::<128>::new().feed_buf_read(&mut reader, |evt| {
+            if let ParserEvent::CentralFileHeader(_, info, _) = evt {
+                names.push(info.file_name().unwrap().to_string());
+            }
+            true
+        }).unwrap();
+        assert_eq!(names, ["README.md", "Cargo.toml", "src/lib.rs"]);
+    }
+
+    #[cfg(feature = "inflate")]
+    #[test]
+    fn deflated_entries_are_inflated() {
+        use crate::{LocalFileOps, SeekingParser};
+
+        let stream = RefCell::new(MemStream::new(std::fs::read("test_deflated.zip").unwrap()));
+        let mut files = Vec::new();
+        for mut file in SeekingParser::<_, 128>::new(&stream) {
+            assert_eq!(file.info.compression_method, CompressMethod::Deflated);
+            let mut data = Vec::new();
+            let mut buf = [0u8; 1000];
+            loop {
+                match file.read(&mut buf).unwrap() {
+                    0 => break,
+                    n => data.extend_from_slice(&buf[..n]),
+                }
+            }
+            assert_eq!(data.len() as u64, file.file_size());
+            files.push((file.file_name().unwrap().to_string(), crate::crc32_update(0, &data)));
+        }
+        assert_eq!(files, [("README.md".to_string(), 0xa3d1389c), ("lorem.txt".to_string(), 0xaed34b37)]);
+    }
+
+    #[test]
+    fn decompressed_reader_yields_uncompressed_data() {
+        use crate::{LocalFileOps, SeekingParser};
+
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let mut file = SeekingParser::<_, 128>::new(&stream).next().unwrap();
+        let mut reader = file.decompressed_reader();
+        let mut data = vec![0u8; reader.file_size() as usize];
+        LocalFileOps::read_exact(&mut reader, &mut data).unwrap();
+        assert_eq!(LocalFileOps::read(&mut reader, &mut [0u8; 8]).unwrap(), 0);
+        assert_eq!(crate::crc32_update(0, &data), 0xa3d1389c);
+
+        let stream = RefCell::new(MemStream::new(std::fs::read("test_deflated.zip").unwrap()));
+        let mut file = SeekingParser::<_, 128>::new(&stream).nth(1).unwrap();
+        let mut reader = file.decompressed_reader();
+        #[cfg(feature = "inflate")]
+        {
+            let mut data = Vec::new();
+            #[cfg(feature = "std")]
+            std::io::Read::read_to_end(&mut reader, &mut data).unwrap();
+            #[cfg(not(feature = "std"))]
+            {
+                data.resize(reader.file_size() as usize, 0);
+                LocalFileOps::read_exact(&mut reader, &mut data).unwrap();
+            }
+            assert_eq!(data.len(), 108890);
+            assert_eq!(crate::crc32_update(0, &data), 0xaed34b37);
+        }
+        #[cfg(not(feature = "inflate"))]
+        assert_eq!(
+            LocalFileOps::read(&mut reader, &mut [0u8; 8]),
+            Err(ParsingError::UnsupportedCompressMethod(CompressMethod::Deflated)),
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn entries_are_read_to_vectors() {
+        use crate::SeekingParser;
+
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let mut parser = SeekingParser::<_, 128>::new(&stream);
+        let mut readme = parser.next().unwrap().with_crc_check(true);
+        assert_eq!(readme.read_to_vec(358), Err(ParsingError::BufferTooSmall));
+        let data = readme.read_to_vec(359).unwrap();
+        assert_eq!(crate::crc32_update(0, &data), 0xa3d1389c);
+
+        let mut cargo = parser.next().unwrap().with_uncompressed_size(600).with_crc_check(false);
+        assert_eq!(cargo.read_to_vec(u64::MAX), Err(ParsingError::UncompressedSizeShort(534)));
+    }
+
+    #[test]
+    fn entries_are_read_into_many_buffers() {
+        use crate::SeekingParser;
+
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let mut parser = SeekingParser::<_, 128>::new(&stream);
+        let mut file = parser.next().unwrap().with_crc_check(true);
+        let (mut head, mut rest) = ([0u8; 2], vec![0u8; 357]);
+        let mut read: usize = 0;
+        while read < 359 {
+            let (a, b) = (read.min(2), read.saturating_sub(2));
+            let n = crate::Read::read_vectored(&mut file, &mut [&mut head[a..], &mut rest[b..]]).unwrap();
+            assert!(n > 0);
+            read += n;
+        }
+        assert_eq!(&head, b"# ");
+        assert!(rest.starts_with(b"zip_parse"));
+        // the CRC-32 went through both buffers
+        assert_eq!(crate::Read::read_vectored(&mut file, &mut [&mut head[..]]), Ok(0));
+
+        // more than the data left, only the first buffer is read into
+        let mut file = parser.next().unwrap();
+        let mut big = vec![0u8; 1000];
+        assert_eq!(crate::Read::read_vectored(&mut file, &mut [&mut [0u8; 0][..], &mut head[..], &mut big[..]]), Ok(2));
+        assert_eq!(&head, b"[p");
+
+        // a stream reading many buffers at once fills them all
+        #[cfg(feature = "std")]
+        {
+            let stream = RefCell::new(std::io::Cursor::new(MemStream::test_zip_without_comment().data));
+            let mut file = SeekingParser::<_, 128>::new(&stream).next().unwrap().with_crc_check(true);
+            assert_eq!(crate::Read::read_vectored(&mut file, &mut [&mut head[..], &mut rest[..]]), Ok(359));
+            assert_eq!(&head, b"# ");
+            assert_eq!(crate::Read::read_vectored(&mut file, &mut [&mut head[..]]), Ok(0));
+        }
+    }
+
+    #[test]
+    fn entries_are_copied_to_sinks() {
+        use crate::{SeekingParser, Write};
+
+        /// Takes at most 100 bytes per write, up to `limit`
+        struct Sink {
+            data: Vec<u8>,
+            limit: usize,
+        }
+
+        impl Write for Sink {
+            fn write(&mut self, buf: &[u8]) -> Result<usize, ParsingError> {
+                let len = buf.len().min(100).min(self.limit - self.data.len());
+                self.data.extend_from_slice(&buf[..len]);
+                Ok(len)
+            }
+        }
+
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let mut parser = SeekingParser::<_, 128>::new(&stream);
+        let mut sink = Sink { data: Vec::new(), limit: usize::MAX };
+        assert_eq!(parser.next().unwrap().with_crc_check(true).copy_to(&mut sink), Ok(359));
+        assert_eq!(crate::crc32_update(0, &sink.data), 0xa3d1389c);
 
-                                // header is ready
-                                self.state = ParserState::RecvLocalFileName;
+        let mut sink = Sink { data: Vec::new(), limit: 500 };
+        assert_eq!(parser.next().unwrap().copy_to(&mut sink), Err(ParsingError::BufferTooSmall));
+        assert_eq!(sink.data.len(), 500);
+    }
 
-                                self.file_name_index = 0;
-                                self.file_name_len = file_info.file_name_length as usize;
-                                self.extra_field_index = 0;
-                                self.extra_field_len = file_info.extra_field_length as usize;
-                                self.file_data_index = 0;
-                                self.file_data_len = file_info.compressed_size as usize;
+    #[test]
+    fn local_files_are_crate_readers() {
+        use crate::SeekingParser;
+
+        fn read_to_end<R: crate::Read>(reader: &mut R, data: &mut Vec<u8>) -> Result<usize, ParsingError> {
+            let mut buf = [0u8; 100];
+            loop {
+                match reader.read(&mut buf)? {
+                    0 => return Ok(data.len()),
+                    n => data.extend_from_slice(&buf[..n]),
+                }
+            }
+        }
 
-                                // The data size in buffer must equal to LOCAL_FILE_HEADER_LEN
-                                let localfile_info = LocalFileInfo::default()
-                                    .with_compression_method(CompressMethod::from(file_info.compression_method))
-                                    .with_compressed_size(file_info.compressed_size as u64)
-                                    .with_uncompressed_size(file_info.uncompressed_size as u64);
-                                self.localfile_info.replace(localfile_info);
-                            } else {
-                                // #[cfg(feature = "std")]
-                                // eprintln!("get LocalFileHeader from raw ptr({:02X?}) failed", self.buffer);
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let mut file = SeekingParser::<_, 128>::new(&stream).next().unwrap();
+        let mut data = Vec::new();
+        assert_eq!(read_to_end(&mut file, &mut data), Ok(359));
+        assert_eq!(crate::crc32_update(0, &data), 0xa3d1389c);
+        assert!(crate::Read::read_exact(&mut file, &mut [0u8; 1]).is_err());
+    }
 
-                                let err = ParsingError::InvalidLocalFileHeader;
-                                continue_parsing = on_event(ParserEvent::ParsingError(self.localfile_index, err));
-                            }
-                            // drop all data
-                            self.buffer.clear();
-                        }
-                        HeaderType::CentralFileHeader => {
-                            // parse
-                            if let Some(header) = unsafe { CentralFileHeader::from_bytes(&self.buffer) } {
-                                self.central_file_header_len = header.len();
-                                self.central_file_header_index = self.buffer_data_len();
-                            } else {
-                                let err = ParsingError::InvalidCentralFileHeader;
-                                continue_parsing = on_event(ParserEvent::ParsingError(self.localfile_index, err));
-                            }
-                            // drop all data
-                            self.buffer.clear();
+    #[cfg(feature = "std")]
+    #[test]
+    fn local_files_are_std_readers() {
+        use std::io::{self, BufRead};
 
-                            self.state = ParserState::RecvCentralFileHeader;
-                        }
-                        HeaderType::CentralDirEnd => {
-                            // parse
-                            if let Some(header) = unsafe { CentralDirEnd::from_bytes(&self.buffer) } {
-                                self.central_dir_end_len = header.len();
-                                self.central_dir_end_index = self.buffer_data_len();
-                            } else {
-                                let err = ParsingError::InvalidCentralDirEnd;
-                                continue_parsing = on_event(ParserEvent::ParsingError(self.localfile_index, err));
-                            }
-                            // drop all data
-                            self.buffer.clear();
+        use crate::SeekingParser;
 
-                            self.state = ParserState::RecvCentralDirEnd;
-                        }
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let mut parser = SeekingParser::<_, 128>::new(&stream);
+        let mut data = Vec::new();
+        assert_eq!(io::copy(&mut parser.next().unwrap(), &mut data).unwrap(), 359);
+        assert_eq!(crate::crc32_update(0, &data), 0xa3d1389c);
+
+        let mut lines = io::BufReader::new(parser.next().unwrap()).lines();
+        assert_eq!(lines.next().unwrap().unwrap(), "[package]");
+    }
+
+    #[test]
+    fn stored_entries_can_seek() {
+        use crate::{LocalFileOps, SeekingParser};
+
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let mut file = SeekingParser::<_, 128>::new(&stream).next().unwrap().with_crc_check(true);
+        let mut buf = [0u8; 9];
+        assert_eq!(Seek::seek(&mut file, SeekFrom::Start(2)), Ok(2));
+        assert_eq!(file.read_exact(&mut buf), Ok(9));
+        assert_eq!(&buf, b"zip_parse");
+        assert_eq!(Seek::seek(&mut file, SeekFrom::Current(-9)), Ok(2));
+        assert_eq!(Seek::seek(&mut file, SeekFrom::End(-1)), Ok(358));
+        // clamped to the data
+        assert_eq!(Seek::seek(&mut file, SeekFrom::End(10)), Ok(359));
+        assert_eq!(file.read(&mut buf), Ok(0));
+        assert_eq!(Seek::seek(&mut file, SeekFrom::Current(-1000)), Ok(0));
+        let mut data = vec![0u8; 359];
+        assert_eq!(file.read_exact(&mut data), Ok(359));
+        assert_eq!(&data[2..11], b"zip_parse");
+
+        let stream = RefCell::new(MemStream::new(std::fs::read("test_deflated.zip").unwrap()));
+        let mut file = SeekingParser::<_, 128>::new(&stream).nth(1).unwrap();
+        assert!(Seek::seek(&mut file, SeekFrom::Start(2)).is_err());
+    }
+
+    #[test]
+    fn stored_entries_are_read_at_offsets() {
+        use crate::{LocalFileOps, SeekingParser};
+
+        let stream = RefCell::new(MemStream::test_zip_without_comment());
+        let mut file = SeekingParser::<_, 128>::new(&stream).next().unwrap();
+        let (mut head, mut buf) = ([0u8; 2], [0u8; 9]);
+        assert_eq!(file.read_exact(&mut head), Ok(2));
+        assert_eq!(file.read_at(2, &mut buf), Ok(9));
+        assert_eq!(&buf, b"zip_parse");
+        assert_eq!(file.read_at(355, &mut buf), Ok(4));
+        assert_eq!(file.read_at(1000, &mut buf), Ok(0));
+        // the position of the entry is kept
+        assert_eq!(file.read_exact(&mut buf), Ok(9));
+        assert_eq!(&buf, b"zip_parse");
+
+        let stream = RefCell::new(MemStream::new(std::fs::read("test_deflated.zip").unwrap()));
+        let file = SeekingParser::<_, 128>::new(&stream).nth(1).unwrap();
+        assert_eq!(file.read_at(0, &mut buf), Err(ParsingError::InvalidStream));
+    }
+
+    #[test]
+    fn raw_reader_yields_compressed_data() {
+        use crate::{LocalFileOps, SeekingParser};
+
+        let archive = std::fs::read("test_deflated.zip").unwrap();
+        let stream = RefCell::new(MemStream::new(archive.clone()));
+        let mut file = SeekingParser::<_, 128>::new(&stream).nth(1).unwrap();
+        assert_eq!(file.info.compression_method, CompressMethod::Deflated);
+        let start = file.info.file_data_offset as usize;
+        let mut reader = file.raw_reader();
+        let mut data = vec![0u8; reader.file_size() as usize];
+        LocalFileOps::read_exact(&mut reader, &mut data).unwrap();
+        assert_eq!(LocalFileOps::read(&mut reader, &mut [0u8; 8]).unwrap(), 0);
+        assert_eq!(data, archive[start..start + data.len()]);
+        assert_eq!(data.len() as u64, file.info.compressed_size);
+    }
+
+    #[cfg(feature = "inflate")]
+    #[test]
+    fn inflate_mode_emits_decompressed_data() {
+        let data = std::fs::read("test_deflated.zip").unwrap();
+        let mut parser = PassiveParser::<128>::new().with_inflate(true).with_crc_check(true);
+        let mut files = vec![Vec::new(); 2];
+        let mut inflated = Vec::new();
+        let mut ends = 0;
+        for chunk in data.chunks(300) {
+            parser.feed_data(chunk, |evt| {
+                match evt {
+                    ParserEvent::LocalFileData { file_index, offset, data } => {
+                        let file = &mut files[file_index as usize];
+                        assert_eq!(offset, file.len());
+                        assert!(data.len() <= 512);
+                        file.extend_from_slice(data);
                     }
-                }
-                ParserState::RecvLocalFileName => {
-                    // if header is ready
-                    if self.localfile_info.is_none() {
-                        let err = ParsingError::LocalFileHeaderNotRecved(self.localfile_index);
-                        if on_event(ParserEvent::ParsingError(self.localfile_index, err)) == false {
-                            break Err(buffer_data.proccessed_data_len());
-                        }
+                    ParserEvent::LocalFileInflated(i, len) => {
+                        assert_eq!(ends, i);
+                        inflated.push(len);
+                    }
+                    ParserEvent::LocalFileEnd(i) => {
+                        assert_eq!(inflated.len() as i32, i + 1);
+                        ends += 1;
                     }
+                    ParserEvent::ParsingError(_, err) => panic!("{err}"),
+                    _ => {}
+                }
+                true
+            });
+            if matches!(parser.state, crate::ParserState::RecvLocalFileData) {
+                assert!(parser.checkpoint().is_none());
+                assert_eq!(parser.save_state(&mut [0u8; 512]), Err(ParsingError::InvalidSavedState));
+            }
+        }
+        assert_eq!(inflated, [359, 108890]);
+        assert_eq!(crate::crc32_update(0, &files[0]), 0xa3d1389c);
+        assert_eq!(crate::crc32_update(0, &files[1]), 0xaed34b37);
+    }
 
-                    // check file name len
-                    if self.file_name_len > N {
-                        let err = ParsingError::LocalFileNameTooLong(self.localfile_index, self.file_name_len as usize);
-                        if on_event(ParserEvent::ParsingError(self.localfile_index, err)) == false {
-                            break Err(buffer_data.proccessed_data_len());
-                        }
+    #[cfg(feature = "inflate")]
+    #[test]
+    fn inflate_mode_ends_descriptor_entries_and_checks_sizes() {
+        /// data of each file, inflated lengths and errors
+        type Parsed = (Vec<Vec<u8>>, Vec<(i32, u64)>, Vec<(i32, ParsingError)>);
+
+        fn parse(data: &[u8], chunk: usize) -> Parsed {
+            let mut parser = PassiveParser::<128>::new().with_inflate(true).with_crc_check(true);
+            let (mut files, mut inflated, mut errors) = (vec![Vec::new(); 3], Vec::new(), Vec::new());
+            for input in data.chunks(chunk) {
+                parser.feed_data(input, |evt| {
+                    match evt {
+                        ParserEvent::LocalFileData { file_index, data, .. } => files[file_index as usize].extend_from_slice(data),
+                        ParserEvent::LocalFileInflated(i, len) => inflated.push((i, len)),
+                        ParserEvent::ParsingError(i, err) => errors.push((i, err)),
+                        _ => {}
                     }
+                    true
+                });
+            }
+            (files, inflated, errors)
+        }
 
-                    // save filename
-                    if self.file_name_index >= self.file_name_len {
-                        self.localfile_info.as_mut().unwrap().file_name_length = self.file_name_len;
+        // deflated entries followed by a data descriptor, and a stored one
+        let data = std::fs::read("test_descriptor.zip").unwrap();
+        for chunk in [97, data.len()] {
+            let (files, inflated, errors) = parse(&data, chunk);
+            assert_eq!(errors, []);
+            assert_eq!(inflated, [(0, 359), (2, 108890)]);
+            assert_eq!(crate::crc32_update(0, &files[0]), 0xa3d1389c);
+            assert_eq!(crate::crc32_update(0, &files[1]), 0x8d24fb90);
+            assert_eq!(crate::crc32_update(0, &files[2]), 0xaed34b37);
+        }
 
-                        self.state = ParserState::RecvLocalFileExtraField;
-                    } else {
-                        let len = cmp::min(
-                            self.file_name_len - self.file_name_index,
-                            buffer_data.unproccessed_data_len(),
-                        );
-                        self.localfile_info.as_mut().unwrap()
-                            .file_name_buffer[self.file_name_index..self.file_name_index + len]
-                            .as_mut()
-                            .copy_from_slice(buffer_data.peek_data(len));
-                        self.file_name_index += len;
+        // uncompressed size of README.md in its local header
+        let mut data = std::fs::read("test_deflated.zip").unwrap();
+        data[22..26].copy_from_slice(&300u32.to_le_bytes());
+        let (files, inflated, errors) = parse(&data, 300);
+        assert_eq!(errors, [(0, ParsingError::UncompressedSizeExceeded(300))]);
+        assert_eq!(files[0].len(), 300);
+        assert_eq!(inflated, [(1, 108890)]);
+        data[22..26].copy_from_slice(&400u32.to_le_bytes());
+        let (_, inflated, errors) = parse(&data, 300);
+        assert_eq!(errors, [(0, ParsingError::UncompressedSizeShort(359))]);
+        assert_eq!(inflated, [(1, 108890)]);
+
+        // a deflate block of the reserved type, no success follows the error
+        let mut data = std::fs::read("test_deflated.zip").unwrap();
+        let data_start = 30 + u16::from_le_bytes([data[26], data[27]]) as usize + u16::from_le_bytes([data[28], data[29]]) as usize;
+        data[data_start] = 0xff;
+        for chunk in [1, 300] {
+            let (_, inflated, errors) = parse(&data, chunk);
+            assert_eq!(errors, [(0, ParsingError::DecompressionFailed)]);
+            assert_eq!(inflated, [(1, 108890)]);
+        }
+    }
 
-                        // count processed data
-                        buffer_data.proccessed(len);
-                    }
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_entries_are_decompressed() {
+        use crate::{LocalFileOps, SeekingParser};
+
+        // lorem.txt spans several blocks, decompressed through a buffer smaller than the input chunks
+        let stream = RefCell::new(MemStream::new(std::fs::read("test_zstd.zip").unwrap()));
+        let mut files = Vec::new();
+        for mut file in SeekingParser::<_, 128>::new(&stream) {
+            assert_eq!(file.info.compression_method, CompressMethod::Zstd);
+            let mut data = Vec::new();
+            let mut buf = [0u8; 300];
+            loop {
+                match file.read(&mut buf).unwrap() {
+                    0 => break,
+                    n => data.extend_from_slice(&buf[..n]),
                 }
-                ParserState::RecvLocalFileExtraField => {
-                    if self.extra_field_index >= self.extra_field_len {
-                        continue_parsing = on_event(ParserEvent::LocalFileHeader(self.localfile_index, self.localfile_info.as_ref().unwrap()));
+            }
+            assert_eq!(data.len() as u64, file.file_size());
+            files.push((file.file_name().unwrap().to_string(), crate::crc32_update(0, &data)));
+        }
+        assert_eq!(files, [("README.md".to_string(), 0xa3d1389c), ("lorem.txt".to_string(), 0xb2b486b4)]);
+    }
 
-                        self.state = ParserState::RecvLocalFileData;
-                    } else {
-                        // fake save
-                        let len = cmp::min(
-                            self.extra_field_len - self.extra_field_index,
-                            buffer_data.unproccessed_data_len(),
-                        );
-                        self.extra_field_index += len;
+    #[cfg(feature = "lzma")]
+    #[test]
+    fn lzma_entries_are_decompressed() {
+        use crate::{LocalFileOps, SeekingParser};
+
+        let stream = RefCell::new(MemStream::new(std::fs::read("test_lzma.zip").unwrap()));
+        let mut files = Vec::new();
+        for mut file in SeekingParser::<_, 128>::new(&stream) {
+            assert_eq!(file.info.compression_method, CompressMethod::LZMA);
+            let mut data = Vec::new();
+            let mut buf = [0u8; 1000];
+            loop {
+                match file.read(&mut buf).unwrap() {
+                    0 => break,
+                    n => data.extend_from_slice(&buf[..n]),
+                }
+            }
+            assert_eq!(data.len() as u64, file.file_size());
+            files.push((file.file_name().unwrap().to_string(), crate::crc32_update(0, &data)));
+        }
+        assert_eq!(files, [("README.md".to_string(), 0xa3d1389c), ("lorem.txt".to_string(), 0xaed34b37)]);
+    }
 
-                        // count processed data
-                        buffer_data.proccessed(len);
-                    }
+    #[cfg(feature = "deflate64")]
+    #[test]
+    fn deflate64_entries_are_decompressed() {
+        use crate::{LocalFileOps, SeekingParser};
+
+        let stream = RefCell::new(MemStream::new(std::fs::read("test_deflate64.zip").unwrap()));
+        let mut files = Vec::new();
+        for mut file in SeekingParser::<_, 128>::new(&stream) {
+            assert_eq!(file.info.compression_method, CompressMethod::Deflate64);
+            let mut data = Vec::new();
+            let mut buf = [0u8; 1000];
+            loop {
+                match file.read(&mut buf).unwrap() {
+                    0 => break,
+                    n => data.extend_from_slice(&buf[..n]),
                 }
-                ParserState::RecvLocalFileData => {
-                    if self.file_data_index >= self.file_data_len {
-                        continue_parsing = on_event(ParserEvent::LocalFileEnd(self.localfile_index));
+            }
+            assert_eq!(data.len() as u64, file.file_size());
+            files.push((file.file_name().unwrap().to_string(), crate::crc32_update(0, &data)));
+        }
+        assert_eq!(files, [("README.md".to_string(), 0xa3d1389c), ("lorem.txt".to_string(), 0xaed34b37)]);
+    }
 
-                        self.localfile_index += 1;
-                        self.state = ParserState::RecvHeader(HeaderType::HeaderSignature, 4);
-                    } else {
-                        // process
-                        let len = cmp::min(
-                            self.file_data_len - self.file_data_index,
-                            buffer_data.unproccessed_data_len(),
-                        );
-                        continue_parsing = on_event(
-                            ParserEvent::LocalFileData{
-                                file_index: self.localfile_index,
-                                offset: self.file_data_index,
-                                data: buffer_data.peek_data(len),
-                            }
-                        );
-                        self.file_data_index += len;
+    #[cfg(feature = "xz")]
+    #[test]
+    fn xz_entries_are_decompressed() {
+        use crate::{DecompressingHandler, Decompressors, XzDecoder};
+
+        let data = std::fs::read("test_xz.zip").unwrap();
+        let mut xz = XzDecoder::new();
+        let mut decompressors = Decompressors::<1>::new();
+        decompressors.register(CompressMethod::XZ, &mut xz).unwrap();
+        let mut handler = DecompressingHandler::<1, 256>::new(decompressors);
+        let mut parser = PassiveParser::<128>::new();
+        let mut files = vec![Vec::new(); 2];
+        for chunk in data.chunks(100) {
+            parser.feed_data(chunk, |evt| handler.handle(evt, |evt| {
+                if let ParserEvent::LocalFileData { file_index, data, .. } = evt {
+                    files[file_index as usize].extend_from_slice(data);
+                }
+                true
+            }));
+        }
+        let crcs: Vec<_> = files.iter().map(|file| crate::crc32_update(0, file)).collect();
+        assert_eq!(crcs, [0xa3d1389c, 0xaed34b37]);
+
+        {
+            use crate::{LocalFileOps, SeekingParser};
+
+            let stream = RefCell::new(MemStream::new(data));
+            let mut file = SeekingParser::<_, 128>::new(&stream).nth(1).unwrap();
+            assert_eq!(file.info.compression_method, CompressMethod::XZ);
+            let mut content = vec![0u8; file.file_size() as usize];
+            file.read_exact(&mut content).unwrap();
+            assert_eq!(file.read(&mut [0u8; 16]).unwrap(), 0);
+            assert_eq!(crate::crc32_update(0, &content), 0xaed34b37);
+        }
+    }
 
-                        // count processed data
-                        buffer_data.proccessed(len);
+    #[cfg(feature = "bzip2")]
+    #[test]
+    fn bzip2_entries_are_decompressed() {
+        use crate::{Bzip2Decoder, DecompressingHandler, Decompressors};
+
+        let data = std::fs::read("test_bzip2.zip").unwrap();
+        let expected = [0xa3d1389c, 0xaed34b37];
+
+        let mut bzip2 = Bzip2Decoder::new();
+        let mut decompressors = Decompressors::<1>::new();
+        decompressors.register(CompressMethod::BZIP2, &mut bzip2).unwrap();
+        let mut handler = DecompressingHandler::<1, 256>::new(decompressors);
+        let mut parser = PassiveParser::<128>::new();
+        let mut files = vec![Vec::new(); 2];
+        for chunk in data.chunks(100) {
+            parser.feed_data(chunk, |evt| handler.handle(evt, |evt| {
+                match evt {
+                    ParserEvent::LocalFileData { file_index, offset, data } => {
+                        let file = &mut files[file_index as usize];
+                        assert_eq!(offset, file.len());
+                        file.extend_from_slice(data);
                     }
+                    ParserEvent::ParsingError(_, err) => panic!("{err}"),
+                    _ => {}
                 }
-                ParserState::RecvCentralFileHeader => {
-                    if self.central_file_header_index >= self.central_file_header_len {
-                        self.centralfile_index += 1;
-                        self.central_file_header_index = 0;
-                        self.central_file_header_len = 0;
-                        self.state = ParserState::RecvHeader(HeaderType::HeaderSignature, 4);
-                    } else {
-                        let len = cmp::min(
-                            self.central_file_header_len - self.central_file_header_index,
-                            buffer_data.unproccessed_data_len(),
-                        );
-                        self.central_file_header_index += len;
-                        buffer_data.proccessed(len);
+                true
+            }));
+        }
+        let crcs: Vec<_> = files.iter().map(|file| crate::crc32_update(0, file)).collect();
+        assert_eq!(crcs, expected);
+
+        {
+            use crate::{LocalFileOps, SeekingParser};
+
+            let stream = RefCell::new(MemStream::new(data));
+            let mut crcs = Vec::new();
+            for mut file in SeekingParser::<_, 128>::new(&stream) {
+                assert_eq!(file.info.compression_method, CompressMethod::BZIP2);
+                let mut content = Vec::new();
+                let mut buf = [0u8; 1000];
+                loop {
+                    match file.read(&mut buf).unwrap() {
+                        0 => break,
+                        n => content.extend_from_slice(&buf[..n]),
                     }
                 }
-                ParserState::RecvCentralDirEnd => {
-                    if self.central_dir_end_index >= self.central_dir_end_len {
-                        self.central_dir_end_index = 0;
-                        self.central_dir_end_len = 0;
-                        self.state = ParserState::RecvHeader(HeaderType::HeaderSignature, 4);
-                    } else {
-                        let len = cmp::min(
-                            self.central_dir_end_len - self.central_dir_end_index,
-                            buffer_data.unproccessed_data_len(),
-                        );
+                assert_eq!(content.len() as u64, file.file_size());
+                crcs.push(crate::crc32_update(0, &content));
+            }
+            assert_eq!(crcs, expected);
+        }
+    }
 
-                        #[cfg(feature = "std")]
-                        self.zip_file_comment.extend(buffer_data.peek_data(len));
+    /// Test decompressor of `Shrunk` entries, upper-casing the data
+    #[derive(Default)]
+    struct UpperCase {
+        inits: usize,
+    }
 
-                        self.central_dir_end_index += len;
-                        buffer_data.proccessed(len);
+    impl crate::Decompressor for UpperCase {
+        fn init(&mut self, method: CompressMethod) -> Result<(), ParsingError> {
+            assert_eq!(method, CompressMethod::Shrunk);
+            self.inits += 1;
+            Ok(())
+        }
+
+        fn feed(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), ParsingError> {
+            let len = input.len().min(output.len());
+            for (o, i) in output.iter_mut().zip(&input[..len]) {
+                *o = i.to_ascii_uppercase();
+            }
+            Ok((len, len))
+        }
+
+        fn finish(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), ParsingError> {
+            self.feed(input, output)
+        }
+    }
+
+    /// test.zip without comment, README.md claiming to be shrunk
+    fn shrunk_readme_zip() -> Vec<u8> {
+        let mut data = MemStream::test_zip_without_comment().data;
+        data[8] = CompressMethod::Shrunk as u8;
+        let central_start = data.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+        data[central_start + 10] = CompressMethod::Shrunk as u8;
+        data
+    }
+
+    #[test]
+    fn decompressors_are_routed_by_method() {
+        use crate::{DecompressingHandler, Decompressors};
+
+        let data = shrunk_readme_zip();
+        let readme = &std::fs::read("test.zip").unwrap()[30 + 9 + 28..][..359];
+        let expected = readme.to_ascii_uppercase();
+
+        let mut upper = UpperCase::default();
+        let mut decompressors = Decompressors::<2>::new();
+        decompressors.register(CompressMethod::Shrunk, &mut upper).unwrap();
+        let mut handler = DecompressingHandler::<2, 64>::new(decompressors);
+        let mut parser = PassiveParser::<128>::new();
+        let mut files = vec![Vec::new(); 3];
+        for chunk in data.chunks(100) {
+            parser.feed_data(chunk, |evt| handler.handle(evt, |evt| {
+                if let ParserEvent::LocalFileData { file_index, offset, data } = evt {
+                    let file = &mut files[file_index as usize];
+                    assert_eq!(offset, file.len());
+                    assert!(data.len() <= 64 || file_index != 0);
+                    file.extend_from_slice(data);
+                }
+                true
+            }));
+        }
+        assert_eq!(files[0], expected);
+        assert_eq!(crate::crc32_update(0, &files[2]), 0x08ec4d9f);
+        drop(handler);
+        assert_eq!(upper.inits, 1);
+
+        {
+            use crate::SeekingParser;
+
+            let mut upper = UpperCase::default();
+            let mut decompressors = Decompressors::<2>::new();
+            decompressors.register(CompressMethod::Shrunk, &mut upper).unwrap();
+            let stream = RefCell::new(MemStream::new(data));
+            let mut files = Vec::new();
+            for file in SeekingParser::<_, 128>::new(&stream) {
+                // the upper-cased data doesn't match the CRC-32
+                let mut file = file.with_crc_check(false);
+                let mut content = Vec::new();
+                let mut buf = [0u8; 100];
+                loop {
+                    match file.read_using(&mut decompressors, &mut buf).unwrap() {
+                        0 => break,
+                        n => content.extend_from_slice(&buf[..n]),
                     }
                 }
+                files.push(content);
             }
-            if continue_parsing == false {
-                break Err(buffer_data.proccessed_data_len());
+            assert_eq!(files[0], expected);
+            assert_eq!(files[1].len(), 534);
+        }
+    }
+
+    #[test]
+    fn decompressed_output_is_bounded_by_the_declared_size() {
+        use crate::{DecompressingHandler, Decompressors};
+
+        let mut data = shrunk_readme_zip();
+        // uncompressed size of README.md in its local header
+        data[22..26].copy_from_slice(&100u32.to_le_bytes());
+        let mut upper = UpperCase::default();
+        let mut decompressors = Decompressors::<1>::new();
+        decompressors.register(CompressMethod::Shrunk, &mut upper).unwrap();
+        let mut handler = DecompressingHandler::<1, 64>::new(decompressors);
+        let (mut output, mut errors) = (0, Vec::new());
+        PassiveParser::<128>::new().feed_data(&data, |evt| handler.handle(evt, |evt| {
+            match evt {
+                ParserEvent::LocalFileData { file_index: 0, data, .. } => output += data.len(),
+                ParserEvent::ParsingError(i, err) => errors.push((i, err)),
+                _ => {}
             }
-        };
+            true
+        }));
+        assert_eq!(errors, [(0, ParsingError::UncompressedSizeExceeded(100))]);
+        assert!(output <= 100);
+    }
 
-        // report consumed len
-        if let Err(n) = res {
-            on_event(ParserEvent::UserCancel(-1, n));
+    #[test]
+    fn page_aligner_delivers_whole_pages() {
+        use crate::PageAligner;
+
+        let data = std::fs::read("test.zip").unwrap();
+        let mut parser = PassiveParser::<128>::new();
+        let mut pages = PageAligner::<4096>::new();
+        let mut lib_rs = Vec::new();
+        let mut short_events = 0;
+        for chunk in data.chunks(1000) {
+            parser.feed_data(chunk, |evt| pages.handle(evt, |evt| {
+                if let ParserEvent::LocalFileData { file_index: 2, offset, data } = evt {
+                    assert_eq!(offset, lib_rs.len());
+                    assert_eq!(offset % 4096, 0);
+                    if data.len() % 4096 != 0 {
+                        short_events += 1;
+                    }
+                    lib_rs.extend_from_slice(data);
+                }
+                true
+            }));
         }
+        assert_eq!(lib_rs.len(), 40643);
+        assert_eq!(short_events, 1);
+        assert_eq!(crate::crc32_update(0, &lib_rs), 0x08ec4d9f);
     }
-}
-
-impl<const N: usize> Default for PassiveParser<N> {
-    fn default() -> Self {
-        Self {
-            state: ParserState::RecvHeader(HeaderType::HeaderSignature, 4),
 
-            #[cfg(feature = "std")]
-            zip_file_comment: Vec::new(),
+    #[test]
+    fn records_are_decoded_from_any_bytes() {
+        use crate::{CentralDirEnd, CentralFileHeader, CENTRAL_DIR_END_LEN, CENTRAL_FILE_HEADER_LEN, LOCAL_FILE_HEADER_LEN};
+
+        let data = std::fs::read("test.zip").unwrap();
+        // unaligned, as the records are in the stream
+        let mut shifted = vec![0u8];
+        shifted.extend_from_slice(&data[..LOCAL_FILE_HEADER_LEN]);
+        let header = LocalFileHeader::from_bytes(&shifted[1..]).unwrap();
+        assert_eq!((header.crc32, header.compressed_size, header.file_name_length), (0xa3d1389c, 359, 9));
+
+        // other signatures and short records are rejected rather than taken for the record
+        assert!(CentralFileHeader::from_bytes(&data[..CENTRAL_FILE_HEADER_LEN]).is_none());
+        assert!(CentralDirEnd::from_bytes(&data[..CENTRAL_DIR_END_LEN]).is_none());
+        assert!(LocalFileHeader::from_bytes(&data[..LOCAL_FILE_HEADER_LEN - 1]).is_none());
+        let mut corrupted = data[..LOCAL_FILE_HEADER_LEN].to_vec();
+        corrupted[3] = 0xff;
+        assert!(LocalFileHeader::from_bytes(&corrupted).is_none());
+        let data = MemStream::test_zip_without_comment().data;
+        let end = &data[data.len() - CENTRAL_DIR_END_LEN..];
+        assert_eq!(CentralDirEnd::from_bytes(end).map(|end| end.total_entries_all_disk), Some(3));
+    }
 
-            buffer: Default::default(),
+    #[test]
+    fn record_accessors_check_bounds() {
+        let data = std::fs::read("test.zip").unwrap();
+        let header = LocalFileHeader::from_bytes(&data).unwrap();
+        let record = &data[..header.len()];
+        assert_eq!(header.file_name(record), Ok(&b"README.md"[..]));
+        assert_eq!(header.extra_field(record).map(|f| f.len()), Ok(28));
+
+        let truncated = &record[..record.len() - 1];
+        assert_eq!(header.file_name(truncated), Ok(&b"README.md"[..]));
+        assert_eq!(header.extra_field(truncated), Err(ParsingError::DataNotEnough));
+    }
 
-            localfile_info: None,
-            localfile_index: 0,
-            centralfile_index: 0,
+    #[cfg(feature = "embedded-storage")]
+    #[test]
+    fn flash_writer_erases_and_aligns() {
+        use crate::FlashWriter;
+        use embedded_storage::nor_flash::{ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash};
 
-            file_name_index: 0,
-            file_name_len: 0,
+        struct MockFlash {
+            memory: Vec<u8>,
+            erased: Vec<bool>,
+        }
 
-            extra_field_index: 0,
-            extra_field_len: 0,
+        impl ErrorType for MockFlash {
+            type Error = NorFlashErrorKind;
+        }
 
-            file_data_index: 0,
-            file_data_len: 0,
+        impl ReadNorFlash for MockFlash {
+            const READ_SIZE: usize = 1;
 
-            central_file_header_index: 0,
-            central_file_header_len: 0,
+            fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+                let offset = offset as usize;
+                bytes.copy_from_slice(&self.memory[offset..offset + bytes.len()]);
+                Ok(())
+            }
 
-            central_dir_end_index: 0,
-            central_dir_end_len: 0,
+            fn capacity(&self) -> usize {
+                self.memory.len()
+            }
         }
-    }
-}
 
-/// Prelude of zip_parser
-pub mod prelude {
-    pub use crate::{
-        LocalFile, LocalFileOps,
-        Parser, ParsingError, ParserEvent,
-        /*SequentialParser,*/ SeekingParser, PassiveParser,
-    };
-}
+        impl NorFlash for MockFlash {
+            const WRITE_SIZE: usize = 8;
+            const ERASE_SIZE: usize = 4096;
 
-#[cfg(test)]
-mod tests {
-    use std::fs::File;
-    use std::io::prelude::*;
+            fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+                let (from, to) = (from as usize, to as usize);
+                if from % Self::ERASE_SIZE != 0 || to % Self::ERASE_SIZE != 0 {
+                    return Err(NorFlashErrorKind::NotAligned);
+                }
+                if to > self.memory.len() {
+                    return Err(NorFlashErrorKind::OutOfBounds);
+                }
+                self.memory[from..to].fill(0xFF);
+                self.erased[from..to].fill(true);
+                Ok(())
+            }
 
-    use crate::{CentralDirEnd, CentralFileHeader, LocalFileHeader, Signature};
+            fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+                let offset = offset as usize;
+                if offset % Self::WRITE_SIZE != 0 || bytes.len() % Self::WRITE_SIZE != 0 {
+                    return Err(NorFlashErrorKind::NotAligned);
+                }
+                for (i, &b) in bytes.iter().enumerate() {
+                    assert!(self.erased[offset + i], "write to unerased flash");
+                    self.erased[offset + i] = false;
+                    self.memory[offset + i] = b;
+                }
+                Ok(())
+            }
+        }
 
-    #[test]
-    fn parse_file_list() {
+        let data = std::fs::read("test.zip").unwrap();
+        let mut flash = MockFlash { memory: vec![0; 64 * 1024], erased: vec![false; 64 * 1024] };
+        let mut writer = Some(FlashWriter::<_, 64>::new(&mut flash, 8192).unwrap());
+        let mut parser = PassiveParser::<128>::new();
+        let mut written = None;
+        for chunk in data.chunks(333) {
+            parser.feed_data(chunk, |evt| {
+                match evt {
+                    ParserEvent::LocalFileData { file_index: 2, data, .. } => {
+                        writer.as_mut().unwrap().write(data).unwrap();
+                    }
+                    ParserEvent::LocalFileEnd(2) => {
+                        written = Some(writer.take().unwrap().finish().unwrap());
+                    }
+                    _ => {}
+                }
+                true
+            });
+        }
+        assert_eq!(written, Some(40643));
+        let copy = &flash.memory[8192..8192 + 40643];
+        assert_eq!(crate::crc32_update(0, copy), 0x08ec4d9f);
+        // padding of the last write unit, then untouched erased flash up to the erase block end
+        assert!(flash.memory[8192 + 40643..8192 + 40960].iter().all(|&b| b == 0xFF));
+        assert!(!flash.erased[..8192].iter().any(|&e| e));
+        assert!(!flash.erased[8192 + 40960..].iter().any(|&e| e));
+
+        let mut flash = MockFlash { memory: vec![0; 8192], erased: vec![false; 8192] };
+        assert!(matches!(
+            FlashWriter::<_, 64>::new(&mut flash, 100),
+            Err(ParsingError::Flash(NorFlashErrorKind::NotAligned))
+        ));
     }
 }
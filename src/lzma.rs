@@ -0,0 +1,129 @@
+//! LZMA (method 14) decompression backed by `lzma-rs`
+
+use core::{cmp, fmt};
+use std::io::Write;
+use std::vec::Vec;
+
+use lzma_rs::decompress::{Options, Stream, UnpackedSize};
+
+use crate::{CompressMethod, Decompressor, ParsingError};
+
+/// Zip specific header: LZMA SDK version (2 bytes), then the length of the properties (2 bytes)
+const ZIP_HEADER_LEN: usize = 4;
+/// The properties (lc/lp/pb, dictionary size) follow the header, as the `.lzma` header does
+/// but without the uncompressed size
+const PROPERTIES_LEN: u16 = 5;
+
+const OPTIONS: Options = Options {
+    unpacked_size: UnpackedSize::UseProvided(None),
+    memlimit: None,
+    allow_incomplete: false,
+};
+
+/// Streaming LZMA decompressor of zip entries.
+///
+/// The decompressed data only comes out once the dictionary is full or the stream is finished,
+/// so it is buffered in memory, up to the dictionary size.
+pub struct LzmaDecoder {
+    header: [u8; ZIP_HEADER_LEN],
+    header_len: usize,
+    /// `None` once finished, the output is then in `output`
+    stream: Option<Stream<Vec<u8>>>,
+    output: Vec<u8>,
+    /// bytes of the output handed out
+    position: usize,
+}
+
+impl LzmaDecoder {
+    pub fn new() -> Self {
+        Self {
+            header: [0; ZIP_HEADER_LEN],
+            header_len: 0,
+            stream: Some(Stream::new_with_options(&OPTIONS, Vec::new())),
+            output: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Get ready for another LZMA stream
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Decompress `input` into `output`, returns `(consumed, produced)`.
+    ///
+    /// Decompressed data not fitting in `output` is kept for the next call, which may pass an
+    /// empty `input`. `last` tells that `input` ends the compressed data.
+    pub fn decompress(&mut self, input: &[u8], output: &mut [u8], last: bool) -> Result<(usize, usize), ParsingError> {
+        let mut consumed = 0;
+        if self.header_len < ZIP_HEADER_LEN {
+            consumed = cmp::min(ZIP_HEADER_LEN - self.header_len, input.len());
+            self.header[self.header_len..self.header_len + consumed].copy_from_slice(&input[..consumed]);
+            self.header_len += consumed;
+            if self.header_len == ZIP_HEADER_LEN && u16::from_le_bytes([self.header[2], self.header[3]]) != PROPERTIES_LEN {
+                return Err(ParsingError::DecompressionFailed);
+            }
+        }
+
+        if let Some(stream) = &mut self.stream {
+            if consumed < input.len() {
+                stream.write_all(&input[consumed..]).map_err(|_| ParsingError::DecompressionFailed)?;
+                consumed = input.len();
+            }
+        }
+        if last && self.stream.is_some() {
+            if self.header_len < ZIP_HEADER_LEN {
+                return Err(ParsingError::DecompressionFailed);
+            }
+            let stream = self.stream.take().ok_or(ParsingError::DecompressionFailed)?;
+            self.output = stream.finish().map_err(|_| ParsingError::DecompressionFailed)?;
+        }
+
+        // hand out the decompressed data
+        let pending = match &mut self.stream {
+            Some(stream) => stream.get_output_mut().ok_or(ParsingError::DecompressionFailed)?,
+            None => &mut self.output,
+        };
+        let produced = cmp::min(output.len(), pending.len() - self.position);
+        output[..produced].copy_from_slice(&pending[self.position..self.position + produced]);
+        self.position += produced;
+        if self.position == pending.len() {
+            pending.clear();
+            self.position = 0;
+        }
+        Ok((consumed, produced))
+    }
+}
+
+impl Default for LzmaDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for LzmaDecoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LzmaDecoder")
+            .field("finished", &self.stream.is_none())
+            .field("position", &self.position)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Decompressor for LzmaDecoder {
+    fn init(&mut self, method: CompressMethod) -> Result<(), ParsingError> {
+        if method != CompressMethod::LZMA {
+            return Err(ParsingError::UnsupportedCompressMethod(method));
+        }
+        self.reset();
+        Ok(())
+    }
+
+    fn feed(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), ParsingError> {
+        self.decompress(input, output, false)
+    }
+
+    fn finish(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), ParsingError> {
+        self.decompress(input, output, true)
+    }
+}
@@ -0,0 +1,62 @@
+//! Detecting entries sharing stream bytes, as crafted by zip bombs and quines
+
+use core::ops::Range;
+
+use crate::ParsingError;
+use crate::{LocalFile, Read, Seek};
+
+/// Stream ranges claimed by the entries of an archive, holding up to `M` of them.
+///
+/// Regular archives never have two entries sharing bytes, overlapping ones make a small
+/// archive expand to many copies of the same data.
+/// ```
+/// use zip_parser::{ClaimedRanges, ParsingError};
+///
+/// let mut claimed = ClaimedRanges::<8>::new();
+/// claimed.claim(0, 0..100).unwrap();
+/// claimed.claim(1, 100..200).unwrap();
+/// assert_eq!(claimed.claim(2, 50..60), Err(ParsingError::OverlappingEntries(0, 2)));
+/// ```
+pub struct ClaimedRanges<const M: usize = 64> {
+    ranges: heapless::Vec<(Range<u64>, usize), M>,
+}
+
+impl<const M: usize> ClaimedRanges<M> {
+    pub fn new() -> Self {
+        Self { ranges: heapless::Vec::new() }
+    }
+
+    /// Claim `range` for the entry `file_index`.
+    ///
+    /// Fails with [`ParsingError::OverlappingEntries`] if another entry claimed some of it, or
+    /// with [`ParsingError::BufferTooSmall`] when `M` ranges are already claimed.
+    pub fn claim(&mut self, file_index: usize, range: Range<u64>) -> Result<(), ParsingError> {
+        if let Some((_, other)) = self
+            .ranges
+            .iter()
+            .find(|(claimed, _)| claimed.start < range.end && range.start < claimed.end)
+        {
+            return Err(ParsingError::OverlappingEntries(*other, file_index));
+        }
+        self.ranges
+            .push((range, file_index))
+            .map_err(|_| ParsingError::BufferTooSmall)
+    }
+
+    /// Claim the local file header and the data of `file`, see [`ClaimedRanges::claim`]
+    pub fn claim_entry<S: Read + Seek, const N: usize>(&mut self, file_index: usize, file: &LocalFile<'_, S, N>) -> Result<(), ParsingError> {
+        let data_end = file.info.file_data_offset + file.info.compressed_size;
+        self.claim(file_index, file.metadata.local_header_offset..data_end)
+    }
+
+    /// Forget all the claimed ranges, e.g. for the next archive
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+    }
+}
+
+impl<const M: usize> Default for ClaimedRanges<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
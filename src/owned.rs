@@ -0,0 +1,147 @@
+//! Seeking parser owning its stream, to be stored or returned along with it
+
+use core::cell::RefCell;
+
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+use crate::{CentralDirEnd, ErrorContext, LocalFile, Parser, ParsingError, Read, Seek, SeekingParser};
+
+/// A [`SeekingParser`] owning its stream, see [`SeekingParser::take`].
+///
+/// The entries borrow the parser, which can be kept in a struct or returned from a function
+/// unlike a parser borrowing the stream.
+pub struct OwnedSeekingParser<S: Read + Seek, const N: usize = 128> {
+    stream: RefCell<S>,
+    state: State<N>,
+}
+
+/// Everything of a [`SeekingParser`] but its stream
+struct State<const N: usize> {
+    number_of_files: Option<u64>,
+    central_directory_offset: u64,
+    next_entry_offset: u64,
+    central_dir_end: Option<CentralDirEnd>,
+    central_dir_end_offset: u64,
+    central_dir_encrypted: bool,
+    strict: bool,
+    file_index: i32,
+    last_error: Option<ErrorContext<N>>,
+    #[cfg(feature = "std")]
+    record_offsets: Vec<u64>,
+}
+
+impl<'a, S: Read + Seek, const N: usize> SeekingParser<'a, S, N> {
+    /// Parse the archive of `stream`, owned by the parser
+    pub fn take(stream: S) -> OwnedSeekingParser<S, N> {
+        OwnedSeekingParser::new(stream)
+    }
+
+    fn into_state(self) -> State<N> {
+        State {
+            number_of_files: self.number_of_files,
+            central_directory_offset: self.central_directory_offset,
+            next_entry_offset: self.next_entry_offset,
+            central_dir_end: self.central_dir_end,
+            central_dir_end_offset: self.central_dir_end_offset,
+            central_dir_encrypted: self.central_dir_encrypted,
+            strict: self.strict,
+            file_index: self.file_index,
+            last_error: self.last_error,
+            #[cfg(feature = "std")]
+            record_offsets: self.record_offsets,
+        }
+    }
+
+    fn from_state(stream: &'a RefCell<S>, state: State<N>) -> Self {
+        Self {
+            number_of_files: state.number_of_files,
+            central_directory_offset: state.central_directory_offset,
+            next_entry_offset: state.next_entry_offset,
+            central_dir_end: state.central_dir_end,
+            central_dir_end_offset: state.central_dir_end_offset,
+            central_dir_encrypted: state.central_dir_encrypted,
+            strict: state.strict,
+            file_index: state.file_index,
+            last_error: state.last_error,
+            #[cfg(feature = "std")]
+            record_offsets: state.record_offsets,
+            stream,
+        }
+    }
+}
+
+impl<S: Read + Seek, const N: usize> OwnedSeekingParser<S, N> {
+    /// Parse the archive of `stream`, see [`SeekingParser::new`]
+    pub fn new(stream: S) -> Self {
+        let stream = RefCell::new(stream);
+        let state = SeekingParser::new(&stream).into_state();
+        Self { stream, state }
+    }
+
+    /// See [`SeekingParser::with_strict`]
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.state.strict = strict;
+        self
+    }
+
+    /// Get the stream back
+    pub fn into_inner(self) -> S {
+        self.stream.into_inner()
+    }
+
+    /// See [`SeekingParser::last_error`]
+    pub fn last_error(&self) -> Option<&ErrorContext<N>> {
+        self.state.last_error.as_ref()
+    }
+
+    /// A parser borrowing the stream, going on from the entry this one is at, e.g. for
+    /// [`SeekingParser::entry`] or [`CentralDirectory::read`](crate::CentralDirectory::read).
+    /// What it lists doesn't move this parser.
+    pub fn parser(&self) -> SeekingParser<'_, S, N> {
+        SeekingParser::from_state(&self.stream, State {
+            last_error: self.state.last_error.clone(),
+            #[cfg(feature = "std")]
+            record_offsets: self.state.record_offsets.clone(),
+            ..self.state
+        })
+    }
+
+    /// Run `f` on a parser made of the state of this one, the state being kept afterwards
+    fn with_parser<'s, T>(&'s mut self, f: impl FnOnce(&mut SeekingParser<'s, S, N>) -> T) -> T {
+        let Self { stream, state } = self;
+        let mut parser = SeekingParser::from_state(stream, state.take());
+        let result = f(&mut parser);
+        *state = parser.into_state();
+        result
+    }
+}
+
+impl<const N: usize> State<N> {
+    /// Move the state out, leaving a copy without the error and the cached offsets
+    fn take(&mut self) -> Self {
+        let copy = Self {
+            last_error: None,
+            #[cfg(feature = "std")]
+            record_offsets: Vec::new(),
+            ..*self
+        };
+        core::mem::replace(self, copy)
+    }
+}
+
+impl<S: Read + Seek, const N: usize> Parser<S> for OwnedSeekingParser<S, N> {
+    type Entry<'e> = LocalFile<'e, S, N> where Self: 'e;
+
+    fn next_entry(&mut self) -> Result<Option<LocalFile<'_, S, N>>, ParsingError> {
+        self.with_parser(|parser| parser.try_next())
+    }
+
+    fn entry_count(&self) -> Option<u64> {
+        self.state.number_of_files
+    }
+
+    fn find_entry(&mut self, name: &str) -> Result<Option<LocalFile<'_, S, N>>, ParsingError> {
+        self.with_parser(|parser| parser.find_entry(name))
+    }
+}
@@ -0,0 +1,112 @@
+//! Reshaping entry data events into whole flash pages
+
+use crate::ParserEvent;
+
+/// Adapter between [`PassiveParser::feed_data`](crate::PassiveParser::feed_data) and an event
+/// handler, delivering `LocalFileData` in multiples of `P` bytes.
+///
+/// Only the last data event of an entry may be shorter than `P`, so flash programming code
+/// never deals with partial pages in the middle of an entry. Data is copied into the internal
+/// page buffer only when a chunk does not fill whole pages by itself.
+/// ```
+/// use zip_parser::{PageAligner, ParserEvent, PassiveParser};
+///
+/// let mut parser = PassiveParser::<128>::new();
+/// let mut pages = PageAligner::<4096>::new();
+/// parser.feed_data(&[], |evt| pages.handle(evt, |evt| {
+///     if let ParserEvent::LocalFileData { offset, data, .. } = evt {
+///         assert_eq!(offset % 4096, 0);
+///     }
+///     true
+/// }));
+/// ```
+pub struct PageAligner<const P: usize> {
+    page: [u8; P],
+    page_len: usize,
+    file_index: i32,
+    /// entry data delivered so far
+    delivered: usize,
+}
+
+impl<const P: usize> PageAligner<P> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle a parser event, forwarding it reshaped to `on_event`
+    pub fn handle<const N: usize, F>(&mut self, evt: ParserEvent<'_, '_, N>, mut on_event: F) -> bool
+    where
+        F: for<'b, 'c> FnMut(ParserEvent<'b, 'c, N>) -> bool,
+    {
+        match evt {
+            ParserEvent::LocalFileHeader(file_index, _) => {
+                self.file_index = file_index;
+                self.page_len = 0;
+                self.delivered = 0;
+                on_event(evt)
+            }
+            ParserEvent::LocalFileData { file_index, mut data, .. } => {
+                self.file_index = file_index;
+
+                // complete the pending page first
+                if self.page_len > 0 {
+                    let len = core::cmp::min(P - self.page_len, data.len());
+                    self.page[self.page_len..self.page_len + len].copy_from_slice(&data[..len]);
+                    self.page_len += len;
+                    data = &data[len..];
+                    if self.page_len < P {
+                        return true;
+                    }
+                    if !self.flush(&mut on_event) {
+                        return false;
+                    }
+                }
+
+                // whole pages straight from the input
+                let whole = data.len() - data.len() % P;
+                if whole > 0 {
+                    let offset = self.delivered;
+                    self.delivered += whole;
+                    if !on_event(ParserEvent::LocalFileData { file_index, offset, data: &data[..whole] }) {
+                        return false;
+                    }
+                }
+
+                let rest = &data[whole..];
+                self.page[..rest.len()].copy_from_slice(rest);
+                self.page_len = rest.len();
+                true
+            }
+            ParserEvent::LocalFileEnd(_) => {
+                // the last page of the entry may be short
+                self.flush(&mut on_event) && on_event(evt)
+            }
+            _ => on_event(evt),
+        }
+    }
+
+    fn flush<const N: usize, F>(&mut self, on_event: &mut F) -> bool
+    where
+        F: for<'b, 'c> FnMut(ParserEvent<'b, 'c, N>) -> bool,
+    {
+        if self.page_len == 0 {
+            return true;
+        }
+        let len = self.page_len;
+        let offset = self.delivered;
+        self.page_len = 0;
+        self.delivered += len;
+        on_event(ParserEvent::LocalFileData { file_index: self.file_index, offset, data: &self.page[..len] })
+    }
+}
+
+impl<const P: usize> Default for PageAligner<P> {
+    fn default() -> Self {
+        Self {
+            page: [0; P],
+            page_len: 0,
+            file_index: 0,
+            delivered: 0,
+        }
+    }
+}
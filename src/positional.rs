@@ -0,0 +1,180 @@
+//! Positional reads, for many readers of the same archive without a shared seek cursor
+
+use core::cmp;
+
+#[cfg(feature = "std")]
+use std::{fs::File, io, vec::Vec};
+
+use crate::{ParsingError, SeekFrom};
+#[cfg(not(feature = "std"))]
+use crate::{Read, Seek};
+
+/// Source read at any offset through a shared reference, e.g. a memory buffer or a [`File`]
+/// with `pread`.
+///
+/// Each parser reads it through its own [`ReadAtCursor`], so that the entries of several
+/// parsers, in as many threads, don't move each other's position.
+pub trait ReadAt {
+    /// Read at `offset` into `buf`, returns the length read, 0 at the end of the source
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize, ParsingError>;
+
+    /// Length of the source
+    fn size(&self) -> Result<u64, ParsingError>;
+}
+
+impl ReadAt for [u8] {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        let start = cmp::min(offset, self.len() as u64) as usize;
+        let len = cmp::min(buf.len(), self.len() - start);
+        buf[..len].copy_from_slice(&self[start..start + len]);
+        Ok(len)
+    }
+
+    fn size(&self) -> Result<u64, ParsingError> {
+        Ok(self.len() as u64)
+    }
+}
+
+#[cfg(feature = "std")]
+impl ReadAt for Vec<u8> {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        self.as_slice().read_at(offset, buf)
+    }
+
+    fn size(&self) -> Result<u64, ParsingError> {
+        Ok(self.len() as u64)
+    }
+}
+
+impl<R: ReadAt + ?Sized> ReadAt for &R {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        (**self).read_at(offset, buf)
+    }
+
+    fn size(&self) -> Result<u64, ParsingError> {
+        (**self).size()
+    }
+}
+
+#[cfg(all(feature = "std", unix))]
+impl ReadAt for File {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        use std::os::unix::fs::FileExt;
+        Ok(FileExt::read_at(self, buf, offset)?)
+    }
+
+    fn size(&self) -> Result<u64, ParsingError> {
+        Ok(self.metadata()?.len())
+    }
+}
+
+/// `seek_read` moves the cursor of the file, which isn't used by the other reads anyway
+#[cfg(all(feature = "std", windows))]
+impl ReadAt for File {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        use std::os::windows::fs::FileExt;
+        Ok(FileExt::seek_read(self, buf, offset)?)
+    }
+
+    fn size(&self) -> Result<u64, ParsingError> {
+        Ok(self.metadata()?.len())
+    }
+}
+
+/// [`Read`](crate::Read) and [`Seek`](crate::Seek) over a [`ReadAt`] source, the position being kept by the cursor.
+///
+/// With a shared source, e.g. `ReadAtCursor::new(&file)`, each parser gets its own cursor:
+/// ```
+/// # #[cfg(all(feature = "std", unix))]
+/// # fn main() -> Result<(), zip_parser::ParsingError> {
+/// use zip_parser::{LocalFileOps, ReadAtCursor, SeekingParser};
+///
+/// let file = std::fs::File::open("test.zip")?;
+/// std::thread::scope(|scope| {
+///     for index in 0..2 {
+///         let file = &file;
+///         scope.spawn(move || {
+///             let mut parser = SeekingParser::<_, 128>::take(ReadAtCursor::new(file));
+///             let size = parser.parser().entry(index).ok().flatten().map(|entry| entry.file_size());
+///             assert!(size.is_some());
+///         });
+///     }
+/// });
+/// # Ok(())
+/// # }
+/// # #[cfg(not(all(feature = "std", unix)))]
+/// # fn main() {}
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReadAtCursor<R: ReadAt> {
+    source: R,
+    position: u64,
+}
+
+impl<R: ReadAt> ReadAtCursor<R> {
+    pub fn new(source: R) -> Self {
+        Self { source, position: 0 }
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+
+    fn read_from(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        let n = self.source.read_at(self.position, buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+
+    /// `None` for a position before the start of the source
+    fn seek_to(&mut self, pos: SeekFrom) -> Result<Option<u64>, ParsingError> {
+        let position = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => self.source.size()?.checked_add_signed(n),
+            SeekFrom::Current(n) => self.position.checked_add_signed(n),
+        };
+        if let Some(position) = position {
+            self.position = position;
+        }
+        Ok(position)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: ReadAt> io::Read for ReadAtCursor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_from(buf)
+            .map_err(io::Error::from)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: ReadAt> io::Seek for ReadAtCursor<R> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.seek_to(pos.into())
+            .map_err(io::Error::from)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before the start of the source"))
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<R: ReadAt> Read for ReadAtCursor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        self.read_from(buf)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<R: ReadAt> Seek for ReadAtCursor<R> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, ParsingError> {
+        self.seek_to(pos)?.ok_or(ParsingError::InvalidStream)
+    }
+
+    fn stream_len(&mut self) -> Option<u64> {
+        self.source.size().ok()
+    }
+}
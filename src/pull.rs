@@ -0,0 +1,222 @@
+//! Entries of a [`PassiveParser`] pulled from a `Read` stream instead of pushed to a callback
+
+use core::cmp;
+use core::str::Utf8Error;
+
+use crate::{
+    ErrorContext, HeaderType, LocalFileInfo, LocalFileOps, Parser, ParserEvent, ParserState, ParsingError,
+    PassiveParser, Read,
+};
+
+/// Longest part of a `LocalFileData` event which may not fit the buffer of a read: the data held
+/// back before a data descriptor, or a chunk of inflated data
+#[cfg(not(feature = "inflate"))]
+const PENDING_LEN: usize = crate::MAX_DATA_DESCRIPTOR_LEN;
+#[cfg(feature = "inflate")]
+const PENDING_LEN: usize = crate::INFLATE_CHUNK_LEN;
+
+/// Drive a [`PassiveParser`] over `stream`, reading `B` bytes at a time, and hand the local
+/// files out one after the other.
+///
+/// The data of the entries is the raw data of the archive, compressed or not, unless the parser
+/// inflates it (see `PassiveParser::with_inflate`). The central directory is passed over, the
+/// parser ends with the stream or at the end of the archive.
+pub struct PullParser<'a, S: Read, const N: usize = 128, const B: usize = 512> {
+    stream: &'a mut S,
+    parser: PassiveParser<N>,
+    input: [u8; B],
+    /// unparsed part of `input`
+    start: usize,
+    end: usize,
+    info: LocalFileInfo<N>,
+    /// the data of the current entry didn't end yet
+    reading: bool,
+    /// data of the last event, which didn't fit in the buffer of the last read
+    pending: heapless::Vec<u8, PENDING_LEN>,
+}
+
+/// Local file of a [`PullParser`], its data is read from the stream as it goes
+pub struct PullEntry<'e, 'a, S: Read, const N: usize, const B: usize> {
+    parser: &'e mut PullParser<'a, S, N, B>,
+}
+
+impl<'a, S: Read, const N: usize, const B: usize> PullParser<'a, S, N, B> {
+    /// `stream` must be at the start of the archive
+    pub fn new(stream: &'a mut S) -> Self {
+        Self::with_parser(stream, PassiveParser::new())
+    }
+
+    /// Go through `stream` with `parser`, e.g. a strict or an inflating one
+    pub fn with_parser(stream: &'a mut S, parser: PassiveParser<N>) -> Self {
+        Self {
+            stream,
+            parser,
+            input: [0; B],
+            start: 0,
+            end: 0,
+            info: LocalFileInfo::default(),
+            reading: false,
+            pending: heapless::Vec::new(),
+        }
+    }
+
+    /// Where the last error happened, see [`PassiveParser::last_error`]
+    pub fn last_error(&self) -> Option<&ErrorContext<N>> {
+        self.parser.last_error()
+    }
+
+    /// Feed at most `limit` bytes to the parser, reading the stream if all the input was parsed.
+    ///
+    /// Returns `false` at the end of the stream.
+    fn feed<F>(&mut self, limit: usize, mut on_event: F) -> Result<bool, ParsingError>
+    where
+        F: for<'b, 'c> FnMut(ParserEvent<'b, 'c, N>) -> bool,
+    {
+        if self.start == self.end {
+            self.start = 0;
+            self.end = self.stream.read(&mut self.input)?;
+            if self.end == 0 {
+                return Ok(false);
+            }
+        }
+        let end = cmp::min(self.end, self.start.saturating_add(limit));
+        let (Ok(n) | Err(n)) = self.parser.parse_data(&self.input[self.start..end], |evt, _| on_event(evt));
+        self.start += n;
+        Ok(true)
+    }
+
+    /// Skip to the next local file header, `false` at the end of the archive
+    fn advance(&mut self) -> Result<bool, ParsingError> {
+        self.pending.clear();
+        loop {
+            if self.parser.is_finished() {
+                self.reading = false;
+                return Ok(false);
+            }
+            let mut found = None;
+            let mut error = None;
+            // the data left of the current entry is skipped
+            let more = self.feed(usize::MAX, |evt| match evt {
+                ParserEvent::LocalFileHeader(_, info) => {
+                    found = Some(info.clone());
+                    false
+                }
+                ParserEvent::ParsingError(_, err) => {
+                    error = Some(err);
+                    false
+                }
+                _ => true,
+            })?;
+            if let Some(err) = error {
+                return Err(err);
+            }
+            if let Some(info) = found {
+                self.info = info;
+                self.reading = true;
+                return Ok(true);
+            }
+            if !more {
+                self.reading = false;
+                // between two records, or the stream was cut
+                let between = matches!(self.parser.state, ParserState::RecvHeader(HeaderType::HeaderSignature, _))
+                    && self.parser.buffer.is_empty();
+                return if between { Ok(false) } else { Err(ParsingError::StreamEnding) };
+            }
+        }
+    }
+}
+
+impl<'a, S: Read, const N: usize, const B: usize> Parser<S> for PullParser<'a, S, N, B> {
+    type Entry<'e> = PullEntry<'e, 'a, S, N, B> where Self: 'e;
+
+    fn next_entry(&mut self) -> Result<Option<Self::Entry<'_>>, ParsingError> {
+        Ok(self.advance()?.then_some(PullEntry { parser: self }))
+    }
+
+    fn find_entry(&mut self, name: &str) -> Result<Option<Self::Entry<'_>>, ParsingError> {
+        while self.advance()? {
+            if self.info.file_name() == Ok(name) {
+                return Ok(Some(PullEntry { parser: self }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<S: Read, const N: usize, const B: usize> PullEntry<'_, '_, S, N, B> {
+    pub fn info(&self) -> &LocalFileInfo<N> {
+        &self.parser.info
+    }
+}
+
+impl<S: Read, const N: usize, const B: usize> LocalFileOps for PullEntry<'_, '_, S, N, B> {
+    fn file_name(&self) -> Result<&str, Utf8Error> {
+        self.parser.info.file_name()
+    }
+
+    /// The compressed size, 0 for the entries whose sizes follow the data in a data descriptor
+    fn file_size(&self) -> u64 {
+        self.parser.info.compressed_size
+    }
+
+    /// As [`LocalFileInfo::is_dir`], the attributes are in the central directory only
+    fn is_dir(&self) -> bool {
+        self.parser.info.is_dir()
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        let parser = &mut *self.parser;
+        if !parser.pending.is_empty() {
+            let n = cmp::min(buf.len(), parser.pending.len());
+            buf[..n].copy_from_slice(&parser.pending[..n]);
+            parser.pending.rotate_left(n);
+            parser.pending.truncate(parser.pending.len() - n);
+            return Ok(n);
+        }
+        if !parser.reading || buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            let mut read = None;
+            let mut ended = false;
+            let mut error = None;
+            let mut pending = heapless::Vec::new();
+            // the data of the input comes in a single event, held or inflated data may not fit in `buf`
+            let more = parser.feed(buf.len(), |evt| match evt {
+                ParserEvent::LocalFileData { data, .. } if !data.is_empty() => {
+                    let n = cmp::min(buf.len(), data.len());
+                    buf[..n].copy_from_slice(&data[..n]);
+                    if pending.extend_from_slice(&data[n..]).is_err() {
+                        error = Some(ParsingError::BufferTooSmall);
+                    }
+                    read = Some(n);
+                    false
+                }
+                ParserEvent::LocalFileEnd(_) => {
+                    ended = true;
+                    false
+                }
+                ParserEvent::ParsingError(_, err) => {
+                    error = Some(err);
+                    false
+                }
+                _ => true,
+            })?;
+            parser.pending = pending;
+            if let Some(err) = error {
+                return Err(err);
+            }
+            if ended {
+                parser.reading = false;
+                return Ok(0);
+            }
+            if let Some(n) = read {
+                return Ok(n);
+            }
+            if !more {
+                return Err(ParsingError::StreamEnding);
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Bytes of the stream which belong to no record nor entry data, e.g. the stub of a
+//! self-extracting archive or the APK signing block
+
+use core::cell::RefCell;
+use core::cmp;
+use core::ops::Range;
+
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::descriptor::DATA_DESCRIPTOR_SIGNATURE;
+use crate::{borrow_stream, checked_offset, le_u32, read_at, ParsingError, Read, Seek, SeekFrom, SeekingParser};
+
+/// Stream ranges around the entries of an archive, empty when nothing is there
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ArchiveRegions {
+    /// Before the first local file header, e.g. the stub of a self-extracting archive
+    pub prefix: Range<u64>,
+    /// Between the end of the last entry, its data descriptor included, and the central
+    /// directory, e.g. the APK signing block
+    pub before_central_directory: Range<u64>,
+}
+
+impl ArchiveRegions {
+    pub fn is_empty(&self) -> bool {
+        self.prefix.is_empty() && self.before_central_directory.is_empty()
+    }
+}
+
+/// Reader over a range of the stream of a [`SeekingParser`], see [`SeekingParser::region_reader`]
+pub struct RegionReader<'a, S: Read + Seek> {
+    stream: &'a RefCell<S>,
+    position: u64,
+    end: u64,
+}
+
+impl<'a, S: Read + Seek, const N: usize> SeekingParser<'a, S, N> {
+    /// Locate the bytes around the entries, going through the whole central directory.
+    ///
+    /// Fails with [`ParsingError::InvalidCentralDirEnd`] without a central directory, or with the
+    /// error stopping the listing of the entries.
+    pub fn regions(&self) -> Result<ArchiveRegions, ParsingError> {
+        if self.central_dir_end.is_none() {
+            return Err(ParsingError::InvalidCentralDirEnd);
+        }
+        let mut first_header = self.central_directory_offset;
+        let mut entries_end = 0;
+        let mut entries = self.entries();
+        while let Some(file) = entries.try_next()? {
+            first_header = cmp::min(first_header, file.metadata.local_header_offset);
+            let mut data_end = checked_offset(file.info.file_data_offset, file.info.compressed_size)?;
+            if file.data_descriptor {
+                // the signature of the descriptor is optional
+                let mut signature = [0u8; 4];
+                let signature = read_at(&mut *borrow_stream(Some(self.stream))?, data_end, 4, &mut signature)?;
+                let signature_len = if le_u32(signature, 0) == DATA_DESCRIPTOR_SIGNATURE { 4 } else { 0 };
+                data_end = checked_offset(data_end, signature_len + if file.zip64_descriptor { 20 } else { 12 })?;
+            }
+            entries_end = cmp::max(entries_end, data_end);
+        }
+        Ok(ArchiveRegions {
+            prefix: 0..first_header,
+            before_central_directory: cmp::min(cmp::max(entries_end, first_header), self.central_directory_offset)..self.central_directory_offset,
+        })
+    }
+
+    /// Read the bytes of `region` of the stream, e.g. one of [`SeekingParser::regions`]
+    pub fn region_reader(&self, region: Range<u64>) -> RegionReader<'a, S> {
+        RegionReader {
+            stream: self.stream,
+            position: region.start,
+            end: region.end,
+        }
+    }
+}
+
+impl<S: Read + Seek> RegionReader<'_, S> {
+    /// Bytes left to read
+    pub fn remaining(&self) -> u64 {
+        self.end.saturating_sub(self.position)
+    }
+
+    fn read_region(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        let len = cmp::min(buf.len() as u64, self.remaining()) as usize;
+        if len == 0 {
+            return Ok(0);
+        }
+        let mut stream = borrow_stream(Some(self.stream))?;
+        stream.seek(SeekFrom::Start(self.position))?;
+        let n = stream.read(&mut buf[..len])?;
+        if n == 0 {
+            return Err(ParsingError::StreamEnding);
+        }
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: Read + Seek> io::Read for RegionReader<'_, S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_region(buf)
+            .map_err(io::Error::from)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<S: Read + Seek> Read for RegionReader<'_, S> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        self.read_region(buf)
+    }
+}
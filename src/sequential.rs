@@ -0,0 +1,245 @@
+//! Listing the local files of a `Read`-only stream, one header after the other
+
+use core::cmp;
+use core::str::Utf8Error;
+
+use crate::descriptor::DATA_DESCRIPTOR_SIGNATURE;
+use crate::{
+    CompressMethod, EncryptionKind, ErrorContext, ExtraFields, LocalFileHeader, LocalFileInfo, LocalFileOps, Parser,
+    ParsingError, Read, Signature, Zip64ExtraField, FLAG_DATA_DESCRIPTOR, FLAG_MASKED_HEADER, LOCAL_FILE_HEADER_LEN,
+    MAX_EXTRA_FIELD_LEN,
+};
+
+/// Walk the local file headers of `stream`, skipping the data of the entries which aren't read.
+///
+/// The data of the entries is the raw data of the archive, compressed or not. The listing ends
+/// with the stream or at the central directory. The length of the data is taken from the local
+/// file header, so an entry whose sizes only follow its data, in a data descriptor, stops the
+/// listing with [`ParsingError::UnknownDataLength`]: go through such archives with a
+/// [`PullParser`](crate::PullParser), which parses the data to find its end.
+pub struct SequentialParser<'a, S: Read, const N: usize = 128> {
+    stream: &'a mut S,
+    info: LocalFileInfo<N>,
+    /// index of the current entry, -1 before the first one
+    file_index: i32,
+    /// data of the current entry left in the stream
+    remaining: u64,
+    /// a data descriptor follows the data of the current entry, with 8 bytes sizes if `zip64`
+    descriptor: bool,
+    zip64: bool,
+    /// stream offset of the next byte read
+    offset: u64,
+    finished: bool,
+    last_error: Option<ErrorContext<N>>,
+}
+
+/// Local file of a [`SequentialParser`], its data is read from the stream as it goes
+pub struct SequentialEntry<'e, 'a, S: Read, const N: usize> {
+    parser: &'e mut SequentialParser<'a, S, N>,
+}
+
+impl<'a, S: Read, const N: usize> SequentialParser<'a, S, N> {
+    /// `stream` must be at the start of the archive
+    pub fn new(stream: &'a mut S) -> Self {
+        Self {
+            stream,
+            info: LocalFileInfo::default(),
+            file_index: -1,
+            remaining: 0,
+            descriptor: false,
+            zip64: false,
+            offset: 0,
+            finished: false,
+            last_error: None,
+        }
+    }
+
+    /// Why the listing ended with an error: the error, the stream offset where it happened and,
+    /// past the fixed part of the local file header, the index and name of the entry.
+    pub fn last_error(&self) -> Option<&ErrorContext<N>> {
+        self.last_error.as_ref()
+    }
+
+    /// Skip to the next local file header, `false` at the end of the archive.
+    ///
+    /// The listing is over after an error, the stream being somewhere in a record.
+    fn advance(&mut self) -> Result<bool, ParsingError> {
+        if self.finished {
+            return Ok(false);
+        }
+        match self.read_header() {
+            Ok(found) => {
+                self.finished = !found;
+                Ok(found)
+            }
+            Err(err) => {
+                let mut context = ErrorContext::new(err.clone(), self.offset);
+                if self.file_index >= 0 {
+                    context = context.with_entry(self.file_index, self.info.file_name_bytes());
+                }
+                diag!(warn, "listing the entries failed: {}", context);
+                self.last_error = Some(context);
+                self.finished = true;
+                Err(err)
+            }
+        }
+    }
+
+    fn read_header(&mut self) -> Result<bool, ParsingError> {
+        self.skip(self.remaining)?;
+        self.remaining = 0;
+        if self.descriptor {
+            self.descriptor = false;
+            // the signature is optional, without it the CRC-32 comes first
+            let mut signature = [0u8; 4];
+            self.read_fully(&mut signature)?;
+            let crc32 = if u32::from_le_bytes(signature) == DATA_DESCRIPTOR_SIGNATURE { 4 } else { 0 };
+            self.skip(crc32 + if self.zip64 { 16 } else { 8 })?;
+        }
+
+        let mut record = [0u8; LOCAL_FILE_HEADER_LEN];
+        match self.read_some(&mut record[..4])? {
+            0 => return Ok(false),
+            4 => {}
+            _ => return Err(ParsingError::StreamEnding),
+        }
+        // the central directory follows the last entry
+        if !matches!(Signature::try_from(&record[..])?, Signature::LocalFileHeader) {
+            return Ok(false);
+        }
+        self.read_fully(&mut record[4..])?;
+        let header = LocalFileHeader::from_bytes(&record).ok_or(ParsingError::InvalidLocalFileHeader)?;
+        if header.general_purpose_bit_flag & FLAG_MASKED_HEADER != 0 {
+            return Err(ParsingError::UnsupportedEncryption(EncryptionKind::CentralDirectory));
+        }
+        self.file_index += 1;
+        self.info = LocalFileInfo::default()
+            .with_general_purpose_bit_flag(header.general_purpose_bit_flag)
+            .with_compression_method(CompressMethod::from(header.compression_method))
+            .with_compressed_size(header.compressed_size as u64)
+            .with_uncompressed_size(header.uncompressed_size as u64);
+
+        // names longer than `N` bytes are truncated
+        let name_len = header.file_name_length as usize;
+        let kept = cmp::min(name_len, N);
+        let mut name = [0u8; N];
+        self.read_fully(&mut name[..kept])?;
+        self.skip((name_len - kept) as u64)?;
+        self.info.file_name_buffer = name;
+        self.info.file_name_length = kept;
+
+        let mut zip64 = Zip64ExtraField::new();
+        let mut extra_field = heapless::Vec::<u8, MAX_EXTRA_FIELD_LEN>::new();
+        let mut buf = [0u8; 32];
+        let mut left = header.extra_field_length as usize;
+        while left > 0 {
+            let n = cmp::min(buf.len(), left);
+            self.read_fully(&mut buf[..n])?;
+            zip64.feed(&buf[..n]);
+            // only the first bytes are kept, as by the other parsers
+            let kept = cmp::min(n, extra_field.capacity() - extra_field.len());
+            let _ = extra_field.extend_from_slice(&buf[..kept]);
+            left -= n;
+        }
+        // sizes beyond 4 GiB are in the extra field
+        zip64.resolve(&mut [&mut self.info.uncompressed_size, &mut self.info.compressed_size]);
+        self.info.apply_unicode_path(ExtraFields::new(&extra_field));
+        self.info.file_data_offset = self.offset;
+
+        self.descriptor = header.general_purpose_bit_flag & FLAG_DATA_DESCRIPTOR != 0;
+        self.zip64 = zip64.is_present();
+        // the header sizes of such entries are usually left to 0, only directories are sure to be empty
+        if self.descriptor && self.info.compressed_size == 0 && !self.info.is_dir() {
+            return Err(ParsingError::UnknownDataLength(self.file_index));
+        }
+        self.remaining = self.info.compressed_size;
+        Ok(true)
+    }
+
+    /// Read into `buf` until it is full or the stream ends, returning the bytes read
+    fn read_some(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        let mut i = 0;
+        while i < buf.len() {
+            match self.stream.read(&mut buf[i..])? {
+                0 => break,
+                n => {
+                    i += n;
+                    self.offset += n as u64;
+                }
+            }
+        }
+        Ok(i)
+    }
+
+    fn read_fully(&mut self, buf: &mut [u8]) -> Result<(), ParsingError> {
+        if self.read_some(buf)? < buf.len() {
+            return Err(ParsingError::StreamEnding);
+        }
+        Ok(())
+    }
+
+    /// Pass over `len` bytes of the stream
+    fn skip(&mut self, mut len: u64) -> Result<(), ParsingError> {
+        let mut scratch = [0u8; 64];
+        while len > 0 {
+            let n = cmp::min(scratch.len() as u64, len) as usize;
+            self.read_fully(&mut scratch[..n])?;
+            len -= n as u64;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, S: Read, const N: usize> Parser<S> for SequentialParser<'a, S, N> {
+    type Entry<'e> = SequentialEntry<'e, 'a, S, N> where Self: 'e;
+
+    fn next_entry(&mut self) -> Result<Option<Self::Entry<'_>>, ParsingError> {
+        Ok(self.advance()?.then_some(SequentialEntry { parser: self }))
+    }
+
+    fn find_entry(&mut self, name: &str) -> Result<Option<Self::Entry<'_>>, ParsingError> {
+        while self.advance()? {
+            if self.info.file_name() == Ok(name) {
+                return Ok(Some(SequentialEntry { parser: self }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<S: Read, const N: usize> SequentialEntry<'_, '_, S, N> {
+    pub fn info(&self) -> &LocalFileInfo<N> {
+        &self.parser.info
+    }
+}
+
+impl<S: Read, const N: usize> LocalFileOps for SequentialEntry<'_, '_, S, N> {
+    fn file_name(&self) -> Result<&str, Utf8Error> {
+        self.parser.info.file_name()
+    }
+
+    /// The compressed size
+    fn file_size(&self) -> u64 {
+        self.parser.info.compressed_size
+    }
+
+    /// As [`LocalFileInfo::is_dir`], the attributes are in the central directory only
+    fn is_dir(&self) -> bool {
+        self.parser.info.is_dir()
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        let parser = &mut *self.parser;
+        let len = cmp::min(buf.len() as u64, parser.remaining) as usize;
+        if len == 0 {
+            return Ok(0);
+        }
+        let n = parser.stream.read(&mut buf[..len])?;
+        if n == 0 {
+            return Err(ParsingError::StreamEnding);
+        }
+        parser.remaining -= n as u64;
+        parser.offset += n as u64;
+        Ok(n)
+    }
+}
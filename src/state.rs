@@ -0,0 +1,372 @@
+//! Saving and restoring the [`PassiveParser`] state as a byte blob
+
+use core::cmp;
+
+use crate::{
+    le_u16, CompressMethod, HeaderType, LocalFileInfo, ParserState, ParsingError, PassiveParser,
+    CENTRAL_DIR_END_LEN, CENTRAL_FILE_HEADER_LEN, LOCAL_FILE_HEADER_LEN, ZIP64_CENTRAL_DIR_END_LEN,
+    ZIP64_CENTRAL_DIR_END_LOCATOR_LEN,
+};
+
+/// Leading bytes of a saved state, the last one is the format version
+const STATE_MAGIC: [u8; 4] = *b"ZPS\x01";
+
+struct StateWriter<'a> {
+    out: &'a mut [u8],
+    len: usize,
+}
+
+impl StateWriter<'_> {
+    fn bytes(&mut self, data: &[u8]) -> Result<(), ParsingError> {
+        let end = self.len + data.len();
+        self.out
+            .get_mut(self.len..end)
+            .ok_or(ParsingError::BufferTooSmall)?
+            .copy_from_slice(data);
+        self.len = end;
+        Ok(())
+    }
+
+    fn u8(&mut self, value: u8) -> Result<(), ParsingError> {
+        self.bytes(&[value])
+    }
+
+    fn u32(&mut self, value: u32) -> Result<(), ParsingError> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    fn u64(&mut self, value: u64) -> Result<(), ParsingError> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    fn usize(&mut self, value: usize) -> Result<(), ParsingError> {
+        self.u64(value as u64)
+    }
+}
+
+struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], ParsingError> {
+        if self.data.len() < len {
+            return Err(ParsingError::InvalidSavedState);
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, ParsingError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, ParsingError> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, ParsingError> {
+        let b = self.bytes(8)?;
+        Ok(u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+    }
+
+    fn usize(&mut self) -> Result<usize, ParsingError> {
+        usize::try_from(self.u64()?).map_err(|_| ParsingError::InvalidSavedState)
+    }
+}
+
+fn encode_state(state: ParserState) -> (u8, u8, usize) {
+    match state {
+        ParserState::RecvHeader(header_type, len) => {
+            let header_type = match header_type {
+                HeaderType::HeaderSignature => 0,
+                HeaderType::LocalFileHeader => 1,
+                HeaderType::CentralFileHeader => 2,
+                HeaderType::CentralDirEnd => 3,
+                HeaderType::Zip64CentralDirEnd => 4,
+                HeaderType::Zip64CentralDirEndLocator => 5,
+            };
+            (0, header_type, len)
+        }
+        ParserState::RecvCentralFileHeader => (1, 0, 0),
+        ParserState::RecvCentralDirEnd => (2, 0, 0),
+        ParserState::RecvLocalFileName => (3, 0, 0),
+        ParserState::RecvLocalFileExtraField => (4, 0, 0),
+        ParserState::RecvLocalFileData => (5, 0, 0),
+        ParserState::Finished => (6, 0, 0),
+        ParserState::RecvZip64CentralDirEnd => (7, 0, 0),
+    }
+}
+
+fn header_len(header_type: HeaderType) -> usize {
+    match header_type {
+        HeaderType::HeaderSignature => 4,
+        HeaderType::LocalFileHeader => LOCAL_FILE_HEADER_LEN,
+        HeaderType::CentralFileHeader => CENTRAL_FILE_HEADER_LEN,
+        HeaderType::CentralDirEnd => CENTRAL_DIR_END_LEN,
+        HeaderType::Zip64CentralDirEnd => ZIP64_CENTRAL_DIR_END_LEN,
+        HeaderType::Zip64CentralDirEndLocator => ZIP64_CENTRAL_DIR_END_LOCATOR_LEN,
+    }
+}
+
+fn decode_state(tag: u8, header_type: u8, len: usize) -> Result<ParserState, ParsingError> {
+    Ok(match tag {
+        0 => {
+            let header_type = match header_type {
+                0 => HeaderType::HeaderSignature,
+                1 => HeaderType::LocalFileHeader,
+                2 => HeaderType::CentralFileHeader,
+                3 => HeaderType::CentralDirEnd,
+                4 => HeaderType::Zip64CentralDirEnd,
+                5 => HeaderType::Zip64CentralDirEndLocator,
+                _ => return Err(ParsingError::InvalidSavedState),
+            };
+            // the parser only waits for whole records
+            if len != header_len(header_type) {
+                return Err(ParsingError::InvalidSavedState);
+            }
+            ParserState::RecvHeader(header_type, len)
+        }
+        1 => ParserState::RecvCentralFileHeader,
+        2 => ParserState::RecvCentralDirEnd,
+        3 => ParserState::RecvLocalFileName,
+        4 => ParserState::RecvLocalFileExtraField,
+        5 => ParserState::RecvLocalFileData,
+        6 => ParserState::Finished,
+        7 => ParserState::RecvZip64CentralDirEnd,
+        _ => return Err(ParsingError::InvalidSavedState),
+    })
+}
+
+impl<const N: usize> PassiveParser<N> {
+    /// Save the whole parser state into `out`, returns the length of the saved state.
+    ///
+    /// The state can be persisted (e.g. to flash) and restored by [`PassiveParser::load_state`],
+    /// parsing then continues with the data following what was fed before saving.
+    ///
+    /// Fails with [`ParsingError::InvalidSavedState`] inside an entry being inflated, whose
+    /// inflater state can't be saved.
+    pub fn save_state(&self, out: &mut [u8]) -> Result<usize, ParsingError> {
+        if self.is_inflating() && matches!(self.state, ParserState::RecvLocalFileData) {
+            return Err(ParsingError::InvalidSavedState);
+        }
+        let mut w = StateWriter { out, len: 0 };
+        w.bytes(&STATE_MAGIC)?;
+
+        let (tag, header_type, len) = encode_state(self.state);
+        w.u8(tag)?;
+        w.u8(header_type)?;
+        w.usize(len)?;
+        w.u8(self.buffer.len() as u8)?;
+        w.bytes(&self.buffer)?;
+
+        match &self.localfile_info {
+            Some(info) => {
+                // the name may be partially received, and not yet accounted in `file_name_length`
+                let received = cmp::max(info.file_name_length, self.file_name_index).min(N);
+                w.u8(1)?;
+                w.usize(info.file_name_length)?;
+                w.usize(received)?;
+                w.bytes(&info.file_name_buffer[..received])?;
+                w.usize(info.extra_field_length)?;
+                w.u64(info.file_data_offset)?;
+                w.u8(info.encrypted as u8 | (info.utf8_name as u8) << 1)?;
+                w.u8(info.compression_method as u8)?;
+                w.u64(info.compressed_size)?;
+                w.u64(info.uncompressed_size)?;
+            }
+            None => w.u8(0)?,
+        }
+
+        w.u32(self.localfile_index as u32)?;
+        w.u32(self.centralfile_index as u32)?;
+        for value in [
+            self.file_name_len, self.file_name_index,
+            self.extra_field_len, self.extra_field_index,
+            self.file_data_len, self.file_data_index,
+            self.central_file_header_index, self.central_file_header_len,
+            self.central_dir_end_index, self.central_dir_end_len,
+        ] {
+            w.usize(value)?;
+        }
+        w.bytes(&self.zip64_extra.block_header)?;
+        w.usize(self.zip64_extra.block_index)?;
+        w.u8(self.zip64_extra.data_len as u8)?;
+        w.bytes(&self.zip64_extra.data)?;
+        // only needed until the extra field events
+        let extra_field: &[u8] = match self.state {
+            ParserState::RecvLocalFileExtraField | ParserState::RecvCentralFileHeader => &self.extra_field,
+            _ => &[],
+        };
+        w.usize(extra_field.len())?;
+        w.bytes(extra_field)?;
+        w.u8(self.data_descriptor as u8)?;
+        w.u8(self.descriptor_scanner.zip64 as u8)?;
+        w.u8(self.descriptor_scanner.stored as u8)?;
+        w.u8(self.descriptor_scanner.held.len() as u8)?;
+        w.bytes(&self.descriptor_scanner.held)?;
+        w.u64(self.stream_offset)?;
+        w.u32(self.crc32)?;
+        w.u32(self.expected_crc32)?;
+        w.u8(self.check_crc as u8)?;
+        w.u8(self.multi_archive as u8)?;
+        w.u32(self.archive_index)?;
+        w.u64(self.stats.entries)?;
+        w.u64(self.stats.declared_entries)?;
+        w.u64(self.stats.compressed_bytes)?;
+        w.u64(self.stats.uncompressed_bytes)?;
+        w.u8(self.strict as u8)?;
+        w.u8(self.central_dir_started as u8)?;
+        w.u8(self.archive_ended as u8)?;
+        w.u8(self.listing_only as u8)?;
+        w.u8(self.stream_len.is_some() as u8)?;
+        w.u64(self.stream_len.unwrap_or(0))?;
+        #[cfg(feature = "inflate")]
+        let inflate = self.inflate;
+        #[cfg(not(feature = "inflate"))]
+        let inflate = false;
+        w.u8(inflate as u8)?;
+        w.bytes(&self.central_header)?;
+        w.bytes(&self.central_file_name)?;
+        w.u64(self.central_header_offset)?;
+
+        #[cfg(feature = "std")]
+        let comment = self.zip_file_comment.as_slice();
+        #[cfg(not(feature = "std"))]
+        let comment: &[u8] = &[];
+        w.usize(comment.len())?;
+        w.bytes(comment)?;
+
+        Ok(w.len)
+    }
+
+    /// Restore a parser saved by [`PassiveParser::save_state`]
+    pub fn load_state(data: &[u8]) -> Result<Self, ParsingError> {
+        let mut r = StateReader { data };
+        if r.bytes(STATE_MAGIC.len())? != STATE_MAGIC {
+            return Err(ParsingError::InvalidSavedState);
+        }
+
+        let mut parser = Self::default();
+        let tag = r.u8()?;
+        let header_type = r.u8()?;
+        let len = r.usize()?;
+        parser.state = decode_state(tag, header_type, len)?;
+        let buffer_len = r.u8()? as usize;
+        parser.buffer
+            .extend_from_slice(r.bytes(buffer_len)?)
+            .map_err(|_| ParsingError::InvalidSavedState)?;
+
+        if r.u8()? == 1 {
+            let file_name_length = r.usize()?;
+            let received = r.usize()?;
+            let mut file_name_buffer = [0u8; N];
+            if file_name_length > N {
+                return Err(ParsingError::InvalidSavedState);
+            }
+            file_name_buffer
+                .get_mut(..received)
+                .ok_or(ParsingError::InvalidSavedState)?
+                .copy_from_slice(r.bytes(received)?);
+            let extra_field_length = r.usize()?;
+            let file_data_offset = r.u64()?;
+            let flags = r.u8()?;
+            let info = LocalFileInfo {
+                file_name_buffer,
+                file_name_length,
+                extra_field_length,
+                file_data_offset,
+                encrypted: flags & 1 != 0,
+                utf8_name: flags & 2 != 0,
+                compression_method: CompressMethod::from(r.u8()? as u16),
+                compressed_size: r.u64()?,
+                uncompressed_size: r.u64()?,
+            };
+            parser.localfile_info = Some(info);
+        }
+
+        parser.localfile_index = r.u32()? as i32;
+        parser.centralfile_index = r.u32()? as i32;
+        for value in [
+            &mut parser.file_name_len, &mut parser.file_name_index,
+            &mut parser.extra_field_len, &mut parser.extra_field_index,
+            &mut parser.file_data_len, &mut parser.file_data_index,
+            &mut parser.central_file_header_index, &mut parser.central_file_header_len,
+            &mut parser.central_dir_end_index, &mut parser.central_dir_end_len,
+        ] {
+            *value = r.usize()?;
+        }
+        let zip64_extra = &mut parser.zip64_extra;
+        let block_header_len = zip64_extra.block_header.len();
+        zip64_extra.block_header.copy_from_slice(r.bytes(block_header_len)?);
+        zip64_extra.block_index = r.usize()?;
+        zip64_extra.data_len = r.u8()? as usize;
+        let data_len = zip64_extra.data.len();
+        zip64_extra.data.copy_from_slice(r.bytes(data_len)?);
+        let block_len = block_header_len + le_u16(&zip64_extra.block_header, 2) as usize;
+        if zip64_extra.data_len > data_len || zip64_extra.block_index > block_len {
+            return Err(ParsingError::InvalidSavedState);
+        }
+        let extra_field_len = r.usize()?;
+        parser.extra_field
+            .extend_from_slice(r.bytes(extra_field_len)?)
+            .map_err(|_| ParsingError::InvalidSavedState)?;
+        parser.data_descriptor = r.u8()? != 0;
+        parser.descriptor_scanner.zip64 = r.u8()? != 0;
+        parser.descriptor_scanner.stored = r.u8()? != 0;
+        let held_len = r.u8()? as usize;
+        parser.descriptor_scanner.held
+            .extend_from_slice(r.bytes(held_len)?)
+            .map_err(|_| ParsingError::InvalidSavedState)?;
+        parser.stream_offset = r.u64()?;
+        parser.crc32 = r.u32()?;
+        parser.expected_crc32 = r.u32()?;
+        parser.check_crc = r.u8()? != 0;
+        parser.multi_archive = r.u8()? != 0;
+        parser.archive_index = r.u32()?;
+        parser.stats.entries = r.u64()?;
+        parser.stats.declared_entries = r.u64()?;
+        parser.stats.compressed_bytes = r.u64()?;
+        parser.stats.uncompressed_bytes = r.u64()?;
+        parser.strict = r.u8()? != 0;
+        parser.central_dir_started = r.u8()? != 0;
+        parser.archive_ended = r.u8()? != 0;
+        parser.listing_only = r.u8()? != 0;
+        let has_stream_len = r.u8()? != 0;
+        let stream_len = r.u64()?;
+        parser.stream_len = has_stream_len.then_some(stream_len);
+        let _inflate = r.u8()? != 0;
+        #[cfg(feature = "inflate")]
+        { parser.inflate = _inflate; }
+        parser.central_header.copy_from_slice(r.bytes(CENTRAL_FILE_HEADER_LEN)?);
+        parser.central_file_name.copy_from_slice(r.bytes(N)?);
+        parser.central_header_offset = r.u64()?;
+
+        let comment_len = r.usize()?;
+        let _comment = r.bytes(comment_len)?;
+        #[cfg(feature = "std")]
+        parser.zip_file_comment.extend_from_slice(_comment);
+
+        // the parser slices its buffers with these, they must be as it leaves them
+        let buffer_fits = match parser.state {
+            ParserState::RecvHeader(_, len) => parser.buffer.len() <= len,
+            ParserState::Finished => true,
+            _ => parser.buffer.is_empty(),
+        };
+        let indexes_fit = parser.file_name_index <= parser.file_name_len
+            && parser.extra_field_index <= parser.extra_field_len
+            && parser.file_data_index <= parser.file_data_len
+            && parser.central_file_header_index <= parser.central_file_header_len
+            && parser.central_dir_end_index <= parser.central_dir_end_len;
+        // the name and extra field of a central header follow its fixed part
+        let central_header_received = !matches!(parser.state, ParserState::RecvCentralFileHeader)
+            || parser.central_file_header_index >= CENTRAL_FILE_HEADER_LEN;
+        if !(buffer_fits && indexes_fit && central_header_received) {
+            return Err(ParsingError::InvalidSavedState);
+        }
+
+        Ok(parser)
+    }
+}
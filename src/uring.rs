@@ -0,0 +1,176 @@
+//! Positional reads through io_uring on Linux
+
+use std::fs::File;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::vec::Vec;
+use std::{cmp, thread};
+
+use io_uring::{opcode, types, IoUring};
+
+use crate::{ParsingError, Read, Seek, SeekFrom};
+
+/// Submission queue depth
+const QUEUE_DEPTH: u32 = 32;
+
+/// `user_data` of the cancellations, told apart from the range indices of the reads
+const CANCEL_USER_DATA: u64 = u64::MAX;
+
+/// File read with positional io_uring reads instead of `lseek` + `read`.
+///
+/// Seeking only moves a cursor, and [`UringFile::read_ranges`] submits many reads at once,
+/// e.g. the central directory and the data of the selected entries.
+pub struct UringFile {
+    file: File,
+    ring: IoUring,
+    position: u64,
+    len: u64,
+    /// length read into each range by [`UringFile::read_ranges`]
+    filled: Vec<usize>,
+}
+
+impl UringFile {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(File::open(path)?)
+    }
+
+    pub fn new(file: File) -> io::Result<Self> {
+        let len = file.metadata()?.len();
+        Ok(Self {
+            file,
+            ring: IoUring::new(QUEUE_DEPTH)?,
+            position: 0,
+            len,
+            filled: Vec::new(),
+        })
+    }
+
+    /// Read at `offset` without moving the cursor, returns the length read, 0 at the end of the file
+    pub fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        let len = buf.len().min(self.len.saturating_sub(offset) as usize);
+        self.read_ranges(&mut [(offset, &mut buf[..len])])?;
+        Ok(len)
+    }
+
+    /// Fill every buffer with the data at its offset, the reads are submitted together.
+    ///
+    /// Fails with [`ParsingError::StreamEnding`] if a range goes past the end of the file.
+    pub fn read_ranges(&mut self, ranges: &mut [(u64, &mut [u8])]) -> Result<(), ParsingError> {
+        let fd = types::Fd(self.file.as_raw_fd());
+        self.filled.clear();
+        self.filled.resize(ranges.len(), 0);
+        let mut error = None;
+        loop {
+            let mut submitted = 0;
+            {
+                let mut queue = self.ring.submission();
+                for (i, (offset, buf)) in ranges.iter_mut().enumerate() {
+                    if self.filled[i] >= buf.len() || queue.is_full() {
+                        continue;
+                    }
+                    let rest = &mut buf[self.filled[i]..];
+                    // longer reads are resumed with the next batch
+                    let len = cmp::min(rest.len(), u32::MAX as usize) as u32;
+                    let entry = opcode::Read::new(fd, rest.as_mut_ptr(), len)
+                        .offset(*offset + self.filled[i] as u64)
+                        .build()
+                        .user_data(i as u64);
+                    // SAFETY: the buffer outlives the read, all submitted reads are waited for below
+                    if unsafe { queue.push(&entry) }.is_err() {
+                        break;
+                    }
+                    submitted += 1;
+                }
+            }
+            if submitted == 0 {
+                return Ok(());
+            }
+
+            let mut completed = 0;
+            while completed < submitted {
+                if let Err(err) = self.wait(submitted - completed) {
+                    self.cancel(ranges.len(), submitted - completed);
+                    return Err(err);
+                }
+                for cqe in self.ring.completion() {
+                    if cqe.user_data() == CANCEL_USER_DATA {
+                        // left over from a failed call
+                        continue;
+                    }
+                    let i = cqe.user_data() as usize;
+                    completed += 1;
+                    match cqe.result() {
+                        n if n < 0 => error = Some(io::Error::from_raw_os_error(-n).into()),
+                        0 => error = Some(ParsingError::StreamEnding),
+                        n => self.filled[i] += n as usize,
+                    }
+                }
+            }
+            if let Some(err) = error {
+                return Err(err);
+            }
+        }
+    }
+
+    /// Submit the queued reads and wait for `want` completions
+    fn wait(&mut self, want: usize) -> Result<(), ParsingError> {
+        loop {
+            match self.ring.submit_and_wait(want) {
+                Ok(_) => return Ok(()),
+                Err(e) if matches!(e.kind(), io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock | io::ErrorKind::ResourceBusy) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Cancel the `in_flight` reads of the `ranges` first ranges, and wait until they complete:
+    /// they write into borrowed buffers.
+    ///
+    /// The reads still queued are submitted along with the cancellations.
+    fn cancel(&mut self, ranges: usize, mut in_flight: usize) {
+        {
+            let mut queue = self.ring.submission();
+            for i in 0..ranges {
+                let entry = opcode::AsyncCancel::new(i as u64).build().user_data(CANCEL_USER_DATA);
+                // SAFETY: cancelling holds no buffer
+                if unsafe { queue.push(&entry) }.is_err() {
+                    // the reads complete anyway
+                    break;
+                }
+            }
+        }
+        while in_flight > 0 {
+            if self.ring.submit_and_wait(1).is_err() {
+                // the completions are posted all the same
+                thread::yield_now();
+            }
+            let completed = self.ring.completion().filter(|cqe| cqe.user_data() != CANCEL_USER_DATA).count();
+            in_flight = in_flight.saturating_sub(completed);
+        }
+    }
+}
+
+impl Read for UringFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ParsingError> {
+        let n = self.read_at(self.position, buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for UringFile {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, ParsingError> {
+        let position = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => self.len.checked_add_signed(n),
+            SeekFrom::Current(n) => self.position.checked_add_signed(n),
+        };
+        self.position = position.ok_or(ParsingError::InvalidStream)?;
+        Ok(self.position)
+    }
+
+    fn stream_len(&mut self) -> Option<u64> {
+        Some(self.len)
+    }
+}
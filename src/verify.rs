@@ -0,0 +1,95 @@
+//! Testing archives without extracting them, as `zip -t` does
+
+use crate::{CompressMethod, EntryMetadata, LocalFileInfo, LocalFileOps, ParsingError, Read, Seek, SeekingParser};
+
+/// What [`SeekingParser::verify_all`] found out about an entry
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EntryCheck {
+    /// The data matches the declared sizes and CRC-32
+    Passed,
+    /// The data is encrypted, it was skipped
+    Encrypted,
+    /// The data is compressed with a method which can't be decompressed, it was skipped
+    Unsupported(CompressMethod),
+    /// Reading the data failed, or it doesn't match the declared sizes or CRC-32
+    Failed(ParsingError),
+}
+
+/// Report of an entry tested by [`SeekingParser::verify_all`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EntryReport<const N: usize> {
+    /// Index of the entry in the central directory
+    pub file_index: usize,
+    pub info: LocalFileInfo<N>,
+    pub metadata: EntryMetadata,
+    /// Length of the uncompressed data read, up to the failure if any
+    pub data_len: u64,
+    pub check: EntryCheck,
+}
+
+/// Totals of [`SeekingParser::verify_all`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct VerifyStats {
+    /// Entries reached
+    pub entries: u64,
+    pub passed: u64,
+    /// Encrypted entries and those compressed with an unsupported method
+    pub skipped: u64,
+    pub failed: u64,
+    /// Entries declared by the end of central directory record, `None` without one
+    pub declared_entries: Option<u64>,
+}
+
+impl VerifyStats {
+    /// No entry failed, and all the declared entries were reached
+    pub fn is_ok(&self) -> bool {
+        self.failed == 0 && self.declared_entries == Some(self.entries)
+    }
+}
+
+impl<'a, S: Read + Seek, const N: usize> SeekingParser<'a, S, N> {
+    /// Test the archive: read the data of every entry, decompressed, and check it against the
+    /// sizes and CRC-32 of the central directory. `on_report` gets the report of each entry.
+    ///
+    /// Encrypted entries and those the built-in decompressors can't handle are skipped. A broken
+    /// central directory or local header ends the iteration, leaving declared entries unreached.
+    pub fn verify_all<F: FnMut(&EntryReport<N>)>(self, mut on_report: F) -> VerifyStats {
+        let mut stats = VerifyStats {
+            declared_entries: self.number_of_files,
+            ..Default::default()
+        };
+        for (file_index, file) in self.enumerate() {
+            let mut file = file.with_crc_check(true);
+            let mut data_len = 0;
+            let check = if file.info.is_encrypted() {
+                EntryCheck::Encrypted
+            } else if !file.reads_uncompressed() {
+                EntryCheck::Unsupported(file.info.compression_method)
+            } else {
+                let mut buf = [0u8; 512];
+                loop {
+                    match LocalFileOps::read(&mut file, &mut buf) {
+                        Ok(0) => break EntryCheck::Passed,
+                        Ok(n) => data_len += n as u64,
+                        Err(err) => break EntryCheck::Failed(err),
+                    }
+                }
+            };
+
+            stats.entries += 1;
+            match check {
+                EntryCheck::Passed => stats.passed += 1,
+                EntryCheck::Failed(_) => stats.failed += 1,
+                _ => stats.skipped += 1,
+            }
+            on_report(&EntryReport {
+                file_index,
+                info: file.info,
+                metadata: file.metadata,
+                data_len,
+                check,
+            });
+        }
+        stats
+    }
+}
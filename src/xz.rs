@@ -0,0 +1,83 @@
+//! XZ (method 95) decompression backed by `lzma-rs`
+
+use core::{cmp, fmt};
+use std::vec::Vec;
+
+use crate::{CompressMethod, Decompressor, ParsingError};
+
+/// XZ decompressor of zip entries.
+///
+/// `lzma-rs` has no incremental XZ decoder, so the compressed entry is buffered in memory and
+/// decompressed once its end is reached, the output is buffered as well.
+#[derive(Default)]
+pub struct XzDecoder {
+    input: Vec<u8>,
+    output: Vec<u8>,
+    /// bytes of the output handed out
+    position: usize,
+    finished: bool,
+}
+
+impl XzDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get ready for another XZ stream, keeping the allocated buffers
+    pub fn reset(&mut self) {
+        self.input.clear();
+        self.output.clear();
+        self.position = 0;
+        self.finished = false;
+    }
+
+    /// Decompress `input` into `output`, returns `(consumed, produced)`.
+    ///
+    /// Nothing is produced before `last` tells that `input` ends the compressed data, the
+    /// following calls may then pass an empty `input` to get the rest of the output.
+    pub fn decompress(&mut self, input: &[u8], output: &mut [u8], last: bool) -> Result<(usize, usize), ParsingError> {
+        let mut consumed = 0;
+        if !self.finished {
+            self.input.extend_from_slice(input);
+            consumed = input.len();
+            if last {
+                lzma_rs::xz_decompress(&mut &self.input[..], &mut self.output)
+                    .map_err(|_| ParsingError::DecompressionFailed)?;
+                self.input.clear();
+                self.finished = true;
+            }
+        }
+
+        let produced = cmp::min(output.len(), self.output.len() - self.position);
+        output[..produced].copy_from_slice(&self.output[self.position..self.position + produced]);
+        self.position += produced;
+        Ok((consumed, produced))
+    }
+}
+
+impl fmt::Debug for XzDecoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("XzDecoder")
+            .field("buffered", &self.input.len())
+            .field("finished", &self.finished)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Decompressor for XzDecoder {
+    fn init(&mut self, method: CompressMethod) -> Result<(), ParsingError> {
+        if method != CompressMethod::XZ {
+            return Err(ParsingError::UnsupportedCompressMethod(method));
+        }
+        self.reset();
+        Ok(())
+    }
+
+    fn feed(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), ParsingError> {
+        self.decompress(input, output, false)
+    }
+
+    fn finish(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), ParsingError> {
+        self.decompress(input, output, true)
+    }
+}
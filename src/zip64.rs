@@ -0,0 +1,217 @@
+//! ZIP64 extended information, holding the sizes and offsets which don't fit in 32 bits
+
+use core::cmp;
+
+use crate::{
+    le_u16, le_u32, ParsingError, Read, Seek, SeekFrom, Signature, CENTRAL_DIR_ENCRYPTION_VERSION,
+    ZIP64_CENTRAL_DIR_END_LEN, ZIP64_CENTRAL_DIR_END_LOCATOR_LEN,
+};
+
+/// Header ID of the ZIP64 extended information extra field
+const ZIP64_EXTRA_FIELD_ID: u16 = 0x0001;
+/// Value of a 32 bits header field whose actual value is in the ZIP64 extended information
+pub(crate) const ZIP64_SATURATED: u32 = 0xFFFF_FFFF;
+/// Original size, compressed size, local header offset (8 bytes each) and disk start number (4 bytes)
+const ZIP64_DATA_MAX_LEN: usize = 28;
+/// ID and data size of an extra field block
+const EXTRA_BLOCK_HEADER_LEN: usize = 4;
+/// Signature and size of the ZIP64 end of central directory record, not counted in the size
+const ZIP64_CENTRAL_DIR_END_UNCOUNTED_LEN: u64 = 12;
+
+#[inline]
+fn le_u64(bytes: &[u8], offset: usize) -> u64 {
+    (le_u32(bytes, offset) as u64) | (le_u32(bytes, offset + 4) as u64) << 32
+}
+
+/// ZIP64 extended information picked out of an extra field, which may be received in pieces
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Zip64ExtraField {
+    /// header of the extra field block being received
+    pub(crate) block_header: [u8; EXTRA_BLOCK_HEADER_LEN],
+    /// bytes of the block received so far, header included
+    pub(crate) block_index: usize,
+    pub(crate) data: [u8; ZIP64_DATA_MAX_LEN],
+    pub(crate) data_len: usize,
+}
+
+impl Zip64ExtraField {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get ready for another extra field
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Whether the extra field has the ZIP64 extended information
+    pub fn is_present(&self) -> bool {
+        self.data_len > 0
+    }
+
+    /// Parse the following bytes of the extra field
+    pub fn feed(&mut self, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            if self.block_index < EXTRA_BLOCK_HEADER_LEN {
+                let len = cmp::min(EXTRA_BLOCK_HEADER_LEN - self.block_index, bytes.len());
+                self.block_header[self.block_index..self.block_index + len].copy_from_slice(&bytes[..len]);
+                self.block_index += len;
+                bytes = &bytes[len..];
+                if self.block_index < EXTRA_BLOCK_HEADER_LEN {
+                    break;
+                }
+            }
+
+            let block_len = EXTRA_BLOCK_HEADER_LEN + le_u16(&self.block_header, 2) as usize;
+            let len = cmp::min(block_len - self.block_index, bytes.len());
+            if le_u16(&self.block_header, 0) == ZIP64_EXTRA_FIELD_ID {
+                // the bytes past the known values are skipped, the block may be longer
+                let data_index = cmp::min(self.block_index - EXTRA_BLOCK_HEADER_LEN, ZIP64_DATA_MAX_LEN);
+                let kept = cmp::min(len, ZIP64_DATA_MAX_LEN - data_index);
+                self.data[data_index..data_index + kept].copy_from_slice(&bytes[..kept]);
+                self.data_len = cmp::min(data_index + kept, ZIP64_DATA_MAX_LEN);
+            }
+            self.block_index += len;
+            bytes = &bytes[len..];
+            if self.block_index == block_len {
+                self.block_index = 0;
+            }
+        }
+    }
+
+    /// Parse the extra field of `len` bytes at `offset` of `stream`
+    pub fn read_from<S: Read + Seek>(stream: &mut S, offset: u64, len: usize) -> Result<Self, ParsingError> {
+        let mut field = Self::new();
+        stream.seek(SeekFrom::Start(offset))?;
+        let mut buf = [0u8; 32];
+        let mut remaining = len;
+        while remaining > 0 {
+            let len = cmp::min(buf.len(), remaining);
+            let n = stream.read(&mut buf[..len])?;
+            if n == 0 {
+                return Err(ParsingError::StreamEnding);
+            }
+            field.feed(&buf[..n]);
+            remaining -= n;
+        }
+        Ok(field)
+    }
+
+    /// Replace the `values` saturated in the header (0xFFFFFFFF) by their 64 bits version.
+    ///
+    /// `values` are the header fields in the order of the extended information: original size,
+    /// compressed size, local header offset. A value missing from the extra field is kept.
+    pub fn resolve(&self, values: &mut [&mut u64]) {
+        let mut offset = 0;
+        for value in values.iter_mut() {
+            if **value != ZIP64_SATURATED as u64 {
+                continue;
+            }
+            if offset + 8 > self.data_len {
+                break;
+            }
+            **value = le_u64(&self.data, offset);
+            offset += 8;
+        }
+    }
+}
+
+/// ZIP64 end of central directory record, holding the values saturated in the end of central
+/// directory record
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Zip64CentralDirEnd {
+    pub(crate) signature: Signature,
+    pub(crate) size_of_record: u64,
+    pub(crate) version_made_by: u16,
+    pub(crate) version_needed_to_extract: u16,
+    pub(crate) number_of_disk: u32,
+    pub(crate) number_of_start_central_directory_disk: u32,
+    pub(crate) total_entries_this_disk: u64,
+    pub(crate) total_entries_all_disk: u64,
+    pub(crate) size_of_the_central_directory: u64,
+    pub(crate) central_directory_offset: u64,
+}
+
+impl Zip64CentralDirEnd {
+    /// Length of the whole record, extensible data sector included
+    pub fn len(&self) -> u64 {
+        ZIP64_CENTRAL_DIR_END_UNCOUNTED_LEN + self.size_of_record
+    }
+
+    /// Whether the central directory is encrypted, the record being then in its version 2
+    pub fn has_encrypted_central_dir(&self) -> bool {
+        self.version_needed_to_extract >= CENTRAL_DIR_ENCRYPTION_VERSION
+    }
+
+    /// Decode the fixed part of the record, `None` if the data is too short or the signature mismatches
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < ZIP64_CENTRAL_DIR_END_LEN
+            || !matches!(Signature::try_from(bytes), Ok(Signature::Zip64CentralDirEnd)) {
+            return None;
+        }
+        Some(Self {
+            signature: Signature::Zip64CentralDirEnd,
+            size_of_record: le_u64(bytes, 4),
+            version_made_by: le_u16(bytes, 12),
+            version_needed_to_extract: le_u16(bytes, 14),
+            number_of_disk: le_u32(bytes, 16),
+            number_of_start_central_directory_disk: le_u32(bytes, 20),
+            total_entries_this_disk: le_u64(bytes, 24),
+            total_entries_all_disk: le_u64(bytes, 32),
+            size_of_the_central_directory: le_u64(bytes, 40),
+            central_directory_offset: le_u64(bytes, 48),
+        })
+    }
+
+    /// Read the record through the locator right before the end of central directory record at
+    /// `end_offset` of `stream`, `None` if the archive has no ZIP64 end of central directory.
+    /// Returns the offset of the record along with it.
+    ///
+    /// If data precedes the archive, the offset of the locator is wrong: the record is then
+    /// looked for right before the locator, where it is unless it has an extensible data sector.
+    pub fn read_from<S: Read + Seek>(stream: &mut S, end_offset: u64) -> Option<(u64, Self)> {
+        let locator_offset = end_offset.checked_sub(ZIP64_CENTRAL_DIR_END_LOCATOR_LEN as u64)?;
+        stream.seek(SeekFrom::Start(locator_offset)).ok()?;
+        let mut buf = [0u8; ZIP64_CENTRAL_DIR_END_LEN];
+        let locator = &mut buf[..ZIP64_CENTRAL_DIR_END_LOCATOR_LEN];
+        if !matches!(stream.read(locator), Ok(n) if n == locator.len()) {
+            return None;
+        }
+        let locator = Zip64CentralDirEndLocator::from_bytes(locator)?;
+
+        let mut read_record = |offset: u64| {
+            stream.seek(SeekFrom::Start(offset)).ok()?;
+            match stream.read(&mut buf) {
+                Ok(n) if n == buf.len() => Self::from_bytes(&buf).map(|record| (offset, record)),
+                _ => None,
+            }
+        };
+        read_record(locator.zip64_central_dir_end_offset)
+            .or_else(|| read_record(locator_offset.checked_sub(ZIP64_CENTRAL_DIR_END_LEN as u64)?))
+    }
+}
+
+/// ZIP64 end of central directory locator, right before the end of central directory record
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Zip64CentralDirEndLocator {
+    pub(crate) signature: Signature,
+    pub(crate) number_of_zip64_central_dir_end_disk: u32,
+    pub(crate) zip64_central_dir_end_offset: u64,
+    pub(crate) total_number_of_disks: u32,
+}
+
+impl Zip64CentralDirEndLocator {
+    /// Decode the record, `None` if the data is too short or the signature mismatches
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < ZIP64_CENTRAL_DIR_END_LOCATOR_LEN
+            || !matches!(Signature::try_from(bytes), Ok(Signature::Zip64CentralDirEndLocator)) {
+            return None;
+        }
+        Some(Self {
+            signature: Signature::Zip64CentralDirEndLocator,
+            number_of_zip64_central_dir_end_disk: le_u32(bytes, 4),
+            zip64_central_dir_end_offset: le_u64(bytes, 8),
+            total_number_of_disks: le_u32(bytes, 16),
+        })
+    }
+}
@@ -0,0 +1,200 @@
+//! Zstandard (method 93) decompression backed by `ruzstd`
+
+use core::fmt;
+
+use alloc::vec::Vec;
+use ruzstd::decoding::FrameDecoder;
+use ruzstd::io::Read as _;
+
+use crate::{CompressMethod, Decompressor, ParsingError};
+
+const FRAME_MAGIC: u32 = 0xFD2F_B528;
+/// Skippable frames use the 16 magic numbers from this one
+const SKIPPABLE_FRAME_MAGIC: u32 = 0x184D_2A50;
+
+const BLOCK_HEADER_LEN: usize = 3;
+const CHECKSUM_LEN: usize = 4;
+
+/// What the buffered input starts with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expect {
+    /// a frame header, or a skippable frame
+    Frame,
+    /// a block of the current frame, which ends with a checksum when the flag is set
+    Block(bool),
+}
+
+/// Streaming Zstandard decompressor.
+///
+/// `ruzstd` only decodes whole blocks, so the compressed input is buffered until a block is
+/// complete: it needs an allocator, and up to 128 KiB for the input plus the frame window.
+pub struct ZstdDecoder {
+    frame: FrameDecoder,
+    /// compressed input not decoded yet
+    input: Vec<u8>,
+    expect: Expect,
+}
+
+impl ZstdDecoder {
+    pub fn new() -> Self {
+        Self {
+            frame: FrameDecoder::new(),
+            input: Vec::new(),
+            expect: Expect::Frame,
+        }
+    }
+
+    /// Get ready for another Zstandard stream
+    pub fn reset(&mut self) {
+        self.frame = FrameDecoder::new();
+        self.input.clear();
+        self.expect = Expect::Frame;
+    }
+
+    /// Decompress `input` into `output`, returns `(consumed, produced)`.
+    ///
+    /// Decompressed data not fitting in `output` is kept for the next call, which may pass an
+    /// empty `input`. `last` tells that `input` ends the compressed data.
+    pub fn decompress(&mut self, input: &[u8], output: &mut [u8], last: bool) -> Result<(usize, usize), ParsingError> {
+        let mut consumed = 0;
+        let mut produced = 0;
+        loop {
+            // hand out the decoded data first, the next frame header would drop it
+            produced += self.frame
+                .read(&mut output[produced..])
+                .map_err(|_| ParsingError::DecompressionFailed)?;
+            if produced == output.len() {
+                break;
+            }
+
+            match self.unit_len()? {
+                Some(len) if len <= self.input.len() => {
+                    self.decode_unit(len)?;
+                    self.input.drain(..len);
+                }
+                _ if consumed < input.len() => {
+                    self.input.extend_from_slice(&input[consumed..]);
+                    consumed = input.len();
+                }
+                _ => break,
+            }
+        }
+
+        let truncated = !self.input.is_empty() || self.expect != Expect::Frame;
+        if last && produced == 0 && truncated {
+            return Err(ParsingError::DecompressionFailed);
+        }
+        Ok((consumed, produced))
+    }
+
+    /// Length of the next unit to decode, `None` until enough input is buffered to tell
+    fn unit_len(&self) -> Result<Option<usize>, ParsingError> {
+        let input = &self.input[..];
+        match self.expect {
+            Expect::Frame => {
+                if input.len() < 5 {
+                    return Ok(None);
+                }
+                let magic = u32::from_le_bytes([input[0], input[1], input[2], input[3]]);
+                if magic & !0xF == SKIPPABLE_FRAME_MAGIC {
+                    if input.len() < 8 {
+                        return Ok(None);
+                    }
+                    let len = u32::from_le_bytes([input[4], input[5], input[6], input[7]]);
+                    return Ok(Some(8 + len as usize));
+                }
+                if magic != FRAME_MAGIC {
+                    return Err(ParsingError::DecompressionFailed);
+                }
+
+                let descriptor = input[4];
+                let single_segment = descriptor & 0x20 != 0;
+                let window_len = if single_segment { 0 } else { 1 };
+                let dict_id_len = [0, 1, 2, 4][(descriptor & 0x03) as usize];
+                let content_size_len = match descriptor >> 6 {
+                    0 if single_segment => 1,
+                    0 => 0,
+                    1 => 2,
+                    2 => 4,
+                    _ => 8,
+                };
+                Ok(Some(5 + window_len + dict_id_len + content_size_len))
+            }
+            Expect::Block(checksum) => {
+                if input.len() < BLOCK_HEADER_LEN {
+                    return Ok(None);
+                }
+                let header = u32::from_le_bytes([input[0], input[1], input[2], 0]);
+                let last = header & 1 != 0;
+                let content_len = match (header >> 1) & 0x03 {
+                    // RLE, a single byte repeated
+                    1 => 1,
+                    3 => return Err(ParsingError::DecompressionFailed),
+                    _ => (header >> 3) as usize,
+                };
+                let checksum_len = if last && checksum { CHECKSUM_LEN } else { 0 };
+                Ok(Some(BLOCK_HEADER_LEN + content_len + checksum_len))
+            }
+        }
+    }
+
+    /// Decode the first `len` bytes of the buffered input, making the unit told by `unit_len`
+    fn decode_unit(&mut self, len: usize) -> Result<(), ParsingError> {
+        let unit = &self.input[..len];
+        match self.expect {
+            Expect::Frame => {
+                let magic = u32::from_le_bytes([unit[0], unit[1], unit[2], unit[3]]);
+                if magic & !0xF == SKIPPABLE_FRAME_MAGIC {
+                    return Ok(());
+                }
+                self.frame.init(unit).map_err(|_| ParsingError::DecompressionFailed)?;
+                self.expect = Expect::Block(unit[4] & 0x04 != 0);
+            }
+            Expect::Block(_) => {
+                let (read, _) = self.frame
+                    .decode_from_to(unit, &mut [])
+                    .map_err(|_| ParsingError::DecompressionFailed)?;
+                if read != len {
+                    return Err(ParsingError::DecompressionFailed);
+                }
+                if unit[0] & 1 != 0 {
+                    self.expect = Expect::Frame;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for ZstdDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for ZstdDecoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ZstdDecoder")
+            .field("buffered", &self.input.len())
+            .field("expect", &self.expect)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Decompressor for ZstdDecoder {
+    fn init(&mut self, method: CompressMethod) -> Result<(), ParsingError> {
+        if method != CompressMethod::Zstd {
+            return Err(ParsingError::UnsupportedCompressMethod(method));
+        }
+        self.reset();
+        Ok(())
+    }
+
+    fn feed(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), ParsingError> {
+        self.decompress(input, output, false)
+    }
+
+    fn finish(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), ParsingError> {
+        self.decompress(input, output, true)
+    }
+}